@@ -0,0 +1,25 @@
+//! Compressed cold storage for pastes which haven't been viewed in a while (requires the `archive` feature, see [`crate::database::ServerOptions::archive_after`])
+//!
+//! Archived content is moved out of `se_pastes` into `se_archive`, compressed with zstd and
+//! stored as base64 (every column is text). Archived pastes are restored the next time
+//! they're read.
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+/// zstd compression level (archiving runs in the background, so favour size over speed)
+const LEVEL: i32 = 19;
+
+/// Compress `content` for storage
+pub fn compress(content: &str) -> Option<String> {
+    match zstd::encode_all(content.as_bytes(), LEVEL) {
+        Ok(bytes) => Some(STANDARD.encode(bytes)),
+        Err(_) => None,
+    }
+}
+
+/// Decompress content stored by [`compress`]
+pub fn decompress(stored: &str) -> Option<String> {
+    let bytes = STANDARD.decode(stored).ok()?;
+    let content = zstd::decode_all(bytes.as_slice()).ok()?;
+    String::from_utf8(content).ok()
+}
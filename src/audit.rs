@@ -0,0 +1,115 @@
+//! Mutation log for compliance (see [`crate::database::ServerOptions::audit_log`])
+//!
+//! Every paste creation, edit and deletion is sent to the configured [`AuditSink`] as a
+//! single line of JSON ([`AuditRecord`]). Records are queued in a bounded buffer and written
+//! by a background task, so a slow sink never holds up requests: when the buffer is full,
+//! records are dropped and an `audit_dropped` record with the number of dropped records is
+//! written once the sink catches up.
+use crate::database::{AuditLogOptions, AuditSink};
+use crate::model::AuditRecord;
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+/// Handle to the background task writing [`AuditRecord`]s
+#[derive(Clone, Debug)]
+pub struct AuditLog {
+    sender: mpsc::Sender<AuditRecord>,
+    /// Records dropped since the last `audit_dropped` record
+    dropped: Arc<AtomicUsize>,
+}
+
+impl AuditLog {
+    /// Start the background task writing records to `options.sink`
+    ///
+    /// Must be called from inside a tokio runtime.
+    pub fn start(options: &AuditLogOptions) -> Self {
+        let (sender, mut receiver) = mpsc::channel::<AuditRecord>(options.buffer.max(1));
+        let dropped = Arc::new(AtomicUsize::new(0));
+
+        let sink = options.sink.clone();
+        let counter = dropped.clone();
+
+        tokio::spawn(async move {
+            #[cfg(feature = "audit")]
+            let http = reqwest::Client::new();
+
+            while let Some(record) = receiver.recv().await {
+                let mut records: Vec<AuditRecord> = Vec::new();
+
+                let count = counter.swap(0, Ordering::Relaxed);
+
+                if count != 0 {
+                    records.push(AuditRecord {
+                        action: String::from("audit_dropped"),
+                        url: String::new(),
+                        new_url: String::new(),
+                        actor: String::new(),
+                        timestamp: dorsal::utility::unix_epoch_timestamp(),
+                        content_length: 0,
+                        content_hash: String::new(),
+                        content: None,
+                        dropped: Some(count),
+                    });
+                }
+
+                records.push(record);
+
+                for record in records {
+                    let line = serde_json::to_string(&record).unwrap();
+
+                    match sink {
+                        AuditSink::File(ref path) => write_file(path, &line),
+                        AuditSink::Syslog => write_syslog(&line),
+                        #[cfg(feature = "audit")]
+                        AuditSink::Http(ref url) => {
+                            let _ = http
+                                .post(url)
+                                .header("Content-Type", "application/json")
+                                .body(line)
+                                .timeout(std::time::Duration::from_secs(10))
+                                .send()
+                                .await;
+                        }
+                    }
+                }
+            }
+        });
+
+        Self { sender, dropped }
+    }
+
+    /// Queue a record (dropped if the buffer is full)
+    pub fn record(&self, record: AuditRecord) {
+        if self.sender.try_send(record).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Append a line to the file at `path`
+fn write_file(path: &str, line: &str) {
+    if let Ok(mut file) = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+    {
+        let _ = writeln!(file, "{}", line);
+    }
+}
+
+/// Send a line to the local syslog daemon (`/dev/log`, facility `local0`)
+#[cfg(unix)]
+fn write_syslog(line: &str) {
+    let socket = match std::os::unix::net::UnixDatagram::unbound() {
+        Ok(s) => s,
+        Err(_) => return,
+    };
+
+    // <134> is local0.info
+    let _ = socket.send_to(format!("<134>pastemd: {}", line).as_bytes(), "/dev/log");
+}
+
+#[cfg(not(unix))]
+fn write_syslog(_line: &str) {}
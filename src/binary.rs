@@ -0,0 +1,78 @@
+//! Binary pastes (requires the `binary` feature, see [`crate::database::ServerOptions::binary_pastes`])
+//!
+//! Every column is text, so binary content is stored as base64 in the paste's content and
+//! its type and decoded size are kept in its metadata. Binary pastes are downloaded from
+//! `/api/:url/binary` instead of being rendered, and are never indexed for search.
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+/// MIME type downloads fall back to when a paste has none
+pub const DEFAULT_MIME_TYPE: &str = "application/octet-stream";
+
+/// Encode `bytes` for storage
+pub fn encode(bytes: &[u8]) -> String {
+    STANDARD.encode(bytes)
+}
+
+/// Decode base64 `content` (whitespace, like line breaks every 76 characters, is ignored)
+///
+/// ## Returns:
+/// * `None` if the content isn't valid base64
+pub fn decode(content: &str) -> Option<Vec<u8>> {
+    let compact: String = content.chars().filter(|c| !c.is_whitespace()).collect();
+    STANDARD.decode(compact).ok()
+}
+
+/// Check if a binary paste with `scan_status` can be downloaded (pastes are held back while
+/// they're scanned, and infected pastes are quarantined)
+pub fn is_downloadable(scan_status: &str) -> bool {
+    (scan_status != "pending") && (scan_status != "infected")
+}
+
+/// Render the notice shown in place of a binary paste's content
+///
+/// ## Arguments:
+/// * `url` - the paste url
+/// * `mime_type` - the paste's MIME type
+/// * `size` - the size of the paste's decoded content in bytes
+/// * `scan_status` - the paste's malware scan status
+pub fn render(url: &str, mime_type: &str, size: usize, scan_status: &str) -> String {
+    let action = match scan_status {
+        "pending" => String::from("<span class=\"bn-scanning\">Scanning…</span>"),
+        "infected" => String::from("<span class=\"bn-quarantined\">Quarantined</span>"),
+        _ => format!(
+            "<a class=\"bn-download\" href=\"/api/{}/binary\" download>Download</a>",
+            escape(url)
+        ),
+    };
+
+    format!(
+        "<div class=\"bn\"><span class=\"bn-type\">{}</span> <span class=\"bn-size\">{}</span> {}</div>",
+        escape(if mime_type.is_empty() {
+            DEFAULT_MIME_TYPE
+        } else {
+            mime_type
+        }),
+        format_size(size),
+        action
+    )
+}
+
+/// Format a size in bytes for people
+fn format_size(size: usize) -> String {
+    if size < 1_024 {
+        format!("{} B", size)
+    } else if size < 1_024 * 1_024 {
+        format!("{:.1} KiB", size as f64 / 1_024.0)
+    } else {
+        format!("{:.1} MiB", size as f64 / (1_024.0 * 1_024.0))
+    }
+}
+
+/// Escape `text` for HTML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,23 @@
+//! Checksums of paste content (requires the `checksums` feature, see `/api/:url/checksums`)
+//!
+//! Checksums are computed whenever a paste's content is written and stored in its metadata,
+//! so people distributing scripts can publish them next to the paste's link.
+use crate::model::PasteChecksums;
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+/// Compute the checksums of `bytes`
+pub fn checksums(bytes: &[u8]) -> PasteChecksums {
+    PasteChecksums {
+        md5: hex(&Md5::digest(bytes)),
+        sha1: hex(&Sha1::digest(bytes)),
+        sha256: hex(&Sha256::digest(bytes)),
+        blake3: blake3::hash(bytes).to_hex().to_string(),
+    }
+}
+
+/// Format `bytes` as lowercase hex
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
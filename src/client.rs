@@ -0,0 +1,169 @@
+//! Typed client for the pastemd REST API (requires the `client` feature)
+use crate::model::{
+    FieldError, Paste, PasteCreate, PasteDelete, PasteEdit, PasteEditMetadata, PasteError,
+    PublicPaste, SearchQuery, SearchResult,
+};
+
+use dorsal::DefaultReturn;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+pub type Result<T> = std::result::Result<T, PasteError>;
+
+/// pastemd API client
+#[derive(Clone, Debug)]
+pub struct Client {
+    /// Base URL of the API (where [`crate::routing::api::routes`] is nested), e.g. `https://example.com/api`
+    pub base: String,
+    http: reqwest::Client,
+}
+
+impl Client {
+    /// Create a new [`Client`]
+    ///
+    /// ## Arguments:
+    /// * `base` - base URL of the API
+    pub fn new(base: impl Into<String>) -> Self {
+        let mut base: String = base.into();
+
+        if base.ends_with("/") {
+            base.pop();
+        }
+
+        Self {
+            base,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Turn an error response into a [`PasteError`]
+    ///
+    /// Uses the response's `X-Error-Code` header, or its status and message for servers
+    /// which don't send it.
+    fn error(status: u16, code: &str, res: DefaultReturn<Value>) -> PasteError {
+        use crate::model::PasteError::*;
+        match PasteError::from_code(code) {
+            Some(Validation(_)) => {
+                return Validation(serde_json::from_value(res.payload).unwrap_or_default())
+            }
+            Some(Rejected(_)) => return Rejected(res.message),
+            Some(e) => return e,
+            None => (),
+        }
+
+        match status {
+            401 => {
+                if res.message == NotAllowed.to_string() {
+                    NotAllowed
+                } else if res.message == WebAuthnRequired.to_string() {
+                    WebAuthnRequired
+                } else {
+                    PasswordIncorrect
+                }
+            }
+            403 => ChangeOwner,
+            404 => NotFound,
+            409 => UrlTaken,
+            413 => ContentTooLarge,
+            429 => RateLimited,
+            503 => Overloaded,
+            451 => GeoBlocked,
+            400 => {
+                if res.message == AlreadyExists.to_string() {
+                    AlreadyExists
+                } else if res.message == QuotaExceeded.to_string() {
+                    QuotaExceeded
+                } else if res.message == TosNotAccepted.to_string() {
+                    TosNotAccepted
+                } else if let Ok(errors) = serde_json::from_value::<Vec<FieldError>>(res.payload) {
+                    Validation(errors)
+                } else {
+                    ValueError
+                }
+            }
+            _ => Other,
+        }
+    }
+
+    /// Send a request and read its payload
+    async fn send<T: DeserializeOwned>(&self, req: reqwest::RequestBuilder) -> Result<T> {
+        let res = match req.send().await {
+            Ok(r) => r,
+            Err(_) => return Err(PasteError::Other),
+        };
+
+        let status = res.status();
+        let code = match res.headers().get("X-Error-Code") {
+            Some(v) => v.to_str().unwrap_or("").to_string(),
+            None => String::new(),
+        };
+
+        let body = match res.json::<DefaultReturn<Value>>().await {
+            Ok(b) => b,
+            Err(_) => return Err(PasteError::Other),
+        };
+
+        if !status.is_success() | !body.success {
+            return Err(Self::error(status.as_u16(), &code, body));
+        }
+
+        match serde_json::from_value::<T>(body.payload) {
+            Ok(p) => Ok(p),
+            Err(_) => Err(PasteError::ValueError),
+        }
+    }
+
+    /// Send a `POST` request with a JSON body
+    async fn post<B: Serialize, T: DeserializeOwned>(&self, path: &str, body: &B) -> Result<T> {
+        self.send(self.http.post(format!("{}{}", self.base, path)).json(body))
+            .await
+    }
+
+    /// Create a new paste (`/api/new`)
+    ///
+    /// ## Returns:
+    /// * Result containing a tuple with the unhashed edit password and the paste
+    pub async fn create(&self, props: PasteCreate) -> Result<(String, Paste)> {
+        self.post("/new", &props).await
+    }
+
+    /// Get an existing paste by `url` (`/api/:url`)
+    pub async fn get(&self, url: &str) -> Result<PublicPaste> {
+        self.send(self.http.get(format!("{}/{}", self.base, url)))
+            .await
+    }
+
+    /// Search public pastes (`/api/search`)
+    pub async fn search(&self, props: SearchQuery) -> Result<Vec<SearchResult>> {
+        self.send(self.http.get(format!("{}/search", self.base)).query(&props))
+            .await
+    }
+
+    /// Get an existing paste's view count by `url` (`/api/:url/views`)
+    pub async fn views(&self, url: &str) -> Result<i32> {
+        self.send(self.http.get(format!("{}/{}/views", self.base, url)))
+            .await
+    }
+
+    /// Edit an existing paste by `url` (`/api/:url/edit`)
+    pub async fn edit(&self, url: &str, props: PasteEdit) -> Result<()> {
+        self.post(&format!("/{}/edit", url), &props).await
+    }
+
+    /// Edit an existing paste's metadata by `url` (`/api/:url/metadata`)
+    pub async fn edit_metadata(&self, url: &str, props: PasteEditMetadata) -> Result<()> {
+        self.post(&format!("/{}/metadata", url), &props).await
+    }
+
+    /// Delete an existing paste by `url` (`/api/:url/delete`)
+    pub async fn delete(&self, url: &str, password: String) -> Result<()> {
+        self.post(
+            &format!("/{}/delete", url),
+            &PasteDelete {
+                password,
+                assertion: None,
+            },
+        )
+        .await
+    }
+}
@@ -0,0 +1,268 @@
+//! Structured data pastes (JSON, YAML and TOML, see [`crate::database::ServerOptions::structured_data`])
+//!
+//! Data can be queried with a JSON pointer (`/items/0/name`) or a subset of JSONPath
+//! (`$.items[0].name`, with `['key']`, `[*]`, `.*` and `..key` for recursive descent), and
+//! is rendered as a tree of collapsible `<details>` elements marked up with `dt-` prefixed
+//! classes so it can be themed with a stylesheet.
+use serde_json::Value;
+
+/// Longest path accepted by [`query`]
+pub const MAX_PATH_LENGTH: usize = 500;
+/// Depth of the rendered tree below which objects and arrays start collapsed
+const OPEN_DEPTH: usize = 2;
+
+/// A step of a JSONPath
+enum Step {
+    /// A key of an object
+    Key(String),
+    /// An index of an array (negative indexes count from the end)
+    Index(i64),
+    /// Every value of an object or array
+    Wildcard,
+    /// A key of an object at any depth
+    Descendant(String),
+}
+
+/// Parse structured `content` written in `language` (`json`, `yaml`/`yml` or `toml`)
+///
+/// ## Returns:
+/// * `None` if the language isn't a data language or the content doesn't parse
+pub fn parse(content: &str, language: &str) -> Option<Value> {
+    match language.to_lowercase().as_str() {
+        "json" => serde_json::from_str(content.trim()).ok(),
+        "yaml" | "yml" => serde_yaml::from_str(content).ok(),
+        "toml" => toml::from_str(content).ok(),
+        _ => None,
+    }
+}
+
+/// Get every value in `value` matched by `path` (a JSON pointer or JSONPath, see the module docs)
+///
+/// ## Returns:
+/// * `None` if the path isn't valid
+pub fn query(value: &Value, path: &str) -> Option<Vec<Value>> {
+    if path.len() > MAX_PATH_LENGTH {
+        return None;
+    }
+
+    // json pointer
+    if path.is_empty() | path.starts_with('/') {
+        return Some(value.pointer(path).cloned().into_iter().collect());
+    }
+
+    // jsonpath
+    let mut matched: Vec<&Value> = vec![value];
+
+    for step in parse_path(path)? {
+        let mut next: Vec<&Value> = Vec::new();
+
+        for value in matched {
+            match (&step, value) {
+                (Step::Key(key), Value::Object(map)) => next.extend(map.get(key)),
+                (Step::Index(index), Value::Array(items)) => {
+                    let index = if *index < 0 {
+                        items.len() as i64 + index
+                    } else {
+                        *index
+                    };
+
+                    if index >= 0 {
+                        next.extend(items.get(index as usize));
+                    }
+                }
+                (Step::Wildcard, Value::Object(map)) => next.extend(map.values()),
+                (Step::Wildcard, Value::Array(items)) => next.extend(items.iter()),
+                (Step::Descendant(key), value) => descendants(value, key, &mut next),
+                _ => (),
+            }
+        }
+
+        matched = next;
+    }
+
+    Some(matched.into_iter().cloned().collect())
+}
+
+/// Push every value of `key` in `value` (at any depth) to `out`
+fn descendants<'a>(value: &'a Value, key: &str, out: &mut Vec<&'a Value>) {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map {
+                if (k == key) | (key == "*") {
+                    out.push(v);
+                }
+
+                descendants(v, key, out);
+            }
+        }
+        Value::Array(items) => {
+            for v in items {
+                if key == "*" {
+                    out.push(v);
+                }
+
+                descendants(v, key, out);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Split a JSONPath into its steps
+///
+/// ## Returns:
+/// * `None` if the path isn't valid
+fn parse_path(path: &str) -> Option<Vec<Step>> {
+    let chars: Vec<char> = path.chars().collect();
+    let mut steps: Vec<Step> = Vec::new();
+    let mut i: usize = 0;
+
+    if chars.first() == Some(&'$') {
+        i += 1;
+    }
+
+    while i < chars.len() {
+        match chars[i] {
+            '.' => {
+                let descendant = chars.get(i + 1) == Some(&'.');
+                i += if descendant { 2 } else { 1 };
+
+                let start = i;
+
+                while (i < chars.len()) && (chars[i] != '.') && (chars[i] != '[') {
+                    i += 1;
+                }
+
+                let key: String = chars[start..i].iter().collect();
+
+                if key.is_empty() {
+                    return None;
+                }
+
+                steps.push(if descendant {
+                    Step::Descendant(key)
+                } else if key == "*" {
+                    Step::Wildcard
+                } else {
+                    Step::Key(key)
+                });
+            }
+            '[' => {
+                let end = i + chars[i..].iter().position(|c| *c == ']')?;
+                let inner: String = chars[i + 1..end].iter().collect();
+                let inner = inner.trim();
+
+                steps.push(if inner == "*" {
+                    Step::Wildcard
+                } else if (inner.len() >= 2)
+                    && ((inner.starts_with('\'') && inner.ends_with('\''))
+                        | (inner.starts_with('"') && inner.ends_with('"')))
+                {
+                    Step::Key(inner[1..inner.len() - 1].to_string())
+                } else {
+                    Step::Index(inner.parse::<i64>().ok()?)
+                });
+
+                i = end + 1;
+            }
+            _ => {
+                // paths can leave out the leading "$."
+                if !steps.is_empty() | (i != 0) {
+                    return None;
+                }
+
+                let start = i;
+
+                while (i < chars.len()) && (chars[i] != '.') && (chars[i] != '[') {
+                    i += 1;
+                }
+
+                steps.push(Step::Key(chars[start..i].iter().collect()));
+            }
+        }
+    }
+
+    Some(steps)
+}
+
+/// Render `value` as a collapsible tree
+pub fn render(value: &Value) -> String {
+    let mut out = String::from("<div class=\"dt\">");
+    push_value(&mut out, value, 0);
+    out.push_str("</div>");
+    out
+}
+
+/// Push the HTML of `value` to `out`
+fn push_value(out: &mut String, value: &Value, depth: usize) {
+    match value {
+        Value::Object(map) => push_container(
+            out,
+            depth,
+            ('{', '}'),
+            map.len(),
+            map.iter().map(|(k, v)| (Some(k.as_str()), v)),
+        ),
+        Value::Array(items) => push_container(
+            out,
+            depth,
+            ('[', ']'),
+            items.len(),
+            items.iter().map(|v| (None, v)),
+        ),
+        Value::String(s) => out.push_str(&format!(
+            "<span class=\"dt-string\">{}</span>",
+            escape(&serde_json::to_string(s).unwrap())
+        )),
+        Value::Number(n) => out.push_str(&format!("<span class=\"dt-number\">{}</span>", n)),
+        Value::Bool(b) => out.push_str(&format!("<span class=\"dt-bool\">{}</span>", b)),
+        Value::Null => out.push_str("<span class=\"dt-null\">null</span>"),
+    }
+}
+
+/// Push the HTML of an object or array to `out`
+fn push_container<'a, I: Iterator<Item = (Option<&'a str>, &'a Value)>>(
+    out: &mut String,
+    depth: usize,
+    brackets: (char, char),
+    len: usize,
+    entries: I,
+) {
+    if len == 0 {
+        out.push_str(&format!("{}{}", brackets.0, brackets.1));
+        return;
+    }
+
+    out.push_str(&format!(
+        "<details class=\"dt-node\"{}><summary>{}<span class=\"dt-count\">{} {}{}</span></summary>",
+        if depth < OPEN_DEPTH { " open" } else { "" },
+        brackets.0,
+        len,
+        if brackets.0 == '{' { "key" } else { "item" },
+        if len == 1 { "" } else { "s" }
+    ));
+
+    for (key, value) in entries {
+        out.push_str("<div class=\"dt-entry\">");
+
+        if let Some(key) = key {
+            out.push_str(&format!(
+                "<span class=\"dt-key\">{}</span>: ",
+                escape(&serde_json::to_string(key).unwrap())
+            ));
+        }
+
+        push_value(out, value, depth + 1);
+        out.push_str("</div>");
+    }
+
+    out.push_str(&format!("</details>{}", brackets.1));
+}
+
+/// Escape `text` for HTML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
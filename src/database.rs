@@ -1,21 +1,268 @@
 use crate::model::{PasteCreate, PasteError, Paste, PasteMetadata, Document, DocumentCreate};
+#[cfg(feature = "fetch")]
+use crate::model::PasteCreateFromUrl;
+#[cfg(feature = "binary")]
+use crate::model::PasteCreateBinary;
+use crate::model::{NamespaceUsage, FieldError, IngestToken, IngestTokenCreate, PasteStats};
+use crate::model::{Invite, InviteCreate, PasteListing, SearchOptions, SearchResult};
+use crate::model::{Collection, CollectionCreate, CollectionNavigation};
+use crate::model::{Suggestion, SuggestionCreate, SuggestionDiff};
+use crate::model::{Annotation, AnnotationCreate, BlockOutput, Download};
+#[cfg(feature = "links")]
+use crate::model::LinkStatus;
+use crate::model::LinkReport;
+use crate::model::{Announcement, AnnouncementCreate, PasteTranslation, PinnedPaste};
+use crate::model::{AuditRecord, Checkpoint, DoctorCheck, DoctorReport, Dump, DumpTable};
+use crate::model::{ImportConflict, ImportResult, ImportedPaste, PasteChecksums, PasteTable};
+use crate::model::{Revision, Rotation, RotationCreate};
+use crate::model::{ModerationAction, ModerationFilter, ModerationJob};
+#[cfg(feature = "scanning")]
+use crate::model::ScanReport;
+#[cfg(feature = "signed_urls")]
+use crate::model::SignedUrl;
+use crate::model::{EditLink, HighlightLanguage, InstanceLimits, InstanceMeta, WebAuthnAssertion};
+#[cfg(feature = "webauthn")]
+use crate::model::{WebAuthnCredential, WebAuthnCredentialCreate};
+use crate::{audit, data, detect, diff, excerpt, front_matter, logs, normalize, search, slug};
+use crate::{stack_trace, table, validation, watermark};
+#[cfg(feature = "runners")]
+use crate::notebook;
+#[cfg(feature = "links")]
+use crate::links;
+#[cfg(feature = "webauthn")]
+use crate::webauthn;
+#[cfg(feature = "plugins")]
+use crate::plugins;
+#[cfg(feature = "scripting")]
+use crate::scripting;
+#[cfg(feature = "archive")]
+use crate::archive;
+#[cfg(feature = "binary")]
+use crate::binary;
+#[cfg(feature = "checksums")]
+use crate::checksum;
+#[cfg(feature = "scanning")]
+use crate::scanner;
+#[cfg(feature = "signed_urls")]
+use crate::signing;
+#[cfg(feature = "highlight")]
+use crate::highlight;
 
 use dorsal::utility;
+use futures_util::Stream;
 use dorsal::query as sqlquery;
 use dorsal::db::special::auth_db::{FullUser, UserMetadata};
-use serde::{Serialize, de::DeserializeOwned};
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+use serde_json::Value;
+use sqlx::Row;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 
 pub type Result<T> = std::result::Result<T, PasteError>;
 
+/// Number of pastes on each page of `/api/pastes` and `/api/owner/:username/pastes`
+pub const PAGE_SIZE: usize = 25;
+
+/// Number of revisions kept for each rotated paste (older revisions are removed)
+pub const MAX_REVISIONS: usize = 50;
+
+/// Conditions matching pastes which could be returned in search results (see
+/// [`search::is_searchable`]), checked against the JSON metadata of `se_pastes`
+const PUBLIC_CONDITIONS: [&str; 3] = [
+    "(\"metadata\" LIKE '%\"view_password\":\"\"%' OR \"metadata\" NOT LIKE '%\"view_password\"%')",
+    "\"metadata\" NOT LIKE '%\"noindex\":true%'",
+    "\"metadata\" NOT LIKE '%\"sensitive\":true%'",
+];
+
+/// Format of dumps made by [`Database::dump`]
+pub const DUMP_FORMAT: &str = "pastemd-v1";
+
+/// Tables copied by [`Database::dump`] (tables which don't exist are skipped)
+const DUMP_TABLES: [&str; 23] = [
+    "se_pastes",
+    "se_settings",
+    "se_views",
+    "se_documents",
+    "se_ingest_tokens",
+    "se_invites",
+    "se_collections",
+    "se_collection_pastes",
+    "se_suggestions",
+    "se_annotations",
+    "se_downloads",
+    "se_paste_translations",
+    "se_link_reports",
+    "se_credentials",
+    "se_expirations",
+    "se_tos_acceptances",
+    "se_archive",
+    "se_activity",
+    "se_scans",
+    "se_rotations",
+    "se_revisions",
+    "se_paste_ips",
+    "se_view_countries",
+];
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum ViewMode {
     /// Only authenticated users can count as a paste view and only once
     AuthenticatedOnce,
     /// Anybody can count as a paste view multiple times;
-    /// views are only stored in redis when using this mode
+    /// views are counted in redis and written to "se_pastes" by [`Database::flush_views`]
     OpenMultiple,
 }
 
+/// Limits applied to the [`Document`]s of a namespace
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DocumentLimits {
+    /// Maximum number of documents in the namespace (`0` for unlimited)
+    pub max_documents: usize,
+    /// Maximum length of a single document's content in bytes (`0` for unlimited)
+    pub max_content_bytes: usize,
+}
+
+impl Default for DocumentLimits {
+    fn default() -> Self {
+        Self {
+            max_documents: 0,
+            max_content_bytes: 200_000,
+        }
+    }
+}
+
+/// JSON Schemas the [`Document`]s of a namespace must match
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct DocumentSchema {
+    /// Schema for document content (content must be JSON if this is set)
+    #[serde(default)]
+    pub content: Option<Value>,
+    /// Schema for document metadata
+    #[serde(default)]
+    pub metadata: Option<Value>,
+}
+
+/// Where [`crate::audit`] records are written
+#[derive(Clone, Debug, PartialEq)]
+pub enum AuditSink {
+    /// Append to a file (one JSON record per line)
+    File(String),
+    /// Send to the local syslog daemon (`/dev/log`, unix only)
+    Syslog,
+    /// `POST` every record to a URL (requires the `audit` feature)
+    #[cfg(feature = "audit")]
+    Http(String),
+}
+
+/// Options for the mutation log (see [`crate::audit`])
+#[derive(Clone, Debug)]
+pub struct AuditLogOptions {
+    pub sink: AuditSink,
+    /// If records should include the paste's full content (only its length and hash otherwise)
+    pub include_content: bool,
+    /// Number of records waiting to be written before new records are dropped
+    pub buffer: usize,
+}
+
+/// Options for pinning paste snapshots to IPFS
+#[derive(Clone, Debug)]
+pub struct IpfsOptions {
+    /// Base URL of an IPFS (Kubo-compatible) HTTP API, e.g. `https://ipfs.example.com:5001`
+    pub api_url: String,
+    /// Bearer token sent to the pinning service (can be empty)
+    pub token: String,
+}
+
+/// Write-time content normalization (see [`crate::normalize`])
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Normalization {
+    /// Convert `\r\n` and `\r` line endings to `\n`
+    pub line_endings: bool,
+    /// Strip spaces and tabs from the end of every line
+    pub trim_trailing_whitespace: bool,
+    /// Maximum number of consecutive blank lines (`0` for unlimited)
+    pub max_blank_lines: usize,
+    /// Replace invalid UTF-8 in uploaded and fetched content instead of rejecting it
+    pub replace_invalid_utf8: bool,
+}
+
+/// Renders paste content to HTML (e.g. Markdown with sanitizing and highlighting)
+#[derive(Clone)]
+pub struct Renderer(pub Arc<dyn Fn(&str) -> String + Send + Sync>);
+
+impl Renderer {
+    /// The built-in Markdown renderer (requires the `markdown` feature, see [`crate::markdown`])
+    #[cfg(feature = "markdown")]
+    pub fn markdown() -> Self {
+        Self(Arc::new(crate::markdown::render))
+    }
+}
+
+impl std::fmt::Debug for Renderer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Renderer")
+    }
+}
+
+/// What a country is blocked from doing (see [`ServerOptions::geo_rules`])
+#[derive(Clone, Debug, PartialEq)]
+pub enum GeoRule {
+    /// Block creating pastes
+    BlockCreate,
+    /// Block viewing pastes
+    BlockView,
+    /// Block creating and viewing pastes
+    BlockAll,
+}
+
+/// External service which runs code blocks for a language
+///
+/// Runners receive `{ "language": "...", "code": "..." }` as a `POST` body and must
+/// respond with `{ "stdout": "...", "stderr": "...", "exit_code": 0 }`.
+#[derive(Clone, Debug)]
+pub struct RunnerOptions {
+    /// URL the code is sent to
+    pub url: String,
+    /// Milliseconds to wait for the runner before giving up
+    pub timeout: u64,
+}
+
+/// Options for checking outbound links in pastes (see [`Database::spawn_link_checker`])
+#[derive(Clone, Debug)]
+pub struct LinkCheckOptions {
+    /// Milliseconds between checks of the same paste's links
+    pub interval: u128,
+    /// Milliseconds to wait for a single link before giving up
+    pub timeout: u64,
+}
+
+/// Malware scanner binary pastes are sent to after they're written (see [`Database::scan_paste_by_url`])
+#[derive(Clone, Debug)]
+pub struct ScannerOptions {
+    /// `tcp://host:port` of clamd, or the url of an HTTP scanner (see [`crate::scanner`])
+    pub url: String,
+    /// Milliseconds to wait for a scan before giving up
+    pub timeout: u64,
+}
+
+/// SQLite options for replicating the database file (e.g. with litestream or snapshot backups)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SqliteOptions {
+    /// Use write-ahead logging (`PRAGMA journal_mode=WAL`)
+    pub wal: bool,
+    /// Milliseconds between passive WAL checkpoints (see [`Database::spawn_checkpointer`], `0` to disable)
+    pub checkpoint_interval: u64,
+}
+
+/// Relying party used to verify passkeys (see [`crate::model::PasteMetadata::high_security`])
+#[derive(Clone, Debug)]
+pub struct WebAuthnOptions {
+    /// Relying party id, the site's domain (e.g. `paste.example.com`)
+    pub rp_id: String,
+    /// Origin assertions must be made from (e.g. `https://paste.example.com`)
+    pub origin: String,
+}
+
 #[derive(Clone, Debug)]
 pub struct ServerOptions {
     /// If pastes can require a password to be viewed
@@ -26,8 +273,116 @@ pub struct ServerOptions {
     pub paste_ownership: bool,
     /// If [`Document`]s are allowed (needed for external plugins)
     pub document_store: bool,
+    /// Default [`DocumentLimits`] for every namespace
+    pub document_limits: DocumentLimits,
+    /// [`DocumentLimits`] overrides for specific namespaces
+    pub namespace_limits: HashMap<String, DocumentLimits>,
+    /// [`DocumentSchema`]s registered for specific namespaces
+    pub document_schemas: HashMap<String, DocumentSchema>,
     /// View mode options
     pub view_mode: ViewMode,
+    /// IPFS pinning service (requires the `ipfs` feature)
+    pub ipfs: Option<IpfsOptions>,
+    /// If pastes can be created with ingest tokens (`/api/ingest/:token`)
+    pub ingest: bool,
+    /// Maximum number of pastes an ingest token can create per minute (`0` for unlimited)
+    pub ingest_rate_limit: usize,
+    /// If pastes can be created from remote URLs (`/api/new/from-url`, requires the `fetch` feature)
+    pub from_url: bool,
+    /// Words masked by [`Database::mask_content`] (matched case-insensitively as whole words)
+    pub masked_words: Vec<String>,
+    /// Path to a MaxMind GeoLite2 Country database for per-country view counts (requires the `geoip` feature, empty to disable)
+    pub geoip_database: String,
+    /// Maximum number of requests each API route handles at once, extra requests are shed with a 503 (`0` for unlimited)
+    pub concurrency_limit: usize,
+    /// `Cache-Control` policies for successful API responses, keyed by route as written in [`crate::routing::api::routes`] (e.g. `"/:url"` to `"private, max-age=60"`)
+    pub cache_control: HashMap<String, String>,
+    /// If Markdown can be linted through `/api/lint`
+    pub lint: bool,
+    /// Longest line allowed by the `long_line` lint rule (`0` to disable it)
+    pub lint_max_line_length: usize,
+    /// If YAML (`---`) or TOML (`+++`) front matter should be read from paste content into its metadata
+    pub front_matter: bool,
+    /// If users can group their pastes into ordered collections
+    pub collections: bool,
+    /// If `{{include:url}}` directives in paste content should be replaced by the content of other public pastes when rendering
+    pub includes: bool,
+    /// If viewers can suggest edits to pastes for their owner to accept or reject
+    pub suggestions: bool,
+    /// If viewers can annotate line ranges of pastes
+    pub annotations: bool,
+    /// External code runners by language, used to run code blocks with a `run` attribute (requires the `runners` feature)
+    pub runners: HashMap<String, RunnerOptions>,
+    /// If pastes with a view password should be watermarked with a unique download id every time they're fetched through the API
+    pub watermark: bool,
+    /// Url of a terms of service paste anonymous users must accept (`accept_tos`) before creating pastes (empty to disable)
+    pub tos_paste: String,
+    /// Header containing the client's IP address when running behind a proxy, e.g. `X-Forwarded-For` (empty to use the connection's address)
+    pub real_ip_header: String,
+    /// Country-based access rules by ISO country code (requires the `geoip` feature and [`ServerOptions::geoip_database`])
+    pub geo_rules: HashMap<String, GeoRule>,
+    /// IP addresses [`ServerOptions::geo_rules`] are never applied to
+    pub geo_allowlist: Vec<String>,
+    /// Renders paste content to HTML for `/api/preview` (should be the same renderer used for stored pastes)
+    pub renderer: Option<Renderer>,
+    /// Maximum number of previews a single IP address can render per minute (`0` for unlimited)
+    pub preview_rate_limit: usize,
+    /// Normalization applied to paste content when it's written
+    pub normalization: Normalization,
+    /// If pastes can have translated variants of their content
+    pub translations: bool,
+    /// Number of sentences kept in the plaintext excerpt stored with each paste (`0` to not store excerpts)
+    pub excerpt_sentences: usize,
+    /// Dead-link checking for outbound links in pastes (requires the `links` feature)
+    pub link_check: Option<LinkCheckOptions>,
+    /// If pastes have minimap thumbnails at `/api/:url/thumb.png` (requires the `thumbnails` feature)
+    pub thumbnails: bool,
+    /// Passkeys protecting high security pastes (requires the `webauthn` feature)
+    pub webauthn: Option<WebAuthnOptions>,
+    /// If pastes can be shared with signed edit links (`/api/:url/edit?t=...`) instead of their password
+    pub edit_links: bool,
+    /// Paths of WASM plugins to load, run in order (requires the `plugins` feature, see [`crate::plugins`])
+    pub plugins: Vec<String>,
+    /// Directory of Rhai rule scripts checked when pastes are created or edited (requires the `scripting` feature, see [`crate::scripting`], empty to disable)
+    pub scripts_dir: String,
+    /// SQLite journaling and checkpointing (only used with the `sqlite` database type)
+    pub sqlite: SqliteOptions,
+    /// If pastes can expire (see [`Database::spawn_expiry_cleaner`])
+    pub expiration: bool,
+    /// If managers can create invites allowing guests to create pastes with boosted limits (`/api/invites/new`)
+    pub invites: bool,
+    /// If pastes can only be created by logged in users or with an invite
+    pub invite_only: bool,
+    /// If public pastes can be searched through `/api/search` (see [`crate::search`])
+    pub search: bool,
+    /// Milliseconds a paste must go without views before its content is compressed into cold storage (requires the `archive` feature, `0` to disable)
+    pub archive_after: u128,
+    /// If pastes with a `language` other than Markdown should be rendered as syntax highlighted code by [`Database::get_rendered`] (requires the `highlight` feature)
+    pub highlight: bool,
+    /// Mutation log of paste creations, edits and deletions (see [`crate::audit`])
+    pub audit_log: Option<AuditLogOptions>,
+    /// Maximum number of pastes a single IP address can create per minute through `/api/new`, `/api/new/from-url`, `/api/new/binary` and `/api/import` (`0` for unlimited)
+    pub create_rate_limit: usize,
+    /// Maximum number of edits a single IP address can make per minute through `/api/:url/edit`, `/api/:url/metadata` and `/api/:url/translations/:lang` (`0` for unlimited)
+    pub edit_rate_limit: usize,
+    /// If stack traces (Rust panics, Python tracebacks and Java exceptions) should be rendered with collapsed frames and highlighted exceptions by [`Database::get_rendered`]
+    pub stack_traces: bool,
+    /// If log files should be rendered with colorized levels, level filtering attributes and repeated lines collapsed by [`Database::get_rendered`]
+    pub log_files: bool,
+    /// If CSV and TSV pastes should be rendered as sortable tables by [`Database::get_rendered`] (and parsed by `/api/:url/table`)
+    pub tables: bool,
+    /// If JSON, YAML and TOML pastes should be rendered as collapsible trees by [`Database::get_rendered`] (and queried by `/api/:url/query`)
+    pub structured_data: bool,
+    /// If binary pastes can be created through `/api/new/binary` and downloaded from `/api/:url/binary` (requires the `binary` feature)
+    pub binary_pastes: bool,
+    /// If logged in users can import pastes (e.g. from `/api/owner/:username/export`) through `/api/import`
+    pub imports: bool,
+    /// Malware scanner binary pastes are sent to after they're written (requires the `scanning` feature, `None` to disable)
+    pub scanner: Option<ScannerOptions>,
+    /// If pastes can have their content replaced from a url or document namespace on a schedule through `/api/:url/rotation` (previous content is kept as revisions)
+    pub rotations: bool,
+    /// If the IP addresses pastes are created from are recorded (hashed) so pastes can be moderated in bulk through `/api/admin/moderation`
+    pub moderation: bool,
 }
 
 impl ServerOptions {
@@ -38,7 +393,66 @@ impl ServerOptions {
             guppy: true,
             paste_ownership: true,
             document_store: true,
+            document_limits: DocumentLimits::default(),
+            namespace_limits: HashMap::new(),
+            document_schemas: HashMap::new(),
             view_mode: ViewMode::OpenMultiple,
+            ipfs: None,
+            ingest: true,
+            ingest_rate_limit: 60,
+            from_url: true,
+            masked_words: Vec::new(),
+            geoip_database: String::new(),
+            concurrency_limit: 0,
+            cache_control: HashMap::new(),
+            lint: true,
+            lint_max_line_length: 120,
+            front_matter: true,
+            collections: true,
+            includes: true,
+            suggestions: true,
+            annotations: true,
+            runners: HashMap::new(),
+            watermark: true,
+            tos_paste: String::new(),
+            real_ip_header: String::new(),
+            geo_rules: HashMap::new(),
+            geo_allowlist: Vec::new(),
+            renderer: None,
+            preview_rate_limit: 60,
+            normalization: Normalization {
+                line_endings: true,
+                trim_trailing_whitespace: true,
+                max_blank_lines: 0,
+                replace_invalid_utf8: true,
+            },
+            translations: true,
+            excerpt_sentences: 2,
+            link_check: None,
+            thumbnails: true,
+            webauthn: None,
+            edit_links: true,
+            plugins: Vec::new(),
+            scripts_dir: String::new(),
+            sqlite: SqliteOptions::default(),
+            expiration: true,
+            invites: true,
+            invite_only: false,
+            search: true,
+            archive_after: 0,
+            highlight: true,
+            audit_log: None,
+            create_rate_limit: 0,
+            edit_rate_limit: 0,
+            stack_traces: true,
+            log_files: true,
+            tables: true,
+            structured_data: true,
+            binary_pastes: true,
+            imports: true,
+            scanner: None,
+            rotations: true,
+            moderation: true,
         }
     }
 }
@@ -50,9 +464,416 @@ impl Default for ServerOptions {
             guppy: false,
             paste_ownership: false,
             document_store: false,
+            document_limits: DocumentLimits::default(),
+            namespace_limits: HashMap::new(),
+            document_schemas: HashMap::new(),
             view_mode: ViewMode::OpenMultiple,
+            ipfs: None,
+            ingest: false,
+            ingest_rate_limit: 60,
+            from_url: false,
+            masked_words: Vec::new(),
+            geoip_database: String::new(),
+            concurrency_limit: 0,
+            cache_control: HashMap::new(),
+            lint: false,
+            lint_max_line_length: 120,
+            front_matter: false,
+            collections: false,
+            includes: false,
+            suggestions: false,
+            annotations: false,
+            runners: HashMap::new(),
+            watermark: false,
+            tos_paste: String::new(),
+            real_ip_header: String::new(),
+            geo_rules: HashMap::new(),
+            geo_allowlist: Vec::new(),
+            renderer: None,
+            preview_rate_limit: 60,
+            normalization: Normalization::default(),
+            translations: false,
+            excerpt_sentences: 2,
+            link_check: None,
+            thumbnails: false,
+            webauthn: None,
+            edit_links: false,
+            plugins: Vec::new(),
+            scripts_dir: String::new(),
+            sqlite: SqliteOptions::default(),
+            expiration: false,
+            invites: false,
+            invite_only: false,
+            search: false,
+            archive_after: 0,
+            highlight: false,
+            audit_log: None,
+            create_rate_limit: 0,
+            edit_rate_limit: 0,
+            stack_traces: false,
+            log_files: false,
+            tables: false,
+            structured_data: false,
+            binary_pastes: false,
+            imports: false,
+            scanner: None,
+            rotations: false,
+            moderation: false,
+        }
+    }
+}
+
+/// An invalid environment variable found by [`ServerOptions::from_env`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnvError {
+    /// Name of the variable
+    pub name: String,
+    /// Value of the variable
+    pub value: String,
+    /// What the value should look like
+    pub expected: String,
+}
+
+impl std::fmt::Display for EnvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid value \"{}\" for {}: expected {}",
+            self.value, self.name, self.expected
+        )
+    }
+}
+
+impl std::error::Error for EnvError {}
+
+/// Read and parse `PASTEMD_{name}` (`None` if it isn't set or is empty)
+fn env_var<T: std::str::FromStr>(
+    name: &str,
+    expected: &str,
+) -> std::result::Result<Option<T>, EnvError> {
+    let name = format!("PASTEMD_{}", name);
+
+    let value = match std::env::var(&name) {
+        Ok(v) => v.trim().to_string(),
+        Err(_) => return Ok(None),
+    };
+
+    if value.is_empty() {
+        return Ok(None);
+    }
+
+    match value.parse::<T>() {
+        Ok(v) => Ok(Some(v)),
+        Err(_) => Err(EnvError {
+            name,
+            value,
+            expected: expected.to_string(),
+        }),
+    }
+}
+
+/// Read `PASTEMD_{name}` as `true`/`false` (also accepts `1`/`0`)
+fn env_bool(name: &str) -> std::result::Result<Option<bool>, EnvError> {
+    match env_var::<String>(name, "") {
+        Ok(Some(v)) => match v.to_lowercase().as_str() {
+            "true" | "1" => Ok(Some(true)),
+            "false" | "0" => Ok(Some(false)),
+            _ => Err(EnvError {
+                name: format!("PASTEMD_{}", name),
+                value: v,
+                expected: String::from("true or false"),
+            }),
+        },
+        Ok(None) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Read `PASTEMD_{name}` as a comma separated list
+fn env_list(name: &str) -> Option<Vec<String>> {
+    match env_var::<String>(name, "") {
+        Ok(Some(v)) => Some(
+            v.split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+impl ServerOptions {
+    /// Build options from `PASTEMD_*` environment variables, using the defaults for
+    /// variables which aren't set
+    ///
+    /// Every option is read from `PASTEMD_` followed by its name in uppercase (e.g.
+    /// `PASTEMD_VIEW_PASSWORD=true` for [`ServerOptions::view_password`]). Lists are comma
+    /// separated, and nested options use their parent's name as a prefix (e.g.
+    /// `PASTEMD_SQLITE_WAL`). [`ServerOptions::view_mode`] is `authenticated_once` or
+    /// `open_multiple`, and [`ServerOptions::renderer`] can be set to `markdown`.
+    ///
+    /// Options which map values to keys (e.g. [`ServerOptions::runners`] and
+    /// [`ServerOptions::geo_rules`]) can't be set from the environment.
+    ///
+    /// ## Returns:
+    /// * Result containing the options, or the first invalid variable
+    pub fn from_env() -> std::result::Result<Self, EnvError> {
+        let mut options = Self::default();
+
+        // flags
+        for (name, option) in [
+            ("VIEW_PASSWORD", &mut options.view_password),
+            ("GUPPY", &mut options.guppy),
+            ("PASTE_OWNERSHIP", &mut options.paste_ownership),
+            ("DOCUMENT_STORE", &mut options.document_store),
+            ("INGEST", &mut options.ingest),
+            ("FROM_URL", &mut options.from_url),
+            ("LINT", &mut options.lint),
+            ("FRONT_MATTER", &mut options.front_matter),
+            ("COLLECTIONS", &mut options.collections),
+            ("INCLUDES", &mut options.includes),
+            ("SUGGESTIONS", &mut options.suggestions),
+            ("ANNOTATIONS", &mut options.annotations),
+            ("WATERMARK", &mut options.watermark),
+            ("TRANSLATIONS", &mut options.translations),
+            ("THUMBNAILS", &mut options.thumbnails),
+            ("EDIT_LINKS", &mut options.edit_links),
+            ("EXPIRATION", &mut options.expiration),
+            ("INVITES", &mut options.invites),
+            ("INVITE_ONLY", &mut options.invite_only),
+            ("SEARCH", &mut options.search),
+            ("HIGHLIGHT", &mut options.highlight),
+            ("STACK_TRACES", &mut options.stack_traces),
+            ("LOG_FILES", &mut options.log_files),
+            ("TABLES", &mut options.tables),
+            ("STRUCTURED_DATA", &mut options.structured_data),
+            ("BINARY_PASTES", &mut options.binary_pastes),
+            ("IMPORTS", &mut options.imports),
+            ("ROTATIONS", &mut options.rotations),
+            ("MODERATION", &mut options.moderation),
+            ("SQLITE_WAL", &mut options.sqlite.wal),
+            (
+                "NORMALIZATION_LINE_ENDINGS",
+                &mut options.normalization.line_endings,
+            ),
+            (
+                "NORMALIZATION_TRIM_TRAILING_WHITESPACE",
+                &mut options.normalization.trim_trailing_whitespace,
+            ),
+            (
+                "NORMALIZATION_REPLACE_INVALID_UTF8",
+                &mut options.normalization.replace_invalid_utf8,
+            ),
+        ] {
+            match env_bool(name) {
+                Ok(Some(v)) => *option = v,
+                Ok(None) => (),
+                Err(e) => return Err(e),
+            }
+        }
+
+        // limits
+        for (name, option) in [
+            ("INGEST_RATE_LIMIT", &mut options.ingest_rate_limit),
+            ("CREATE_RATE_LIMIT", &mut options.create_rate_limit),
+            ("EDIT_RATE_LIMIT", &mut options.edit_rate_limit),
+            ("CONCURRENCY_LIMIT", &mut options.concurrency_limit),
+            ("LINT_MAX_LINE_LENGTH", &mut options.lint_max_line_length),
+            ("PREVIEW_RATE_LIMIT", &mut options.preview_rate_limit),
+            ("EXCERPT_SENTENCES", &mut options.excerpt_sentences),
+            (
+                "NORMALIZATION_MAX_BLANK_LINES",
+                &mut options.normalization.max_blank_lines,
+            ),
+            (
+                "DOCUMENT_MAX_DOCUMENTS",
+                &mut options.document_limits.max_documents,
+            ),
+            (
+                "DOCUMENT_MAX_CONTENT_BYTES",
+                &mut options.document_limits.max_content_bytes,
+            ),
+        ] {
+            match env_var::<usize>(name, "a whole number") {
+                Ok(Some(v)) => *option = v,
+                Ok(None) => (),
+                Err(e) => return Err(e),
+            }
+        }
+
+        match env_var::<u128>("ARCHIVE_AFTER", "a number of milliseconds") {
+            Ok(Some(v)) => options.archive_after = v,
+            Ok(None) => (),
+            Err(e) => return Err(e),
+        }
+
+        match env_var::<u64>("SQLITE_CHECKPOINT_INTERVAL", "a number of milliseconds") {
+            Ok(Some(v)) => options.sqlite.checkpoint_interval = v,
+            Ok(None) => (),
+            Err(e) => return Err(e),
+        }
+
+        // text
+        for (name, option) in [
+            ("GEOIP_DATABASE", &mut options.geoip_database),
+            ("TOS_PASTE", &mut options.tos_paste),
+            ("REAL_IP_HEADER", &mut options.real_ip_header),
+            ("SCRIPTS_DIR", &mut options.scripts_dir),
+        ] {
+            if let Ok(Some(v)) = env_var::<String>(name, "") {
+                *option = v;
+            }
+        }
+
+        for (name, option) in [
+            ("MASKED_WORDS", &mut options.masked_words),
+            ("GEO_ALLOWLIST", &mut options.geo_allowlist),
+            ("PLUGINS", &mut options.plugins),
+        ] {
+            if let Some(v) = env_list(name) {
+                *option = v;
+            }
+        }
+
+        if let Ok(Some(v)) = env_var::<String>("VIEW_MODE", "") {
+            options.view_mode = match v.to_lowercase().as_str() {
+                "authenticated_once" => ViewMode::AuthenticatedOnce,
+                "open_multiple" => ViewMode::OpenMultiple,
+                _ => {
+                    return Err(EnvError {
+                        name: String::from("PASTEMD_VIEW_MODE"),
+                        value: v,
+                        expected: String::from("authenticated_once or open_multiple"),
+                    })
+                }
+            };
+        }
+
+        if let Ok(Some(v)) = env_var::<String>("RENDERER", "") {
+            #[cfg(feature = "markdown")]
+            if v == "markdown" {
+                options.renderer = Some(Renderer::markdown());
+            }
+
+            if options.renderer.is_none() {
+                return Err(EnvError {
+                    name: String::from("PASTEMD_RENDERER"),
+                    value: v,
+                    expected: String::from(
+                        "markdown (and the crate built with the `markdown` feature)",
+                    ),
+                });
+            }
+        }
+
+        // nested options
+        if let Ok(Some(api_url)) = env_var::<String>("IPFS_API_URL", "") {
+            options.ipfs = Some(IpfsOptions {
+                api_url,
+                token: env_var::<String>("IPFS_TOKEN", "")
+                    .unwrap_or(None)
+                    .unwrap_or_default(),
+            });
+        }
+
+        match env_var::<u128>("LINK_CHECK_INTERVAL", "a number of milliseconds") {
+            Ok(Some(interval)) => {
+                options.link_check = Some(LinkCheckOptions {
+                    interval,
+                    timeout: match env_var::<u64>("LINK_CHECK_TIMEOUT", "a number of milliseconds")
+                    {
+                        Ok(v) => v.unwrap_or(5000),
+                        Err(e) => return Err(e),
+                    },
+                })
+            }
+            Ok(None) => (),
+            Err(e) => return Err(e),
         }
+
+        if let Ok(Some(url)) = env_var::<String>("SCANNER_URL", "") {
+            options.scanner = Some(ScannerOptions {
+                url,
+                timeout: match env_var::<u64>("SCANNER_TIMEOUT", "a number of milliseconds") {
+                    Ok(v) => v.unwrap_or(30_000),
+                    Err(e) => return Err(e),
+                },
+            });
+        }
+
+        match (
+            env_var::<String>("WEBAUTHN_RP_ID", "").unwrap_or(None),
+            env_var::<String>("WEBAUTHN_ORIGIN", "").unwrap_or(None),
+        ) {
+            (Some(rp_id), Some(origin)) => {
+                options.webauthn = Some(WebAuthnOptions { rp_id, origin })
+            }
+            (Some(rp_id), None) => {
+                return Err(EnvError {
+                    name: String::from("PASTEMD_WEBAUTHN_RP_ID"),
+                    value: rp_id,
+                    expected: String::from("PASTEMD_WEBAUTHN_ORIGIN to be set as well"),
+                })
+            }
+            (None, Some(origin)) => {
+                return Err(EnvError {
+                    name: String::from("PASTEMD_WEBAUTHN_ORIGIN"),
+                    value: origin,
+                    expected: String::from("PASTEMD_WEBAUTHN_RP_ID to be set as well"),
+                })
+            }
+            (None, None) => (),
+        }
+
+        // check options which depend on each other
+        if (options.paste_ownership == true) && (options.guppy == false) {
+            return Err(EnvError {
+                name: String::from("PASTEMD_PASTE_OWNERSHIP"),
+                value: String::from("true"),
+                expected: String::from("false unless PASTEMD_GUPPY is true"),
+            });
+        }
+
+        if (options.invite_only == true) && (options.guppy == false) && (options.invites == false) {
+            return Err(EnvError {
+                name: String::from("PASTEMD_INVITE_ONLY"),
+                value: String::from("true"),
+                expected: String::from(
+                    "false unless PASTEMD_GUPPY or PASTEMD_INVITES is true (nobody could create pastes)",
+                ),
+            });
+        }
+
+        Ok(options)
+    }
+}
+
+/// Validate `instance` against a JSON `schema`
+///
+/// ## Arguments:
+/// * `schema` - the JSON Schema
+/// * `instance` - the value to validate
+/// * `field` - the name of the field being validated (used as the error path prefix)
+fn validate_schema(schema: &Value, instance: &Value, field: &str) -> Result<()> {
+    let compiled = match jsonschema::JSONSchema::compile(schema) {
+        Ok(s) => s,
+        Err(_) => return Err(PasteError::Other),
+    };
+
+    if let Err(errors) = compiled.validate(instance) {
+        return Err(PasteError::Validation(
+            errors
+                .map(|e| FieldError {
+                    field: format!("{}{}", field, e.instance_path),
+                    error: e.to_string(),
+                    min: None,
+                    max: None,
+                })
+                .collect(),
+        ));
     }
+
+    Ok(())
 }
 
 /// Database connector
@@ -61,6 +882,19 @@ pub struct Database {
     pub base: dorsal::StarterDatabase,
     pub auth: dorsal::AuthDatabase,
     pub options: ServerOptions,
+    /// GeoIP database reader (requires the `geoip` feature)
+    #[cfg(feature = "geoip")]
+    pub geoip: Option<std::sync::Arc<maxminddb::Reader<Vec<u8>>>>,
+    /// WASM plugins loaded from [`ServerOptions::plugins`] (requires the `plugins` feature)
+    #[cfg(feature = "plugins")]
+    pub plugins: plugins::Plugins,
+    /// Rules from [`ServerOptions::scripts_dir`] (requires the `scripting` feature)
+    #[cfg(feature = "scripting")]
+    pub scripts: Option<scripting::Scripts>,
+    /// Mutation log from [`ServerOptions::audit_log`]
+    pub audit: Option<audit::AuditLog>,
+    /// Urls whose view count changed since the last [`Database::flush_views`]
+    pub pending_views: Arc<Mutex<HashSet<String>>>,
 }
 
 impl Database {
@@ -70,6 +904,25 @@ impl Database {
         Self {
             base: base.clone(),
             auth: dorsal::AuthDatabase::new(base).await,
+            #[cfg(feature = "geoip")]
+            geoip: if opts1.geoip_database.is_empty() {
+                None
+            } else {
+                match maxminddb::Reader::open_readfile(&opts1.geoip_database) {
+                    Ok(r) => Some(std::sync::Arc::new(r)),
+                    Err(_) => None,
+                }
+            },
+            #[cfg(feature = "plugins")]
+            plugins: plugins::Plugins::load(&opts1.plugins),
+            #[cfg(feature = "scripting")]
+            scripts: if opts1.scripts_dir.is_empty() {
+                None
+            } else {
+                Some(scripting::Scripts::new(&opts1.scripts_dir))
+            },
+            audit: opts1.audit_log.as_ref().map(audit::AuditLog::start),
+            pending_views: Arc::new(Mutex::new(HashSet::new())),
             options: opts1,
         }
     }
@@ -79,6 +932,10 @@ impl Database {
         // create tables
         let c = &self.base.db.client;
 
+        if (self.base.db._type == "sqlite") && (self.options.sqlite.wal == true) {
+            let _ = sqlquery("PRAGMA journal_mode=WAL").execute(c).await;
+        }
+
         let _ = sqlquery(
             "CREATE TABLE IF NOT EXISTS \"se_pastes\" (
                  id             TEXT,
@@ -87,12 +944,39 @@ impl Database {
                  content        TEXT,
                  date_published TEXT,
                  date_edited    TEXT,
-                 metadata       TEXT
+                 metadata       TEXT,
+                 views          TEXT
+             )",
+        )
+        .execute(c)
+        .await;
+
+        // add columns missing from tables created by older versions (fails if they exist)
+        let _ = sqlquery("ALTER TABLE \"se_pastes\" ADD COLUMN \"views\" TEXT")
+            .execute(c)
+            .await;
+
+        let _ = sqlquery("UPDATE \"se_pastes\" SET \"views\" = '0' WHERE \"views\" IS NULL")
+            .execute(c)
+            .await;
+
+        // make sure urls are unique (fails, leaving urls unchecked, if duplicates already exist)
+        self.create_index("se_pastes_url", "se_pastes", &["url"], true)
+            .await;
+
+        let _ = sqlquery(
+            "CREATE TABLE IF NOT EXISTS \"se_settings\" (
+                 name  TEXT,
+                 value TEXT
              )",
         )
         .execute(c)
         .await;
 
+        // make sure settings are only stored once (see `Database::get_or_create_setting`)
+        self.create_index("se_settings_name", "se_settings", &["name"], true)
+            .await;
+
         if self.options.view_mode == ViewMode::AuthenticatedOnce {
             // create table to track views
             let _ = sqlquery(
@@ -103,6 +987,9 @@ impl Database {
             )
             .execute(c)
             .await;
+
+            self.create_index("se_views_url", "se_views", &["url", "username"], false)
+                .await;
         }
 
         if self.options.document_store == true {
@@ -118,388 +1005,8642 @@ impl Database {
             )
             .execute(c)
             .await;
-        }
-    }
-
-    // ...
 
-    /// Get an existing paste by `url`
-    ///
-    /// ## Arguments:
-    /// * `url` - [`String`] of the paste's `url` field
-    pub async fn get_paste_by_url(&self, mut url: String) -> Result<Paste> {
-        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+            self.create_index(
+                "se_documents_namespace",
+                "se_documents",
+                &["namespace", "id"],
+                false,
+            )
+            .await;
+        }
 
-        if url.ends_with("-") {
-            url.pop();
+        if self.options.ingest == true {
+            // create table to store ingest tokens
+            let _ = sqlquery(
+                "CREATE TABLE IF NOT EXISTS \"se_ingest_tokens\" (
+                    id        TEXT,
+                    prefix    TEXT,
+                    expires   TEXT,
+                    max_size  TEXT,
+                    owner     TEXT,
+                    timestamp TEXT,
+                    uses      TEXT,
+                    last_used TEXT
+                )",
+            )
+            .execute(c)
+            .await;
         }
 
-        // check in cache
-        match self.base.cachedb.get(format!("se_paste:{}", url)).await {
-            Some(c) => return Ok(serde_json::from_str::<Paste>(c.as_str()).unwrap()),
-            None => (),
-        };
+        if self.options.invites == true {
+            // create table to store invites
+            let _ = sqlquery(
+                "CREATE TABLE IF NOT EXISTS \"se_invites\" (
+                    id        TEXT,
+                    owner     TEXT,
+                    remaining TEXT,
+                    max_size  TEXT,
+                    expires   TEXT,
+                    timestamp TEXT
+                )",
+            )
+            .execute(c)
+            .await;
+        }
 
-        // pull from database
-        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
-            "SELECT * FROM \"se_pastes\" WHERE \"url\" = ?"
-        } else {
-            "SELECT * FROM \"se_pastes\" WHERE \"url\" = $1"
-        };
+        if self.options.collections == true {
+            // create tables to store collections
+            let _ = sqlquery(
+                "CREATE TABLE IF NOT EXISTS \"se_collections\" (
+                    id        TEXT,
+                    title     TEXT,
+                    owner     TEXT,
+                    timestamp TEXT
+                )",
+            )
+            .execute(c)
+            .await;
 
-        let c = &self.base.db.client;
-        let res = match sqlquery(query)
-            .bind::<&String>(&url.to_lowercase())
-            .fetch_one(c)
-            .await
-        {
-            Ok(p) => self.base.textify_row(p).data,
-            Err(_) => return Err(PasteError::NotFound),
-        };
+            let _ = sqlquery(
+                "CREATE TABLE IF NOT EXISTS \"se_collection_pastes\" (
+                    collection TEXT,
+                    url        TEXT,
+                    position   TEXT
+                )",
+            )
+            .execute(c)
+            .await;
+        }
 
-        // return
-        let paste = Paste {
-            id: res.get("id").unwrap().to_string(),
-            url: res.get("url").unwrap().to_string(),
-            content: res.get("content").unwrap().to_string(),
-            password: res.get("password").unwrap().to_string(),
-            date_published: res.get("date_published").unwrap().parse::<u128>().unwrap(),
-            date_edited: res.get("date_edited").unwrap().parse::<u128>().unwrap(),
-            metadata: match serde_json::from_str(res.get("metadata").unwrap()) {
-                Ok(m) => m,
-                Err(_) => return Err(PasteError::ValueError),
-            },
-        };
+        if self.options.suggestions == true {
+            // create table to store suggested edits
+            let _ = sqlquery(
+                "CREATE TABLE IF NOT EXISTS \"se_suggestions\" (
+                    id        TEXT,
+                    url       TEXT,
+                    content   TEXT,
+                    author    TEXT,
+                    timestamp TEXT
+                )",
+            )
+            .execute(c)
+            .await;
+        }
 
-        // store in cache
-        self.base
-            .cachedb
-            .set(
-                format!("se_paste:{}", url),
-                serde_json::to_string::<Paste>(&paste).unwrap(),
+        if self.options.annotations == true {
+            // create table to store line annotations
+            let _ = sqlquery(
+                "CREATE TABLE IF NOT EXISTS \"se_annotations\" (
+                    id         TEXT,
+                    url        TEXT,
+                    line_start TEXT,
+                    line_end   TEXT,
+                    content    TEXT,
+                    author     TEXT,
+                    timestamp  TEXT
+                )",
             )
+            .execute(c)
             .await;
+        }
 
-        // return
-        Ok(paste)
-    }
+        if self.options.watermark == true {
+            // create table to log watermarked downloads
+            let _ = sqlquery(
+                "CREATE TABLE IF NOT EXISTS \"se_downloads\" (
+                    id        TEXT,
+                    url       TEXT,
+                    username  TEXT,
+                    timestamp TEXT
+                )",
+            )
+            .execute(c)
+            .await;
+        }
 
-    /// Create a new paste
-    ///
-    /// ## Arguments:
-    /// * `props` - [`PasteCreate`]
+        if self.options.translations == true {
+            // create table to store translations
+            let _ = sqlquery(
+                "CREATE TABLE IF NOT EXISTS \"se_paste_translations\" (
+                    url       TEXT,
+                    lang      TEXT,
+                    content   TEXT,
+                    timestamp TEXT
+                )",
+            )
+            .execute(c)
+            .await;
+        }
+
+        if self.options.link_check.is_some() {
+            // create table to store link reports
+            let _ = sqlquery(
+                "CREATE TABLE IF NOT EXISTS \"se_link_reports\" (
+                    url     TEXT,
+                    report  TEXT,
+                    checked TEXT
+                )",
+            )
+            .execute(c)
+            .await;
+        }
+
+        if self.options.scanner.is_some() {
+            // create table to store malware scan results
+            let _ = sqlquery(
+                "CREATE TABLE IF NOT EXISTS \"se_scans\" (
+                    url       TEXT,
+                    status    TEXT,
+                    signature TEXT,
+                    scanned   TEXT
+                )",
+            )
+            .execute(c)
+            .await;
+        }
+
+        if self.options.webauthn.is_some() {
+            // create table to store passkeys
+            let _ = sqlquery(
+                "CREATE TABLE IF NOT EXISTS \"se_credentials\" (
+                    id         TEXT,
+                    username   TEXT,
+                    public_key TEXT,
+                    sign_count TEXT,
+                    timestamp  TEXT
+                )",
+            )
+            .execute(c)
+            .await;
+        }
+
+        if self.options.rotations == true {
+            // create tables to store rotation schedules and replaced content
+            let _ = sqlquery(
+                "CREATE TABLE IF NOT EXISTS \"se_rotations\" (
+                    url      TEXT,
+                    source   TEXT,
+                    period   TEXT,
+                    next_run TEXT
+                )",
+            )
+            .execute(c)
+            .await;
+
+            let _ = sqlquery(
+                "CREATE TABLE IF NOT EXISTS \"se_revisions\" (
+                    url       TEXT,
+                    content   TEXT,
+                    timestamp TEXT
+                )",
+            )
+            .execute(c)
+            .await;
+        }
+
+        if self.options.moderation == true {
+            // create table to find pastes by the IP they were created from
+            let _ = sqlquery(
+                "CREATE TABLE IF NOT EXISTS \"se_paste_ips\" (
+                    url       TEXT,
+                    ip        TEXT,
+                    timestamp TEXT
+                )",
+            )
+            .execute(c)
+            .await;
+        }
+
+        if self.options.expiration == true {
+            // create table to find expired pastes
+            let _ = sqlquery(
+                "CREATE TABLE IF NOT EXISTS \"se_expirations\" (
+                    url     TEXT,
+                    expires TEXT
+                )",
+            )
+            .execute(c)
+            .await;
+        }
+
+        #[cfg(feature = "geoip")]
+        if !self.options.geoip_database.is_empty() {
+            // create table to count views by country (one row per url and country)
+            let _ = sqlquery(
+                "CREATE TABLE IF NOT EXISTS \"se_view_countries\" (
+                    url     TEXT,
+                    country TEXT,
+                    count   TEXT
+                )",
+            )
+            .execute(c)
+            .await;
+
+            // views are counted with an upsert, which needs this
+            self.create_index(
+                "se_view_countries_url_country",
+                "se_view_countries",
+                &["url", "country"],
+                true,
+            )
+            .await;
+        }
+
+        if !self.options.tos_paste.is_empty() {
+            // create table to log terms of service acceptance
+            let _ = sqlquery(
+                "CREATE TABLE IF NOT EXISTS \"se_tos_acceptances\" (
+                    url       TEXT,
+                    timestamp TEXT,
+                    ip        TEXT
+                )",
+            )
+            .execute(c)
+            .await;
+        }
+
+        #[cfg(feature = "archive")]
+        {
+            // create tables to store archived content and find cold pastes
+            let _ = sqlquery(
+                "CREATE TABLE IF NOT EXISTS \"se_archive\" (
+                    url     TEXT,
+                    content TEXT
+                )",
+            )
+            .execute(c)
+            .await;
+
+            let _ = sqlquery(
+                "CREATE TABLE IF NOT EXISTS \"se_activity\" (
+                    url         TEXT,
+                    last_viewed TEXT
+                )",
+            )
+            .execute(c)
+            .await;
+        }
+
+        if self.options.search == true {
+            // create table to search pastes
+            let query: &str = if self.base.db._type == "sqlite" {
+                "CREATE VIRTUAL TABLE IF NOT EXISTS \"se_search\" USING fts5(
+                    url UNINDEXED,
+                    title,
+                    content
+                )"
+            } else if self.base.db._type == "mysql" {
+                "CREATE TABLE IF NOT EXISTS \"se_search\" (
+                    url     TEXT,
+                    title   TEXT,
+                    content LONGTEXT,
+                    FULLTEXT (\"title\", \"content\")
+                )"
+            } else {
+                "CREATE TABLE IF NOT EXISTS \"se_search\" (
+                    url      TEXT,
+                    title    TEXT,
+                    content  TEXT,
+                    document TSVECTOR GENERATED ALWAYS AS (
+                        setweight(to_tsvector('simple', coalesce(title, '')), 'A') ||
+                        setweight(to_tsvector('simple', coalesce(content, '')), 'B')
+                    ) STORED
+                )"
+            };
+
+            let _ = sqlquery(query).execute(c).await;
+
+            if self.base.db._type == "postgres" {
+                let _ = sqlquery(
+                    "CREATE INDEX IF NOT EXISTS \"se_search_document\" ON \"se_search\" USING GIN (\"document\")",
+                )
+                .execute(c)
+                .await;
+            }
+
+            self.index_existing_pastes().await;
+        } else {
+            // pastes written while search is disabled aren't indexed, so rebuild it next time
+            let _ = self.set_setting(String::from("search_indexed"), None).await;
+        }
+
+        if self.options.view_password == true {
+            self.hash_view_passwords().await;
+        }
+
+        // check everything was created, so problems show up now instead of as
+        // `PasteError::Other` responses later
+        for check in self.doctor().await.checks {
+            if check.ok == false {
+                tracing::warn!(
+                    check = %check.name,
+                    remediation = %check.remediation,
+                    "doctor check failed"
+                );
+            }
+        }
+    }
+
+    /// Add every paste to the search index (only runs once after search is enabled)
+    async fn index_existing_pastes(&self) {
+        if self
+            .get_setting(String::from("search_indexed"))
+            .await
+            .is_some()
+        {
+            return;
+        }
+
+        let c = &self.base.db.client;
+        let rows = match sqlquery("SELECT \"url\" FROM \"se_pastes\"")
+            .fetch_all(c)
+            .await
+        {
+            Ok(r) => r,
+            Err(_) => return,
+        };
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "INSERT INTO \"se_search\" (\"url\", \"title\", \"content\") VALUES (?, ?, ?)"
+        } else {
+            "INSERT INTO \"se_search\" (\"url\", \"title\", \"content\") VALUES ($1, $2, $3)"
+        };
+
+        let mut statements: Vec<(&str, Vec<String>)> =
+            vec![("DELETE FROM \"se_search\"", Vec::new())];
+
+        for row in rows {
+            let url = self
+                .base
+                .textify_row(row)
+                .data
+                .get("url")
+                .unwrap()
+                .to_string();
+
+            let paste = match self.get_paste_by_url(url).await {
+                Ok(p) => p,
+                Err(_) => continue,
+            };
+
+            if search::is_searchable(&paste) {
+                statements.push((query, vec![paste.url, paste.metadata.title, paste.content]));
+            }
+        }
+
+        statements.extend(
+            self.set_setting_statements(String::from("search_indexed"), Some(String::from("1"))),
+        );
+
+        if let Err(_) = self.transaction(statements).await {
+            return;
+        }
+
+        // remove from cache
+        self.base
+            .cachedb
+            .remove(String::from("se_setting:search_indexed"))
+            .await;
+    }
+
+    /// Hash view passwords stored in plaintext by older versions (only runs once)
+    ///
+    /// Values already hashed are marked by `view_password_hashed`, so they're never hashed twice.
+    async fn hash_view_passwords(&self) {
+        if self
+            .get_setting(String::from("view_passwords_hashed"))
+            .await
+            .is_some()
+        {
+            return;
+        }
+
+        let c = &self.base.db.client;
+        let rows = match sqlquery("SELECT \"url\", \"metadata\" FROM \"se_pastes\"")
+            .fetch_all(c)
+            .await
+        {
+            Ok(r) => r,
+            Err(_) => return,
+        };
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "UPDATE \"se_pastes\" SET \"metadata\" = ? WHERE \"url\" = ?"
+        } else {
+            "UPDATE \"se_pastes\" SET \"metadata\" = $1 WHERE \"url\" = $2"
+        };
+
+        let mut statements: Vec<(&str, Vec<String>)> = Vec::new();
+        let mut urls: Vec<String> = Vec::new();
+
+        for row in rows {
+            let res = self.base.textify_row(row).data;
+            let url = res.get("url").unwrap().to_string();
+
+            let mut metadata: PasteMetadata =
+                match serde_json::from_str(res.get("metadata").unwrap()) {
+                    Ok(m) => m,
+                    Err(_) => continue,
+                };
+
+            if metadata.view_password.is_empty() | (metadata.view_password_hashed == true) {
+                continue;
+            }
+
+            metadata.view_password = utility::hash(metadata.view_password);
+            metadata.view_password_hashed = true;
+            statements.push((
+                query,
+                vec![serde_json::to_string(&metadata).unwrap(), url.clone()],
+            ));
+            urls.push(url);
+        }
+
+        statements.extend(self.set_setting_statements(
+            String::from("view_passwords_hashed"),
+            Some(String::from("1")),
+        ));
+
+        if let Err(_) = self.transaction(statements).await {
+            return;
+        }
+
+        // remove from cache
+        self.base
+            .cachedb
+            .remove(String::from("se_setting:view_passwords_hashed"))
+            .await;
+
+        for url in urls {
+            self.base.cachedb.remove(format!("se_paste:{}", url)).await;
+        }
+    }
+
+    // ...
+
+    /// Get a paste's content with [`ServerOptions::masked_words`] replaced by asterisks
+    ///
+    /// Meant for rendered views only, the stored content is never changed.
+    ///
+    /// ## Arguments:
+    /// * `paste` - the paste to mask
+    pub fn mask_content(&self, paste: &Paste) -> String {
+        if self.options.masked_words.is_empty() | (paste.metadata.unmasked == true) {
+            return paste.content.clone();
+        }
+
+        let words: Vec<String> = self
+            .options
+            .masked_words
+            .iter()
+            .map(|w| regex::escape(w))
+            .collect();
+
+        let regex = match regex::RegexBuilder::new(&format!("\\b(?:{})\\b", words.join("|")))
+            .case_insensitive(true)
+            .build()
+        {
+            Ok(r) => r,
+            Err(_) => return paste.content.clone(),
+        };
+
+        regex
+            .replace_all(&paste.content, |c: &regex::Captures| {
+                "*".repeat(c[0].chars().count())
+            })
+            .to_string()
+    }
+
+    /// Get a paste's content with `{{include:url}}` directives replaced by the content of the pastes they name
+    ///
+    /// Included pastes are expanded too, up to 5 levels deep. Directives naming a paste which
+    /// doesn't exist, has a view password, or is already being included are left as they are.
+    ///
+    /// ## Arguments:
+    /// * `paste` - the paste to expand
+    pub async fn expand_includes(&self, paste: &Paste) -> String {
+        self.expand_includes_inner(paste.content.clone(), vec![paste.url.clone()])
+            .await
+    }
+
+    fn expand_includes_inner<'a>(
+        &'a self,
+        content: String,
+        stack: Vec<String>,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = String> + Send + 'a>> {
+        Box::pin(async move {
+            if stack.len() > 5 {
+                return content;
+            }
+
+            let regex = regex::Regex::new(r"\{\{include:([^\s{}]+)\}\}").unwrap();
+            let mut out = String::new();
+            let mut last: usize = 0;
+
+            for c in regex.captures_iter(&content) {
+                let directive = c.get(0).unwrap();
+                out.push_str(&content[last..directive.start()]);
+                last = directive.end();
+
+                let mut url = idna::punycode::encode_str(&c[1]).unwrap().to_lowercase();
+
+                if url.ends_with("-") {
+                    url.pop();
+                }
+
+                // check paste
+                if stack.contains(&url) {
+                    out.push_str(directive.as_str());
+                    continue;
+                }
+
+                let paste = match self.get_paste_by_url(url.clone()).await {
+                    Ok(p) => p,
+                    Err(_) => {
+                        out.push_str(directive.as_str());
+                        continue;
+                    }
+                };
+
+                if !paste.metadata.view_password.is_empty() {
+                    out.push_str(directive.as_str());
+                    continue;
+                }
+
+                // include
+                let included = if self.options.front_matter == true {
+                    front_matter::strip(&paste.content).to_string()
+                } else {
+                    paste.content
+                };
+
+                let mut stack = stack.clone();
+                stack.push(url);
+
+                out.push_str(&self.expand_includes_inner(included, stack).await);
+            }
+
+            out.push_str(&content[last..]);
+            out
+        })
+    }
+
+    /// Get the renderer options an existing url has cached HTML for
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste url
+    async fn get_render_options_by_url(&self, url: String) -> Vec<String> {
+        match self.base.cachedb.get(format!("se_renders:{}", url)).await {
+            Some(c) => serde_json::from_str::<Vec<String>>(c.as_str()).unwrap_or_default(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Get a paste's rendered HTML, only calling `render` if it isn't already cached
+    ///
+    /// Cached HTML is tied to a hash of the content it was rendered from, so edited
+    /// pastes (and pastes including an edited paste) are rendered again automatically.
+    /// It's also cached under the options below (see [`Database::get_render_fingerprint`]),
+    /// so changing them renders pastes again too.
+    ///
+    /// Code pastes are highlighted instead of calling `render` when [`ServerOptions::highlight`]
+    /// is enabled, falling back to `render` for languages which aren't known. Stack traces
+    /// are rendered by [`stack_trace::render`] when [`ServerOptions::stack_traces`] is enabled,
+    /// log files by [`logs::render`] when [`ServerOptions::log_files`] is enabled, tables
+    /// by [`table::render`] when [`ServerOptions::tables`] is enabled, and structured data by
+    /// [`data::render`] when [`ServerOptions::structured_data`] is enabled. Binary pastes are
+    /// never rendered, only a notice with a link to download them is.
+    ///
+    /// ## Arguments:
+    /// * `paste` - the paste to render
+    /// * `options` - a key identifying the renderer and its options
+    /// * `render` - renders the paste's content to HTML
+    pub async fn get_rendered<F: FnOnce(&str) -> String>(
+        &self,
+        paste: &Paste,
+        options: &str,
+        render: F,
+    ) -> String {
+        let options = format!("{}:{}", options, self.get_render_fingerprint());
+
+        self.get_cached_render(paste, &options, |content| {
+            if let Some(html) = self.render_binary(paste) {
+                html
+            } else if let Some(html) = self.render_stack_trace(content, &paste.metadata) {
+                html
+            } else if let Some(html) = self.render_log(content, &paste.metadata) {
+                html
+            } else if let Some(html) = self.render_table(content, &paste.metadata, "") {
+                html
+            } else if let Some(html) = self.render_data(content, &paste.metadata) {
+                html
+            } else if let Some(html) = self.highlight(content, &paste.metadata.language) {
+                html
+            } else if self.options.front_matter == true {
+                render(front_matter::strip(content))
+            } else {
+                render(content)
+            }
+        })
+        .await
+    }
+
+    /// Get a key identifying the [`ServerOptions`] which change what [`Database::get_rendered`]
+    /// renders (the names of the enabled ones)
+    fn get_render_fingerprint(&self) -> String {
+        [
+            ("highlight", self.options.highlight),
+            ("stack_traces", self.options.stack_traces),
+            ("log_files", self.options.log_files),
+            ("tables", self.options.tables),
+            ("structured_data", self.options.structured_data),
+            ("front_matter", self.options.front_matter),
+        ]
+        .iter()
+        .filter(|o| o.1 == true)
+        .map(|o| o.0)
+        .collect::<Vec<&str>>()
+        .join(",")
+    }
+
+    /// Get a CSV or TSV paste's rendered HTML with its rows sorted by `sort` (see
+    /// [`Database::get_rendered`])
+    ///
+    /// ## Arguments:
+    /// * `paste` - the paste to render
+    /// * `sort` - the column to sort by (see [`table::parse_sort`])
+    ///
+    /// ## Returns:
+    /// * `None` if the paste isn't a table
+    pub async fn get_rendered_table(&self, paste: &Paste, sort: &str) -> Option<String> {
+        // the delimiter is checked first so a missing table isn't cached
+        if self
+            .get_table_delimiter(&paste.content, &paste.metadata)
+            .is_none()
+        {
+            return None;
+        }
+
+        let html = self
+            .get_cached_render(paste, &format!("table:{}", sort), |content| {
+                self.render_table(content, &paste.metadata, sort)
+                    .unwrap_or_default()
+            })
+            .await;
+
+        Some(html)
+    }
+
+    /// Get a paste's HTML from the cache, only calling `render` (with its content, includes
+    /// expanded) if it isn't already cached
+    ///
+    /// ## Arguments:
+    /// * `paste` - the paste to render
+    /// * `options` - a key identifying the renderer and its options
+    /// * `render` - renders the paste's content to HTML
+    async fn get_cached_render<F: FnOnce(&str) -> String>(
+        &self,
+        paste: &Paste,
+        options: &str,
+        render: F,
+    ) -> String {
+        let content = if self.options.includes == true {
+            self.expand_includes(paste).await
+        } else {
+            paste.content.clone()
+        };
+
+        let hash = utility::hash(format!(
+            "{}\n{}\n{}",
+            paste.metadata.language, paste.metadata.content_type, content
+        ));
+
+        // check in cache
+        if let Some(c) = self
+            .base
+            .cachedb
+            .get(format!("se_render:{}:{}", paste.url, options))
+            .await
+        {
+            if let Some((cached_hash, html)) = c.split_once("\n") {
+                if cached_hash == hash {
+                    return html.to_string();
+                }
+            }
+        }
+
+        // render
+        let html = self.run_render_hook(paste, render(&content));
+
+        // store in cache
+        let mut rendered = self.get_render_options_by_url(paste.url.clone()).await;
+
+        if !rendered.contains(&options.to_string()) {
+            rendered.push(options.to_string());
+
+            self.base
+                .cachedb
+                .set(
+                    format!("se_renders:{}", paste.url),
+                    serde_json::to_string(&rendered).unwrap(),
+                )
+                .await;
+        }
+
+        self.base
+            .cachedb
+            .set(
+                format!("se_render:{}:{}", paste.url, options),
+                format!("{}\n{}", hash, html),
+            )
+            .await;
+
+        // return
+        html
+    }
+
+    /// Highlight code `content` written in `language` (see [`ServerOptions::highlight`])
+    ///
+    /// ## Returns:
+    /// * the highlighted HTML, or `None` if the content should be rendered normally
+    #[cfg(feature = "highlight")]
+    fn highlight(&self, content: &str, language: &str) -> Option<String> {
+        if (self.options.highlight == false) | highlight::is_markdown(language) {
+            return None;
+        }
+
+        highlight::highlight(content, language)
+    }
+
+    /// Render the notice shown in place of a binary paste's content (see [`ServerOptions::binary_pastes`])
+    ///
+    /// ## Returns:
+    /// * the rendered HTML, or `None` if the paste isn't binary
+    #[cfg(feature = "binary")]
+    fn render_binary(&self, paste: &Paste) -> Option<String> {
+        if paste.metadata.content_type != "binary" {
+            return None;
+        }
+
+        Some(binary::render(
+            &paste.url,
+            &paste.metadata.mime_type,
+            paste.metadata.size,
+            &paste.metadata.scan_status,
+        ))
+    }
+
+    /// Get the size of binary `content` after it's decoded (see [`ServerOptions::binary_pastes`])
+    ///
+    /// ## Returns:
+    /// * `PasteError::ValueError` if the content isn't valid base64
+    #[cfg(feature = "binary")]
+    fn get_binary_size(&self, content: &str) -> Result<usize> {
+        if self.options.binary_pastes == false {
+            return Err(PasteError::NotAllowed);
+        }
+
+        match binary::decode(content) {
+            Some(bytes) => Ok(bytes.len()),
+            None => Err(PasteError::ValueError),
+        }
+    }
+
+    /// Render `content` as a stack trace (see [`ServerOptions::stack_traces`])
+    ///
+    /// Pastes are rendered as stack traces if their `content_type` is `stack_trace`, if their
+    /// `language` is one of [`stack_trace::LANGUAGES`], or if they have neither and start
+    /// with a stack trace.
+    ///
+    /// ## Returns:
+    /// * the rendered HTML, or `None` if the content should be rendered normally
+    fn render_stack_trace(&self, content: &str, metadata: &PasteMetadata) -> Option<String> {
+        if self.options.stack_traces == false {
+            return None;
+        }
+
+        let language = metadata.language.to_lowercase();
+
+        if (metadata.content_type == "stack_trace")
+            | stack_trace::LANGUAGES.contains(&language.as_str())
+            | (metadata.content_type.is_empty()
+                && language.is_empty()
+                && stack_trace::is_stack_trace(content))
+        {
+            return stack_trace::render(content);
+        }
+
+        None
+    }
+
+    /// Render `content` as a log file (see [`ServerOptions::log_files`])
+    ///
+    /// Pastes are rendered as log files if their `content_type` is `log`, or if they have
+    /// no `content_type` or `language` and most of their lines look like log entries.
+    ///
+    /// ## Returns:
+    /// * the rendered HTML, or `None` if the content should be rendered normally
+    fn render_log(&self, content: &str, metadata: &PasteMetadata) -> Option<String> {
+        if self.options.log_files == false {
+            return None;
+        }
+
+        if (metadata.content_type == "log")
+            | (metadata.content_type.is_empty()
+                && metadata.language.is_empty()
+                && detect::detect(content).log)
+        {
+            return Some(logs::render(content));
+        }
+
+        None
+    }
+
+    /// Get the delimiter of a CSV or TSV paste (see [`ServerOptions::tables`])
+    ///
+    /// Pastes are tables if their `content_type` or `language` is `csv` or `tsv`, or if
+    /// they have neither and look like a table.
+    fn get_table_delimiter(&self, content: &str, metadata: &PasteMetadata) -> Option<char> {
+        if self.options.tables == false {
+            return None;
+        }
+
+        let content_type = if !metadata.content_type.is_empty() {
+            metadata.content_type.clone()
+        } else if !metadata.language.is_empty() {
+            metadata.language.to_lowercase()
+        } else {
+            detect::detect(content).content_type
+        };
+
+        match content_type.as_str() {
+            "csv" => Some(','),
+            "tsv" => Some('\t'),
+            _ => None,
+        }
+    }
+
+    /// Render `content` as a table sorted by `sort` (see [`ServerOptions::tables`])
+    ///
+    /// ## Returns:
+    /// * the rendered HTML, or `None` if the content should be rendered normally
+    fn render_table(&self, content: &str, metadata: &PasteMetadata, sort: &str) -> Option<String> {
+        let delimiter = self.get_table_delimiter(content, metadata)?;
+        let content = if self.options.front_matter == true {
+            front_matter::strip(content)
+        } else {
+            content
+        };
+
+        let mut table = table::parse(content, delimiter);
+        table::sort(&mut table, sort);
+
+        Some(table::render(&table, sort))
+    }
+
+    /// Parse structured `content` (see [`ServerOptions::structured_data`])
+    ///
+    /// Pastes are structured data if their `language` is `json`, `yaml` or `toml`, or if they
+    /// have no `content_type` or `language` and are JSON (prose can easily be valid YAML, so
+    /// YAML has to be marked).
+    fn get_data(&self, content: &str, metadata: &PasteMetadata) -> Option<Value> {
+        if self.options.structured_data == false {
+            return None;
+        }
+
+        let language = if !metadata.language.is_empty() {
+            metadata.language.as_str()
+        } else if metadata.content_type.is_empty() {
+            "json"
+        } else {
+            return None;
+        };
+
+        data::parse(content, language)
+    }
+
+    /// Render `content` as a tree of structured data (see [`ServerOptions::structured_data`])
+    ///
+    /// ## Returns:
+    /// * the rendered HTML, or `None` if the content should be rendered normally
+    fn render_data(&self, content: &str, metadata: &PasteMetadata) -> Option<String> {
+        let value = self.get_data(content, metadata)?;
+
+        // scalars (e.g. plain text parsed as a YAML string) aren't worth a tree
+        if !value.is_object() && !value.is_array() {
+            return None;
+        }
+
+        Some(data::render(&value))
+    }
+
+    /// Get every value matched by `path` in a structured data paste (see [`data::query`])
+    ///
+    /// ## Arguments:
+    /// * `paste` - the paste
+    /// * `path` - a JSON pointer or JSONPath
+    pub fn query_paste(&self, paste: &Paste, path: &str) -> Result<Vec<Value>> {
+        let value = match self.get_data(&paste.content, &paste.metadata) {
+            Some(v) => v,
+            None => return Err(PasteError::NotAllowed),
+        };
+
+        match data::query(&value, path) {
+            Some(values) => Ok(values),
+            None => Err(PasteError::ValueError),
+        }
+    }
+
+    /// Get a CSV or TSV paste's rows, sorted by `sort` (see [`ServerOptions::tables`])
+    ///
+    /// ## Arguments:
+    /// * `paste` - the paste
+    /// * `sort` - the column to sort by (see [`table::parse_sort`])
+    pub fn get_table(&self, paste: &Paste, sort: &str) -> Result<PasteTable> {
+        let delimiter = match self.get_table_delimiter(&paste.content, &paste.metadata) {
+            Some(d) => d,
+            None => return Err(PasteError::NotAllowed),
+        };
+
+        let content = if self.options.front_matter == true {
+            front_matter::strip(&paste.content)
+        } else {
+            &paste.content
+        };
+
+        let mut table = table::parse(content, delimiter);
+        table::sort(&mut table, sort);
+
+        Ok(table)
+    }
+
+    /// Get the checksums of a paste's raw content (see [`PasteChecksums`])
+    ///
+    /// Pastes written before checksums (or their BLAKE3 checksum) were stored have theirs
+    /// computed on request.
+    #[cfg(feature = "checksums")]
+    pub fn get_checksums(&self, paste: &Paste) -> PasteChecksums {
+        if !paste.metadata.checksums.sha256.is_empty()
+            && !paste.metadata.checksums.blake3.is_empty()
+        {
+            return paste.metadata.checksums.clone();
+        }
+
+        checksum::checksums(&self.get_raw_bytes(&paste.content, &paste.metadata))
+    }
+
+    /// Store the checksums of `content` in `metadata` (see [`PasteChecksums`])
+    #[cfg(feature = "checksums")]
+    fn apply_checksums(&self, content: &str, metadata: &mut PasteMetadata) {
+        metadata.checksums = checksum::checksums(&self.get_raw_bytes(content, metadata));
+    }
+
+    /// Checksums are never stored without the `checksums` feature
+    #[cfg(not(feature = "checksums"))]
+    fn apply_checksums(&self, _content: &str, _metadata: &mut PasteMetadata) {}
+
+    /// Get the bytes a paste's content is downloaded as (binary pastes are decoded)
+    #[cfg(all(feature = "checksums", feature = "binary"))]
+    fn get_raw_bytes(&self, content: &str, metadata: &PasteMetadata) -> Vec<u8> {
+        if metadata.content_type == "binary" {
+            if let Some(bytes) = binary::decode(content) {
+                return bytes;
+            }
+        }
+
+        content.as_bytes().to_vec()
+    }
+
+    /// Content is always downloaded as it's stored without the `binary` feature
+    #[cfg(all(feature = "checksums", not(feature = "binary")))]
+    fn get_raw_bytes(&self, content: &str, _metadata: &PasteMetadata) -> Vec<u8> {
+        content.as_bytes().to_vec()
+    }
+
+    /// Get the [`PasteMetadata::scan_status`] of binary content which was just written
+    fn get_pending_scan_status(&self) -> String {
+        if self.options.scanner.is_some() && cfg!(feature = "scanning") {
+            String::from("pending")
+        } else {
+            String::new()
+        }
+    }
+
+    /// Binary pastes are shown like any other paste without the `binary` feature
+    #[cfg(not(feature = "binary"))]
+    fn render_binary(&self, _paste: &Paste) -> Option<String> {
+        None
+    }
+
+    /// Pastes can't be made binary without the `binary` feature
+    #[cfg(not(feature = "binary"))]
+    fn get_binary_size(&self, _content: &str) -> Result<usize> {
+        Err(PasteError::NotAllowed)
+    }
+
+    /// Code is never highlighted without the `highlight` feature
+    #[cfg(not(feature = "highlight"))]
+    fn highlight(&self, _content: &str, _language: &str) -> Option<String> {
+        None
+    }
+
+    /// Get a shared page fragment (e.g. a sidebar widget), only calling `render` once its
+    /// cached copy is older than `ttl`
+    ///
+    /// ## Arguments:
+    /// * `key` - a key identifying the fragment
+    /// * `ttl` - how long the fragment is cached for, in milliseconds
+    /// * `render` - renders the fragment
+    pub async fn get_fragment<F: FnOnce() -> String>(
+        &self,
+        key: &str,
+        ttl: u128,
+        render: F,
+    ) -> String {
+        let now = utility::unix_epoch_timestamp();
+
+        // check in cache
+        if let Some(c) = self.base.cachedb.get(format!("se_fragment:{}", key)).await {
+            if let Some((expires, html)) = c.split_once("\n") {
+                if expires.parse::<u128>().unwrap_or(0) > now {
+                    return html.to_string();
+                }
+            }
+        }
+
+        // render
+        let html = render();
+
+        // store in cache
+        self.base
+            .cachedb
+            .set(
+                format!("se_fragment:{}", key),
+                format!("{}\n{}", now + ttl, html),
+            )
+            .await;
+
+        // return
+        html
+    }
+
+    /// Get an existing paste by `url`, skipping (and replacing) its cached copy
+    ///
+    /// ## Arguments:
+    /// * `url` - [`String`] of the paste's `url` field
+    pub async fn refresh_paste_by_url(&self, mut url: String) -> Result<Paste> {
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+
+        if url.ends_with("-") {
+            url.pop();
+        }
+
+        self.base.cachedb.remove(format!("se_paste:{}", url)).await;
+        self.get_paste_by_url(url).await
+    }
+
+    /// Remove cache entries by key pattern
+    ///
+    /// Only keys used by pastemd (starting with `se_`) can be removed.
+    /// Permission checks should be done before calling `purge_cache`.
+    ///
+    /// ## Arguments:
+    /// * `patterns` - the keys to remove (a `*` matches any characters)
+    pub async fn purge_cache(&self, patterns: Vec<String>) -> Result<()> {
+        if patterns.is_empty() | (patterns.len() > 50) {
+            return Err(PasteError::ValueError);
+        }
+
+        for pattern in &patterns {
+            if !pattern.starts_with("se_") | pattern.contains(char::is_whitespace) {
+                return Err(PasteError::ValueError);
+            }
+        }
+
+        for pattern in patterns {
+            if pattern.contains('*') {
+                self.base.cachedb.remove_starting_with(pattern).await;
+            } else {
+                self.base.cachedb.remove(pattern).await;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get an existing paste by `url`
+    ///
+    /// ## Arguments:
+    /// * `url` - [`String`] of the paste's `url` field
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn get_paste_by_url(&self, mut url: String) -> Result<Paste> {
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+
+        if url.ends_with("-") {
+            url.pop();
+        }
+
+        // check in cache (invalid or truncated entries are replaced)
+        match self.base.cachedb.get(format!("se_paste:{}", url)).await {
+            Some(c) => match verify_checksum(&c).map(serde_json::from_str::<Paste>) {
+                Some(Ok(p)) => {
+                    tracing::trace!("cache hit");
+
+                    if is_expired(&p) {
+                        return Err(PasteError::NotFound);
+                    }
+
+                    return Ok(p);
+                }
+                _ => tracing::warn!("invalid cache entry"),
+            },
+            None => tracing::trace!("cache miss"),
+        };
+
+        // pull from database
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"se_pastes\" WHERE \"url\" = ?"
+        } else {
+            "SELECT * FROM \"se_pastes\" WHERE \"url\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        let res = match sqlquery(query)
+            .bind::<&String>(&url.to_lowercase())
+            .fetch_one(c)
+            .await
+        {
+            Ok(p) => self.base.textify_row(p).data,
+            Err(_) => return Err(PasteError::NotFound),
+        };
+
+        // return
+        let mut paste = Paste {
+            id: res.get("id").unwrap().to_string(),
+            url: res.get("url").unwrap().to_string(),
+            content: res.get("content").unwrap().to_string(),
+            password: res.get("password").unwrap().to_string(),
+            date_published: res.get("date_published").unwrap().parse::<u128>().unwrap(),
+            date_edited: res.get("date_edited").unwrap().parse::<u128>().unwrap(),
+            metadata: match serde_json::from_str(res.get("metadata").unwrap()) {
+                Ok(m) => m,
+                Err(_) => return Err(PasteError::ValueError),
+            },
+        };
+
+        // restore archived content (pastes can't be empty otherwise)
+        if paste.content.is_empty() {
+            paste.content = match self.restore_archived_by_url(&paste.url).await {
+                Ok(c) => c,
+                Err(e) => return Err(e),
+            };
+        }
+
+        if is_expired(&paste) {
+            return Err(PasteError::NotFound);
+        }
+
+        // store in cache
+        self.base
+            .cachedb
+            .set(
+                format!("se_paste:{}", url),
+                with_checksum(serde_json::to_string::<Paste>(&paste).unwrap()),
+            )
+            .await;
+
+        // return
+        Ok(paste)
+    }
+
+    /// Run `statements` in a single transaction, rolling all of them back if any of them fails
+    ///
+    /// ## Arguments:
+    /// * `statements` - the queries to run and the values bound to each of them, in order
+    #[tracing::instrument(level = "debug", skip_all, fields(statements = statements.len()))]
+    pub async fn transaction(&self, statements: Vec<(&str, Vec<String>)>) -> Result<()> {
+        let mut transaction = match self.base.db.client.begin().await {
+            Ok(t) => t,
+            Err(e) => return Err(query_failed(e)),
+        };
+
+        for (query, values) in statements {
+            let mut query = sqlquery(query);
+
+            for value in values {
+                query = query.bind(value);
+            }
+
+            if let Err(e) = query.execute(&mut *transaction).await {
+                let _ = transaction.rollback().await;
+
+                if is_unique_violation(&e) {
+                    return Err(PasteError::UrlTaken);
+                }
+
+                return Err(query_failed(e));
+            }
+        }
+
+        match transaction.commit().await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(query_failed(e)),
+        }
+    }
+
+    /// Create an index on `table` if it doesn't already exist
+    ///
+    /// ## Arguments:
+    /// * `name` - the name of the index
+    /// * `table` - the table to index
+    /// * `columns` - the indexed columns
+    /// * `unique` - if values in the indexed columns must be unique
+    async fn create_index(&self, name: &str, table: &str, columns: &[&str], unique: bool) {
+        let columns: Vec<String> = columns
+            .iter()
+            .map(|c| {
+                if self.base.db._type == "mysql" {
+                    // mysql can't index TEXT columns without a prefix length
+                    format!("\"{}\"(255)", c)
+                } else {
+                    format!("\"{}\"", c)
+                }
+            })
+            .collect();
+
+        let query = format!(
+            "CREATE {}INDEX {}\"{}\" ON \"{}\" ({})",
+            if unique { "UNIQUE " } else { "" },
+            // mysql doesn't support "IF NOT EXISTS" here, but fails harmlessly if the index exists
+            if self.base.db._type == "mysql" {
+                ""
+            } else {
+                "IF NOT EXISTS "
+            },
+            name,
+            table,
+            columns.join(", ")
+        );
+
+        let c = &self.base.db.client;
+        let _ = sqlquery(&query).execute(c).await;
+    }
+
+    /// Create a new paste
+    ///
+    /// ## Arguments:
+    /// * `props` - [`PasteCreate`]
+    ///
+    /// ## Returns:
+    /// * Result containing a tuple with the unhashed edit password and the paste
+    #[tracing::instrument(level = "debug", skip_all, fields(url = %props.url))]
+    pub async fn create_paste(&self, props: PasteCreate) -> Result<(String, Paste)> {
+        self.create_paste_with_metadata(props, PasteMetadata::default())
+            .await
+    }
+
+    /// Create a new paste starting from `metadata` (so it's written with the paste in one
+    /// statement, instead of being edited in after the paste was created)
+    ///
+    /// Front matter, the excerpt and expiry are still set from `props`.
+    ///
+    /// ## Arguments:
+    /// * `props` - [`PasteCreate`]
+    /// * `metadata` - the paste's initial metadata
+    ///
+    /// ## Returns:
+    /// * Result containing a tuple with the unhashed edit password and the paste
+    async fn create_paste_with_metadata(
+        &self,
+        mut props: PasteCreate,
+        mut metadata: PasteMetadata,
+    ) -> Result<(String, Paste)> {
+        props.url = idna::punycode::encode_str(&props.url)
+            .unwrap()
+            .to_lowercase();
+
+        if props.url.ends_with("-") {
+            props.url.pop();
+        }
+
+        // make sure paste doesn't already exist
+        if let Ok(_) = self.get_paste_by_url(props.url.clone()).await {
+            return Err(PasteError::UrlTaken);
+        }
+
+        // create url if not supplied
+        if props.url.is_empty() {
+            props.url = utility::random_id().chars().take(10).collect();
+        }
+
+        // create random password if not supplied
+        if props.password.is_empty() {
+            props.password = utility::random_id().chars().take(10).collect();
+        }
+
+        // normalize content
+        props.content = normalize::normalize(&props.content, &self.options.normalization).0;
+
+        // check invite
+        let invite = if props.invite.is_empty() {
+            None
+        } else {
+            match self.get_invite(props.invite.clone()).await {
+                Ok(i) => Some(i),
+                Err(PasteError::NotFound) => return Err(PasteError::NotAllowed),
+                Err(e) => return Err(e),
+            }
+        };
+
+        if (self.options.invite_only == true) && props.author.is_empty() && invite.is_none() {
+            return Err(PasteError::NotAllowed);
+        }
+
+        // check lengths and characters used
+        let max_size = match invite {
+            Some(ref i) if i.max_size > validation::MAX_CONTENT_LENGTH => i.max_size,
+            _ => validation::MAX_CONTENT_LENGTH,
+        };
+
+        let mut v = validation::Validator::new();
+        validation::paste(
+            &mut v,
+            "url",
+            &props.url,
+            "content",
+            &props.content,
+            max_size,
+        );
+
+        // check expiry
+        let now = utility::unix_epoch_timestamp();
+        let expires_at = match props.expires_in.as_str() {
+            "" | "never" => props.expires_at,
+            "1h" => now + 60 * 60 * 1000,
+            "1d" => now + 24 * 60 * 60 * 1000,
+            "1w" => now + 7 * 24 * 60 * 60 * 1000,
+            _ => {
+                v.error("expires_in", "invalid_value", None, None);
+                0
+            }
+        };
+
+        if expires_at != 0 {
+            if self.options.expiration == false {
+                return Err(PasteError::NotAllowed);
+            }
+
+            if expires_at <= now {
+                v.error("expires_at", "in_past", None, None);
+            }
+        }
+
+        if let Err(e) = v.finish() {
+            return Err(e);
+        }
+
+        if let Err(e) = self.check_rules("create", &props.url, &props.content, &props.author) {
+            return Err(e);
+        }
+
+        // ...
+        if self.options.front_matter == true {
+            front_matter::apply(&props.content, &mut metadata);
+        }
+
+        // binary pastes have their size instead of an excerpt
+        if (self.options.excerpt_sentences != 0) && (metadata.content_type != "binary") {
+            metadata.excerpt = excerpt::excerpt(&props.content, self.options.excerpt_sentences);
+        }
+
+        self.apply_checksums(&props.content, &mut metadata);
+
+        metadata.expires_at = expires_at;
+
+        let mut paste = Paste {
+            id: utility::random_id(),
+            url: props.url,
+            content: props.content,
+            password: utility::hash(props.password.clone()),
+            date_published: utility::unix_epoch_timestamp(),
+            date_edited: utility::unix_epoch_timestamp(),
+            metadata,
+        };
+
+        if let Err(e) = self.run_create_hook(&mut paste) {
+            return Err(e);
+        }
+
+        let used_invite = match invite {
+            Some(invite) => {
+                let id = invite.id.clone();
+
+                if let Err(e) = self.use_invite(invite).await {
+                    return Err(e);
+                }
+
+                Some(id)
+            }
+            None => None,
+        };
+
+        // create paste
+        let (query, expiry_query) =
+            if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+                (
+                    "INSERT INTO \"se_pastes\" VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+                    "INSERT INTO \"se_expirations\" VALUES (?, ?)",
+                )
+            } else {
+                (
+                    "INSERT INTO \"se_pastes\" VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+                    "INSERT INTO \"se_expirations\" VALUES ($1, $2)",
+                )
+            };
+
+        let mut statements: Vec<(&str, Vec<String>)> = vec![(
+            query,
+            vec![
+                paste.id.clone(),
+                paste.url.clone(),
+                paste.password.clone(),
+                paste.content.clone(),
+                paste.date_published.to_string(),
+                paste.date_edited.to_string(),
+                match serde_json::to_string(&paste.metadata) {
+                    Ok(s) => s,
+                    Err(_) => return Err(PasteError::ValueError),
+                },
+                String::from("0"),
+            ],
+        )];
+
+        if paste.metadata.expires_at != 0 {
+            statements.push((
+                expiry_query,
+                vec![paste.url.clone(), paste.metadata.expires_at.to_string()],
+            ));
+        }
+
+        if let Err(e) = self.transaction(statements).await {
+            // the paste wasn't created, so it didn't use the invite
+            if let Some(id) = used_invite {
+                self.restore_invite(id).await;
+            }
+
+            return Err(e);
+        }
+
+        self.index_paste_by_url(paste.url.clone()).await;
+        self.log_mutation(
+            "create",
+            &paste.url,
+            "",
+            &props.author,
+            Some(&paste.content),
+        );
+
+        Ok((props.password, paste))
+    }
+
+    /// Get up to 3 available paste urls generated from `title`
+    ///
+    /// ## Arguments:
+    /// * `title` - the title to generate urls from
+    pub async fn suggest_urls(&self, title: String) -> Result<Vec<String>> {
+        if (title.len() > 250) | (title.trim().len() < 1) {
+            return Err(PasteError::ValueError);
+        }
+
+        let mut candidates = slug::candidates(&title);
+
+        // fall back to random suffixes if every readable url is taken
+        if let Some(base) = candidates.first().cloned() {
+            for _ in 0..3 {
+                candidates.push(format!(
+                    "{}-{}",
+                    base,
+                    utility::random_id().chars().take(6).collect::<String>()
+                ));
+            }
+        }
+
+        // check candidates
+        let mut out: Vec<String> = Vec::new();
+
+        for candidate in candidates {
+            if out.len() >= 3 {
+                break;
+            }
+
+            if candidate.len() < 3 {
+                continue;
+            }
+
+            if let Err(PasteError::NotFound) = self.get_paste_by_url(candidate.clone()).await {
+                out.push(candidate);
+            }
+        }
+
+        // return
+        Ok(out)
+    }
+
+    /// Fetch the text content of a remote URL
+    ///
+    /// Only `http(s)` text resources up to the paste size limit are accepted, and the
+    /// request times out after 10 seconds. Hosts which resolve to addresses that aren't
+    /// public (see [`is_global`]) are refused, and redirects are followed by hand so every
+    /// hop is checked the same way.
+    ///
+    /// ## Arguments:
+    /// * `source` - the url to fetch
+    ///
+    /// ## Returns:
+    /// * Result containing a tuple with the parsed url and the content
+    #[cfg(feature = "fetch")]
+    async fn fetch_text(&self, source: &str) -> Result<(String, String)> {
+        const MAX_REDIRECTS: usize = 5;
+
+        // check url
+        let mut source = match reqwest::Url::parse(source) {
+            Ok(u) => u,
+            Err(_) => return Err(PasteError::ValueError),
+        };
+
+        // fetch
+        let mut redirects: usize = 0;
+
+        let mut res = loop {
+            if (source.scheme() != "http") && (source.scheme() != "https") {
+                return Err(PasteError::ValueError);
+            }
+
+            let client = match pinned_client(&source, std::time::Duration::from_secs(10)).await {
+                Ok(c) => c,
+                Err(e) => return Err(e),
+            };
+
+            let res = match client.get(source.clone()).send().await {
+                Ok(r) => r,
+                Err(_) => return Err(PasteError::ValueError),
+            };
+
+            if !res.status().is_redirection() {
+                break res;
+            }
+
+            redirects += 1;
+
+            if redirects > MAX_REDIRECTS {
+                return Err(PasteError::ValueError);
+            }
+
+            source = match res
+                .headers()
+                .get("Location")
+                .and_then(|l| l.to_str().ok())
+                .and_then(|l| source.join(l).ok())
+            {
+                Some(u) => u,
+                None => return Err(PasteError::ValueError),
+            };
+        };
+
+        if !res.status().is_success() {
+            return Err(PasteError::ValueError);
+        }
+
+        // check content type
+        let content_type = match res.headers().get("Content-Type") {
+            Some(t) => t.to_str().unwrap_or("").to_lowercase(),
+            None => return Err(PasteError::ValueError),
+        };
+
+        if !content_type.starts_with("text/")
+            && !content_type.starts_with("application/json")
+            && !content_type.starts_with("application/xml")
+            && !content_type.starts_with("application/javascript")
+        {
+            return Err(PasteError::ValueError);
+        }
+
+        // read body (stopping once it's too large)
+        let mut body: Vec<u8> = Vec::new();
+
+        loop {
+            match res.chunk().await {
+                Ok(Some(chunk)) => {
+                    body.extend_from_slice(&chunk);
+
+                    if body.len() > validation::MAX_CONTENT_LENGTH {
+                        return Err(PasteError::ContentTooLarge);
+                    }
+                }
+                Ok(None) => break,
+                Err(_) => return Err(PasteError::ValueError),
+            }
+        }
+
+        let content = match String::from_utf8(body) {
+            Ok(c) => c,
+            Err(e) => {
+                if self.options.normalization.replace_invalid_utf8 == false {
+                    return Err(PasteError::ValueError);
+                }
+
+                String::from_utf8_lossy(e.as_bytes()).to_string()
+            }
+        };
+
+        Ok((source.to_string(), content))
+    }
+
+    /// Remote URLs can't be fetched without the `fetch` feature
+    #[cfg(not(feature = "fetch"))]
+    async fn fetch_text(&self, _source: &str) -> Result<(String, String)> {
+        Err(PasteError::NotAllowed)
+    }
+
+    /// Create a new paste from the content of a remote URL (see [`Database::fetch_text`])
+    ///
+    /// ## Arguments:
+    /// * `props` - [`PasteCreateFromUrl`]
+    ///
+    /// ## Returns:
+    /// * Result containing a tuple with the unhashed edit password and the paste
+    #[cfg(feature = "fetch")]
+    pub async fn create_paste_from_url(
+        &self,
+        props: PasteCreateFromUrl,
+    ) -> Result<(String, Paste)> {
+        if self.options.from_url == false {
+            return Err(PasteError::NotAllowed);
+        }
+
+        let (source, content) = match self.fetch_text(&props.source).await {
+            Ok(f) => f,
+            Err(e) => return Err(e),
+        };
+
+        // create paste (recording its source)
+        let mut metadata = PasteMetadata::default();
+        metadata.source_url = source;
+
+        self.create_paste_with_metadata(
+            PasteCreate {
+                url: props.url,
+                content,
+                password: props.password,
+                accept_tos: props.accept_tos,
+                author: props.author,
+                expires_in: String::new(),
+                expires_at: 0,
+                invite: String::new(),
+            },
+            metadata,
+        )
+        .await
+    }
+
+    /// Create a new binary paste from raw `bytes` (see [`ServerOptions::binary_pastes`])
+    ///
+    /// ## Arguments:
+    /// * `props` - [`PasteCreateBinary`]
+    /// * `bytes` - the paste's content
+    ///
+    /// ## Returns:
+    /// * Result containing a tuple with the unhashed edit password and the paste
+    #[cfg(feature = "binary")]
+    pub async fn create_binary_paste(
+        &self,
+        props: PasteCreateBinary,
+        bytes: &[u8],
+    ) -> Result<(String, Paste)> {
+        if self.options.binary_pastes == false {
+            return Err(PasteError::NotAllowed);
+        }
+
+        // check metadata
+        let mut metadata = PasteMetadata::default();
+        metadata.content_type = String::from("binary");
+        metadata.mime_type = props.mime_type;
+        metadata.size = bytes.len();
+        metadata.scan_status = self.get_pending_scan_status();
+
+        let mut v = validation::Validator::new();
+        validation::metadata(&mut v, &metadata);
+
+        if let Err(e) = v.finish() {
+            return Err(e);
+        }
+
+        // create paste
+        match self
+            .create_paste_with_metadata(
+                PasteCreate {
+                    url: props.url,
+                    content: binary::encode(bytes),
+                    password: props.password,
+                    accept_tos: props.accept_tos,
+                    author: props.author,
+                    expires_in: String::new(),
+                    expires_at: 0,
+                    invite: String::new(),
+                },
+                metadata,
+            )
+            .await
+        {
+            Ok((password, paste)) => {
+                self.spawn_scan(paste.url.clone());
+                Ok((password, paste))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Delete an existing paste by `url`
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste to delete
+    /// * `password` - the paste's edit password
+    /// * `assertion` - passkey assertion from the owner (for high security pastes)
+    #[tracing::instrument(level = "debug", skip_all, fields(url = %url))]
+    pub async fn delete_paste_by_url(
+        &self,
+        mut url: String,
+        password: String,
+        assertion: Option<WebAuthnAssertion>,
+    ) -> Result<()> {
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+
+        if url.ends_with("-") {
+            url.pop();
+        }
+
+        // get paste
+        let existing = match self.get_paste_by_url(url.clone()).await {
+            Ok(p) => p,
+            Err(err) => return Err(err),
+        };
+
+        // check password
+        if utility::hash(password) != existing.password {
+            return Err(PasteError::PasswordIncorrect);
+        }
+
+        if existing.metadata.legal_hold == true {
+            return Err(PasteError::NotAllowed);
+        }
+
+        if let Err(e) = self.check_high_security(&existing, assertion).await {
+            return Err(e);
+        }
+
+        if let Err(e) = self.remove_paste_by_url(url.clone()).await {
+            return Err(e);
+        }
+
+        self.log_mutation("delete", &url, "", "", None);
+        Ok(())
+    }
+
+    /// Send a record of a paste being written to [`ServerOptions::audit_log`]
+    ///
+    /// ## Arguments:
+    /// * `action` - what was done (`create`, `edit`, `append`, `edit_metadata`, `delete` or `expire`)
+    /// * `url` - the paste's url
+    /// * `new_url` - the paste's new url (empty if it wasn't renamed)
+    /// * `actor` - the username of the user who did it (empty for anonymous users)
+    /// * `content` - the paste's new content (only its length and hash are logged unless [`AuditLogOptions::include_content`] is set)
+    fn log_mutation(
+        &self,
+        action: &str,
+        url: &str,
+        new_url: &str,
+        actor: &str,
+        content: Option<&str>,
+    ) {
+        let log = match self.audit {
+            Some(ref l) => l,
+            None => return,
+        };
+
+        let include_content = match self.options.audit_log {
+            Some(ref o) => o.include_content,
+            None => false,
+        };
+
+        log.record(AuditRecord {
+            action: action.to_string(),
+            url: url.to_string(),
+            new_url: new_url.to_string(),
+            actor: actor.to_string(),
+            timestamp: utility::unix_epoch_timestamp(),
+            content_length: content.map(|c| c.len()).unwrap_or(0),
+            content_hash: content
+                .map(|c| utility::hash(c.to_string()))
+                .unwrap_or_default(),
+            content: if include_content == true {
+                content.map(|c| c.to_string())
+            } else {
+                None
+            },
+            dropped: None,
+        });
+    }
+
+    /// Delete a paste and everything attached to it, without any checks
+    ///
+    /// ## Arguments:
+    /// * `url` - the (normalized) url of the paste to delete
+    async fn remove_paste_by_url(&self, url: String) -> Result<()> {
+        // delete paste view count (views by country are attached rows)
+        self.base.cachedb.remove(format!("se_views:{}", url)).await;
+
+        // delete rendered html
+        for options in self.get_render_options_by_url(url.clone()).await {
+            self.base
+                .cachedb
+                .remove(format!("se_render:{}:{}", url, options))
+                .await;
+        }
+
+        self.base
+            .cachedb
+            .remove(format!("se_renders:{}", url))
+            .await;
+
+        // delete code block outputs
+        self.base
+            .cachedb
+            .remove(format!("se_outputs:{}", url))
+            .await;
+
+        // delete paste and everything attached to it
+        let placeholder = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "?"
+        } else {
+            "$1"
+        };
+
+        let mut tables: Vec<&str> = vec!["se_pastes"];
+        tables.extend(self.get_attached_tables());
+
+        let queries: Vec<String> = tables
+            .iter()
+            .map(|t| format!("DELETE FROM \"{}\" WHERE \"url\" = {}", t, placeholder))
+            .collect();
+
+        let mut statements: Vec<(&str, Vec<String>)> = queries
+            .iter()
+            .map(|q| (q.as_str(), vec![url.clone()]))
+            .collect();
+
+        // unpin
+        let mut pinned = self.get_pinned_urls().await;
+        let unpin = pinned.contains(&url);
+
+        if unpin == true {
+            pinned.retain(|p| p != &url);
+            statements.extend(self.set_setting_statements(
+                String::from("pinned"),
+                if pinned.is_empty() {
+                    None
+                } else {
+                    Some(serde_json::to_string(&pinned).unwrap())
+                },
+            ));
+        }
+
+        if let Err(e) = self.transaction(statements).await {
+            return Err(e);
+        }
+
+        // remove from cache
+        self.base.cachedb.remove(format!("se_paste:{}", url)).await;
+        self.base
+            .cachedb
+            .remove(format!("se_translations:{}", url))
+            .await;
+        self.base.cachedb.remove(format!("se_links:{}", url)).await;
+
+        if unpin == true {
+            self.base
+                .cachedb
+                .remove(String::from("se_setting:pinned"))
+                .await;
+        }
+
+        // return
+        Ok(())
+    }
+
+    /// Delete every expired paste (pastes under a legal hold are kept)
+    pub async fn delete_expired_pastes(&self) {
+        if self.options.expiration == false {
+            return;
+        }
+
+        let c = &self.base.db.client;
+        let rows = match sqlquery("SELECT * FROM \"se_expirations\"")
+            .fetch_all(c)
+            .await
+        {
+            Ok(r) => r,
+            Err(_) => return,
+        };
+
+        let now = utility::unix_epoch_timestamp();
+
+        for row in rows {
+            let res = self.base.textify_row(row).data;
+            let url = res.get("url").unwrap().to_string();
+
+            if res.get("expires").unwrap().parse::<u128>().unwrap_or(0) > now {
+                continue;
+            }
+
+            // expired pastes are only found when they're held
+            match self.get_paste_by_url(url.clone()).await {
+                Ok(_) => continue,
+                Err(PasteError::NotFound) => (),
+                Err(_) => continue,
+            };
+
+            if let Ok(_) = self.remove_paste_by_url(url.clone()).await {
+                self.log_mutation("expire", &url, "", "", None);
+            }
+        }
+    }
+
+    /// Start a background task which calls [`Database::delete_expired_pastes`] every minute
+    pub fn spawn_expiry_cleaner(&self) {
+        if self.options.expiration == false {
+            return;
+        }
+
+        let database = self.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+
+            loop {
+                interval.tick().await;
+                database.delete_expired_pastes().await;
+            }
+        });
+    }
+
+    // rotations
+
+    /// Get the current content of a rotation `source` (see [`RotationCreate::source`])
+    async fn get_rotation_content(&self, source: &str) -> Result<String> {
+        let namespace = match source.strip_prefix("docs:") {
+            Some(n) => n,
+            None => {
+                return match self.fetch_text(source).await {
+                    Ok((_, content)) => Ok(content),
+                    Err(e) => Err(e),
+                }
+            }
+        };
+
+        if self.options.document_store == false {
+            return Err(PasteError::NotAllowed);
+        }
+
+        // newest document in the namespace
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT \"content\" FROM \"se_documents\" WHERE \"namespace\" = ? ORDER BY \"timestamp\" DESC LIMIT 1"
+        } else {
+            "SELECT \"content\" FROM \"se_documents\" WHERE \"namespace\" = $1 ORDER BY \"timestamp\" DESC LIMIT 1"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query).bind::<&str>(namespace).fetch_one(c).await {
+            Ok(r) => Ok(self
+                .base
+                .textify_row(r)
+                .data
+                .get("content")
+                .unwrap()
+                .to_string()),
+            Err(_) => Err(PasteError::NotFound),
+        }
+    }
+
+    /// Replace the content of a rotated paste, keeping its previous content as a revision
+    ///
+    /// The rotation is stored (or replaced) with its next run in the same transaction.
+    ///
+    /// ## Arguments:
+    /// * `rotation` - the paste's rotation
+    /// * `content` - the source's current content
+    async fn apply_rotation(&self, rotation: &Rotation, content: String) -> Result<()> {
+        let existing = match self.get_paste_by_url(rotation.url.clone()).await {
+            Ok(p) => p,
+            Err(e) => return Err(e),
+        };
+
+        if existing.metadata.locked == true {
+            return Err(PasteError::NotAllowed);
+        }
+
+        // check content
+        let content = normalize::normalize(&content, &self.options.normalization).0;
+
+        if content.len() > validation::MAX_CONTENT_LENGTH {
+            return Err(PasteError::ContentTooLarge);
+        }
+
+        if let Err(e) = self.check_rules("edit", &existing.url, &content, "") {
+            return Err(e);
+        }
+
+        let queries = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            [
+                "DELETE FROM \"se_rotations\" WHERE \"url\" = ?",
+                "INSERT INTO \"se_rotations\" VALUES (?, ?, ?, ?)",
+                "INSERT INTO \"se_revisions\" VALUES (?, ?, ?)",
+                "UPDATE \"se_pastes\" SET \"content\" = ?, \"date_edited\" = ?, \"metadata\" = ? WHERE \"url\" = ?",
+            ]
+        } else {
+            [
+                "DELETE FROM \"se_rotations\" WHERE \"url\" = $1",
+                "INSERT INTO \"se_rotations\" VALUES ($1, $2, $3, $4)",
+                "INSERT INTO \"se_revisions\" VALUES ($1, $2, $3)",
+                "UPDATE \"se_pastes\" SET \"content\" = $1, \"date_edited\" = $2, \"metadata\" = $3 WHERE \"url\" = $4",
+            ]
+        };
+
+        let now = utility::unix_epoch_timestamp();
+        let mut statements = vec![
+            (queries[0], vec![existing.url.clone()]),
+            (
+                queries[1],
+                vec![
+                    existing.url.clone(),
+                    rotation.source.clone(),
+                    rotation.interval.to_string(),
+                    (now + rotation.interval).to_string(),
+                ],
+            ),
+        ];
+
+        // unchanged content only moves the next run
+        let changed = content != existing.content;
+
+        if changed == true {
+            let mut metadata = existing.metadata;
+
+            if self.options.excerpt_sentences != 0 {
+                metadata.excerpt = excerpt::excerpt(&content, self.options.excerpt_sentences);
+            }
+
+            self.apply_checksums(&content, &mut metadata);
+
+            statements.push((
+                queries[2],
+                vec![existing.url.clone(), existing.content, now.to_string()],
+            ));
+
+            statements.push((
+                queries[3],
+                vec![
+                    content.clone(),
+                    now.to_string(),
+                    match serde_json::to_string(&metadata) {
+                        Ok(s) => s,
+                        Err(_) => return Err(PasteError::ValueError),
+                    },
+                    existing.url.clone(),
+                ],
+            ));
+        }
+
+        if let Err(e) = self.transaction(statements).await {
+            return Err(e);
+        }
+
+        if changed == false {
+            return Ok(());
+        }
+
+        // remove from cache
+        self.base
+            .cachedb
+            .remove(format!("se_paste:{}", existing.url))
+            .await;
+
+        self.log_mutation("rotate", &existing.url, "", "", Some(&content));
+        self.prune_revisions_by_url(&existing.url).await;
+        self.index_paste_by_url(existing.url).await;
+
+        Ok(())
+    }
+
+    /// Remove all but the newest [`MAX_REVISIONS`] revisions of a paste by `url`
+    async fn prune_revisions_by_url(&self, url: &str) {
+        let queries = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            [
+                "SELECT \"timestamp\" FROM \"se_revisions\" WHERE \"url\" = ? ORDER BY \"timestamp\" DESC",
+                "DELETE FROM \"se_revisions\" WHERE \"url\" = ? AND \"timestamp\" <= ?",
+            ]
+        } else {
+            [
+                "SELECT \"timestamp\" FROM \"se_revisions\" WHERE \"url\" = $1 ORDER BY \"timestamp\" DESC",
+                "DELETE FROM \"se_revisions\" WHERE \"url\" = $1 AND \"timestamp\" <= $2",
+            ]
+        };
+
+        let c = &self.base.db.client;
+        let rows = match sqlquery(queries[0]).bind::<&str>(url).fetch_all(c).await {
+            Ok(r) => r,
+            Err(_) => return,
+        };
+
+        let oldest_removed = match rows.into_iter().nth(MAX_REVISIONS) {
+            Some(r) => self
+                .base
+                .textify_row(r)
+                .data
+                .get("timestamp")
+                .unwrap()
+                .to_string(),
+            None => return,
+        };
+
+        let _ = sqlquery(queries[1])
+            .bind::<&str>(url)
+            .bind::<&String>(&oldest_removed)
+            .execute(c)
+            .await;
+    }
+
+    /// Schedule replacing the content of an existing paste by `url` (see [`ServerOptions::rotations`])
+    ///
+    /// The paste is rotated straight away, so sources which can't be read are rejected.
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste
+    /// * `props` - [`RotationCreate`]
+    /// * `editing_as` - the userstate of the user scheduling the rotation (documents can only be used as sources by users with the "ManageDocuments" permission)
+    pub async fn set_rotation_by_url(
+        &self,
+        mut url: String,
+        props: RotationCreate,
+        editing_as: Option<FullUser<UserMetadata>>,
+    ) -> Result<Rotation> {
+        if self.options.rotations == false {
+            return Err(PasteError::NotAllowed);
+        }
+
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+
+        if url.ends_with("-") {
+            url.pop();
+        }
+
+        // get paste
+        let existing = match self.get_paste_by_url(url.clone()).await {
+            Ok(p) => p,
+            Err(err) => return Err(err),
+        };
+
+        // locked pastes can only be changed by managers
+        if (existing.metadata.locked == true) && !is_manager(&editing_as) {
+            return Err(PasteError::NotAllowed);
+        }
+
+        // check password
+        let mut skip_password_check: bool = false;
+        let mut can_read_documents: bool = false;
+
+        if let Some(ref ua) = editing_as {
+            // check if we're the paste owner
+            if ua.user.username == existing.metadata.owner {
+                skip_password_check = true;
+            }
+            // check if we have the "ManagePastes" permission
+            else if ua.level.permissions.contains(&"ManagePastes".to_string()) {
+                skip_password_check = true;
+            }
+
+            can_read_documents = ua
+                .level
+                .permissions
+                .contains(&"ManageDocuments".to_string());
+        }
+
+        if skip_password_check == false {
+            if utility::hash(props.password) != existing.password {
+                return Err(PasteError::PasswordIncorrect);
+            }
+        }
+
+        // base64 content can't come from a source
+        if existing.metadata.content_type == "binary" {
+            return Err(PasteError::NotAllowed);
+        }
+
+        // check rotation
+        if (props.interval < validation::MIN_ROTATION_INTERVAL)
+            | (props.interval > validation::MAX_ROTATION_INTERVAL)
+        {
+            return Err(PasteError::ValueError);
+        }
+
+        if props.source.starts_with("docs:") && (can_read_documents == false) {
+            return Err(PasteError::NotAllowed);
+        }
+
+        // rotate
+        let content = match self.get_rotation_content(&props.source).await {
+            Ok(c) => c,
+            Err(e) => return Err(e),
+        };
+
+        let rotation = Rotation {
+            url: existing.url,
+            source: props.source,
+            interval: props.interval,
+            next_run: utility::unix_epoch_timestamp() + props.interval,
+        };
+
+        match self.apply_rotation(&rotation, content).await {
+            Ok(_) => Ok(rotation),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Stop rotating an existing paste by `url` (its revisions are kept)
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste
+    /// * `password` - the paste's edit password
+    /// * `editing_as` - the userstate of the user removing the rotation
+    pub async fn delete_rotation_by_url(
+        &self,
+        mut url: String,
+        password: String,
+        editing_as: Option<FullUser<UserMetadata>>,
+    ) -> Result<()> {
+        if self.options.rotations == false {
+            return Err(PasteError::NotAllowed);
+        }
+
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+
+        if url.ends_with("-") {
+            url.pop();
+        }
+
+        // get paste
+        let existing = match self.get_paste_by_url(url.clone()).await {
+            Ok(p) => p,
+            Err(err) => return Err(err),
+        };
+
+        // check password
+        let mut skip_password_check: bool = false;
+
+        if let Some(ua) = editing_as {
+            // check if we're the paste owner
+            if ua.user.username == existing.metadata.owner {
+                skip_password_check = true;
+            }
+            // check if we have the "ManagePastes" permission
+            else if ua.level.permissions.contains(&"ManagePastes".to_string()) {
+                skip_password_check = true;
+            }
+        }
+
+        if skip_password_check == false {
+            if utility::hash(password) != existing.password {
+                return Err(PasteError::PasswordIncorrect);
+            }
+        }
+
+        // delete rotation
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"se_rotations\" WHERE \"url\" = ?"
+        } else {
+            "DELETE FROM \"se_rotations\" WHERE \"url\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query)
+            .bind::<&String>(&existing.url)
+            .execute(c)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(query_failed(e)),
+        }
+    }
+
+    /// Get the revisions of an existing paste by `url` (newest first)
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste
+    pub async fn get_revisions_by_url(&self, url: String) -> Result<Vec<Revision>> {
+        if self.options.rotations == false {
+            return Err(PasteError::NotAllowed);
+        }
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"se_revisions\" WHERE \"url\" = ? ORDER BY \"timestamp\" DESC"
+        } else {
+            "SELECT * FROM \"se_revisions\" WHERE \"url\" = $1 ORDER BY \"timestamp\" DESC"
+        };
+
+        let c = &self.base.db.client;
+        let rows = match sqlquery(query).bind::<&String>(&url).fetch_all(c).await {
+            Ok(r) => r,
+            Err(e) => return Err(query_failed(e)),
+        };
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let res = self.base.textify_row(row).data;
+
+                Revision {
+                    content: res.get("content").unwrap().to_string(),
+                    timestamp: res.get("timestamp").unwrap().parse::<u128>().unwrap_or(0),
+                }
+            })
+            .collect())
+    }
+
+    /// Rotate every paste whose next rotation is due
+    ///
+    /// Pastes whose source can't be read keep their content until their next run.
+    pub async fn rotate_due_pastes(&self) {
+        let c = &self.base.db.client;
+        let rows = match sqlquery("SELECT * FROM \"se_rotations\"")
+            .fetch_all(c)
+            .await
+        {
+            Ok(r) => r,
+            Err(_) => return,
+        };
+
+        let now = utility::unix_epoch_timestamp();
+
+        for row in rows {
+            let res = self.base.textify_row(row).data;
+            let rotation = Rotation {
+                url: res.get("url").unwrap().to_string(),
+                source: res.get("source").unwrap().to_string(),
+                interval: res.get("period").unwrap().parse::<u128>().unwrap_or(0),
+                next_run: res.get("next_run").unwrap().parse::<u128>().unwrap_or(0),
+            };
+
+            if rotation.next_run > now {
+                continue;
+            }
+
+            let result = match self.get_rotation_content(&rotation.source).await {
+                Ok(content) => self.apply_rotation(&rotation, content).await,
+                Err(e) => Err(e),
+            };
+
+            if let Err(e) = result {
+                tracing::warn!(url = %rotation.url, error = %e.to_string(), "rotation failed");
+
+                // try again on the next run
+                self.postpone_rotation(&rotation, now).await;
+            }
+        }
+    }
+
+    /// Move the next run of `rotation` one interval past `now` (without changing the paste)
+    async fn postpone_rotation(&self, rotation: &Rotation, now: u128) {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "UPDATE \"se_rotations\" SET \"next_run\" = ? WHERE \"url\" = ?"
+        } else {
+            "UPDATE \"se_rotations\" SET \"next_run\" = $1 WHERE \"url\" = $2"
+        };
+
+        let c = &self.base.db.client;
+        let _ = sqlquery(query)
+            .bind::<&String>(&(now + rotation.interval).to_string())
+            .bind::<&String>(&rotation.url)
+            .execute(c)
+            .await;
+    }
+
+    /// Start a background task which calls [`Database::rotate_due_pastes`] every minute
+    pub fn spawn_rotator(&self) {
+        if self.options.rotations == false {
+            return;
+        }
+
+        let database = self.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+
+            loop {
+                interval.tick().await;
+                database.rotate_due_pastes().await;
+            }
+        });
+    }
+
+    // archive
+
+    /// Move the archived content of a paste by `url` back into "se_pastes"
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste to restore
+    ///
+    /// ## Returns:
+    /// * Result containing the restored content
+    #[cfg(feature = "archive")]
+    async fn restore_archived_by_url(&self, url: &str) -> Result<String> {
+        let queries = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            [
+                "SELECT * FROM \"se_archive\" WHERE \"url\" = ?",
+                "UPDATE \"se_pastes\" SET \"content\" = ? WHERE \"url\" = ?",
+                "DELETE FROM \"se_archive\" WHERE \"url\" = ?",
+                "DELETE FROM \"se_activity\" WHERE \"url\" = ?",
+                "INSERT INTO \"se_activity\" VALUES (?, ?)",
+            ]
+        } else {
+            [
+                "SELECT * FROM \"se_archive\" WHERE \"url\" = $1",
+                "UPDATE \"se_pastes\" SET \"content\" = $1 WHERE \"url\" = $2",
+                "DELETE FROM \"se_archive\" WHERE \"url\" = $1",
+                "DELETE FROM \"se_activity\" WHERE \"url\" = $1",
+                "INSERT INTO \"se_activity\" VALUES ($1, $2)",
+            ]
+        };
+
+        let c = &self.base.db.client;
+        let res = match sqlquery(queries[0]).bind::<&str>(url).fetch_one(c).await {
+            Ok(r) => self.base.textify_row(r).data,
+            Err(_) => return Err(PasteError::NotFound),
+        };
+
+        let content = match archive::decompress(res.get("content").unwrap()) {
+            Some(c) => c,
+            None => return Err(PasteError::Other),
+        };
+
+        // restore (and count as viewed so it isn't archived again right away)
+        if let Err(e) = self
+            .transaction(vec![
+                (queries[1], vec![content.clone(), url.to_string()]),
+                (queries[2], vec![url.to_string()]),
+                (queries[3], vec![url.to_string()]),
+                (
+                    queries[4],
+                    vec![url.to_string(), utility::unix_epoch_timestamp().to_string()],
+                ),
+            ])
+            .await
+        {
+            return Err(e);
+        }
+
+        Ok(content)
+    }
+
+    /// Archived content can't be read without the `archive` feature
+    #[cfg(not(feature = "archive"))]
+    async fn restore_archived_by_url(&self, _url: &str) -> Result<String> {
+        Err(PasteError::Other)
+    }
+
+    /// Record that a paste by `url` was viewed (written at most once a day per paste)
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste which was viewed
+    #[cfg(feature = "archive")]
+    async fn touch_paste_by_url(&self, url: &str) {
+        if self.options.archive_after == 0 {
+            return;
+        }
+
+        let now = utility::unix_epoch_timestamp();
+
+        if let Some(c) = self.base.cachedb.get(format!("se_activity:{}", url)).await {
+            if now.saturating_sub(c.parse::<u128>().unwrap_or(0)) < 24 * 60 * 60 * 1000 {
+                return;
+            }
+        }
+
+        let (delete, insert) = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql")
+        {
+            (
+                "DELETE FROM \"se_activity\" WHERE \"url\" = ?",
+                "INSERT INTO \"se_activity\" VALUES (?, ?)",
+            )
+        } else {
+            (
+                "DELETE FROM \"se_activity\" WHERE \"url\" = $1",
+                "INSERT INTO \"se_activity\" VALUES ($1, $2)",
+            )
+        };
+
+        if let Ok(_) = self
+            .transaction(vec![
+                (delete, vec![url.to_string()]),
+                (insert, vec![url.to_string(), now.to_string()]),
+            ])
+            .await
+        {
+            self.base
+                .cachedb
+                .set(format!("se_activity:{}", url), now.to_string())
+                .await;
+        }
+    }
+
+    /// Archive every paste which hasn't been viewed or edited in [`ServerOptions::archive_after`]
+    #[cfg(feature = "archive")]
+    pub async fn archive_cold_pastes(&self) {
+        if self.options.archive_after == 0 {
+            return;
+        }
+
+        // get last views
+        let c = &self.base.db.client;
+        let activity: HashMap<String, u128> =
+            match sqlquery("SELECT * FROM \"se_activity\"").fetch_all(c).await {
+                Ok(rows) => rows
+                    .into_iter()
+                    .map(|r| {
+                        let res = self.base.textify_row(r).data;
+                        (
+                            res.get("url").unwrap().to_string(),
+                            res.get("last_viewed").unwrap().parse::<u128>().unwrap_or(0),
+                        )
+                    })
+                    .collect(),
+                Err(_) => return,
+            };
+
+        // find cold pastes
+        let rows = match sqlquery(
+            "SELECT \"url\", \"date_edited\" FROM \"se_pastes\" WHERE \"content\" != ''",
+        )
+        .fetch_all(c)
+        .await
+        {
+            Ok(r) => r,
+            Err(_) => return,
+        };
+
+        let cutoff = utility::unix_epoch_timestamp().saturating_sub(self.options.archive_after);
+
+        for row in rows {
+            let res = self.base.textify_row(row).data;
+            let url = res.get("url").unwrap().to_string();
+            let date_edited = res.get("date_edited").unwrap().to_string();
+
+            let last_active = date_edited
+                .parse::<u128>()
+                .unwrap_or(0)
+                .max(*activity.get(&url).unwrap_or(&0));
+
+            if last_active > cutoff {
+                continue;
+            }
+
+            self.archive_paste_by_url(url, date_edited).await;
+        }
+    }
+
+    /// Compress the content of a paste by `url` into "se_archive"
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste to archive
+    /// * `date_edited` - the paste's `date_edited` when it was found to be cold (the paste is left alone if it was edited since)
+    #[cfg(feature = "archive")]
+    async fn archive_paste_by_url(&self, url: String, date_edited: String) {
+        let queries = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            [
+                "SELECT \"content\" FROM \"se_pastes\" WHERE \"url\" = ?",
+                "DELETE FROM \"se_archive\" WHERE \"url\" = ?",
+                "INSERT INTO \"se_archive\" VALUES (?, ?)",
+                "UPDATE \"se_pastes\" SET \"content\" = '' WHERE \"url\" = ? AND \"date_edited\" = ?",
+            ]
+        } else {
+            [
+                "SELECT \"content\" FROM \"se_pastes\" WHERE \"url\" = $1",
+                "DELETE FROM \"se_archive\" WHERE \"url\" = $1",
+                "INSERT INTO \"se_archive\" VALUES ($1, $2)",
+                "UPDATE \"se_pastes\" SET \"content\" = '' WHERE \"url\" = $1 AND \"date_edited\" = $2",
+            ]
+        };
+
+        let c = &self.base.db.client;
+        let content = match sqlquery(queries[0])
+            .bind::<&String>(&url)
+            .fetch_one(c)
+            .await
+        {
+            Ok(r) => self
+                .base
+                .textify_row(r)
+                .data
+                .get("content")
+                .unwrap()
+                .to_string(),
+            Err(_) => return,
+        };
+
+        if content.is_empty() {
+            return;
+        }
+
+        let compressed = match archive::compress(&content) {
+            Some(c) => c,
+            None => return,
+        };
+
+        if let Err(_) = self
+            .transaction(vec![
+                (queries[1], vec![url.clone()]),
+                (queries[2], vec![url.clone(), compressed]),
+                (queries[3], vec![url.clone(), date_edited]),
+            ])
+            .await
+        {
+            return;
+        }
+
+        // remove from cache
+        self.base.cachedb.remove(format!("se_paste:{}", url)).await;
+    }
+
+    /// Start a background task which calls [`Database::archive_cold_pastes`] every hour
+    #[cfg(feature = "archive")]
+    pub fn spawn_archiver(&self) {
+        if self.options.archive_after == 0 {
+            return;
+        }
+
+        let database = self.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(60 * 60));
+
+            loop {
+                interval.tick().await;
+                database.archive_cold_pastes().await;
+            }
+        });
+    }
+
+    /// Get an existing paste by `url`, checking its view password
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste to unlock
+    /// * `password` - the paste's view password
+    pub async fn unlock_paste_by_url(&self, url: String, password: String) -> Result<Paste> {
+        let paste = match self.get_paste_by_url(url).await {
+            Ok(p) => p,
+            Err(e) => return Err(e),
+        };
+
+        if paste.metadata.view_password.is_empty() {
+            return Ok(paste);
+        }
+
+        if password.is_empty() {
+            return Err(PasteError::PasswordRequired);
+        }
+
+        if utility::hash(password) != paste.metadata.view_password {
+            return Err(PasteError::PasswordIncorrect);
+        }
+
+        Ok(paste)
+    }
+
+    /// Edit an existing paste by `url`
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste to edit
+    /// * `password` - the paste's edit password
+    /// * `edit_link` - token of an edit link used instead of `password` (can only change content)
+    /// * `new_content` - the new content of the paste
+    /// * `new_url` - the new url of the paste
+    /// * `new_password` - the new password of the paste
+    /// * `editing_as` - the userstate of the user we're editing the paste as
+    /// * `assertion` - passkey assertion from the owner (for changing the password of high security pastes)
+    #[tracing::instrument(level = "debug", skip_all, fields(url = %url))]
+    pub async fn edit_paste_by_url(
+        &self,
+        mut url: String,
+        password: String,
+        edit_link: String,
+        new_content: String,
+        mut new_url: String,
+        mut new_password: String,
+        editing_as: Option<FullUser<UserMetadata>>,
+        assertion: Option<WebAuthnAssertion>,
+    ) -> Result<()> {
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+
+        if url.ends_with("-") {
+            url.pop();
+        }
+
+        // get paste
+        let existing = match self.get_paste_by_url(url.clone()).await {
+            Ok(p) => p,
+            Err(err) => return Err(err),
+        };
+
+        // locked pastes can only be changed by managers
+        if (existing.metadata.locked == true) && !is_manager(&editing_as) {
+            return Err(PasteError::NotAllowed);
+        }
+
+        let author = match editing_as {
+            Some(ref ua) => ua.user.username.clone(),
+            None => String::new(),
+        };
+
+        // check password
+        let mut skip_password_check: bool = false;
+
+        if let Some(ua) = editing_as {
+            // check if we're the paste owner
+            if ua.user.username == existing.metadata.owner {
+                skip_password_check = true;
+            }
+            // check if we have the "ManagePastes" permission
+            else if ua.level.permissions.contains(&"ManagePastes".to_string()) {
+                skip_password_check = true;
+            }
+        }
+
+        if (skip_password_check == false) && !edit_link.is_empty() {
+            if self.check_edit_link(&existing, &edit_link).await == false {
+                return Err(PasteError::PasswordIncorrect);
+            }
+
+            // edit links can't move the paste or take it over
+            if !new_password.is_empty() | (!new_url.is_empty() && (new_url.to_lowercase() != url)) {
+                return Err(PasteError::NotAllowed);
+            }
+        } else if skip_password_check == false {
+            if utility::hash(password) != existing.password {
+                return Err(PasteError::PasswordIncorrect);
+            }
+        }
+
+        // hash new password
+        if !new_password.is_empty() {
+            if let Err(e) = self.check_high_security(&existing, assertion).await {
+                return Err(e);
+            }
+
+            new_password = utility::hash(new_password);
+        } else {
+            new_password = existing.password;
+        }
+
+        // update new_url
+        if new_url.is_empty() {
+            new_url = existing.url;
+        }
+
+        new_url = idna::punycode::encode_str(&new_url).unwrap().to_lowercase();
+
+        if new_url.ends_with("-") {
+            new_url.pop();
+        }
+
+        // normalize content
+        let new_content = normalize::normalize(&new_content, &self.options.normalization).0;
+
+        // check lengths and characters used
+        let mut v = validation::Validator::new();
+        validation::paste(
+            &mut v,
+            "new_url",
+            &new_url,
+            "new_content",
+            &new_content,
+            validation::MAX_CONTENT_LENGTH,
+        );
+
+        if let Err(e) = v.finish() {
+            return Err(e);
+        }
+
+        if let Err(e) = self.check_rules("edit", &new_url, &new_content, &author) {
+            return Err(e);
+        }
+
+        if new_url != url {
+            // make sure new url isn't taken
+            if let Ok(_) = self.get_paste_by_url(new_url.clone()).await {
+                return Err(PasteError::UrlTaken);
+            }
+        }
+
+        // update front matter
+        let mut metadata = existing.metadata;
+
+        if self.options.front_matter == true {
+            front_matter::apply(&new_content, &mut metadata);
+        }
+
+        if self.options.excerpt_sentences != 0 {
+            metadata.excerpt = excerpt::excerpt(&new_content, self.options.excerpt_sentences);
+        }
+
+        // binary pastes have their size instead of an excerpt
+        if metadata.content_type == "binary" {
+            metadata.size = match self.get_binary_size(&new_content) {
+                Ok(s) => s,
+                Err(e) => return Err(e),
+            };
+
+            metadata.excerpt = String::new();
+            metadata.scan_status = self.get_pending_scan_status();
+        }
+
+        self.apply_checksums(&new_content, &mut metadata);
+
+        // edit paste
+        let (query, placeholders) = if (self.base.db._type == "sqlite")
+            | (self.base.db._type == "mysql")
+        {
+            (
+                    "UPDATE \"se_pastes\" SET \"content\" = ?, \"password\" = ?, \"url\" = ?, \"date_edited\" = ?, \"metadata\" = ? WHERE \"url\" = ?",
+                    ("?", "?"),
+                )
+        } else {
+            (
+                    "UPDATE \"se_pastes\" SET \"content\" = $1, \"password\" = $2, \"url\" = $3, \"date_edited\" = $4, \"metadata\" = $5 WHERE \"url\" = $6",
+                    ("$1", "$2"),
+                )
+        };
+
+        let mut statements: Vec<(&str, Vec<String>)> = vec![(
+            query,
+            vec![
+                new_content.clone(),
+                new_password,
+                new_url.clone(),
+                utility::unix_epoch_timestamp().to_string(),
+                match serde_json::to_string(&metadata) {
+                    Ok(s) => s,
+                    Err(_) => return Err(PasteError::ValueError),
+                },
+                url.clone(),
+            ],
+        )];
+
+        // move everything attached to the paste to its new url
+        let renamed = new_url != url;
+        let mut pinned = self.get_pinned_urls().await;
+        let repin = renamed && pinned.contains(&url);
+
+        let queries: Vec<String> = if renamed {
+            self.get_attached_tables()
+                .iter()
+                .map(|t| {
+                    format!(
+                        "UPDATE \"{}\" SET \"url\" = {} WHERE \"url\" = {}",
+                        t, placeholders.0, placeholders.1
+                    )
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        for query in &queries {
+            statements.push((query.as_str(), vec![new_url.clone(), url.clone()]));
+        }
+
+        if repin == true {
+            for p in pinned.iter_mut() {
+                if *p == url {
+                    *p = new_url.clone();
+                }
+            }
+
+            statements.extend(self.set_setting_statements(
+                String::from("pinned"),
+                Some(serde_json::to_string(&pinned).unwrap()),
+            ));
+        }
+
+        if let Err(e) = self.transaction(statements).await {
+            return Err(e);
+        }
+
+        // remove from cache
+        self.base.cachedb.remove(format!("se_paste:{}", url)).await;
+
+        if renamed == true {
+            self.move_cache_by_url(&url, &new_url).await;
+        }
+
+        if repin == true {
+            self.base
+                .cachedb
+                .remove(String::from("se_setting:pinned"))
+                .await;
+        }
+
+        self.log_mutation(
+            "edit",
+            &url,
+            if renamed == true { &new_url } else { "" },
+            &author,
+            Some(&new_content),
+        );
+
+        if metadata.content_type == "binary" {
+            self.spawn_scan(new_url.clone());
+        }
+
+        self.index_paste_by_url(new_url).await;
+
+        // return
+        Ok(())
+    }
+
+    /// Get the tables (other than "se_pastes") which store rows by paste url
+    fn get_attached_tables(&self) -> Vec<&'static str> {
+        let mut tables: Vec<&str> = Vec::new();
+
+        if self.options.view_mode == ViewMode::AuthenticatedOnce {
+            tables.push("se_views");
+        }
+
+        if self.options.collections == true {
+            tables.push("se_collection_pastes");
+        }
+
+        if self.options.suggestions == true {
+            tables.push("se_suggestions");
+        }
+
+        if self.options.annotations == true {
+            tables.push("se_annotations");
+        }
+
+        if self.options.translations == true {
+            tables.push("se_paste_translations");
+        }
+
+        if self.options.link_check.is_some() {
+            tables.push("se_link_reports");
+        }
+
+        if self.options.scanner.is_some() {
+            tables.push("se_scans");
+        }
+
+        if self.options.watermark == true {
+            tables.push("se_downloads");
+        }
+
+        if self.options.expiration == true {
+            tables.push("se_expirations");
+        }
+
+        if self.options.rotations == true {
+            tables.extend(["se_rotations", "se_revisions"]);
+        }
+
+        if self.options.moderation == true {
+            tables.push("se_paste_ips");
+        }
+
+        #[cfg(feature = "geoip")]
+        if !self.options.geoip_database.is_empty() {
+            tables.push("se_view_countries");
+        }
+
+        if self.options.search == true {
+            tables.push("se_search");
+        }
+
+        #[cfg(feature = "archive")]
+        tables.extend(["se_archive", "se_activity"]);
+
+        tables
+    }
+
+    /// Move cached values kept for a paste from its old url to its new url
+    ///
+    /// View counts and code block outputs are moved, everything else which can be
+    /// rebuilt is removed.
+    ///
+    /// ## Arguments:
+    /// * `url` - the old url of the paste
+    /// * `new_url` - the new url of the paste
+    async fn move_cache_by_url(&self, url: &str, new_url: &str) {
+        let keys: Vec<(String, String)> = vec![
+            (format!("se_views:{}", url), format!("se_views:{}", new_url)),
+            (
+                format!("se_outputs:{}", url),
+                format!("se_outputs:{}", new_url),
+            ),
+        ];
+
+        for (from, to) in keys {
+            if let Some(value) = self.base.cachedb.get(from.clone()).await {
+                self.base.cachedb.set(to, value).await;
+                self.base.cachedb.remove(from).await;
+            }
+        }
+
+        // rebuilt on next use
+        for options in self.get_render_options_by_url(url.to_string()).await {
+            self.base
+                .cachedb
+                .remove(format!("se_render:{}:{}", url, options))
+                .await;
+        }
+
+        for key in [
+            format!("se_renders:{}", url),
+            format!("se_translations:{}", url),
+            format!("se_links:{}", url),
+            format!("se_paste:{}", new_url),
+        ] {
+            self.base.cachedb.remove(key).await;
+        }
+    }
+
+    /// Append content to the end of an existing paste by `url`
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste to edit
+    /// * `password` - the paste's edit password
+    /// * `content` - the content to append
+    /// * `editing_as` - the userstate of the user we're editing the paste as
+    #[tracing::instrument(level = "debug", skip_all, fields(url = %url))]
+    pub async fn append_paste_by_url(
+        &self,
+        mut url: String,
+        password: String,
+        content: String,
+        editing_as: Option<FullUser<UserMetadata>>,
+    ) -> Result<()> {
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+
+        if url.ends_with("-") {
+            url.pop();
+        }
+
+        // get paste
+        let existing = match self.get_paste_by_url(url.clone()).await {
+            Ok(p) => p,
+            Err(err) => return Err(err),
+        };
+
+        // locked pastes can only be changed by managers
+        if (existing.metadata.locked == true) && !is_manager(&editing_as) {
+            return Err(PasteError::NotAllowed);
+        }
+
+        let author = match editing_as {
+            Some(ref ua) => ua.user.username.clone(),
+            None => String::new(),
+        };
+
+        // check password
+        let mut skip_password_check: bool = false;
+
+        if let Some(ua) = editing_as {
+            // check if we're the paste owner
+            if ua.user.username == existing.metadata.owner {
+                skip_password_check = true;
+            }
+            // check if we have the "ManagePastes" permission
+            else if ua.level.permissions.contains(&"ManagePastes".to_string()) {
+                skip_password_check = true;
+            }
+        }
+
+        if skip_password_check == false {
+            if utility::hash(password) != existing.password {
+                return Err(PasteError::PasswordIncorrect);
+            }
+        }
+
+        // base64 can't be appended to
+        if existing.metadata.content_type == "binary" {
+            return Err(PasteError::NotAllowed);
+        }
+
+        // check length
+        let content = normalize::normalize(&content, &self.options.normalization).0;
+        let new_content = format!("{}{}", existing.content, content);
+
+        if new_content.len() > validation::MAX_CONTENT_LENGTH {
+            return Err(PasteError::ContentTooLarge);
+        }
+
+        if let Err(e) = self.check_rules("edit", &url, &new_content, &author) {
+            return Err(e);
+        }
+
+        // update excerpt
+        let mut metadata = existing.metadata;
+
+        if self.options.excerpt_sentences != 0 {
+            metadata.excerpt = excerpt::excerpt(&new_content, self.options.excerpt_sentences);
+        }
+
+        self.apply_checksums(&new_content, &mut metadata);
+
+        // edit paste
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "UPDATE \"se_pastes\" SET \"content\" = ?, \"date_edited\" = ?, \"metadata\" = ? WHERE \"url\" = ?"
+        } else {
+            "UPDATE \"se_pastes\" SET \"content\" = $1, \"date_edited\" = $2, \"metadata\" = $3 WHERE \"url\" = $4"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query)
+            .bind::<&String>(&new_content)
+            .bind::<&String>(&utility::unix_epoch_timestamp().to_string())
+            .bind::<&String>(match serde_json::to_string(&metadata) {
+                Ok(ref s) => s,
+                Err(_) => return Err(PasteError::ValueError),
+            })
+            .bind::<&String>(&url)
+            .execute(c)
+            .await
+        {
+            Ok(_) => {
+                // remove from cache
+                self.base.cachedb.remove(format!("se_paste:{}", url)).await;
+                self.log_mutation("append", &url, "", &author, Some(&new_content));
+                self.index_paste_by_url(url).await;
+
+                // return
+                return Ok(());
+            }
+            Err(e) => return Err(query_failed(e)),
+        };
+    }
+
+    /// Edit an existing paste's metadata by `url`
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste to edit
+    /// * `password` - the paste's edit password
+    /// * `metadata` - the new metadata of the paste
+    /// * `editing_as` - the userstate of the user we're editing the paste as
+    /// * `assertion` - passkey assertion from the owner (for changing the view password, owner or `high_security` of high security pastes)
+    #[tracing::instrument(level = "debug", skip_all, fields(url = %url))]
+    pub async fn edit_paste_metadata_by_url(
+        &self,
+        mut url: String,
+        password: String,
+        mut metadata: PasteMetadata,
+        editing_as: Option<FullUser<UserMetadata>>,
+        assertion: Option<WebAuthnAssertion>,
+    ) -> Result<()> {
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+
+        if url.ends_with("-") {
+            url.pop();
+        }
+
+        // get paste
+        let existing = match self.get_paste_by_url(url.clone()).await {
+            Ok(p) => p,
+            Err(err) => return Err(err),
+        };
+
+        // locked pastes can only be changed by managers
+        if (existing.metadata.locked == true) && !is_manager(&editing_as) {
+            return Err(PasteError::NotAllowed);
+        }
+
+        // check password
+        let mut skip_password_check: bool = false;
+        let mut is_manager: bool = false;
+
+        if let Some(ref ua) = editing_as {
+            // check if we're the paste owner
+            if ua.user.username == existing.metadata.owner {
+                skip_password_check = true;
+            }
+            // check if we have the "ManagePastes" permission
+            else if ua.level.permissions.contains(&"ManagePastes".to_string()) {
+                skip_password_check = true;
+                is_manager = true;
+            }
+        }
+
+        if skip_password_check == false {
+            if utility::hash(password) != existing.password {
+                return Err(PasteError::PasswordIncorrect);
+            }
+        }
+
+        // check owner
+        let username = match editing_as {
+            Some(ref ua) => ua.user.username.as_str(),
+            None => "",
+        };
+
+        if let Err(e) = check_owner_change(
+            &existing.metadata.owner,
+            &metadata.owner,
+            username,
+            is_manager,
+            self.options.paste_ownership,
+        ) {
+            return Err(e);
+        }
+
+        // check passkey (any of these changes could be used to get around it)
+        if (metadata.view_password != existing.metadata.view_password)
+            | (metadata.owner != existing.metadata.owner)
+            | (metadata.high_security != existing.metadata.high_security)
+        {
+            if let Err(e) = self.check_high_security(&existing, assertion).await {
+                return Err(e);
+            }
+        }
+
+        // check metadata
+        let mut v = validation::Validator::new();
+        validation::metadata(&mut v, &metadata);
+
+        if let Err(e) = v.finish() {
+            return Err(e);
+        }
+
+        // hash view password (an unchanged value is the stored hash)
+        if metadata.view_password == existing.metadata.view_password {
+            metadata.view_password_hashed = existing.metadata.view_password_hashed;
+        } else if !metadata.view_password.is_empty() {
+            metadata.view_password = utility::hash(metadata.view_password);
+            metadata.view_password_hashed = true;
+        } else {
+            metadata.view_password_hashed = false;
+        }
+
+        // legal holds are only changed through set_legal_hold_by_url
+        metadata.legal_hold = existing.metadata.legal_hold;
+
+        // locks are only changed through moderation jobs
+        metadata.locked = existing.metadata.locked;
+
+        // expiry is only set when creating the paste
+        metadata.expires_at = existing.metadata.expires_at;
+
+        // excerpts and checksums are only changed with content (or the content type)
+        metadata.excerpt = existing.metadata.excerpt;
+        metadata.checksums = existing.metadata.checksums;
+
+        // scan results are only changed by scans (or by the paste becoming binary)
+        metadata.scan_status = existing.metadata.scan_status;
+
+        // signed urls are only revoked through revoke_signed_urls_by_url
+        metadata.signing_nonce = existing.metadata.signing_nonce;
+
+        let scan =
+            (metadata.content_type == "binary") && (existing.metadata.content_type != "binary");
+
+        // binary pastes have their size instead of an excerpt
+        if metadata.content_type == "binary" {
+            metadata.size = match self.get_binary_size(&existing.content) {
+                Ok(s) => s,
+                Err(e) => return Err(e),
+            };
+
+            metadata.excerpt = String::new();
+
+            if scan == true {
+                metadata.scan_status = self.get_pending_scan_status();
+            }
+        } else {
+            metadata.size = 0;
+        }
+
+        if metadata.content_type != existing.metadata.content_type {
+            self.apply_checksums(&existing.content, &mut metadata);
+        }
+
+        // edit paste
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "UPDATE \"se_pastes\" SET \"metadata\" = ? WHERE \"url\" = ?"
+        } else {
+            "UPDATE \"se_pastes\" SET \"metadata\" = $1 WHERE \"url\" = $2"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query)
+            .bind::<&String>(match serde_json::to_string(&metadata) {
+                Ok(ref m) => m,
+                Err(_) => return Err(PasteError::ValueError),
+            })
+            .bind::<&String>(&url)
+            .execute(c)
+            .await
+        {
+            Ok(_) => {
+                // remove from cache
+                self.base.cachedb.remove(format!("se_paste:{}", url)).await;
+                self.log_mutation(
+                    "edit_metadata",
+                    &url,
+                    "",
+                    match editing_as {
+                        Some(ref ua) => &ua.user.username,
+                        None => "",
+                    },
+                    None,
+                );
+
+                if scan == true {
+                    self.spawn_scan(url.clone());
+                }
+
+                self.index_paste_by_url(url).await;
+
+                // return
+                return Ok(());
+            }
+            Err(e) => return Err(query_failed(e)),
+        };
+    }
+
+    /// Claim an existing paste without an owner by `url` (see [`ServerOptions::paste_ownership`])
+    ///
+    /// Lets users take ownership of pastes they created before logging in.
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste to claim
+    /// * `password` - the paste's edit password
+    /// * `user` - the userstate of the user claiming the paste
+    pub async fn claim_paste_by_url(
+        &self,
+        mut url: String,
+        password: String,
+        user: FullUser<UserMetadata>,
+    ) -> Result<()> {
+        if self.options.paste_ownership == false {
+            return Err(PasteError::NotAllowed);
+        }
+
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+
+        if url.ends_with("-") {
+            url.pop();
+        }
+
+        // get paste
+        let existing = match self.get_paste_by_url(url.clone()).await {
+            Ok(p) => p,
+            Err(err) => return Err(err),
+        };
+
+        // check owner
+        if existing.metadata.owner == user.user.username {
+            return Ok(());
+        }
+
+        if !existing.metadata.owner.is_empty() {
+            return Err(PasteError::ChangeOwner);
+        }
+
+        // check password
+        if utility::hash(password) != existing.password {
+            return Err(PasteError::PasswordIncorrect);
+        }
+
+        // claim
+        let mut metadata = existing.metadata;
+        metadata.owner = user.user.username.clone();
+
+        self.set_metadata_by_url(url, metadata, "claim", &user.user.username)
+            .await
+    }
+
+    /// Give an existing paste by `url` to another user (see [`ServerOptions::paste_ownership`])
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste to transfer
+    /// * `owner` - the username of the new owner
+    /// * `user` - the userstate of the user transferring the paste (must be the owner or have the "ManagePastes" permission)
+    /// * `assertion` - passkey assertion from the owner (for high security pastes)
+    pub async fn transfer_paste_by_url(
+        &self,
+        mut url: String,
+        owner: String,
+        user: FullUser<UserMetadata>,
+        assertion: Option<WebAuthnAssertion>,
+    ) -> Result<()> {
+        if self.options.paste_ownership == false {
+            return Err(PasteError::NotAllowed);
+        }
+
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+
+        if url.ends_with("-") {
+            url.pop();
+        }
+
+        // get paste
+        let existing = match self.get_paste_by_url(url.clone()).await {
+            Ok(p) => p,
+            Err(err) => return Err(err),
+        };
+
+        // check owner
+        if (existing.metadata.owner != user.user.username)
+            && !user.level.permissions.contains(&"ManagePastes".to_string())
+        {
+            return Err(PasteError::ChangeOwner);
+        }
+
+        // the new owner has to exist
+        if owner.is_empty() | self.auth.get_user_by_username(owner.clone()).await.is_err() {
+            return Err(PasteError::ValueError);
+        }
+
+        if let Err(e) = self.check_high_security(&existing, assertion).await {
+            return Err(e);
+        }
+
+        // transfer
+        let mut metadata = existing.metadata;
+        metadata.owner = owner;
+
+        self.set_metadata_by_url(url, metadata, "transfer", &user.user.username)
+            .await
+    }
+
+    /// Write the metadata of an existing paste by `url` (once the change has been checked)
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste
+    /// * `metadata` - the paste's new metadata
+    /// * `action` - the change, for the audit log
+    /// * `actor` - the username of the user who changed the metadata
+    async fn set_metadata_by_url(
+        &self,
+        url: String,
+        metadata: PasteMetadata,
+        action: &str,
+        actor: &str,
+    ) -> Result<()> {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "UPDATE \"se_pastes\" SET \"metadata\" = ? WHERE \"url\" = ?"
+        } else {
+            "UPDATE \"se_pastes\" SET \"metadata\" = $1 WHERE \"url\" = $2"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query)
+            .bind::<&String>(match serde_json::to_string(&metadata) {
+                Ok(ref m) => m,
+                Err(_) => return Err(PasteError::ValueError),
+            })
+            .bind::<&String>(&url)
+            .execute(c)
+            .await
+        {
+            Ok(_) => {
+                // remove from cache
+                self.base.cachedb.remove(format!("se_paste:{}", url)).await;
+                self.log_mutation(action, &url, "", actor, None);
+                self.index_paste_by_url(url).await;
+
+                // return
+                Ok(())
+            }
+            Err(e) => Err(query_failed(e)),
+        }
+    }
+
+    /// Place or clear a legal hold on an existing paste by `url`
+    ///
+    /// Pastes under a legal hold can't be deleted.
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste
+    /// * `legal_hold` - if the paste should be held
+    /// * `user` - the userstate of the user changing the hold (must have the "ManagePastes" permission)
+    pub async fn set_legal_hold_by_url(
+        &self,
+        mut url: String,
+        legal_hold: bool,
+        user: FullUser<UserMetadata>,
+    ) -> Result<()> {
+        if !user.level.permissions.contains(&"ManagePastes".to_string()) {
+            return Err(PasteError::NotAllowed);
+        }
+
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+
+        if url.ends_with("-") {
+            url.pop();
+        }
+
+        // get paste
+        let existing = match self.get_paste_by_url(url.clone()).await {
+            Ok(p) => p,
+            Err(err) => return Err(err),
+        };
+
+        let mut metadata = existing.metadata;
+        metadata.legal_hold = legal_hold;
+
+        // edit paste
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "UPDATE \"se_pastes\" SET \"metadata\" = ? WHERE \"url\" = ?"
+        } else {
+            "UPDATE \"se_pastes\" SET \"metadata\" = $1 WHERE \"url\" = $2"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query)
+            .bind::<&String>(match serde_json::to_string(&metadata) {
+                Ok(ref m) => m,
+                Err(_) => return Err(PasteError::ValueError),
+            })
+            .bind::<&String>(&url)
+            .execute(c)
+            .await
+        {
+            Ok(_) => {
+                // remove from cache
+                self.base.cachedb.remove(format!("se_paste:{}", url)).await;
+
+                // return
+                return Ok(());
+            }
+            Err(e) => return Err(query_failed(e)),
+        };
+    }
+
+    /// Pin a snapshot of an existing paste's content to IPFS by `url`
+    ///
+    /// The resulting CID is stored in the paste's `ipfs_cid` metadata field.
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste to pin
+    /// * `password` - the paste's edit password
+    /// * `editing_as` - the userstate of the user we're pinning the paste as
+    ///
+    /// ## Returns:
+    /// * Result containing the CID of the snapshot
+    #[cfg(feature = "ipfs")]
+    pub async fn pin_paste_by_url(
+        &self,
+        mut url: String,
+        password: String,
+        editing_as: Option<FullUser<UserMetadata>>,
+    ) -> Result<String> {
+        let ipfs = match self.options.ipfs {
+            Some(ref i) => i,
+            None => return Err(PasteError::NotAllowed),
+        };
+
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+
+        if url.ends_with("-") {
+            url.pop();
+        }
+
+        // get paste
+        let existing = match self.get_paste_by_url(url.clone()).await {
+            Ok(p) => p,
+            Err(err) => return Err(err),
+        };
+
+        // check password
+        let mut skip_password_check: bool = false;
+
+        if let Some(ref ua) = editing_as {
+            // check if we're the paste owner
+            if ua.user.username == existing.metadata.owner {
+                skip_password_check = true;
+            }
+            // check if we have the "ManagePastes" permission
+            else if ua.level.permissions.contains(&"ManagePastes".to_string()) {
+                skip_password_check = true;
+            }
+        }
+
+        if skip_password_check == false {
+            if utility::hash(password.clone()) != existing.password {
+                return Err(PasteError::PasswordIncorrect);
+            }
+        }
+
+        // upload content
+        let form = reqwest::multipart::Form::new().part(
+            "file",
+            reqwest::multipart::Part::text(existing.content).file_name(format!("{}.md", url)),
+        );
+
+        let res = match reqwest::Client::new()
+            .post(format!("{}/api/v0/add?pin=true", ipfs.api_url))
+            .bearer_auth(&ipfs.token)
+            .multipart(form)
+            .send()
+            .await
+        {
+            Ok(r) => r,
+            Err(_) => return Err(PasteError::Other),
+        };
+
+        let cid = match res.json::<Value>().await {
+            Ok(v) => match v.get("Hash").and_then(|h| h.as_str()) {
+                Some(h) => h.to_string(),
+                None => return Err(PasteError::Other),
+            },
+            Err(_) => return Err(PasteError::Other),
+        };
+
+        // store cid
+        let mut metadata = existing.metadata;
+        metadata.ipfs_cid = cid.clone();
+
+        match self
+            .edit_paste_metadata_by_url(url, password, metadata, editing_as, None)
+            .await
+        {
+            Ok(_) => Ok(cid),
+            Err(e) => Err(e),
+        }
+    }
+
+    // runners
+
+    /// Get the stored outputs of an existing paste's runnable code blocks by `url`
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste
+    pub async fn get_outputs_by_url(&self, mut url: String) -> Vec<BlockOutput> {
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+
+        if url.ends_with("-") {
+            url.pop();
+        }
+
+        match self.base.cachedb.get(format!("se_outputs:{}", url)).await {
+            Some(c) => serde_json::from_str::<Vec<BlockOutput>>(c.as_str()).unwrap_or_default(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Run an existing paste's runnable code blocks by `url`
+    ///
+    /// Blocks whose code hasn't changed since they were last run keep their stored output.
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste to run
+    /// * `password` - the paste's edit password
+    /// * `editing_as` - the userstate of the user we're running the paste as
+    #[cfg(feature = "runners")]
+    pub async fn run_paste_by_url(
+        &self,
+        mut url: String,
+        password: String,
+        editing_as: Option<FullUser<UserMetadata>>,
+    ) -> Result<Vec<BlockOutput>> {
+        if self.options.runners.is_empty() {
+            return Err(PasteError::NotAllowed);
+        }
+
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+
+        if url.ends_with("-") {
+            url.pop();
+        }
+
+        // get paste
+        let existing = match self.get_paste_by_url(url.clone()).await {
+            Ok(p) => p,
+            Err(err) => return Err(err),
+        };
+
+        // check password
+        let mut skip_password_check: bool = false;
+
+        if let Some(ua) = editing_as {
+            // check if we're the paste owner
+            if ua.user.username == existing.metadata.owner {
+                skip_password_check = true;
+            }
+            // check if we have the "ManagePastes" permission
+            else if ua.level.permissions.contains(&"ManagePastes".to_string()) {
+                skip_password_check = true;
+            }
+        }
+
+        if skip_password_check == false {
+            if utility::hash(password) != existing.password {
+                return Err(PasteError::PasswordIncorrect);
+            }
+        }
+
+        // run blocks
+        let stored = self.get_outputs_by_url(url.clone()).await;
+        let http = reqwest::Client::new();
+        let mut outputs: Vec<BlockOutput> = Vec::new();
+
+        for block in notebook::runnable_blocks(&existing.content) {
+            let hash = utility::hash(format!("{}\n{}", block.language, block.code));
+
+            // reuse stored output
+            if let Some(o) = stored
+                .iter()
+                .find(|o| (o.index == block.index) && (o.hash == hash))
+            {
+                outputs.push(o.clone());
+                continue;
+            }
+
+            let mut output = BlockOutput {
+                index: block.index,
+                language: block.language.clone(),
+                hash,
+                stdout: String::new(),
+                stderr: String::new(),
+                exit_code: -1,
+                error: String::new(),
+            };
+
+            let runner = match self.options.runners.get(&block.language) {
+                Some(r) => r,
+                None => {
+                    output.error = format!("No runner for \"{}\".", block.language);
+                    outputs.push(output);
+                    continue;
+                }
+            };
+
+            match http
+                .post(&runner.url)
+                .timeout(std::time::Duration::from_millis(runner.timeout))
+                .json(&serde_json::json!({
+                    "language": block.language,
+                    "code": block.code,
+                }))
+                .send()
+                .await
+            {
+                Ok(r) => match r.json::<Value>().await {
+                    Ok(v) => {
+                        output.stdout = v["stdout"].as_str().unwrap_or("").to_string();
+                        output.stderr = v["stderr"].as_str().unwrap_or("").to_string();
+                        output.exit_code = v["exit_code"].as_i64().unwrap_or(-1) as i32;
+                    }
+                    Err(_) => output.error = String::from("Runner returned an invalid response."),
+                },
+                Err(e) => {
+                    output.error = if e.is_timeout() {
+                        String::from("Runner timed out.")
+                    } else {
+                        String::from("Runner could not be reached.")
+                    }
+                }
+            }
+
+            outputs.push(output);
+        }
+
+        // store outputs
+        self.base
+            .cachedb
+            .set(
+                format!("se_outputs:{}", url),
+                serde_json::to_string(&outputs).unwrap(),
+            )
+            .await;
+
+        // return
+        Ok(outputs)
+    }
+
+    // passkeys
+
+    /// Check a passkey assertion before a destructive action on `paste`
+    ///
+    /// Only [`PasteMetadata::high_security`] pastes whose owner has registered passkeys
+    /// need an assertion, every other paste (or a server without [`ServerOptions::webauthn`])
+    /// passes without one.
+    ///
+    /// ## Arguments:
+    /// * `paste` - the paste being changed
+    /// * `assertion` - passkey assertion from the paste's owner
+    #[cfg(feature = "webauthn")]
+    pub async fn check_high_security(
+        &self,
+        paste: &Paste,
+        assertion: Option<WebAuthnAssertion>,
+    ) -> Result<()> {
+        if (paste.metadata.high_security == false)
+            | paste.metadata.owner.is_empty()
+            | self.options.webauthn.is_none()
+        {
+            return Ok(());
+        }
+
+        if self
+            .get_credentials(paste.metadata.owner.clone())
+            .await
+            .is_empty()
+        {
+            return Ok(());
+        }
+
+        match assertion {
+            Some(a) => self.verify_assertion(paste.metadata.owner.clone(), a).await,
+            None => Err(PasteError::WebAuthnRequired),
+        }
+    }
+
+    /// Check a passkey assertion before a destructive action on `paste`
+    ///
+    /// Always passes without the `webauthn` feature.
+    #[cfg(not(feature = "webauthn"))]
+    pub async fn check_high_security(
+        &self,
+        _paste: &Paste,
+        _assertion: Option<WebAuthnAssertion>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Create a new single-use challenge for passkey assertions
+    #[cfg(feature = "webauthn")]
+    pub async fn create_webauthn_challenge(&self) -> Result<String> {
+        if self.options.webauthn.is_none() {
+            return Err(PasteError::NotAllowed);
+        }
+
+        let challenge = webauthn::challenge();
+        self.base
+            .cachedb
+            .set(
+                format!("se_webauthn_challenge:{}", challenge),
+                utility::unix_epoch_timestamp().to_string(),
+            )
+            .await;
+
+        Ok(challenge)
+    }
+
+    /// Verify a passkey assertion made with one of `username`'s credentials
+    ///
+    /// ## Arguments:
+    /// * `username` - the user the assertion must be from
+    /// * `assertion` - the assertion
+    #[cfg(feature = "webauthn")]
+    pub async fn verify_assertion(
+        &self,
+        username: String,
+        assertion: WebAuthnAssertion,
+    ) -> Result<()> {
+        let options = match self.options.webauthn {
+            Some(ref o) => o,
+            None => return Err(PasteError::NotAllowed),
+        };
+
+        // get credential
+        let credential = match self
+            .get_credentials(username)
+            .await
+            .into_iter()
+            .find(|c| c.id == assertion.credential_id)
+        {
+            Some(c) => c,
+            None => return Err(PasteError::WebAuthnRequired),
+        };
+
+        // use challenge
+        let challenge = match webauthn::get_challenge(&assertion) {
+            Some(c) => c,
+            None => return Err(PasteError::WebAuthnRequired),
+        };
+
+        let key = format!("se_webauthn_challenge:{}", challenge);
+        let issued = match self.base.cachedb.get(key.clone()).await {
+            Some(t) => t.parse::<u128>().unwrap_or(0),
+            None => return Err(PasteError::WebAuthnRequired),
+        };
+
+        self.base.cachedb.remove(key).await;
+
+        if utility::unix_epoch_timestamp() - issued > webauthn::CHALLENGE_TTL {
+            return Err(PasteError::WebAuthnRequired);
+        }
+
+        // verify
+        let public_key = match webauthn::decode(&credential.public_key) {
+            Some(k) => k,
+            None => return Err(PasteError::Other),
+        };
+
+        let sign_count = match webauthn::verify(
+            &assertion,
+            &public_key,
+            &challenge,
+            &options.rp_id,
+            &options.origin,
+        ) {
+            Some(c) => c,
+            None => return Err(PasteError::WebAuthnRequired),
+        };
+
+        // authenticators without a counter always send 0, anything else must go up
+        // (a counter going backwards means the credential may have been cloned)
+        if ((sign_count != 0) | (credential.sign_count != 0))
+            && (sign_count <= credential.sign_count)
+        {
+            return Err(PasteError::WebAuthnRequired);
+        }
+
+        // update counter
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "UPDATE \"se_credentials\" SET \"sign_count\" = ? WHERE \"id\" = ?"
+        } else {
+            "UPDATE \"se_credentials\" SET \"sign_count\" = $1 WHERE \"id\" = $2"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query)
+            .bind::<&String>(&sign_count.to_string())
+            .bind::<&String>(&credential.id)
+            .execute(c)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(query_failed(e)),
+        }
+    }
+
+    /// Get all passkeys registered by `username`
+    ///
+    /// ## Arguments:
+    /// * `username` - the user
+    #[cfg(feature = "webauthn")]
+    pub async fn get_credentials(&self, username: String) -> Vec<WebAuthnCredential> {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"se_credentials\" WHERE \"username\" = ?"
+        } else {
+            "SELECT * FROM \"se_credentials\" WHERE \"username\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        let rows = match sqlquery(query)
+            .bind::<&String>(&username)
+            .fetch_all(c)
+            .await
+        {
+            Ok(r) => r,
+            Err(_) => return Vec::new(),
+        };
+
+        let mut out: Vec<WebAuthnCredential> = Vec::new();
+
+        for row in rows {
+            let res = self.base.textify_row(row).data;
+            out.push(WebAuthnCredential {
+                id: res.get("id").unwrap().to_string(),
+                username: res.get("username").unwrap().to_string(),
+                public_key: res.get("public_key").unwrap().to_string(),
+                sign_count: res.get("sign_count").unwrap().parse::<u32>().unwrap_or(0),
+                timestamp: res.get("timestamp").unwrap().parse::<u128>().unwrap(),
+            });
+        }
+
+        out
+    }
+
+    /// Register a new passkey for `username`
+    ///
+    /// The credential isn't attested, the user registering it is trusted to have created it.
+    ///
+    /// ## Arguments:
+    /// * `props` - [`WebAuthnCredentialCreate`]
+    /// * `username` - the user registering the credential
+    #[cfg(feature = "webauthn")]
+    pub async fn create_credential(
+        &self,
+        props: WebAuthnCredentialCreate,
+        username: String,
+    ) -> Result<WebAuthnCredential> {
+        if self.options.webauthn.is_none() {
+            return Err(PasteError::NotAllowed);
+        }
+
+        // check credential
+        if props.algorithm != webauthn::ES256 {
+            return Err(PasteError::ValueError);
+        }
+
+        if (props.id.len() > 1024) | webauthn::decode(&props.id).map_or(true, |i| i.is_empty()) {
+            return Err(PasteError::ValueError);
+        }
+
+        match webauthn::decode(&props.public_key) {
+            Some(k) => {
+                if !webauthn::is_public_key(&k) {
+                    return Err(PasteError::ValueError);
+                }
+            }
+            None => return Err(PasteError::ValueError),
+        };
+
+        // make sure credential doesn't already exist
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"se_credentials\" WHERE \"id\" = ?"
+        } else {
+            "SELECT * FROM \"se_credentials\" WHERE \"id\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        if let Ok(_) = sqlquery(query)
+            .bind::<&String>(&props.id)
+            .fetch_one(c)
+            .await
+        {
+            return Err(PasteError::AlreadyExists);
+        }
+
+        // create credential
+        let credential = WebAuthnCredential {
+            id: props.id,
+            username,
+            public_key: props.public_key,
+            sign_count: 0,
+            timestamp: utility::unix_epoch_timestamp(),
+        };
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "INSERT INTO \"se_credentials\" VALUES (?, ?, ?, ?, ?)"
+        } else {
+            "INSERT INTO \"se_credentials\" VALUES ($1, $2, $3, $4, $5)"
+        };
+
+        match sqlquery(query)
+            .bind::<&String>(&credential.id)
+            .bind::<&String>(&credential.username)
+            .bind::<&String>(&credential.public_key)
+            .bind::<&String>(&credential.sign_count.to_string())
+            .bind::<&String>(&credential.timestamp.to_string())
+            .execute(c)
+            .await
+        {
+            Ok(_) => Ok(credential),
+            Err(e) => Err(query_failed(e)),
+        }
+    }
+
+    /// Delete one of `username`'s passkeys by `id`
+    ///
+    /// Needs an assertion from any of the user's passkeys, so a stolen session can't remove
+    /// the protection from their pastes.
+    ///
+    /// ## Arguments:
+    /// * `id` - the credential's id
+    /// * `username` - the user deleting the credential
+    /// * `assertion` - passkey assertion from the user
+    #[cfg(feature = "webauthn")]
+    pub async fn delete_credential(
+        &self,
+        id: String,
+        username: String,
+        assertion: WebAuthnAssertion,
+    ) -> Result<()> {
+        // make sure credential exists
+        if !self
+            .get_credentials(username.clone())
+            .await
+            .iter()
+            .any(|c| c.id == id)
+        {
+            return Err(PasteError::NotFound);
+        }
+
+        if let Err(e) = self.verify_assertion(username.clone(), assertion).await {
+            return Err(e);
+        }
+
+        // delete credential
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"se_credentials\" WHERE \"id\" = ? AND \"username\" = ?"
+        } else {
+            "DELETE FROM \"se_credentials\" WHERE \"id\" = $1 AND \"username\" = $2"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query)
+            .bind::<&String>(&id)
+            .bind::<&String>(&username)
+            .execute(c)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(query_failed(e)),
+        }
+    }
+
+    // edit links
+
+    /// Get the secret edit links are signed with (created the first time it's needed)
+    async fn get_edit_link_secret(&self) -> Result<String> {
+        self.get_or_create_setting(String::from("edit_link_secret"), utility::random_id())
+            .await
+    }
+
+    /// Sign an edit link for `paste`
+    ///
+    /// The paste's password hash is part of the signature, so changing it revokes the link.
+    async fn sign_edit_link(&self, paste: &Paste, expires: u128) -> Result<String> {
+        let secret = match self.get_edit_link_secret().await {
+            Ok(s) => s,
+            Err(e) => return Err(e),
+        };
+
+        Ok(format!(
+            "{}.{}",
+            expires,
+            utility::hash(format!(
+                "{}:{}:{}:{}",
+                secret, paste.url, expires, paste.password
+            ))
+        ))
+    }
+
+    /// Create an edit link for an existing paste by `url`
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste
+    /// * `password` - the paste's edit password
+    /// * `expires_in` - milliseconds until the link expires (`0` for 7 days, at most 30 days)
+    /// * `editing_as` - the userstate of the user creating the link
+    pub async fn create_edit_link(
+        &self,
+        mut url: String,
+        password: String,
+        expires_in: u128,
+        editing_as: Option<FullUser<UserMetadata>>,
+    ) -> Result<EditLink> {
+        if self.options.edit_links == false {
+            return Err(PasteError::NotAllowed);
+        }
+
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+
+        if url.ends_with("-") {
+            url.pop();
+        }
+
+        // get paste
+        let existing = match self.get_paste_by_url(url.clone()).await {
+            Ok(p) => p,
+            Err(err) => return Err(err),
+        };
+
+        // check password
+        let mut skip_password_check: bool = false;
+
+        if let Some(ua) = editing_as {
+            // check if we're the paste owner
+            if ua.user.username == existing.metadata.owner {
+                skip_password_check = true;
+            }
+            // check if we have the "ManagePastes" permission
+            else if ua.level.permissions.contains(&"ManagePastes".to_string()) {
+                skip_password_check = true;
+            }
+        }
+
+        if skip_password_check == false {
+            if utility::hash(password) != existing.password {
+                return Err(PasteError::PasswordIncorrect);
+            }
+        }
+
+        // check expiry
+        let expires_in = if expires_in == 0 {
+            7 * 24 * 60 * 60 * 1000
+        } else {
+            expires_in
+        };
+
+        if expires_in > 30 * 24 * 60 * 60 * 1000 {
+            return Err(PasteError::ValueError);
+        }
+
+        // sign
+        let expires = utility::unix_epoch_timestamp() + expires_in;
+
+        match self.sign_edit_link(&existing, expires).await {
+            Ok(token) => Ok(EditLink {
+                url: existing.url,
+                token,
+                expires,
+            }),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Check if `token` is an unexpired edit link for `paste`
+    ///
+    /// ## Arguments:
+    /// * `paste` - the paste being edited
+    /// * `token` - the edit link's token
+    pub async fn check_edit_link(&self, paste: &Paste, token: &str) -> bool {
+        if self.options.edit_links == false {
+            return false;
+        }
+
+        let expires = match token.split_once('.') {
+            Some((e, _)) => match e.parse::<u128>() {
+                Ok(e) => e,
+                Err(_) => return false,
+            },
+            None => return false,
+        };
+
+        if utility::unix_epoch_timestamp() > expires {
+            return false;
+        }
+
+        match self.sign_edit_link(paste, expires).await {
+            Ok(signed) => signed == token,
+            Err(_) => false,
+        }
+    }
+
+    // signed urls
+
+    /// Get the secret signed urls are signed with (created the first time it's needed)
+    #[cfg(feature = "signed_urls")]
+    async fn get_signed_url_secret(&self) -> Result<String> {
+        self.get_or_create_setting(String::from("signed_url_secret"), utility::random_id())
+            .await
+    }
+
+    /// Check if `user` (or someone with `password`) can manage the signed urls of `paste`
+    #[cfg(feature = "signed_urls")]
+    fn can_sign(
+        &self,
+        paste: &Paste,
+        password: String,
+        user: &Option<FullUser<UserMetadata>>,
+    ) -> Result<()> {
+        if let Some(ua) = user {
+            // check if we're the paste owner
+            if ua.user.username == paste.metadata.owner {
+                return Ok(());
+            }
+            // check if we have the "ManagePastes" permission
+            else if ua.level.permissions.contains(&"ManagePastes".to_string()) {
+                return Ok(());
+            }
+        }
+
+        if utility::hash(password) != paste.password {
+            return Err(PasteError::PasswordIncorrect);
+        }
+
+        Ok(())
+    }
+
+    /// Create a signed url for an existing paste by `url` (see [`crate::signing`])
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste
+    /// * `password` - the paste's edit password
+    /// * `expires_in` - milliseconds until the url expires (`0` for 1 day, at most 30 days)
+    /// * `user` - the userstate of the user creating the url
+    #[cfg(feature = "signed_urls")]
+    pub async fn create_signed_url(
+        &self,
+        mut url: String,
+        password: String,
+        expires_in: u128,
+        user: Option<FullUser<UserMetadata>>,
+    ) -> Result<SignedUrl> {
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+
+        if url.ends_with("-") {
+            url.pop();
+        }
+
+        // get paste
+        let existing = match self.get_paste_by_url(url.clone()).await {
+            Ok(p) => p,
+            Err(err) => return Err(err),
+        };
+
+        if let Err(e) = self.can_sign(&existing, password, &user) {
+            return Err(e);
+        }
+
+        // check expiry
+        let expires_in = if expires_in == 0 {
+            24 * 60 * 60 * 1000
+        } else {
+            expires_in
+        };
+
+        if expires_in > 30 * 24 * 60 * 60 * 1000 {
+            return Err(PasteError::ValueError);
+        }
+
+        // sign
+        let secret = match self.get_signed_url_secret().await {
+            Ok(s) => s,
+            Err(e) => return Err(e),
+        };
+
+        let exp = utility::unix_epoch_timestamp() + expires_in;
+        let sig = signing::sign(
+            &secret,
+            &existing.url,
+            &existing.metadata.signing_nonce,
+            exp,
+        );
+
+        Ok(SignedUrl {
+            query: format!("?sig={}&exp={}", sig, exp),
+            url: existing.url,
+            sig,
+            exp,
+        })
+    }
+
+    /// Revoke every signed url of an existing paste by `url` (by rotating its signing nonce)
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste
+    /// * `password` - the paste's edit password
+    /// * `user` - the userstate of the user revoking the urls
+    #[cfg(feature = "signed_urls")]
+    pub async fn revoke_signed_urls_by_url(
+        &self,
+        mut url: String,
+        password: String,
+        user: Option<FullUser<UserMetadata>>,
+    ) -> Result<()> {
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+
+        if url.ends_with("-") {
+            url.pop();
+        }
+
+        // get paste
+        let existing = match self.get_paste_by_url(url.clone()).await {
+            Ok(p) => p,
+            Err(err) => return Err(err),
+        };
+
+        if let Err(e) = self.can_sign(&existing, password, &user) {
+            return Err(e);
+        }
+
+        // rotate nonce
+        let mut metadata = existing.metadata;
+        metadata.signing_nonce = utility::random_id();
+
+        let actor = match user {
+            Some(ua) => ua.user.username,
+            None => String::new(),
+        };
+
+        self.set_metadata_by_url(url, metadata, "revoke_signed_urls", &actor)
+            .await
+    }
+
+    /// Check if `sig` is an unexpired signed url for `paste`
+    ///
+    /// ## Arguments:
+    /// * `paste` - the paste being read
+    /// * `sig` - the url's signature
+    /// * `exp` - timestamp the url expires at
+    #[cfg(feature = "signed_urls")]
+    pub async fn check_signed_url(&self, paste: &Paste, sig: &str, exp: u64) -> bool {
+        if sig.is_empty() | (utility::unix_epoch_timestamp() > exp as u128) {
+            return false;
+        }
+
+        match self.get_signed_url_secret().await {
+            Ok(secret) => signing::verify(
+                &secret,
+                &paste.url,
+                &paste.metadata.signing_nonce,
+                exp as u128,
+                sig,
+            ),
+            Err(_) => false,
+        }
+    }
+
+    /// Signed urls are never valid without the `signed_urls` feature
+    #[cfg(not(feature = "signed_urls"))]
+    pub async fn check_signed_url(&self, _paste: &Paste, _sig: &str, _exp: u64) -> bool {
+        false
+    }
+
+    // plugins
+
+    /// Run the `on_create` hook of every plugin, applying the tags they set
+    #[cfg(feature = "plugins")]
+    fn run_create_hook(&self, paste: &mut Paste) -> Result<()> {
+        if self.plugins.is_empty() {
+            return Ok(());
+        }
+
+        match self.plugins.run(
+            plugins::Hook::Create,
+            plugins::HookInput {
+                url: paste.url.clone(),
+                content: paste.content.clone(),
+                metadata: paste.metadata.clone(),
+                html: None,
+            },
+        ) {
+            Ok(output) => {
+                paste.metadata.tags = output.metadata.tags;
+
+                // tags are checked like the ones users give
+                let mut v = validation::Validator::new();
+                validation::metadata(&mut v, &paste.metadata);
+                v.finish()
+            }
+            Err(reason) => Err(PasteError::Rejected(reason)),
+        }
+    }
+
+    /// Run the `on_create` hook of every plugin (does nothing without the `plugins` feature)
+    #[cfg(not(feature = "plugins"))]
+    fn run_create_hook(&self, _paste: &mut Paste) -> Result<()> {
+        Ok(())
+    }
+
+    /// Run the `on_render` hook of every plugin, returning the HTML they set
+    #[cfg(feature = "plugins")]
+    fn run_render_hook(&self, paste: &Paste, html: String) -> String {
+        if self.plugins.is_empty() {
+            return html;
+        }
+
+        let input = plugins::HookInput {
+            url: paste.url.clone(),
+            content: paste.content.clone(),
+            metadata: paste.metadata.clone(),
+            html: Some(html.clone()),
+        };
+
+        match self.plugins.run(plugins::Hook::Render, input) {
+            Ok(output) => output.html.unwrap_or(html),
+            Err(_) => html,
+        }
+    }
+
+    /// Run the `on_render` hook of every plugin (does nothing without the `plugins` feature)
+    #[cfg(not(feature = "plugins"))]
+    fn run_render_hook(&self, _paste: &Paste, html: String) -> String {
+        html
+    }
+
+    /// Run the `on_view` hook of every plugin, failing if any of them rejects the view
+    ///
+    /// ## Arguments:
+    /// * `paste` - the paste being viewed
+    #[cfg(feature = "plugins")]
+    pub fn run_view_hook(&self, paste: &Paste) -> Result<()> {
+        if self.plugins.is_empty() {
+            return Ok(());
+        }
+
+        let input = plugins::HookInput {
+            url: paste.url.clone(),
+            content: paste.content.clone(),
+            metadata: paste.metadata.clone(),
+            html: None,
+        };
+
+        match self.plugins.run(plugins::Hook::View, input) {
+            Ok(_) => Ok(()),
+            Err(reason) => Err(PasteError::Rejected(reason)),
+        }
+    }
+
+    /// Run the `on_view` hook of every plugin (does nothing without the `plugins` feature)
+    #[cfg(not(feature = "plugins"))]
+    pub fn run_view_hook(&self, _paste: &Paste) -> Result<()> {
+        Ok(())
+    }
+
+    // rules
+
+    /// Check a change against the scripted rules in [`ServerOptions::scripts_dir`]
+    ///
+    /// ## Arguments:
+    /// * `action` - `"create"` or `"edit"`
+    /// * `url` - the paste's url
+    /// * `content` - the paste's new content
+    /// * `author` - username of the user making the change (empty when anonymous)
+    #[cfg(feature = "scripting")]
+    pub fn check_rules(&self, action: &str, url: &str, content: &str, author: &str) -> Result<()> {
+        let scripts = match self.scripts {
+            Some(ref s) => s,
+            None => return Ok(()),
+        };
+
+        match scripts.check(&scripting::RuleInput {
+            action,
+            url,
+            content,
+            author,
+        }) {
+            Ok(_) => Ok(()),
+            Err(reason) => Err(PasteError::Rejected(reason)),
+        }
+    }
+
+    /// Check a change against scripted rules (does nothing without the `scripting` feature)
+    #[cfg(not(feature = "scripting"))]
+    pub fn check_rules(
+        &self,
+        _action: &str,
+        _url: &str,
+        _content: &str,
+        _author: &str,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    // rate limits
+
+    /// Count a request against a per-minute rate limit
+    ///
+    /// Counters are kept in the shared cache, so every instance using it enforces the same limit.
+    /// The counter is incremented before it's read (and `INCR` is atomic), so concurrent
+    /// requests can't all pass by reading the same count.
+    ///
+    /// ## Arguments:
+    /// * `key` - the cache key prefix identifying what's being limited
+    /// * `limit` - the maximum number of requests per minute (`0` for unlimited)
+    pub async fn check_rate_limit(&self, key: String, limit: usize) -> Result<()> {
+        if limit == 0 {
+            return Ok(());
+        }
+
+        let minute = utility::unix_epoch_timestamp() / 60_000;
+
+        self.base.cachedb.incr(format!("{}:{}", key, minute)).await;
+
+        let count = match self.base.cachedb.get(format!("{}:{}", key, minute)).await {
+            Some(c) => c.parse::<usize>().unwrap_or(0),
+            None => 0,
+        };
+
+        if count <= 1 {
+            // new minute, previous one is no longer needed
+            self.base
+                .cachedb
+                .remove(format!("{}:{}", key, minute - 1))
+                .await;
+        }
+
+        if count > limit {
+            return Err(PasteError::RateLimited);
+        }
+
+        Ok(())
+    }
+
+    // preview
+
+    /// Render `content` with [`ServerOptions::renderer`] without storing it
+    ///
+    /// Content goes through the same steps as stored pastes in [`Database::get_rendered`].
+    ///
+    /// ## Arguments:
+    /// * `content` - the content to render
+    pub async fn preview(&self, content: String) -> Result<String> {
+        let renderer = match self.options.renderer {
+            Some(ref r) => r.clone(),
+            None => return Err(PasteError::NotAllowed),
+        };
+
+        if content.len() > validation::MAX_CONTENT_LENGTH {
+            return Err(PasteError::ContentTooLarge);
+        }
+
+        if content.len() < 1 {
+            return Err(PasteError::ValueError);
+        }
+
+        let content = if self.options.includes == true {
+            self.expand_includes_inner(content, Vec::new()).await
+        } else {
+            content
+        };
+
+        // return
+        if self.options.front_matter == true {
+            Ok((renderer.0)(front_matter::strip(&content)))
+        } else {
+            Ok((renderer.0)(&content))
+        }
+    }
+
+    // search
+
+    /// Update the search index entry of a paste by `url` after it was written
+    ///
+    /// Pastes which can't be searched (see [`search::is_searchable`]) are removed from the index.
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste to index
+    async fn index_paste_by_url(&self, url: String) {
+        if self.options.search == false {
+            return;
+        }
+
+        let (delete, insert) = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql")
+        {
+            (
+                "DELETE FROM \"se_search\" WHERE \"url\" = ?",
+                "INSERT INTO \"se_search\" (\"url\", \"title\", \"content\") VALUES (?, ?, ?)",
+            )
+        } else {
+            (
+                "DELETE FROM \"se_search\" WHERE \"url\" = $1",
+                "INSERT INTO \"se_search\" (\"url\", \"title\", \"content\") VALUES ($1, $2, $3)",
+            )
+        };
+
+        let mut statements: Vec<(&str, Vec<String>)> = vec![(delete, vec![url.clone()])];
+
+        if let Ok(paste) = self.get_paste_by_url(url).await {
+            if search::is_searchable(&paste) {
+                statements.push((insert, vec![paste.url, paste.metadata.title, paste.content]));
+            }
+        }
+
+        let _ = self.transaction(statements).await;
+    }
+
+    /// Search public pastes, best matches first
+    ///
+    /// Falls back to matching every word with `LIKE` (newest pastes first) if the search
+    /// index can't be queried, e.g. when SQLite was built without FTS5.
+    ///
+    /// ## Arguments:
+    /// * `query` - the words to search for
+    /// * `opts` - [`SearchOptions`]
+    pub async fn search_pastes(
+        &self,
+        query: String,
+        opts: SearchOptions,
+    ) -> Result<Vec<SearchResult>> {
+        if self.options.search == false {
+            return Err(PasteError::NotAllowed);
+        }
+
+        let query = query.trim().to_string();
+
+        if (query.len() < 1) | (query.len() > search::MAX_QUERY_LENGTH) {
+            return Err(PasteError::ValueError);
+        }
+
+        let limit = match opts.limit {
+            0 => 10,
+            l => l.min(50),
+        };
+
+        // search index
+        let c = &self.base.db.client;
+        let rows = if self.base.db._type == "sqlite" {
+            let sql = format!(
+                "SELECT \"url\" FROM \"se_search\" WHERE \"se_search\" MATCH ? ORDER BY bm25(\"se_search\", 0.0, 10.0, 1.0) LIMIT {} OFFSET {}",
+                limit, opts.offset
+            );
+
+            sqlquery(&sql)
+                .bind::<String>(search::fts5_query(&query))
+                .fetch_all(c)
+                .await
+        } else if self.base.db._type == "mysql" {
+            let sql = format!(
+                "SELECT \"url\" FROM \"se_search\" WHERE MATCH (\"title\", \"content\") AGAINST (? IN NATURAL LANGUAGE MODE) ORDER BY MATCH (\"title\", \"content\") AGAINST (? IN NATURAL LANGUAGE MODE) DESC LIMIT {} OFFSET {}",
+                limit, opts.offset
+            );
+
+            sqlquery(&sql)
+                .bind::<&String>(&query)
+                .bind::<&String>(&query)
+                .fetch_all(c)
+                .await
+        } else {
+            let sql = format!(
+                "SELECT \"url\" FROM \"se_search\" WHERE \"document\" @@ plainto_tsquery('simple', $1) ORDER BY ts_rank(\"document\", plainto_tsquery('simple', $1)) DESC LIMIT {} OFFSET {}",
+                limit, opts.offset
+            );
+
+            sqlquery(&sql).bind::<&String>(&query).fetch_all(c).await
+        };
+
+        let urls: Vec<String> = match rows {
+            Ok(r) => r
+                .into_iter()
+                .map(|row| {
+                    self.base
+                        .textify_row(row)
+                        .data
+                        .get("url")
+                        .unwrap()
+                        .to_string()
+                })
+                .collect(),
+            Err(_) => match self.search_pastes_like(&query, limit, opts.offset).await {
+                Ok(u) => u,
+                Err(e) => return Err(e),
+            },
+        };
+
+        // get pastes (expired pastes are skipped)
+        let mut out: Vec<SearchResult> = Vec::new();
+
+        for url in urls {
+            let paste = match self.get_paste_by_url(url).await {
+                Ok(p) => p,
+                Err(_) => continue,
+            };
+
+            if !search::is_searchable(&paste) {
+                continue;
+            }
+
+            out.push(SearchResult {
+                url: paste.url,
+                title: paste.metadata.title,
+                excerpt: paste.metadata.excerpt,
+                snippet: search::snippet(&paste.content, &query),
+                date_published: paste.date_published,
+                date_edited: paste.date_edited,
+            });
+        }
+
+        // return
+        Ok(out)
+    }
+
+    /// Find public pastes containing every word of `query` without the search index
+    async fn search_pastes_like(
+        &self,
+        query: &str,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<String>> {
+        let words: Vec<String> = query
+            .split_whitespace()
+            .take(10)
+            .map(|w| {
+                format!(
+                    "%{}%",
+                    w.to_lowercase()
+                        .replace('!', "!!")
+                        .replace('%', "!%")
+                        .replace('_', "!_")
+                )
+            })
+            .collect();
+
+        let mut conditions: Vec<String> = PUBLIC_CONDITIONS.iter().map(|c| c.to_string()).collect();
+
+        for i in 1..=words.len() {
+            conditions.push(
+                if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+                    String::from("LOWER(\"content\") LIKE ? ESCAPE '!'")
+                } else {
+                    format!("LOWER(\"content\") LIKE ${} ESCAPE '!'", i)
+                },
+            );
+        }
+
+        let sql = format!(
+            "SELECT \"url\" FROM \"se_pastes\" WHERE {} ORDER BY \"date_published\" DESC LIMIT {} OFFSET {}",
+            conditions.join(" AND "),
+            limit,
+            offset
+        );
+
+        let mut q = sqlquery(&sql);
+
+        for word in words {
+            q = q.bind(word);
+        }
+
+        let c = &self.base.db.client;
+        match q.fetch_all(c).await {
+            Ok(r) => Ok(r
+                .into_iter()
+                .map(|row| {
+                    self.base
+                        .textify_row(row)
+                        .data
+                        .get("url")
+                        .unwrap()
+                        .to_string()
+                })
+                .collect()),
+            Err(e) => Err(query_failed(e)),
+        }
+    }
+
+    // listing
+
+    /// Get a page of pastes, newest first
+    ///
+    /// ## Arguments:
+    /// * `owner` - only list pastes owned by this user
+    /// * `public` - only list pastes which could be returned in search results (see [`search::is_searchable`])
+    /// * `offset` - the number of pastes to skip
+    /// * `limit` - the maximum number of pastes (up to `100`, `0` for [`PAGE_SIZE`])
+    async fn list_pastes(
+        &self,
+        owner: Option<String>,
+        public: bool,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<PasteListing>> {
+        let limit = match limit {
+            0 => PAGE_SIZE,
+            l => l.min(100),
+        };
+
+        // build query (metadata is stored as JSON, so it's matched as text)
+        let mut conditions: Vec<&str> = Vec::new();
+
+        if owner.is_some() {
+            conditions.push(
+                if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+                    "\"metadata\" LIKE ? ESCAPE '!'"
+                } else {
+                    "\"metadata\" LIKE $1 ESCAPE '!'"
+                },
+            );
+        }
+
+        if public == true {
+            conditions.extend(PUBLIC_CONDITIONS);
+        }
+
+        // timestamps are stored as text, but they all have the same length
+        let query = format!(
+            "SELECT \"url\" FROM \"se_pastes\"{} ORDER BY \"date_published\" DESC LIMIT {} OFFSET {}",
+            if conditions.is_empty() {
+                String::new()
+            } else {
+                format!(" WHERE {}", conditions.join(" AND "))
+            },
+            limit,
+            offset
+        );
+
+        let c = &self.base.db.client;
+        let rows = match owner {
+            Some(ref owner) => {
+                let pattern = format!(
+                    "%\"owner\":{}%",
+                    serde_json::to_string(owner)
+                        .unwrap()
+                        .replace('!', "!!")
+                        .replace('%', "!%")
+                        .replace('_', "!_")
+                );
+
+                sqlquery(&query).bind::<String>(pattern).fetch_all(c).await
+            }
+            None => sqlquery(&query).fetch_all(c).await,
+        };
+
+        let rows = match rows {
+            Ok(r) => r,
+            Err(e) => return Err(query_failed(e)),
+        };
+
+        // get pastes (expired pastes are skipped)
+        let mut out: Vec<PasteListing> = Vec::new();
+
+        for row in rows {
+            let url = self
+                .base
+                .textify_row(row)
+                .data
+                .get("url")
+                .unwrap()
+                .to_string();
+
+            let paste = match self.get_paste_by_url(url).await {
+                Ok(p) => p,
+                Err(_) => continue,
+            };
+
+            if (public == true) && !search::is_searchable(&paste) {
+                continue;
+            }
+
+            out.push(PasteListing {
+                excerpt: if paste.metadata.view_password.is_empty() {
+                    paste.metadata.excerpt
+                } else {
+                    String::new()
+                },
+                url: paste.url,
+                title: paste.metadata.title,
+                owner: paste.metadata.owner,
+                date_published: paste.date_published,
+                date_edited: paste.date_edited,
+            });
+        }
+
+        // return
+        Ok(out)
+    }
+
+    /// Get a page of every paste owned by `username`, newest first
+    ///
+    /// ## Arguments:
+    /// * `username` - the owner of the pastes
+    /// * `offset` - the number of pastes to skip
+    /// * `limit` - the maximum number of pastes (up to `100`, `0` for [`PAGE_SIZE`])
+    pub async fn get_pastes_by_owner(
+        &self,
+        username: String,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<PasteListing>> {
+        self.list_pastes(Some(username), false, offset, limit).await
+    }
+
+    /// Get a page of the public pastes owned by `username`, newest first
+    ///
+    /// Pastes with a view password, pastes which ask not to be indexed and sensitive pastes
+    /// aren't listed.
+    ///
+    /// ## Arguments:
+    /// * `username` - the owner of the pastes
+    /// * `offset` - the number of pastes to skip
+    /// * `limit` - the maximum number of pastes (up to `100`, `0` for [`PAGE_SIZE`])
+    pub async fn get_public_pastes_by_owner(
+        &self,
+        username: String,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<PasteListing>> {
+        self.list_pastes(Some(username), true, offset, limit).await
+    }
+
+    /// Get a page of the newest public pastes
+    ///
+    /// Pastes with a view password, pastes which ask not to be indexed and sensitive pastes
+    /// aren't listed.
+    ///
+    /// ## Arguments:
+    /// * `offset` - the number of pastes to skip
+    /// * `limit` - the maximum number of pastes (up to `100`, `0` for [`PAGE_SIZE`])
+    pub async fn get_recent_public_pastes(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<PasteListing>> {
+        self.list_pastes(None, true, offset, limit).await
+    }
+
+    /// Stream every paste owned by `username`, newest first (for data exports)
+    ///
+    /// Pastes are fetched a page at a time as the stream is read, and their edit password
+    /// hashes are removed.
+    ///
+    /// ## Arguments:
+    /// * `username` - the owner of the pastes
+    pub fn export_pastes_by_owner(
+        &self,
+        username: String,
+    ) -> impl Stream<Item = Paste> + Send + 'static {
+        futures_util::stream::unfold(
+            (self.clone(), username, 0, Vec::<String>::new()),
+            |(database, username, mut offset, mut urls)| async move {
+                loop {
+                    if let Some(url) = urls.pop() {
+                        match database.get_paste_by_url(url).await {
+                            Ok(mut paste) => {
+                                paste.password = String::new();
+                                return Some((paste, (database, username, offset, urls)));
+                            }
+                            // deleted since the page was fetched
+                            Err(_) => continue,
+                        }
+                    }
+
+                    // next page
+                    let page = match database
+                        .get_pastes_by_owner(username.clone(), offset, 100)
+                        .await
+                    {
+                        Ok(p) => p,
+                        Err(_) => return None,
+                    };
+
+                    if page.is_empty() {
+                        return None;
+                    }
+
+                    offset += page.len();
+                    urls = page.into_iter().rev().map(|p| p.url).collect();
+                }
+            },
+        )
+    }
+
+    /// Import pastes (e.g. from [`Database::export_pastes_by_owner`]) in a single transaction
+    /// (see [`ServerOptions::imports`])
+    ///
+    /// Imported pastes are owned by `user` and get new edit passwords. Nothing is imported if
+    /// any of the pastes is invalid. Legal holds, expiry, IPFS pins and passkey protection
+    /// aren't imported.
+    ///
+    /// ## Arguments:
+    /// * `pastes` - the pastes to import
+    /// * `on_conflict` - what to do with pastes whose url is already taken
+    /// * `user` - the userstate of the user importing the pastes
+    pub async fn import_pastes(
+        &self,
+        pastes: Vec<ImportedPaste>,
+        on_conflict: ImportConflict,
+        user: FullUser<UserMetadata>,
+    ) -> Result<Vec<ImportResult>> {
+        if self.options.imports == false {
+            return Err(PasteError::NotAllowed);
+        }
+
+        let mut v = validation::Validator::new();
+        v.count("pastes", pastes.len(), validation::MAX_IMPORTED_PASTES);
+
+        if let Err(e) = v.finish() {
+            return Err(e);
+        }
+
+        let (insert_query, update_query) = if (self.base.db._type == "sqlite")
+            | (self.base.db._type == "mysql")
+        {
+            (
+                    "INSERT INTO \"se_pastes\" VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+                    "UPDATE \"se_pastes\" SET \"content\" = ?, \"date_edited\" = ?, \"metadata\" = ? WHERE \"url\" = ?",
+                )
+        } else {
+            (
+                    "INSERT INTO \"se_pastes\" VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+                    "UPDATE \"se_pastes\" SET \"content\" = $1, \"date_edited\" = $2, \"metadata\" = $3 WHERE \"url\" = $4",
+                )
+        };
+
+        let now = utility::unix_epoch_timestamp();
+        let author = user.user.username.clone();
+
+        let mut v = validation::Validator::new();
+        let mut results: Vec<ImportResult> = Vec::new();
+        let mut statements: Vec<(&str, Vec<String>)> = Vec::new();
+        let mut imported: Vec<(String, String, bool)> = Vec::new();
+        let mut taken: HashSet<String> = HashSet::new();
+
+        for (i, props) in pastes.into_iter().enumerate() {
+            let original_url = props.url.clone();
+            let mut url = idna::punycode::encode_str(&props.url)
+                .unwrap()
+                .to_lowercase();
+
+            if url.ends_with("-") {
+                url.pop();
+            }
+
+            if url.is_empty() {
+                url = utility::random_id().chars().take(10).collect();
+            }
+
+            let content = normalize::normalize(&props.content, &self.options.normalization).0;
+
+            // metadata
+            let mut metadata = props.metadata;
+
+            if metadata.title.is_empty() {
+                metadata.title = props.title;
+            }
+
+            metadata.owner = author.clone();
+            metadata.legal_hold = false;
+            metadata.locked = false;
+            metadata.expires_at = 0;
+            metadata.ipfs_cid = String::new();
+            metadata.high_security = false;
+            metadata.scan_status = String::new();
+            metadata.checksums = PasteChecksums::default();
+            metadata.signing_nonce = String::new();
+
+            // view passwords are stored hashed (exports include the hash, but pastes written
+            // for an import by hand may not)
+            if !metadata.view_password.is_empty() && (metadata.view_password_hashed == false) {
+                metadata.view_password = utility::hash(metadata.view_password);
+                metadata.view_password_hashed = true;
+            } else if metadata.view_password.is_empty() {
+                metadata.view_password_hashed = false;
+            }
+
+            if self.options.front_matter == true {
+                front_matter::apply(&content, &mut metadata);
+            }
+
+            metadata.excerpt = if self.options.excerpt_sentences != 0 {
+                excerpt::excerpt(&content, self.options.excerpt_sentences)
+            } else {
+                String::new()
+            };
+
+            // binary pastes have their size instead of an excerpt
+            if metadata.content_type == "binary" {
+                metadata.size = match self.get_binary_size(&content) {
+                    Ok(s) => s,
+                    Err(e) => return Err(e),
+                };
+
+                metadata.excerpt = String::new();
+                metadata.scan_status = self.get_pending_scan_status();
+            } else {
+                metadata.size = 0;
+            }
+
+            self.apply_checksums(&content, &mut metadata);
+
+            // check lengths and characters used (errors are reported under the paste's index)
+            let mut pv = validation::Validator::new();
+            validation::paste(
+                &mut pv,
+                "url",
+                &url,
+                "content",
+                &content,
+                validation::MAX_CONTENT_LENGTH,
+            );
+            validation::metadata(&mut pv, &metadata);
+
+            if let Err(PasteError::Validation(errors)) = pv.finish() {
+                for e in errors {
+                    v.error(&format!("pastes/{}/{}", i, e.field), &e.error, e.min, e.max);
+                }
+
+                continue;
+            }
+
+            if let Err(e) = self.check_rules("create", &url, &content, &author) {
+                return Err(e);
+            }
+
+            // resolve conflicts
+            let existing = self.get_paste_by_url(url.clone()).await.ok();
+            let mut status = "created";
+
+            if taken.contains(&url) | existing.is_some() {
+                match on_conflict {
+                    ImportConflict::Skip => status = "skipped",
+                    ImportConflict::Rename => {
+                        let base: String =
+                            url.chars().take(validation::MAX_URL_LENGTH - 7).collect();
+
+                        loop {
+                            let suffix: String = utility::random_id().chars().take(6).collect();
+                            let candidate = format!("{}-{}", base, suffix);
+
+                            if !taken.contains(&candidate)
+                                && self.get_paste_by_url(candidate.clone()).await.is_err()
+                            {
+                                url = candidate;
+                                break;
+                            }
+                        }
+
+                        status = "renamed";
+                    }
+                    ImportConflict::Overwrite => match existing {
+                        Some(ref p) if !taken.contains(&url) && (p.metadata.owner == author) => {
+                            status = "overwritten"
+                        }
+                        _ => status = "skipped",
+                    },
+                }
+            }
+
+            if status == "skipped" {
+                results.push(ImportResult {
+                    url: original_url,
+                    new_url: String::new(),
+                    status: status.to_string(),
+                    password: String::new(),
+                });
+
+                continue;
+            }
+
+            taken.insert(url.clone());
+
+            if let Some(existing) = existing.filter(|_| status == "overwritten") {
+                // passwords and protection stay with the existing paste
+                metadata.view_password = existing.metadata.view_password;
+                metadata.view_password_hashed = existing.metadata.view_password_hashed;
+                metadata.high_security = existing.metadata.high_security;
+                metadata.legal_hold = existing.metadata.legal_hold;
+                metadata.locked = existing.metadata.locked;
+                metadata.expires_at = existing.metadata.expires_at;
+                metadata.signing_nonce = existing.metadata.signing_nonce;
+
+                statements.push((
+                    update_query,
+                    vec![
+                        content.clone(),
+                        now.to_string(),
+                        match serde_json::to_string(&metadata) {
+                            Ok(s) => s,
+                            Err(_) => return Err(PasteError::ValueError),
+                        },
+                        url.clone(),
+                    ],
+                ));
+
+                results.push(ImportResult {
+                    url: original_url,
+                    new_url: url.clone(),
+                    status: status.to_string(),
+                    password: String::new(),
+                });
+
+                imported.push((url, content, metadata.content_type == "binary"));
+                continue;
+            }
+
+            let password: String = utility::random_id().chars().take(10).collect();
+            let mut paste = Paste {
+                id: utility::random_id(),
+                url,
+                content,
+                password: utility::hash(password.clone()),
+                date_published: if props.date_published == 0 {
+                    now
+                } else {
+                    props.date_published
+                },
+                date_edited: now,
+                metadata,
+            };
+
+            if let Err(e) = self.run_create_hook(&mut paste) {
+                return Err(e);
+            }
+
+            statements.push((
+                insert_query,
+                vec![
+                    paste.id,
+                    paste.url.clone(),
+                    paste.password,
+                    paste.content.clone(),
+                    paste.date_published.to_string(),
+                    paste.date_edited.to_string(),
+                    match serde_json::to_string(&paste.metadata) {
+                        Ok(s) => s,
+                        Err(_) => return Err(PasteError::ValueError),
+                    },
+                    String::from("0"),
+                ],
+            ));
+
+            results.push(ImportResult {
+                url: original_url,
+                new_url: paste.url.clone(),
+                status: status.to_string(),
+                password,
+            });
+
+            imported.push((
+                paste.url,
+                paste.content,
+                paste.metadata.content_type == "binary",
+            ));
+        }
+
+        if let Err(e) = v.finish() {
+            return Err(e);
+        }
+
+        // import
+        if statements.is_empty() {
+            return Ok(results);
+        }
+
+        if let Err(e) = self.transaction(statements).await {
+            return Err(e);
+        }
+
+        for (url, content, binary) in imported {
+            self.base.cachedb.remove(format!("se_paste:{}", url)).await;
+            self.log_mutation("import", &url, "", &author, Some(&content));
+
+            if binary == true {
+                self.spawn_scan(url.clone());
+            }
+
+            self.index_paste_by_url(url).await;
+        }
+
+        Ok(results)
+    }
+
+    // ingest
+
+    /// Get an existing ingest token by its unhashed value
+    ///
+    /// ## Arguments:
+    /// * `token` - the unhashed token
+    pub async fn get_ingest_token(&self, token: String) -> Result<IngestToken> {
+        self.get_ingest_token_by_id(utility::hash(token)).await
+    }
+
+    /// Get an existing ingest token by `id` (the hashed token)
+    ///
+    /// ## Arguments:
+    /// * `id` - the token's `id` field
+    pub async fn get_ingest_token_by_id(&self, id: String) -> Result<IngestToken> {
+        if self.options.ingest == false {
+            return Err(PasteError::NotAllowed);
+        }
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"se_ingest_tokens\" WHERE \"id\" = ?"
+        } else {
+            "SELECT * FROM \"se_ingest_tokens\" WHERE \"id\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        let res = match sqlquery(query).bind::<&String>(&id).fetch_one(c).await {
+            Ok(p) => self.base.textify_row(p).data,
+            Err(_) => return Err(PasteError::NotFound),
+        };
+
+        // return
+        Ok(IngestToken {
+            id: res.get("id").unwrap().to_string(),
+            prefix: res.get("prefix").unwrap().to_string(),
+            expires: res.get("expires").unwrap().parse::<u128>().unwrap(),
+            max_size: res.get("max_size").unwrap().parse::<usize>().unwrap(),
+            owner: res.get("owner").unwrap().to_string(),
+            timestamp: res.get("timestamp").unwrap().parse::<u128>().unwrap(),
+            uses: res.get("uses").unwrap().parse::<usize>().unwrap(),
+            last_used: res.get("last_used").unwrap().parse::<u128>().unwrap(),
+        })
+    }
+
+    /// Create a new ingest token
+    ///
+    /// ## Arguments:
+    /// * `props` - [`IngestTokenCreate`]
+    /// * `owner` - the username of the user creating the token
+    ///
+    /// ## Returns:
+    /// * Result containing a tuple with the unhashed token and the token
+    pub async fn create_ingest_token(
+        &self,
+        props: IngestTokenCreate,
+        owner: String,
+    ) -> Result<(String, IngestToken)> {
+        if self.options.ingest == false {
+            return Err(PasteError::NotAllowed);
+        }
+
+        // check prefix
+        if props.prefix.len() > 200 {
+            return Err(PasteError::ValueError);
+        }
+
+        // ...
+        let unhashed = utility::random_id();
+        let timestamp = utility::unix_epoch_timestamp();
+
+        let token = IngestToken {
+            id: utility::hash(unhashed.clone()),
+            prefix: props.prefix,
+            expires: if props.expires_in == 0 {
+                0
+            } else {
+                timestamp + props.expires_in
+            },
+            max_size: props.max_size,
+            owner,
+            timestamp,
+            uses: 0,
+            last_used: 0,
+        };
+
+        // create token
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "INSERT INTO \"se_ingest_tokens\" VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+        } else {
+            "INSERT INTO \"se_ingest_tokens\" VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query)
+            .bind::<&String>(&token.id)
+            .bind::<&String>(&token.prefix)
+            .bind::<&String>(&token.expires.to_string())
+            .bind::<&String>(&token.max_size.to_string())
+            .bind::<&String>(&token.owner)
+            .bind::<&String>(&token.timestamp.to_string())
+            .bind::<&String>(&token.uses.to_string())
+            .bind::<&String>(&token.last_used.to_string())
+            .execute(c)
+            .await
+        {
+            Ok(_) => return Ok((unhashed, token)),
+            Err(e) => return Err(query_failed(e)),
+        };
+    }
+
+    /// Delete an existing ingest token by `id`
+    ///
+    /// Permission checks should be done before calling `delete_ingest_token`.
+    ///
+    /// ## Arguments:
+    /// * `id` - the token's `id` field
+    pub async fn delete_ingest_token(&self, id: String) -> Result<()> {
+        // make sure token exists
+        if let Err(e) = self.get_ingest_token_by_id(id.clone()).await {
+            return Err(e);
+        };
+
+        // delete token
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"se_ingest_tokens\" WHERE \"id\" = ?"
+        } else {
+            "DELETE FROM \"se_ingest_tokens\" WHERE \"id\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query).bind::<&String>(&id).execute(c).await {
+            Ok(_) => return Ok(()),
+            Err(e) => return Err(query_failed(e)),
+        };
+    }
+
+    /// Create a new paste using an ingest token
+    ///
+    /// ## Arguments:
+    /// * `token` - the unhashed ingest token
+    /// * `content` - the content of the paste
+    ///
+    /// ## Returns:
+    /// * Result containing a tuple with the unhashed edit password and the paste
+    pub async fn ingest_paste(&self, token: String, content: String) -> Result<(String, Paste)> {
+        let token = match self.get_ingest_token(token).await {
+            Ok(t) => t,
+            Err(PasteError::NotFound) => return Err(PasteError::NotAllowed),
+            Err(e) => return Err(e),
+        };
+
+        // check token
+        let now = utility::unix_epoch_timestamp();
+
+        if (token.expires != 0) && (now > token.expires) {
+            return Err(PasteError::NotAllowed);
+        }
+
+        if (token.max_size != 0) && (content.len() > token.max_size) {
+            return Err(PasteError::ContentTooLarge);
+        }
+
+        // check rate limit
+        if let Err(e) = self
+            .check_rate_limit(
+                format!("se_ingest_rate:{}", token.id),
+                self.options.ingest_rate_limit,
+            )
+            .await
+        {
+            return Err(e);
+        }
+
+        // pastes are created already owned by the token's owner
+        let mut metadata = PasteMetadata::default();
+
+        if self.options.paste_ownership == true {
+            metadata.owner = token.owner.clone();
+        }
+
+        // create paste
+        let (password, paste) = match self
+            .create_paste_with_metadata(
+                PasteCreate {
+                    url: format!(
+                        "{}{}",
+                        token.prefix,
+                        utility::random_id().chars().take(10).collect::<String>()
+                    ),
+                    content,
+                    password: String::new(),
+                    accept_tos: false,
+                    author: token.owner.clone(),
+                    expires_in: String::new(),
+                    expires_at: 0,
+                    invite: String::new(),
+                },
+                metadata,
+            )
+            .await
+        {
+            Ok(p) => p,
+            Err(e) => return Err(e),
+        };
+
+        // record use (incremented in the database, so concurrent ingests are all counted)
+        let query: &str = if self.base.db._type == "sqlite" {
+            "UPDATE \"se_ingest_tokens\" SET \"uses\" = CAST(\"uses\" AS INTEGER) + 1, \"last_used\" = ? WHERE \"id\" = ?"
+        } else if self.base.db._type == "mysql" {
+            "UPDATE \"se_ingest_tokens\" SET \"uses\" = \"uses\" + 1, \"last_used\" = ? WHERE \"id\" = ?"
+        } else {
+            "UPDATE \"se_ingest_tokens\" SET \"uses\" = CAST(CAST(\"uses\" AS BIGINT) + 1 AS TEXT), \"last_used\" = $1 WHERE \"id\" = $2"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query)
+            .bind::<&String>(&now.to_string())
+            .bind::<&String>(&token.id)
+            .execute(c)
+            .await
+        {
+            Ok(_) => return Ok((password, paste)),
+            Err(e) => return Err(query_failed(e)),
+        };
+    }
+
+    // invites
+
+    /// Get an existing invite by its unhashed code
+    ///
+    /// Invites which have expired or been used up are treated as not found.
+    ///
+    /// ## Arguments:
+    /// * `code` - the unhashed invite code
+    pub async fn get_invite(&self, code: String) -> Result<Invite> {
+        let invite = match self.get_invite_by_id(utility::hash(code)).await {
+            Ok(i) => i,
+            Err(e) => return Err(e),
+        };
+
+        if (invite.remaining == 0)
+            | ((invite.expires != 0) && (utility::unix_epoch_timestamp() > invite.expires))
+        {
+            return Err(PasteError::NotFound);
+        }
+
+        Ok(invite)
+    }
+
+    /// Get an existing invite by `id` (the hashed code)
+    ///
+    /// ## Arguments:
+    /// * `id` - the invite's `id` field
+    pub async fn get_invite_by_id(&self, id: String) -> Result<Invite> {
+        if self.options.invites == false {
+            return Err(PasteError::NotAllowed);
+        }
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"se_invites\" WHERE \"id\" = ?"
+        } else {
+            "SELECT * FROM \"se_invites\" WHERE \"id\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        let res = match sqlquery(query).bind::<&String>(&id).fetch_one(c).await {
+            Ok(p) => self.base.textify_row(p).data,
+            Err(_) => return Err(PasteError::NotFound),
+        };
+
+        // return
+        Ok(Invite {
+            id: res.get("id").unwrap().to_string(),
+            owner: res.get("owner").unwrap().to_string(),
+            remaining: res.get("remaining").unwrap().parse::<usize>().unwrap(),
+            max_size: res.get("max_size").unwrap().parse::<usize>().unwrap(),
+            expires: res.get("expires").unwrap().parse::<u128>().unwrap(),
+            timestamp: res.get("timestamp").unwrap().parse::<u128>().unwrap(),
+        })
+    }
+
+    /// Create a new invite
+    ///
+    /// ## Arguments:
+    /// * `props` - [`InviteCreate`]
+    /// * `owner` - the username of the user creating the invite
+    ///
+    /// ## Returns:
+    /// * Result containing a tuple with the unhashed invite code and the invite
+    pub async fn create_invite(
+        &self,
+        props: InviteCreate,
+        owner: String,
+    ) -> Result<(String, Invite)> {
+        if self.options.invites == false {
+            return Err(PasteError::NotAllowed);
+        }
+
+        // check limits
+        let mut v = validation::Validator::new();
+
+        if (props.uses < 1) | (props.uses > 1_000) {
+            v.error("uses", "out_of_range", Some(1), Some(1_000));
+        }
+
+        if props.max_size > 1_000_000 {
+            v.error("max_size", "out_of_range", Some(0), Some(1_000_000));
+        }
+
+        if let Err(e) = v.finish() {
+            return Err(e);
+        }
+
+        // ...
+        let unhashed = utility::random_id();
+        let timestamp = utility::unix_epoch_timestamp();
+
+        let invite = Invite {
+            id: utility::hash(unhashed.clone()),
+            owner,
+            remaining: props.uses,
+            max_size: props.max_size,
+            expires: if props.expires_in == 0 {
+                0
+            } else {
+                timestamp + props.expires_in
+            },
+            timestamp,
+        };
+
+        // create invite
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "INSERT INTO \"se_invites\" VALUES (?, ?, ?, ?, ?, ?)"
+        } else {
+            "INSERT INTO \"se_invites\" VALUES ($1, $2, $3, $4, $5, $6)"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query)
+            .bind::<&String>(&invite.id)
+            .bind::<&String>(&invite.owner)
+            .bind::<&String>(&invite.remaining.to_string())
+            .bind::<&String>(&invite.max_size.to_string())
+            .bind::<&String>(&invite.expires.to_string())
+            .bind::<&String>(&invite.timestamp.to_string())
+            .execute(c)
+            .await
+        {
+            Ok(_) => return Ok((unhashed, invite)),
+            Err(e) => return Err(query_failed(e)),
+        };
+    }
+
+    /// Give back a use taken from an invite by [`Database::use_invite`]
+    ///
+    /// ## Arguments:
+    /// * `id` - the invite's `id` field
+    async fn restore_invite(&self, id: String) {
+        let query: &str = if self.base.db._type == "sqlite" {
+            "UPDATE \"se_invites\" SET \"remaining\" = CAST(\"remaining\" AS INTEGER) + 1 WHERE \"id\" = ?"
+        } else if self.base.db._type == "mysql" {
+            "UPDATE \"se_invites\" SET \"remaining\" = \"remaining\" + 1 WHERE \"id\" = ?"
+        } else {
+            "UPDATE \"se_invites\" SET \"remaining\" = CAST(CAST(\"remaining\" AS BIGINT) + 1 AS TEXT) WHERE \"id\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        if let Err(e) = sqlquery(query).bind::<&String>(&id).execute(c).await {
+            query_failed(e);
+        }
+    }
+
+    /// Take a use from an invite
+    ///
+    /// The counter is only changed if nobody else used the invite since it was read, so
+    /// two guests can't both take its last use.
+    ///
+    /// ## Arguments:
+    /// * `invite` - the invite to use
+    async fn use_invite(&self, mut invite: Invite) -> Result<()> {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "UPDATE \"se_invites\" SET \"remaining\" = ? WHERE \"id\" = ? AND \"remaining\" = ?"
+        } else {
+            "UPDATE \"se_invites\" SET \"remaining\" = $1 WHERE \"id\" = $2 AND \"remaining\" = $3"
+        };
+
+        let c = &self.base.db.client;
+
+        for _ in 0..5 {
+            if invite.remaining == 0 {
+                return Err(PasteError::NotAllowed);
+            }
+
+            match sqlquery(query)
+                .bind::<&String>(&(invite.remaining - 1).to_string())
+                .bind::<&String>(&invite.id)
+                .bind::<&String>(&invite.remaining.to_string())
+                .execute(c)
+                .await
+            {
+                Ok(r) => {
+                    if r.rows_affected() == 1 {
+                        return Ok(());
+                    }
+                }
+                Err(e) => return Err(query_failed(e)),
+            };
+
+            // someone else used the invite, try again with the new counter
+            invite = match self.get_invite_by_id(invite.id.clone()).await {
+                Ok(i) => i,
+                Err(PasteError::NotFound) => return Err(PasteError::NotAllowed),
+                Err(e) => return Err(e),
+            };
+        }
+
+        Err(PasteError::Other)
+    }
+
+    /// Delete an existing invite by `id`
+    ///
+    /// Permission checks should be done before calling `delete_invite`.
+    ///
+    /// ## Arguments:
+    /// * `id` - the invite's `id` field
+    pub async fn delete_invite(&self, id: String) -> Result<()> {
+        // make sure invite exists
+        if let Err(e) = self.get_invite_by_id(id.clone()).await {
+            return Err(e);
+        };
+
+        // delete invite
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"se_invites\" WHERE \"id\" = ?"
+        } else {
+            "DELETE FROM \"se_invites\" WHERE \"id\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query).bind::<&String>(&id).execute(c).await {
+            Ok(_) => return Ok(()),
+            Err(e) => return Err(query_failed(e)),
+        };
+    }
+
+    // collections
+
+    /// Get an existing collection by `id`
+    ///
+    /// ## Arguments:
+    /// * `id` - the collection's `id` field
+    pub async fn get_collection(&self, id: String) -> Result<Collection> {
+        if self.options.collections == false {
+            return Err(PasteError::NotAllowed);
+        }
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"se_collections\" WHERE \"id\" = ?"
+        } else {
+            "SELECT * FROM \"se_collections\" WHERE \"id\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        let res = match sqlquery(query).bind::<&String>(&id).fetch_one(c).await {
+            Ok(p) => self.base.textify_row(p).data,
+            Err(_) => return Err(PasteError::NotFound),
+        };
+
+        // get pastes
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"se_collection_pastes\" WHERE \"collection\" = ?"
+        } else {
+            "SELECT * FROM \"se_collection_pastes\" WHERE \"collection\" = $1"
+        };
+
+        let mut pastes: Vec<(u64, String)> =
+            match sqlquery(query).bind::<&String>(&id).fetch_all(c).await {
+                Ok(rows) => rows
+                    .into_iter()
+                    .map(|r| {
+                        let row = self.base.textify_row(r).data;
+                        (
+                            row.get("position").unwrap().parse::<u64>().unwrap(),
+                            row.get("url").unwrap().to_string(),
+                        )
+                    })
+                    .collect(),
+                Err(e) => return Err(query_failed(e)),
+            };
+
+        pastes.sort_by_key(|p| p.0);
+
+        // return
+        Ok(Collection {
+            id: res.get("id").unwrap().to_string(),
+            title: res.get("title").unwrap().to_string(),
+            owner: res.get("owner").unwrap().to_string(),
+            timestamp: res.get("timestamp").unwrap().parse::<u128>().unwrap(),
+            pastes: pastes.into_iter().map(|p| p.1).collect(),
+        })
+    }
+
+    /// Create a new collection
+    ///
+    /// ## Arguments:
+    /// * `props` - [`CollectionCreate`]
+    /// * `owner` - the username of the user creating the collection
+    pub async fn create_collection(
+        &self,
+        props: CollectionCreate,
+        owner: String,
+    ) -> Result<Collection> {
+        if self.options.collections == false {
+            return Err(PasteError::NotAllowed);
+        }
+
+        // check lengths
+        if (props.title.len() > 250) | (props.title.trim().len() < 1) {
+            return Err(PasteError::ValueError);
+        }
+
+        // ...
+        let collection = Collection {
+            id: utility::random_id(),
+            title: props.title,
+            owner,
+            timestamp: utility::unix_epoch_timestamp(),
+            pastes: Vec::new(),
+        };
+
+        // create collection
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "INSERT INTO \"se_collections\" VALUES (?, ?, ?, ?)"
+        } else {
+            "INSERT INTO \"se_collections\" VALUES ($1, $2, $3, $4)"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query)
+            .bind::<&String>(&collection.id)
+            .bind::<&String>(&collection.title)
+            .bind::<&String>(&collection.owner)
+            .bind::<&String>(&collection.timestamp.to_string())
+            .execute(c)
+            .await
+        {
+            Ok(_) => return Ok(collection),
+            Err(e) => return Err(query_failed(e)),
+        };
+    }
+
+    /// Delete an existing collection by `id` (the pastes in it are kept)
+    ///
+    /// ## Arguments:
+    /// * `id` - the collection's `id` field
+    /// * `user` - the userstate of the user deleting the collection
+    pub async fn delete_collection(&self, id: String, user: FullUser<UserMetadata>) -> Result<()> {
+        // make sure collection exists
+        let existing = match self.get_collection(id.clone()).await {
+            Ok(c) => c,
+            Err(e) => return Err(e),
+        };
+
+        if (existing.owner != user.user.username)
+            && !user.level.permissions.contains(&"ManagePastes".to_string())
+        {
+            return Err(PasteError::NotAllowed);
+        }
+
+        // delete collection
+        let c = &self.base.db.client;
+
+        for table in ["se_collection_pastes", "se_collections"] {
+            let column = if table == "se_collections" {
+                "id"
+            } else {
+                "collection"
+            };
+
+            let query = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+                format!("DELETE FROM \"{}\" WHERE \"{}\" = ?", table, column)
+            } else {
+                format!("DELETE FROM \"{}\" WHERE \"{}\" = $1", table, column)
+            };
+
+            if let Err(_) = sqlquery(&query).bind::<&String>(&id).execute(c).await {
+                return Err(PasteError::Other);
+            };
+        }
+
+        // return
+        Ok(())
+    }
+
+    /// Add a paste to the end of an existing collection
+    ///
+    /// ## Arguments:
+    /// * `id` - the collection's `id` field
+    /// * `url` - the paste to add
+    /// * `user` - the userstate of the user adding the paste (must own the collection and the paste)
+    pub async fn add_paste_to_collection(
+        &self,
+        id: String,
+        mut url: String,
+        user: FullUser<UserMetadata>,
+    ) -> Result<Collection> {
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+
+        if url.ends_with("-") {
+            url.pop();
+        }
+
+        // get collection
+        let mut collection = match self.get_collection(id.clone()).await {
+            Ok(c) => c,
+            Err(e) => return Err(e),
+        };
+
+        // check permissions
+        let manager = user.level.permissions.contains(&"ManagePastes".to_string());
+
+        if (collection.owner != user.user.username) && !manager {
+            return Err(PasteError::NotAllowed);
+        }
+
+        let paste = match self.get_paste_by_url(url.clone()).await {
+            Ok(p) => p,
+            Err(e) => return Err(e),
+        };
+
+        if (paste.metadata.owner != user.user.username) && !manager {
+            return Err(PasteError::NotAllowed);
+        }
+
+        if collection.pastes.contains(&url) {
+            return Err(PasteError::AlreadyExists);
+        }
+
+        // add paste
+        let position = match self.get_collection_positions(&id).await {
+            Ok(p) => p.into_iter().max().map_or(0, |p| p + 1),
+            Err(e) => return Err(e),
+        };
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "INSERT INTO \"se_collection_pastes\" VALUES (?, ?, ?)"
+        } else {
+            "INSERT INTO \"se_collection_pastes\" VALUES ($1, $2, $3)"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query)
+            .bind::<&String>(&id)
+            .bind::<&String>(&url)
+            .bind::<&String>(&position.to_string())
+            .execute(c)
+            .await
+        {
+            Ok(_) => {
+                collection.pastes.push(url);
+                return Ok(collection);
+            }
+            Err(e) => return Err(query_failed(e)),
+        };
+    }
+
+    /// Remove a paste from an existing collection
+    ///
+    /// ## Arguments:
+    /// * `id` - the collection's `id` field
+    /// * `url` - the paste to remove
+    /// * `user` - the userstate of the user removing the paste (must own the collection)
+    pub async fn remove_paste_from_collection(
+        &self,
+        id: String,
+        mut url: String,
+        user: FullUser<UserMetadata>,
+    ) -> Result<Collection> {
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+
+        if url.ends_with("-") {
+            url.pop();
+        }
+
+        // get collection
+        let mut collection = match self.get_collection(id.clone()).await {
+            Ok(c) => c,
+            Err(e) => return Err(e),
+        };
+
+        if (collection.owner != user.user.username)
+            && !user.level.permissions.contains(&"ManagePastes".to_string())
+        {
+            return Err(PasteError::NotAllowed);
+        }
+
+        if !collection.pastes.contains(&url) {
+            return Err(PasteError::NotFound);
+        }
+
+        // remove paste
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"se_collection_pastes\" WHERE \"collection\" = ? AND \"url\" = ?"
+        } else {
+            "DELETE FROM \"se_collection_pastes\" WHERE \"collection\" = $1 AND \"url\" = $2"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query)
+            .bind::<&String>(&id)
+            .bind::<&String>(&url)
+            .execute(c)
+            .await
+        {
+            Ok(_) => {
+                collection.pastes.retain(|p| p != &url);
+                return Ok(collection);
+            }
+            Err(e) => return Err(query_failed(e)),
+        };
+    }
+
+    /// Get the positions used in a collection
+    async fn get_collection_positions(&self, id: &String) -> Result<Vec<u64>> {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"se_collection_pastes\" WHERE \"collection\" = ?"
+        } else {
+            "SELECT * FROM \"se_collection_pastes\" WHERE \"collection\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query).bind::<&String>(id).fetch_all(c).await {
+            Ok(rows) => Ok(rows
+                .into_iter()
+                .map(|r| {
+                    self.base
+                        .textify_row(r)
+                        .data
+                        .get("position")
+                        .unwrap()
+                        .parse::<u64>()
+                        .unwrap()
+                })
+                .collect()),
+            Err(e) => Err(query_failed(e)),
+        }
+    }
+
+    /// Get the previous and next pastes of every collection a paste is in
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste
+    pub async fn get_collection_navigation_by_url(
+        &self,
+        mut url: String,
+    ) -> Result<Vec<CollectionNavigation>> {
+        if self.options.collections == false {
+            return Err(PasteError::NotAllowed);
+        }
+
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+
+        if url.ends_with("-") {
+            url.pop();
+        }
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"se_collection_pastes\" WHERE \"url\" = ?"
+        } else {
+            "SELECT * FROM \"se_collection_pastes\" WHERE \"url\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        let ids: Vec<String> = match sqlquery(query).bind::<&String>(&url).fetch_all(c).await {
+            Ok(rows) => rows
+                .into_iter()
+                .map(|r| {
+                    self.base
+                        .textify_row(r)
+                        .data
+                        .get("collection")
+                        .unwrap()
+                        .to_string()
+                })
+                .collect(),
+            Err(e) => return Err(query_failed(e)),
+        };
+
+        // build navigation
+        let mut out: Vec<CollectionNavigation> = Vec::new();
+
+        for id in ids {
+            let collection = match self.get_collection(id).await {
+                Ok(c) => c,
+                Err(_) => continue,
+            };
+
+            let index = match collection.pastes.iter().position(|p| p == &url) {
+                Some(i) => i,
+                None => continue,
+            };
+
+            out.push(CollectionNavigation {
+                collection: collection.id,
+                title: collection.title,
+                previous: if index == 0 {
+                    None
+                } else {
+                    collection.pastes.get(index - 1).cloned()
+                },
+                next: collection.pastes.get(index + 1).cloned(),
+            });
+        }
+
+        // return
+        Ok(out)
+    }
+
+    // suggestions
+
+    /// Get all pending suggestions for an existing paste by `url`
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste
+    pub async fn get_suggestions_by_url(&self, mut url: String) -> Result<Vec<Suggestion>> {
+        if self.options.suggestions == false {
+            return Err(PasteError::NotAllowed);
+        }
+
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+
+        if url.ends_with("-") {
+            url.pop();
+        }
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"se_suggestions\" WHERE \"url\" = ?"
+        } else {
+            "SELECT * FROM \"se_suggestions\" WHERE \"url\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        let mut suggestions: Vec<Suggestion> =
+            match sqlquery(query).bind::<&String>(&url).fetch_all(c).await {
+                Ok(rows) => rows
+                    .into_iter()
+                    .map(|r| {
+                        let res = self.base.textify_row(r).data;
+                        Suggestion {
+                            id: res.get("id").unwrap().to_string(),
+                            url: res.get("url").unwrap().to_string(),
+                            content: res.get("content").unwrap().to_string(),
+                            author: res.get("author").unwrap().to_string(),
+                            timestamp: res.get("timestamp").unwrap().parse::<u128>().unwrap(),
+                        }
+                    })
+                    .collect(),
+                Err(e) => return Err(query_failed(e)),
+            };
+
+        suggestions.sort_by_key(|s| s.timestamp);
+
+        // return
+        Ok(suggestions)
+    }
+
+    /// Get an existing suggestion for a paste by `url` and `id`
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste
+    /// * `id` - the suggestion's `id` field
+    pub async fn get_suggestion(&self, url: String, id: String) -> Result<Suggestion> {
+        match self.get_suggestions_by_url(url).await {
+            Ok(s) => match s.into_iter().find(|s| s.id == id) {
+                Some(s) => Ok(s),
+                None => Err(PasteError::NotFound),
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Get an existing suggestion and its diff against the paste's current content
+    ///
+    /// The diff includes the paste's content, so read access (view passwords and quarantine)
+    /// should be checked before calling `get_suggestion_diff`.
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste
+    /// * `id` - the suggestion's `id` field
+    pub async fn get_suggestion_diff(&self, url: String, id: String) -> Result<SuggestionDiff> {
+        let suggestion = match self.get_suggestion(url.clone(), id).await {
+            Ok(s) => s,
+            Err(e) => return Err(e),
+        };
+
+        let paste = match self.get_paste_by_url(suggestion.url.clone()).await {
+            Ok(p) => p,
+            Err(e) => return Err(e),
+        };
+
+        // return
+        Ok(SuggestionDiff {
+            diff: diff::diff_lines(&paste.content, &suggestion.content),
+            suggestion,
+        })
+    }
+
+    /// Suggest an edit to an existing paste by `url`
+    ///
+    /// Read access (view passwords and quarantine) should be checked before calling `create_suggestion`.
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste
+    /// * `props` - [`SuggestionCreate`]
+    /// * `author` - the username of the user making the suggestion (can be empty)
+    pub async fn create_suggestion(
+        &self,
+        url: String,
+        props: SuggestionCreate,
+        author: String,
+    ) -> Result<Suggestion> {
+        let existing = match self.get_suggestions_by_url(url.clone()).await {
+            Ok(s) => s,
+            Err(e) => return Err(e),
+        };
+
+        // make sure paste exists
+        let paste = match self.get_paste_by_url(url).await {
+            Ok(p) => p,
+            Err(e) => return Err(e),
+        };
+
+        // check limits
+        if existing.len() >= 50 {
+            return Err(PasteError::QuotaExceeded);
+        }
+
+        if props.content.len() > validation::MAX_CONTENT_LENGTH {
+            return Err(PasteError::ContentTooLarge);
+        }
+
+        if props.content.len() < 1 {
+            return Err(PasteError::ValueError);
+        }
+
+        if props.content == paste.content {
+            return Err(PasteError::ValueError);
+        }
+
+        // ...
+        let suggestion = Suggestion {
+            id: utility::random_id(),
+            url: paste.url,
+            content: props.content,
+            author,
+            timestamp: utility::unix_epoch_timestamp(),
+        };
+
+        // create suggestion
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "INSERT INTO \"se_suggestions\" VALUES (?, ?, ?, ?, ?)"
+        } else {
+            "INSERT INTO \"se_suggestions\" VALUES ($1, $2, $3, $4, $5)"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query)
+            .bind::<&String>(&suggestion.id)
+            .bind::<&String>(&suggestion.url)
+            .bind::<&String>(&suggestion.content)
+            .bind::<&String>(&suggestion.author)
+            .bind::<&String>(&suggestion.timestamp.to_string())
+            .execute(c)
+            .await
+        {
+            Ok(_) => return Ok(suggestion),
+            Err(e) => return Err(query_failed(e)),
+        };
+    }
+
+    /// Accept an existing suggestion, replacing the paste's content with it
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste
+    /// * `id` - the suggestion's `id` field
+    /// * `password` - the paste's edit password
+    /// * `editing_as` - the userstate of the user we're editing the paste as
+    pub async fn accept_suggestion(
+        &self,
+        url: String,
+        id: String,
+        password: String,
+        editing_as: Option<FullUser<UserMetadata>>,
+    ) -> Result<()> {
+        let suggestion = match self.get_suggestion(url, id).await {
+            Ok(s) => s,
+            Err(e) => return Err(e),
+        };
+
+        // edit paste (checks password)
+        if let Err(e) = self
+            .edit_paste_by_url(
+                suggestion.url,
+                password,
+                String::new(),
+                suggestion.content,
+                String::new(),
+                String::new(),
+                editing_as,
+                None,
+            )
+            .await
+        {
+            return Err(e);
+        }
+
+        // delete suggestion
+        self.delete_suggestion(suggestion.id).await
+    }
+
+    /// Reject an existing suggestion
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste
+    /// * `id` - the suggestion's `id` field
+    /// * `password` - the paste's edit password
+    /// * `editing_as` - the userstate of the user we're editing the paste as
+    pub async fn reject_suggestion(
+        &self,
+        url: String,
+        id: String,
+        password: String,
+        editing_as: Option<FullUser<UserMetadata>>,
+    ) -> Result<()> {
+        let suggestion = match self.get_suggestion(url, id).await {
+            Ok(s) => s,
+            Err(e) => return Err(e),
+        };
+
+        let existing = match self.get_paste_by_url(suggestion.url).await {
+            Ok(p) => p,
+            Err(e) => return Err(e),
+        };
+
+        // check password
+        if let Err(e) = self.check_suggestion_reviewer(&existing, password, editing_as) {
+            return Err(e);
+        }
+
+        // delete suggestion
+        self.delete_suggestion(suggestion.id).await
+    }
+
+    /// Check if a user can review the suggestions made to an existing paste
+    ///
+    /// Only the paste's owner, users with the "ManagePastes" permission and anyone with
+    /// the paste's edit password can.
+    ///
+    /// ## Arguments:
+    /// * `paste` - the paste
+    /// * `password` - the paste's edit password (can be empty if `editing_as` is the owner)
+    /// * `editing_as` - the user reviewing the suggestions
+    pub fn check_suggestion_reviewer(
+        &self,
+        paste: &Paste,
+        password: String,
+        editing_as: Option<FullUser<UserMetadata>>,
+    ) -> Result<()> {
+        if let Some(ua) = editing_as {
+            if (ua.user.username == paste.metadata.owner)
+                | ua.level.permissions.contains(&"ManagePastes".to_string())
+            {
+                return Ok(());
+            }
+        }
+
+        if utility::hash(password) != paste.password {
+            return Err(PasteError::PasswordIncorrect);
+        }
+
+        Ok(())
+    }
+
+    /// Delete a suggestion by `id`
+    async fn delete_suggestion(&self, id: String) -> Result<()> {
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"se_suggestions\" WHERE \"id\" = ?"
+        } else {
+            "DELETE FROM \"se_suggestions\" WHERE \"id\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query).bind::<&String>(&id).execute(c).await {
+            Ok(_) => return Ok(()),
+            Err(e) => return Err(query_failed(e)),
+        };
+    }
+
+    // annotations
+
+    /// Get all annotations on an existing paste by `url`, ordered by line
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste
+    pub async fn get_annotations_by_url(&self, mut url: String) -> Result<Vec<Annotation>> {
+        if self.options.annotations == false {
+            return Err(PasteError::NotAllowed);
+        }
+
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+
+        if url.ends_with("-") {
+            url.pop();
+        }
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"se_annotations\" WHERE \"url\" = ?"
+        } else {
+            "SELECT * FROM \"se_annotations\" WHERE \"url\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        let mut annotations: Vec<Annotation> =
+            match sqlquery(query).bind::<&String>(&url).fetch_all(c).await {
+                Ok(rows) => rows
+                    .into_iter()
+                    .map(|r| {
+                        let res = self.base.textify_row(r).data;
+                        Annotation {
+                            id: res.get("id").unwrap().to_string(),
+                            url: res.get("url").unwrap().to_string(),
+                            line_start: res.get("line_start").unwrap().parse::<usize>().unwrap(),
+                            line_end: res.get("line_end").unwrap().parse::<usize>().unwrap(),
+                            content: res.get("content").unwrap().to_string(),
+                            author: res.get("author").unwrap().to_string(),
+                            timestamp: res.get("timestamp").unwrap().parse::<u128>().unwrap(),
+                        }
+                    })
+                    .collect(),
+                Err(e) => return Err(query_failed(e)),
+            };
+
+        annotations.sort_by_key(|a| (a.line_start, a.timestamp));
+
+        // return
+        Ok(annotations)
+    }
+
+    /// Annotate a range of lines in an existing paste by `url`
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste
+    /// * `props` - [`AnnotationCreate`]
+    /// * `author` - the username of the user making the annotation (can be empty)
+    pub async fn create_annotation(
+        &self,
+        url: String,
+        mut props: AnnotationCreate,
+        author: String,
+    ) -> Result<Annotation> {
+        if self.options.annotations == false {
+            return Err(PasteError::NotAllowed);
+        }
+
+        let paste = match self.get_paste_by_url(url).await {
+            Ok(p) => p,
+            Err(e) => return Err(e),
+        };
+
+        // check range
+        if props.line_end == 0 {
+            props.line_end = props.line_start;
+        }
+
+        if (props.line_start < 1)
+            | (props.line_end < props.line_start)
+            | (props.line_end > paste.content.lines().count())
+        {
+            return Err(PasteError::ValueError);
+        }
+
+        // check lengths
+        if (props.content.len() > 5_000) | (props.content.trim().len() < 1) {
+            return Err(PasteError::ValueError);
+        }
+
+        // ...
+        let annotation = Annotation {
+            id: utility::random_id(),
+            url: paste.url,
+            line_start: props.line_start,
+            line_end: props.line_end,
+            content: props.content,
+            author,
+            timestamp: utility::unix_epoch_timestamp(),
+        };
+
+        // create annotation
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "INSERT INTO \"se_annotations\" VALUES (?, ?, ?, ?, ?, ?, ?)"
+        } else {
+            "INSERT INTO \"se_annotations\" VALUES ($1, $2, $3, $4, $5, $6, $7)"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query)
+            .bind::<&String>(&annotation.id)
+            .bind::<&String>(&annotation.url)
+            .bind::<&String>(&annotation.line_start.to_string())
+            .bind::<&String>(&annotation.line_end.to_string())
+            .bind::<&String>(&annotation.content)
+            .bind::<&String>(&annotation.author)
+            .bind::<&String>(&annotation.timestamp.to_string())
+            .execute(c)
+            .await
+        {
+            Ok(_) => return Ok(annotation),
+            Err(e) => return Err(query_failed(e)),
+        };
+    }
+
+    /// Delete an existing annotation
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste
+    /// * `id` - the annotation's `id` field
+    /// * `password` - the paste's edit password
+    /// * `editing_as` - the userstate of the user deleting the annotation
+    pub async fn delete_annotation(
+        &self,
+        url: String,
+        id: String,
+        password: String,
+        editing_as: Option<FullUser<UserMetadata>>,
+    ) -> Result<()> {
+        let annotation = match self.get_annotations_by_url(url).await {
+            Ok(a) => match a.into_iter().find(|a| a.id == id) {
+                Some(a) => a,
+                None => return Err(PasteError::NotFound),
+            },
+            Err(e) => return Err(e),
+        };
+
+        let existing = match self.get_paste_by_url(annotation.url).await {
+            Ok(p) => p,
+            Err(e) => return Err(e),
+        };
+
+        // check password
+        let mut skip_password_check: bool = false;
+
+        if let Some(ua) = editing_as {
+            if (!annotation.author.is_empty() && (ua.user.username == annotation.author))
+                | (ua.user.username == existing.metadata.owner)
+                | ua.level.permissions.contains(&"ManagePastes".to_string())
+            {
+                skip_password_check = true;
+            }
+        }
+
+        if skip_password_check == false {
+            if utility::hash(password) != existing.password {
+                return Err(PasteError::PasswordIncorrect);
+            }
+        }
+
+        // delete annotation
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "DELETE FROM \"se_annotations\" WHERE \"id\" = ?"
+        } else {
+            "DELETE FROM \"se_annotations\" WHERE \"id\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query).bind::<&String>(&id).execute(c).await {
+            Ok(_) => return Ok(()),
+            Err(e) => return Err(query_failed(e)),
+        };
+    }
+
+    // links
+
+    /// Get the stored link report of an existing paste by `url`
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste
+    pub async fn get_link_report_by_url(&self, mut url: String) -> Option<LinkReport> {
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+
+        if url.ends_with("-") {
+            url.pop();
+        }
+
+        // check in cache
+        if let Some(c) = self.base.cachedb.get(format!("se_links:{}", url)).await {
+            if let Ok(r) = serde_json::from_str::<LinkReport>(c.as_str()) {
+                return Some(r);
+            }
+        }
+
+        // pull from db
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"se_link_reports\" WHERE \"url\" = ?"
+        } else {
+            "SELECT * FROM \"se_link_reports\" WHERE \"url\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        let report = match sqlquery(query).bind::<&String>(&url).fetch_one(c).await {
+            Ok(r) => self
+                .base
+                .textify_row(r)
+                .data
+                .get("report")
+                .unwrap()
+                .to_string(),
+            Err(_) => return None,
+        };
+
+        // store in cache
+        self.base
+            .cachedb
+            .set(format!("se_links:{}", url), report.clone())
+            .await;
+
+        // return (reports moved by a rename still have the old url)
+        match serde_json::from_str::<LinkReport>(&report) {
+            Ok(mut r) => {
+                r.url = url;
+                Some(r)
+            }
+            Err(_) => None,
+        }
+    }
+
+    /// Check every outbound link in an existing paste by `url`, replacing its stored report
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste to check
+    #[cfg(feature = "links")]
+    pub async fn check_links_by_url(&self, mut url: String) -> Result<LinkReport> {
+        let options = match self.options.link_check {
+            Some(ref o) => o,
+            None => return Err(PasteError::NotAllowed),
+        };
+
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+
+        if url.ends_with("-") {
+            url.pop();
+        }
+
+        // get paste
+        let existing = match self.get_paste_by_url(url.clone()).await {
+            Ok(p) => p,
+            Err(err) => return Err(err),
+        };
+
+        // check links
+        let timeout = std::time::Duration::from_millis(options.timeout);
+
+        let links = futures_util::future::join_all(
+            links::extract(&existing.content)
+                .into_iter()
+                .map(|link| check_link(link, timeout)),
+        )
+        .await;
+
+        let report = LinkReport {
+            url: url.clone(),
+            links,
+            checked: utility::unix_epoch_timestamp(),
+        };
+
+        // replace existing report
+        let (delete, insert) = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql")
+        {
+            (
+                "DELETE FROM \"se_link_reports\" WHERE \"url\" = ?",
+                "INSERT INTO \"se_link_reports\" VALUES (?, ?, ?)",
+            )
+        } else {
+            (
+                "DELETE FROM \"se_link_reports\" WHERE \"url\" = $1",
+                "INSERT INTO \"se_link_reports\" VALUES ($1, $2, $3)",
+            )
+        };
+
+        match self
+            .transaction(vec![
+                (delete, vec![url.clone()]),
+                (
+                    insert,
+                    vec![
+                        url.clone(),
+                        serde_json::to_string(&report).unwrap(),
+                        report.checked.to_string(),
+                    ],
+                ),
+            ])
+            .await
+        {
+            Ok(_) => {
+                // remove from cache
+                self.base.cachedb.remove(format!("se_links:{}", url)).await;
+                Ok(report)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Re-check the links of every paste whose report is older than [`LinkCheckOptions::interval`]
+    ///
+    /// Reports of pastes which no longer exist are removed.
+    #[cfg(feature = "links")]
+    pub async fn check_stale_links(&self) {
+        let interval = match self.options.link_check {
+            Some(ref o) => o.interval,
+            None => return,
+        };
+
+        let c = &self.base.db.client;
+        let stale: Vec<String> = match sqlquery("SELECT * FROM \"se_link_reports\"")
+            .fetch_all(c)
+            .await
+        {
+            Ok(rows) => {
+                let now = utility::unix_epoch_timestamp();
+
+                rows.into_iter()
+                    .map(|r| self.base.textify_row(r).data)
+                    .filter(|r| {
+                        let checked = r.get("checked").unwrap().parse::<u128>().unwrap_or(0);
+                        now.saturating_sub(checked) >= interval
+                    })
+                    .map(|r| r.get("url").unwrap().to_string())
+                    .collect()
+            }
+            Err(_) => return,
+        };
+
+        for url in stale {
+            if let Err(PasteError::NotFound) = self.check_links_by_url(url.clone()).await {
+                let query: &str =
+                    if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+                        "DELETE FROM \"se_link_reports\" WHERE \"url\" = ?"
+                    } else {
+                        "DELETE FROM \"se_link_reports\" WHERE \"url\" = $1"
+                    };
+
+                let _ = sqlquery(query).bind::<&String>(&url).execute(c).await;
+                self.base.cachedb.remove(format!("se_links:{}", url)).await;
+            }
+        }
+    }
+
+    /// Start a background task which calls [`Database::check_stale_links`] every minute
+    ///
+    /// Pastes are only checked in the background after their first report has been requested.
+    #[cfg(feature = "links")]
+    pub fn spawn_link_checker(&self) {
+        if self.options.link_check.is_none() {
+            return;
+        }
+
+        let database = self.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+
+            loop {
+                interval.tick().await;
+                database.check_stale_links().await;
+            }
+        });
+    }
+
+    // scanning
+
+    /// Scan a binary paste by `url` for malware (see [`ServerOptions::scanner`])
+    ///
+    /// The result replaces the paste's stored [`ScanReport`], and is recorded in its
+    /// [`PasteMetadata::scan_status`]. Infected pastes are quarantined (they can't be downloaded).
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste to scan
+    #[cfg(feature = "scanning")]
+    pub async fn scan_paste_by_url(&self, mut url: String) -> Result<ScanReport> {
+        let options = match self.options.scanner {
+            Some(ref o) => o,
+            None => return Err(PasteError::NotAllowed),
+        };
+
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+
+        if url.ends_with("-") {
+            url.pop();
+        }
+
+        // get paste
+        let existing = match self.get_paste_by_url(url.clone()).await {
+            Ok(p) => p,
+            Err(err) => return Err(err),
+        };
+
+        if existing.metadata.content_type != "binary" {
+            return Err(PasteError::ValueError);
+        }
+
+        let bytes = match binary::decode(&existing.content) {
+            Some(b) => b,
+            None => return Err(PasteError::ValueError),
+        };
+
+        // scan
+        let (status, signature) = match scanner::scan(&options.url, options.timeout, &bytes).await {
+            Some(scanner::Verdict::Clean) => ("clean", String::new()),
+            Some(scanner::Verdict::Infected(s)) => ("infected", s),
+            None => ("failed", String::new()),
+        };
+
+        let report = ScanReport {
+            url: url.clone(),
+            status: status.to_string(),
+            signature,
+            scanned: utility::unix_epoch_timestamp(),
+        };
+
+        // record status (the paste is read again since it could've been edited during the scan)
+        let mut metadata = match self.get_paste_by_url(url.clone()).await {
+            Ok(p) => p.metadata,
+            Err(e) => return Err(e),
+        };
+
+        metadata.scan_status = report.status.clone();
+
+        // replace existing report
+        let (delete, insert, update) =
+            if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+                (
+                    "DELETE FROM \"se_scans\" WHERE \"url\" = ?",
+                    "INSERT INTO \"se_scans\" VALUES (?, ?, ?, ?)",
+                    "UPDATE \"se_pastes\" SET \"metadata\" = ? WHERE \"url\" = ?",
+                )
+            } else {
+                (
+                    "DELETE FROM \"se_scans\" WHERE \"url\" = $1",
+                    "INSERT INTO \"se_scans\" VALUES ($1, $2, $3, $4)",
+                    "UPDATE \"se_pastes\" SET \"metadata\" = $1 WHERE \"url\" = $2",
+                )
+            };
+
+        match self
+            .transaction(vec![
+                (delete, vec![url.clone()]),
+                (
+                    insert,
+                    vec![
+                        url.clone(),
+                        report.status.clone(),
+                        report.signature.clone(),
+                        report.scanned.to_string(),
+                    ],
+                ),
+                (
+                    update,
+                    vec![
+                        match serde_json::to_string(&metadata) {
+                            Ok(m) => m,
+                            Err(_) => return Err(PasteError::ValueError),
+                        },
+                        url.clone(),
+                    ],
+                ),
+            ])
+            .await
+        {
+            Ok(_) => {
+                // remove from cache
+                self.base.cachedb.remove(format!("se_paste:{}", url)).await;
+                self.log_mutation("scan", &url, "", "", None);
+                Ok(report)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Scan a binary paste by `url` for malware in the background (see [`Database::scan_paste_by_url`])
+    #[cfg(feature = "scanning")]
+    fn spawn_scan(&self, url: String) {
+        if self.options.scanner.is_none() {
+            return;
+        }
+
+        let database = self.clone();
+
+        tokio::spawn(async move {
+            let _ = database.scan_paste_by_url(url).await;
+        });
+    }
+
+    /// Pastes are never scanned without the `scanning` feature
+    #[cfg(not(feature = "scanning"))]
+    fn spawn_scan(&self, _url: String) {}
+
+    /// Get the scan reports of every quarantined (infected) paste, newest first
+    #[cfg(feature = "scanning")]
+    pub async fn get_quarantined_pastes(&self) -> Result<Vec<ScanReport>> {
+        if self.options.scanner.is_none() {
+            return Err(PasteError::NotAllowed);
+        }
+
+        let c = &self.base.db.client;
+        match sqlquery(
+            "SELECT * FROM \"se_scans\" WHERE \"status\" = 'infected' ORDER BY \"scanned\" DESC",
+        )
+        .fetch_all(c)
+        .await
+        {
+            Ok(rows) => Ok(rows
+                .into_iter()
+                .map(|r| {
+                    let row = self.base.textify_row(r).data;
+
+                    ScanReport {
+                        url: row.get("url").unwrap().to_string(),
+                        status: row.get("status").unwrap().to_string(),
+                        signature: row.get("signature").unwrap().to_string(),
+                        scanned: row.get("scanned").unwrap().parse::<u128>().unwrap_or(0),
+                    }
+                })
+                .collect()),
+            Err(e) => Err(query_failed(e)),
+        }
+    }
+
+    // migrations
+
+    /// Read every row of `table` for a [`Dump`]
+    ///
+    /// ## Returns:
+    /// * `None` if the table doesn't exist
+    async fn dump_table(&self, table: &str) -> Option<DumpTable> {
+        let c = &self.base.db.client;
+        let rows = match sqlquery(&format!("SELECT * FROM \"{}\"", table))
+            .fetch_all(c)
+            .await
+        {
+            Ok(r) => r,
+            Err(_) => return None,
+        };
+
+        let rows: Vec<HashMap<String, String>> = rows
+            .into_iter()
+            .map(|r| self.base.textify_row(r).data)
+            .collect();
+
+        let mut columns: Vec<String> = match rows.first() {
+            Some(r) => r.keys().cloned().collect(),
+            None => Vec::new(),
+        };
+
+        columns.sort();
+
+        let mut values: Vec<Vec<String>> = rows
+            .iter()
+            .map(|r| {
+                columns
+                    .iter()
+                    .map(|c| r.get(c).cloned().unwrap_or_default())
+                    .collect()
+            })
+            .collect();
+
+        values.sort();
+
+        Some(DumpTable {
+            name: table.to_string(),
+            checksum: utility::hash(serde_json::to_string(&values).unwrap()),
+            columns,
+            rows: values,
+        })
+    }
+
+    /// Copy every table of the instance into a portable [`Dump`] which can be loaded into
+    /// any database type with [`Database::load`]
+    ///
+    /// The search index isn't included (it's rebuilt from pastes after loading), and
+    /// neither is anything only kept in the cache (e.g. view counts outside of
+    /// [`ViewMode::AuthenticatedOnce`] which haven't been written by [`Database::flush_views`]).
+    pub async fn dump(&self) -> Dump {
+        let mut tables: Vec<DumpTable> = Vec::new();
+
+        for table in DUMP_TABLES {
+            if let Some(t) = self.dump_table(table).await {
+                tables.push(t);
+            }
+        }
+
+        Dump {
+            format: DUMP_FORMAT.to_string(),
+            source: self.base.db._type.clone(),
+            timestamp: utility::unix_epoch_timestamp(),
+            tables,
+        }
+    }
+
+    /// Replace the tables in `dump` with its rows
+    ///
+    /// Every table's checksum is checked before anything is written, everything is
+    /// written in a single transaction, and the written rows are checked against the
+    /// dump afterwards. [`Database::init`] should be called with the same options as the
+    /// dumped instance first, so every table exists.
+    ///
+    /// ## Arguments:
+    /// * `dump` - [`Dump`]
+    pub async fn load(&self, mut dump: Dump) -> Result<()> {
+        if dump.format != DUMP_FORMAT {
+            return Err(PasteError::ValueError);
+        }
+
+        // check dump
+        let column = regex::Regex::new("^[a-z_]+$").unwrap();
+
+        for table in &dump.tables {
+            if !DUMP_TABLES.contains(&table.name.as_str())
+                | !table.columns.iter().all(|c| column.is_match(c))
+                | table.rows.iter().any(|r| r.len() != table.columns.len())
+                | (utility::hash(serde_json::to_string(&table.rows).unwrap()) != table.checksum)
+            {
+                return Err(PasteError::ValueError);
+            }
+        }
+
+        // dumps from older versions don't have view counts ("views" sorts last, so it's
+        // added to the end of every row)
+        for table in dump.tables.iter_mut() {
+            if (table.name == "se_pastes") && !table.columns.contains(&String::from("views")) {
+                table.columns.push(String::from("views"));
+
+                for row in table.rows.iter_mut() {
+                    row.push(String::from("0"));
+                }
+
+                table.checksum = utility::hash(serde_json::to_string(&table.rows).unwrap());
+            }
+        }
+
+        // build statements
+        let queries: Vec<(String, String)> = dump
+            .tables
+            .iter()
+            .map(|t| {
+                let placeholders: Vec<String> = (1..=t.columns.len())
+                    .map(|i| {
+                        if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+                            String::from("?")
+                        } else {
+                            format!("${}", i)
+                        }
+                    })
+                    .collect();
+
+                (
+                    format!("DELETE FROM \"{}\"", t.name),
+                    format!(
+                        "INSERT INTO \"{}\" ({}) VALUES ({})",
+                        t.name,
+                        t.columns
+                            .iter()
+                            .map(|c| format!("\"{}\"", c))
+                            .collect::<Vec<String>>()
+                            .join(", "),
+                        placeholders.join(", ")
+                    ),
+                )
+            })
+            .collect();
+
+        let mut statements: Vec<(&str, Vec<String>)> = Vec::new();
+
+        for (table, (delete, insert)) in dump.tables.iter().zip(queries.iter()) {
+            statements.push((delete.as_str(), Vec::new()));
+
+            for row in &table.rows {
+                statements.push((insert.as_str(), row.clone()));
+            }
+        }
+
+        if let Err(e) = self.transaction(statements).await {
+            return Err(e);
+        }
+
+        // remove from cache
+        self.base
+            .cachedb
+            .remove_starting_with(String::from("se_*"))
+            .await;
+
+        // verify
+        for table in &dump.tables {
+            match self.dump_table(&table.name).await {
+                Some(t) => {
+                    if t.checksum != table.checksum {
+                        return Err(PasteError::Other);
+                    }
+                }
+                None => return Err(PasteError::Other),
+            }
+        }
+
+        // the search index is rebuilt on the next start
+        self.set_setting(String::from("search_indexed"), None).await
+    }
+
+    // checkpoints
+
+    /// Checkpoint the SQLite write-ahead log into the database file
+    ///
+    /// ## Arguments:
+    /// * `mode` - `passive` (default), `full`, `restart` or `truncate` (see the SQLite docs for `wal_checkpoint`)
+    pub async fn checkpoint(&self, mode: String) -> Result<Checkpoint> {
+        if self.base.db._type != "sqlite" {
+            return Err(PasteError::NotAllowed);
+        }
+
+        let mode = if mode.is_empty() {
+            String::from("PASSIVE")
+        } else {
+            mode.to_uppercase()
+        };
+
+        if !["PASSIVE", "FULL", "RESTART", "TRUNCATE"].contains(&mode.as_str()) {
+            return Err(PasteError::ValueError);
+        }
+
+        let c = &self.base.db.client;
+        match sqlquery(&format!("PRAGMA wal_checkpoint({})", mode))
+            .fetch_one(c)
+            .await
+        {
+            Ok(row) => Ok(Checkpoint {
+                busy: row.try_get::<i64, _>(0).unwrap_or(0) != 0,
+                log_frames: row.try_get::<i64, _>(1).unwrap_or(-1),
+                checkpointed_frames: row.try_get::<i64, _>(2).unwrap_or(-1),
+            }),
+            Err(e) => Err(query_failed(e)),
+        }
+    }
+
+    /// Start a background task which runs a passive [`Database::checkpoint`] every
+    /// [`SqliteOptions::checkpoint_interval`]
+    pub fn spawn_checkpointer(&self) {
+        if (self.base.db._type != "sqlite") | (self.options.sqlite.checkpoint_interval == 0) {
+            return;
+        }
+
+        let database = self.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_millis(
+                database.options.sqlite.checkpoint_interval,
+            ));
+
+            loop {
+                interval.tick().await;
+                let _ = database.checkpoint(String::new()).await;
+            }
+        });
+    }
+
+    // meta
+
+    /// Get the limits and features of the instance
+    pub fn get_instance_meta(&self) -> InstanceMeta {
+        let features: Vec<String> = [
+            ("view_password", self.options.view_password),
+            ("guppy", self.options.guppy),
+            ("paste_ownership", self.options.paste_ownership),
+            ("document_store", self.options.document_store),
+            ("ingest", self.options.ingest),
+            ("from_url", self.options.from_url && cfg!(feature = "fetch")),
+            ("lint", self.options.lint),
+            ("preview", self.options.renderer.is_some()),
+            ("front_matter", self.options.front_matter),
+            ("collections", self.options.collections),
+            ("includes", self.options.includes),
+            ("suggestions", self.options.suggestions),
+            ("annotations", self.options.annotations),
+            (
+                "runners",
+                !self.options.runners.is_empty() && cfg!(feature = "runners"),
+            ),
+            ("watermark", self.options.watermark),
+            ("translations", self.options.translations),
+            (
+                "link_check",
+                self.options.link_check.is_some() && cfg!(feature = "links"),
+            ),
+            (
+                "thumbnails",
+                self.options.thumbnails && cfg!(feature = "thumbnails"),
+            ),
+            (
+                "webauthn",
+                self.options.webauthn.is_some() && cfg!(feature = "webauthn"),
+            ),
+            ("edit_links", self.options.edit_links),
+            ("expiration", self.options.expiration),
+            ("invites", self.options.invites),
+            ("invite_only", self.options.invite_only),
+            ("search", self.options.search),
+            (
+                "archive",
+                (self.options.archive_after != 0) && cfg!(feature = "archive"),
+            ),
+            (
+                "highlight",
+                self.options.highlight && cfg!(feature = "highlight"),
+            ),
+            ("stack_traces", self.options.stack_traces),
+            ("log_files", self.options.log_files),
+            ("tables", self.options.tables),
+            ("structured_data", self.options.structured_data),
+            (
+                "binary_pastes",
+                self.options.binary_pastes && cfg!(feature = "binary"),
+            ),
+            ("imports", self.options.imports),
+            ("rotations", self.options.rotations),
+            ("moderation", self.options.moderation),
+            ("checksums", cfg!(feature = "checksums")),
+            (
+                "signed_urls",
+                self.options.view_password && cfg!(feature = "signed_urls"),
+            ),
+            (
+                "scanning",
+                self.options.scanner.is_some() && cfg!(feature = "scanning"),
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| name.to_string())
+        .collect();
+
+        InstanceMeta {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            limits: InstanceLimits {
+                url_min_length: validation::MIN_URL_LENGTH,
+                url_max_length: validation::MAX_URL_LENGTH,
+                url_pattern: validation::URL_PATTERN.to_string(),
+                content_max_length: validation::MAX_CONTENT_LENGTH,
+                title_max_length: validation::MAX_TITLE_LENGTH,
+                description_max_length: validation::MAX_DESCRIPTION_LENGTH,
+                max_tags: validation::MAX_TAGS,
+                tag_max_length: validation::MAX_TAG_LENGTH,
+            },
+            features,
+            expiry_options: if self.options.expiration == true {
+                ["never", "1h", "1d", "1w"]
+                    .iter()
+                    .map(|o| o.to_string())
+                    .collect()
+            } else {
+                Vec::new()
+            },
+            languages: self.get_highlight_languages(),
+        }
+    }
+
+    /// Get every language pastes can be highlighted as
+    #[cfg(feature = "highlight")]
+    fn get_highlight_languages(&self) -> Vec<HighlightLanguage> {
+        if self.options.highlight == false {
+            return Vec::new();
+        }
+
+        highlight::languages()
+    }
+
+    /// Get every language pastes can be highlighted as
+    #[cfg(not(feature = "highlight"))]
+    fn get_highlight_languages(&self) -> Vec<HighlightLanguage> {
+        Vec::new()
+    }
+
+    // doctor
+
+    /// Get the tables (and their columns) [`Database::init`] creates with the current options
+    fn get_expected_tables(&self) -> Vec<(&'static str, Vec<&'static str>)> {
+        let mut tables: Vec<(&str, Vec<&str>)> = vec![
+            (
+                "se_pastes",
+                vec![
+                    "id",
+                    "url",
+                    "password",
+                    "content",
+                    "date_published",
+                    "date_edited",
+                    "metadata",
+                    "views",
+                ],
+            ),
+            ("se_settings", vec!["name", "value"]),
+        ];
+
+        if self.options.view_mode == ViewMode::AuthenticatedOnce {
+            tables.push(("se_views", vec!["url", "username"]));
+        }
+
+        if self.options.document_store == true {
+            tables.push((
+                "se_documents",
+                vec!["id", "namespace", "content", "timestamp", "metadata"],
+            ));
+        }
+
+        if self.options.ingest == true {
+            tables.push((
+                "se_ingest_tokens",
+                vec![
+                    "id",
+                    "prefix",
+                    "expires",
+                    "max_size",
+                    "owner",
+                    "timestamp",
+                    "uses",
+                    "last_used",
+                ],
+            ));
+        }
+
+        if self.options.invites == true {
+            tables.push((
+                "se_invites",
+                vec![
+                    "id",
+                    "owner",
+                    "remaining",
+                    "max_size",
+                    "expires",
+                    "timestamp",
+                ],
+            ));
+        }
+
+        if self.options.collections == true {
+            tables.push(("se_collections", vec!["id", "title", "owner", "timestamp"]));
+            tables.push((
+                "se_collection_pastes",
+                vec!["collection", "url", "position"],
+            ));
+        }
+
+        if self.options.suggestions == true {
+            tables.push((
+                "se_suggestions",
+                vec!["id", "url", "content", "author", "timestamp"],
+            ));
+        }
+
+        if self.options.annotations == true {
+            tables.push((
+                "se_annotations",
+                vec![
+                    "id",
+                    "url",
+                    "line_start",
+                    "line_end",
+                    "content",
+                    "author",
+                    "timestamp",
+                ],
+            ));
+        }
+
+        if self.options.watermark == true {
+            tables.push(("se_downloads", vec!["id", "url", "username", "timestamp"]));
+        }
+
+        if self.options.translations == true {
+            tables.push((
+                "se_paste_translations",
+                vec!["url", "lang", "content", "timestamp"],
+            ));
+        }
+
+        if self.options.link_check.is_some() {
+            tables.push(("se_link_reports", vec!["url", "report", "checked"]));
+        }
+
+        if self.options.scanner.is_some() {
+            tables.push(("se_scans", vec!["url", "status", "signature", "scanned"]));
+        }
+
+        if self.options.webauthn.is_some() {
+            tables.push((
+                "se_credentials",
+                vec!["id", "username", "public_key", "sign_count", "timestamp"],
+            ));
+        }
+
+        if self.options.expiration == true {
+            tables.push(("se_expirations", vec!["url", "expires"]));
+        }
+
+        if self.options.rotations == true {
+            tables.push(("se_rotations", vec!["url", "source", "period", "next_run"]));
+            tables.push(("se_revisions", vec!["url", "content", "timestamp"]));
+        }
+
+        if self.options.moderation == true {
+            tables.push(("se_paste_ips", vec!["url", "ip", "timestamp"]));
+        }
+
+        #[cfg(feature = "geoip")]
+        if !self.options.geoip_database.is_empty() {
+            tables.push(("se_view_countries", vec!["url", "country", "count"]));
+        }
+
+        if !self.options.tos_paste.is_empty() {
+            tables.push(("se_tos_acceptances", vec!["url", "timestamp", "ip"]));
+        }
+
+        #[cfg(feature = "archive")]
+        {
+            tables.push(("se_archive", vec!["url", "content"]));
+            tables.push(("se_activity", vec!["url", "last_viewed"]));
+        }
+
+        if self.options.search == true {
+            tables.push(("se_search", vec!["url", "title", "content"]));
+        }
+
+        tables
+    }
+
+    /// Get the indexes (and their tables) [`Database::init`] creates with the current options
+    fn get_expected_indexes(&self) -> Vec<(&'static str, &'static str)> {
+        let mut indexes: Vec<(&str, &str)> = vec![("se_pastes_url", "se_pastes")];
+
+        if self.options.view_mode == ViewMode::AuthenticatedOnce {
+            indexes.push(("se_views_url", "se_views"));
+        }
+
+        if self.options.document_store == true {
+            indexes.push(("se_documents_namespace", "se_documents"));
+        }
+
+        if (self.options.search == true) && (self.base.db._type == "postgres") {
+            indexes.push(("se_search_document", "se_search"));
+        }
+
+        indexes
+    }
+
+    /// Check if an index exists
+    async fn index_exists(&self, name: &str) -> bool {
+        let query: &str = if self.base.db._type == "sqlite" {
+            "SELECT \"name\" FROM \"sqlite_master\" WHERE \"type\" = 'index' AND \"name\" = ?"
+        } else if self.base.db._type == "mysql" {
+            "SELECT \"INDEX_NAME\" FROM information_schema.statistics WHERE \"TABLE_SCHEMA\" = DATABASE() AND \"INDEX_NAME\" = ?"
+        } else {
+            "SELECT \"indexname\" FROM \"pg_indexes\" WHERE \"indexname\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query).bind::<&str>(name).fetch_all(c).await {
+            Ok(r) => !r.is_empty(),
+            Err(_) => false,
+        }
+    }
+
+    /// Check that the database and cache are reachable, and that every table, column and
+    /// index [`Database::init`] creates with the current options exists
+    ///
+    /// Failed checks include steps to fix them. [`Database::init`] prints failed checks
+    /// when it finishes.
+    pub async fn doctor(&self) -> DoctorReport {
+        let c = &self.base.db.client;
+        let mut checks: Vec<DoctorCheck> = Vec::new();
+
+        // database
+        let reachable = sqlquery("SELECT 1").fetch_one(c).await.is_ok();
+
+        checks.push(DoctorCheck {
+            name: String::from("database"),
+            ok: reachable,
+            remediation: if reachable {
+                String::new()
+            } else {
+                format!(
+                    "the {} database can't be queried: check the connection options given to \
+                     Database::new, that the database server is running and that the user can log in",
+                    self.base.db._type
+                )
+            },
+        });
+
+        // cache
+        let key = format!("se_doctor:{}", utility::random_id());
+        let cached = self.base.cachedb.set(key.clone(), String::from("1")).await
+            && (self.base.cachedb.get(key.clone()).await == Some(String::from("1")));
+
+        self.base.cachedb.remove(key).await;
+
+        checks.push(DoctorCheck {
+            name: String::from("cache"),
+            ok: cached,
+            remediation: if cached {
+                String::new()
+            } else {
+                String::from(
+                    "the cache can't be written to: check that the redis server is running and \
+                     reachable from this host",
+                )
+            },
+        });
+
+        if reachable == false {
+            // everything else would fail for the same reason
+            return DoctorReport { ok: false, checks };
+        }
+
+        // tables
+        for (table, columns) in self.get_expected_tables() {
+            if sqlquery(&format!("SELECT 1 FROM \"{}\" WHERE 1 = 0", table))
+                .fetch_all(c)
+                .await
+                .is_err()
+            {
+                checks.push(DoctorCheck {
+                    name: format!("table {}", table),
+                    ok: false,
+                    remediation: format!(
+                        "table \"{}\" doesn't exist: make sure the database user can create \
+                         tables, then restart so Database::init creates it",
+                        table
+                    ),
+                });
+
+                continue;
+            }
+
+            let mut missing: Vec<&str> = Vec::new();
+
+            for column in columns {
+                if sqlquery(&format!(
+                    "SELECT \"{}\" FROM \"{}\" WHERE 1 = 0",
+                    column, table
+                ))
+                .fetch_all(c)
+                .await
+                .is_err()
+                {
+                    missing.push(column);
+                }
+            }
+
+            checks.push(DoctorCheck {
+                name: format!("table {}", table),
+                ok: missing.is_empty(),
+                remediation: match missing.first() {
+                    Some(column) => format!(
+                        "table \"{}\" is missing the columns {} (it was likely created by an \
+                         older version): add them with ALTER TABLE \"{}\" ADD COLUMN \"{}\" TEXT \
+                         (and so on for each column), or load a dump into a fresh database",
+                        table,
+                        missing.join(", "),
+                        table,
+                        column
+                    ),
+                    None => String::new(),
+                },
+            });
+        }
+
+        // indexes
+        for (index, table) in self.get_expected_indexes() {
+            let exists = self.index_exists(index).await;
+
+            checks.push(DoctorCheck {
+                name: format!("index {}", index),
+                ok: exists,
+                remediation: if exists {
+                    String::new()
+                } else if index == "se_pastes_url" {
+                    String::from(
+                        "urls in \"se_pastes\" aren't unique: delete or rename the duplicate \
+                         pastes (SELECT \"url\" FROM \"se_pastes\" GROUP BY \"url\" HAVING \
+                         COUNT(*) > 1), then restart so Database::init creates the index",
+                    )
+                } else {
+                    format!(
+                        "index \"{}\" on \"{}\" doesn't exist: make sure the database user can \
+                         create indexes, then restart so Database::init creates it",
+                        index, table
+                    )
+                },
+            });
+        }
+
+        DoctorReport {
+            ok: checks.iter().all(|c| c.ok),
+            checks,
+        }
+    }
+
+    // watermarks
+
+    /// Watermark a copy of a paste, logging who it was given to
+    ///
+    /// Pastes without a view password are returned as they are.
+    ///
+    /// ## Arguments:
+    /// * `paste` - the paste to watermark
+    /// * `username` - the username of the user fetching the paste (can be empty)
+    pub async fn watermark_paste(&self, mut paste: Paste, username: String) -> Result<Paste> {
+        if (self.options.watermark == false) | paste.metadata.view_password.is_empty() {
+            return Ok(paste);
+        }
+
+        let download = Download {
+            id: utility::random_id().chars().take(16).collect(),
+            url: paste.url.clone(),
+            username,
+            timestamp: utility::unix_epoch_timestamp(),
+        };
+
+        // log download
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "INSERT INTO \"se_downloads\" VALUES (?, ?, ?, ?)"
+        } else {
+            "INSERT INTO \"se_downloads\" VALUES ($1, $2, $3, $4)"
+        };
+
+        let c = &self.base.db.client;
+        if let Err(_) = sqlquery(query)
+            .bind::<&String>(&download.id)
+            .bind::<&String>(&download.url)
+            .bind::<&String>(&download.username)
+            .bind::<&String>(&download.timestamp.to_string())
+            .execute(c)
+            .await
+        {
+            return Err(PasteError::Other);
+        };
+
+        // return
+        paste.content = watermark::embed(&paste.content, &download.id);
+        Ok(paste)
+    }
+
+    /// Find which download of an existing paste leaked `content`
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste
+    /// * `content` - the leaked content
+    /// * `password` - the paste's edit password
+    /// * `editing_as` - the userstate of the user tracing the paste
+    pub async fn trace_paste_by_url(
+        &self,
+        mut url: String,
+        content: String,
+        password: String,
+        editing_as: Option<FullUser<UserMetadata>>,
+    ) -> Result<Download> {
+        if self.options.watermark == false {
+            return Err(PasteError::NotAllowed);
+        }
+
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+
+        if url.ends_with("-") {
+            url.pop();
+        }
+
+        // get paste
+        let existing = match self.get_paste_by_url(url.clone()).await {
+            Ok(p) => p,
+            Err(err) => return Err(err),
+        };
+
+        // check password
+        let mut skip_password_check: bool = false;
+
+        if let Some(ua) = editing_as {
+            if (ua.user.username == existing.metadata.owner)
+                | ua.level.permissions.contains(&"ManagePastes".to_string())
+            {
+                skip_password_check = true;
+            }
+        }
+
+        if skip_password_check == false {
+            if utility::hash(password) != existing.password {
+                return Err(PasteError::PasswordIncorrect);
+            }
+        }
+
+        // find download
+        let id = match watermark::extract(&content) {
+            Some(i) => i,
+            None => return Err(PasteError::NotFound),
+        };
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"se_downloads\" WHERE \"id\" = ? AND \"url\" = ?"
+        } else {
+            "SELECT * FROM \"se_downloads\" WHERE \"id\" = $1 AND \"url\" = $2"
+        };
+
+        let c = &self.base.db.client;
+        let res = match sqlquery(query)
+            .bind::<&String>(&id)
+            .bind::<&String>(&url)
+            .fetch_one(c)
+            .await
+        {
+            Ok(p) => self.base.textify_row(p).data,
+            Err(_) => return Err(PasteError::NotFound),
+        };
+
+        // return
+        Ok(Download {
+            id: res.get("id").unwrap().to_string(),
+            url: res.get("url").unwrap().to_string(),
+            username: res.get("username").unwrap().to_string(),
+            timestamp: res.get("timestamp").unwrap().parse::<u128>().unwrap(),
+        })
+    }
+
+    // translations
+
+    /// Get all translations of an existing paste by `url`
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste
+    pub async fn get_translations_by_url(&self, mut url: String) -> Result<Vec<PasteTranslation>> {
+        if self.options.translations == false {
+            return Err(PasteError::NotAllowed);
+        }
+
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+
+        if url.ends_with("-") {
+            url.pop();
+        }
+
+        // check in cache
+        if let Some(c) = self
+            .base
+            .cachedb
+            .get(format!("se_translations:{}", url))
+            .await
+        {
+            if let Ok(t) = serde_json::from_str::<Vec<PasteTranslation>>(c.as_str()) {
+                return Ok(t);
+            }
+        }
+
+        // pull from db
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT * FROM \"se_paste_translations\" WHERE \"url\" = ?"
+        } else {
+            "SELECT * FROM \"se_paste_translations\" WHERE \"url\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        let mut translations: Vec<PasteTranslation> =
+            match sqlquery(query).bind::<&String>(&url).fetch_all(c).await {
+                Ok(rows) => rows
+                    .into_iter()
+                    .map(|r| {
+                        let res = self.base.textify_row(r).data;
+                        PasteTranslation {
+                            url: res.get("url").unwrap().to_string(),
+                            lang: res.get("lang").unwrap().to_string(),
+                            content: res.get("content").unwrap().to_string(),
+                            timestamp: res.get("timestamp").unwrap().parse::<u128>().unwrap(),
+                        }
+                    })
+                    .collect(),
+                Err(e) => return Err(query_failed(e)),
+            };
+
+        translations.sort_by(|a, b| a.lang.cmp(&b.lang));
+
+        // store in cache
+        self.base
+            .cachedb
+            .set(
+                format!("se_translations:{}", url),
+                serde_json::to_string(&translations).unwrap(),
+            )
+            .await;
+
+        // return
+        Ok(translations)
+    }
+
+    /// Get the translation of an existing paste best matching a list of preferred languages
+    ///
+    /// Each language is tried as given, then without its region (`fr-ca` falls back to `fr`).
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste
+    /// * `langs` - preferred language tags, most preferred first
     ///
     /// ## Returns:
-    /// * Result containing a tuple with the unhashed edit password and the paste
-    pub async fn create_paste(&self, mut props: PasteCreate) -> Result<(String, Paste)> {
-        props.url = idna::punycode::encode_str(&props.url)
-            .unwrap()
-            .to_lowercase();
+    /// * the translation, or `None` if the original content should be used
+    pub async fn get_translation(
+        &self,
+        url: String,
+        langs: Vec<String>,
+    ) -> Option<PasteTranslation> {
+        let translations = match self.get_translations_by_url(url).await {
+            Ok(t) => t,
+            Err(_) => return None,
+        };
 
-        if props.url.ends_with("-") {
-            props.url.pop();
+        if translations.is_empty() {
+            return None;
+        }
+
+        for lang in langs {
+            let lang = lang.to_lowercase();
+            let primary = lang.split("-").next().unwrap_or("").to_string();
+
+            for tag in [lang, primary] {
+                if let Some(t) = translations.iter().find(|t| t.lang == tag) {
+                    return Some(t.clone());
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Create or replace the translation of an existing paste by `url`
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste
+    /// * `lang` - the language tag of the translation
+    /// * `content` - the translated content (empty to delete the translation)
+    /// * `password` - the paste's edit password
+    /// * `editing_as` - the userstate of the user we're editing the paste as
+    pub async fn edit_translation_by_url(
+        &self,
+        mut url: String,
+        mut lang: String,
+        content: String,
+        password: String,
+        editing_as: Option<FullUser<UserMetadata>>,
+    ) -> Result<()> {
+        if self.options.translations == false {
+            return Err(PasteError::NotAllowed);
+        }
+
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+
+        if url.ends_with("-") {
+            url.pop();
+        }
+
+        // get paste
+        let existing = match self.get_paste_by_url(url.clone()).await {
+            Ok(p) => p,
+            Err(err) => return Err(err),
+        };
+
+        // check password
+        let mut skip_password_check: bool = false;
+
+        if let Some(ua) = editing_as {
+            // check if we're the paste owner
+            if ua.user.username == existing.metadata.owner {
+                skip_password_check = true;
+            }
+            // check if we have the "ManagePastes" permission
+            else if ua.level.permissions.contains(&"ManagePastes".to_string()) {
+                skip_password_check = true;
+            }
+        }
+
+        if skip_password_check == false {
+            if utility::hash(password) != existing.password {
+                return Err(PasteError::PasswordIncorrect);
+            }
+        }
+
+        // check lang
+        lang = lang.to_lowercase();
+
+        let regex = regex::Regex::new("^[a-z]{2,3}(-[a-z0-9]{2,8})*$").unwrap();
+
+        if !regex.is_match(&lang) {
+            return Err(PasteError::ValueError);
+        }
+
+        // check length
+        let content = normalize::normalize(&content, &self.options.normalization).0;
+
+        if content.len() > validation::MAX_CONTENT_LENGTH {
+            return Err(PasteError::ContentTooLarge);
+        }
+
+        // replace existing translation
+        let (delete, insert) = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql")
+        {
+            (
+                "DELETE FROM \"se_paste_translations\" WHERE \"url\" = ? AND \"lang\" = ?",
+                "INSERT INTO \"se_paste_translations\" VALUES (?, ?, ?, ?)",
+            )
+        } else {
+            (
+                "DELETE FROM \"se_paste_translations\" WHERE \"url\" = $1 AND \"lang\" = $2",
+                "INSERT INTO \"se_paste_translations\" VALUES ($1, $2, $3, $4)",
+            )
+        };
+
+        let mut statements = vec![(delete, vec![url.clone(), lang.clone()])];
+
+        if !content.is_empty() {
+            statements.push((
+                insert,
+                vec![
+                    url.clone(),
+                    lang,
+                    content,
+                    utility::unix_epoch_timestamp().to_string(),
+                ],
+            ));
+        }
+
+        match self.transaction(statements).await {
+            Ok(_) => {
+                // remove from cache
+                self.base
+                    .cachedb
+                    .remove(format!("se_translations:{}", url))
+                    .await;
+
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // moderation
+
+    /// Record the IP address a paste was created from (see [`ServerOptions::moderation`])
+    ///
+    /// ## Arguments:
+    /// * `url` - the created paste
+    /// * `ip` - the IP address of the creator (only its hash is stored)
+    pub async fn record_creator_ip(&self, url: String, ip: String) -> Result<()> {
+        if self.options.moderation == false {
+            return Ok(());
         }
 
-        // make sure paste doesn't already exist
-        if let Ok(_) = self.get_paste_by_url(props.url.clone()).await {
-            return Err(PasteError::AlreadyExists);
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "INSERT INTO \"se_paste_ips\" VALUES (?, ?, ?)"
+        } else {
+            "INSERT INTO \"se_paste_ips\" VALUES ($1, $2, $3)"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query)
+            .bind::<&String>(&url)
+            .bind::<&String>(&utility::hash(ip))
+            .bind::<&String>(&utility::unix_epoch_timestamp().to_string())
+            .execute(c)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(query_failed(e)),
         }
+    }
 
-        // create url if not supplied
-        if props.url.is_empty() {
-            props.url = utility::random_id().chars().take(10).collect();
+    /// Get the urls of every paste matching `filter`, newest first
+    ///
+    /// Archived pastes are only matched by their content once they've been restored.
+    ///
+    /// ## Arguments:
+    /// * `filter` - [`ModerationFilter`]
+    pub async fn get_moderated_pastes(&self, filter: &ModerationFilter) -> Result<Vec<String>> {
+        if self.options.moderation == false {
+            return Err(PasteError::NotAllowed);
         }
 
-        // create random password if not supplied
-        if props.password.is_empty() {
-            props.password = utility::random_id().chars().take(10).collect();
+        let content = match check_moderation_filter(filter) {
+            Ok(r) => r,
+            Err(e) => return Err(e),
+        };
+
+        let c = &self.base.db.client;
+
+        // pastes created from the ip
+        let ip_urls: Option<HashSet<String>> = if filter.ip.is_empty() {
+            None
+        } else {
+            let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql")
+            {
+                "SELECT \"url\" FROM \"se_paste_ips\" WHERE \"ip\" = ?"
+            } else {
+                "SELECT \"url\" FROM \"se_paste_ips\" WHERE \"ip\" = $1"
+            };
+
+            match sqlquery(query)
+                .bind::<String>(utility::hash(filter.ip.clone()))
+                .fetch_all(c)
+                .await
+            {
+                Ok(rows) => Some(
+                    rows.into_iter()
+                        .map(|r| {
+                            self.base
+                                .textify_row(r)
+                                .data
+                                .get("url")
+                                .unwrap()
+                                .to_string()
+                        })
+                        .collect(),
+                ),
+                Err(e) => return Err(query_failed(e)),
+            }
+        };
+
+        // match pastes a page at a time
+        let mut out: Vec<String> = Vec::new();
+        let mut offset: usize = 0;
+
+        loop {
+            // timestamps are stored as text, but they all have the same length
+            let query = format!(
+                "SELECT * FROM \"se_pastes\" ORDER BY \"date_published\" DESC LIMIT 100 OFFSET {}",
+                offset
+            );
+
+            let rows = match sqlquery(&query).fetch_all(c).await {
+                Ok(r) => r,
+                Err(e) => return Err(query_failed(e)),
+            };
+
+            if rows.is_empty() {
+                break;
+            }
+
+            offset += rows.len();
+
+            for row in rows {
+                let res = self.base.textify_row(row).data;
+                let url = res.get("url").unwrap().to_string();
+                let date_published = res
+                    .get("date_published")
+                    .unwrap()
+                    .parse::<u128>()
+                    .unwrap_or(0);
+
+                let metadata: PasteMetadata =
+                    match serde_json::from_str(res.get("metadata").unwrap()) {
+                        Ok(m) => m,
+                        Err(_) => continue,
+                    };
+
+                if (!filter.owner.is_empty() && (metadata.owner != filter.owner))
+                    | (!filter.tag.is_empty() && !metadata.tags.contains(&filter.tag))
+                    | ((filter.created_after != 0) && (date_published < filter.created_after))
+                    | ((filter.created_before != 0) && (date_published > filter.created_before))
+                {
+                    continue;
+                }
+
+                if let Some(ref urls) = ip_urls {
+                    if !urls.contains(&url) {
+                        continue;
+                    }
+                }
+
+                if let Some(ref regex) = content {
+                    if !regex.is_match(res.get("content").unwrap()) {
+                        continue;
+                    }
+                }
+
+                out.push(url);
+            }
         }
 
-        // check lengths
-        if (props.url.len() > 250) | (props.url.len() < 3) {
-            return Err(PasteError::ValueError);
+        Ok(out)
+    }
+
+    /// Apply a moderation `action` to an existing paste by `url`
+    ///
+    /// ## Arguments:
+    /// * `url` - the (normalized) paste url
+    /// * `action` - [`ModerationAction`]
+    /// * `actor` - the username of the user moderating the paste
+    async fn moderate_paste_by_url(
+        &self,
+        url: String,
+        action: ModerationAction,
+        actor: &str,
+    ) -> Result<()> {
+        let existing = match self.get_paste_by_url(url.clone()).await {
+            Ok(p) => p,
+            Err(e) => return Err(e),
+        };
+
+        match action {
+            ModerationAction::Delete => {
+                if existing.metadata.legal_hold == true {
+                    return Err(PasteError::NotAllowed);
+                }
+
+                if let Err(e) = self.remove_paste_by_url(url.clone()).await {
+                    return Err(e);
+                }
+
+                self.log_mutation("delete", &url, "", actor, None);
+                Ok(())
+            }
+            ModerationAction::Lock | ModerationAction::Unlock => {
+                let mut metadata = existing.metadata;
+                metadata.locked = action == ModerationAction::Lock;
+
+                self.set_metadata_by_url(
+                    url,
+                    metadata,
+                    if action == ModerationAction::Lock {
+                        "lock"
+                    } else {
+                        "unlock"
+                    },
+                    actor,
+                )
+                .await
+            }
         }
+    }
 
-        if (props.content.len() > 200_000) | (props.content.len() < 1) {
-            return Err(PasteError::ValueError);
+    /// Store the progress of a moderation job in the cache
+    async fn save_moderation_job(&self, job: &ModerationJob) {
+        self.base
+            .cachedb
+            .set(
+                format!("se_moderation_job:{}", job.id),
+                serde_json::to_string(job).unwrap(),
+            )
+            .await;
+    }
+
+    /// Start a background job applying `action` to every paste matching `filter`
+    ///
+    /// ## Arguments:
+    /// * `action` - [`ModerationAction`]
+    /// * `filter` - [`ModerationFilter`]
+    /// * `user` - the userstate of the user starting the job (must have the "ManagePastes" permission)
+    ///
+    /// ## Returns:
+    /// * the job, which can be polled with [`Database::get_moderation_job`]
+    pub async fn start_moderation_job(
+        &self,
+        action: ModerationAction,
+        filter: ModerationFilter,
+        user: FullUser<UserMetadata>,
+    ) -> Result<ModerationJob> {
+        if self.options.moderation == false {
+            return Err(PasteError::NotAllowed);
         }
 
-        // (characters used)
-        let regex = regex::RegexBuilder::new("^[\\w\\_\\-\\.\\!\\p{Extended_Pictographic}]+$")
-            .multi_line(true)
-            .build()
-            .unwrap();
+        if !user.level.permissions.contains(&"ManagePastes".to_string()) {
+            return Err(PasteError::NotAllowed);
+        }
 
-        if regex.captures(&props.url).iter().len() < 1 {
-            return Err(PasteError::ValueError);
+        // check the filter before starting (bad filters fail straight away)
+        if let Err(e) = check_moderation_filter(&filter) {
+            return Err(e);
         }
 
-        // ...
-        let paste = Paste {
+        let mut job = ModerationJob {
             id: utility::random_id(),
-            url: props.url,
-            content: props.content,
-            password: utility::hash(props.password.clone()),
-            date_published: utility::unix_epoch_timestamp(),
-            date_edited: utility::unix_epoch_timestamp(),
-            metadata: super::model::PasteMetadata::default(),
+            action,
+            status: String::from("matching"),
+            matched: 0,
+            processed: 0,
+            failed: 0,
+            started_by: user.user.username,
+            started: utility::unix_epoch_timestamp(),
+            finished: 0,
         };
 
-        // create paste
+        self.save_moderation_job(&job).await;
+
+        let database = self.clone();
+        let out = job.clone();
+
+        tokio::spawn(async move {
+            let urls = database
+                .get_moderated_pastes(&filter)
+                .await
+                .unwrap_or_default();
+
+            job.status = String::from("running");
+            job.matched = urls.len();
+            database.save_moderation_job(&job).await;
+
+            for url in urls {
+                match database
+                    .moderate_paste_by_url(url, job.action, &job.started_by)
+                    .await
+                {
+                    Ok(_) => job.processed += 1,
+                    Err(_) => job.failed += 1,
+                }
+
+                database.save_moderation_job(&job).await;
+            }
+
+            job.status = String::from("finished");
+            job.finished = utility::unix_epoch_timestamp();
+            database.save_moderation_job(&job).await;
+        });
+
+        Ok(out)
+    }
+
+    /// Get the progress of a moderation job by `id` (see [`Database::start_moderation_job`])
+    ///
+    /// ## Arguments:
+    /// * `id` - the job
+    pub async fn get_moderation_job(&self, id: String) -> Result<ModerationJob> {
+        match self
+            .base
+            .cachedb
+            .get(format!("se_moderation_job:{}", id))
+            .await
+        {
+            Some(c) => match serde_json::from_str(&c) {
+                Ok(job) => Ok(job),
+                Err(_) => Err(PasteError::NotFound),
+            },
+            None => Err(PasteError::NotFound),
+        }
+    }
+
+    // terms of service
+
+    /// Record that the creator of a paste accepted the terms of service
+    ///
+    /// ## Arguments:
+    /// * `url` - the created paste
+    /// * `ip` - the IP address of the creator (only its hash is stored)
+    pub async fn record_tos_acceptance(&self, url: String, ip: String) -> Result<()> {
         let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
-            "INSERT INTO \"se_pastes\" VALUES (?, ?, ?, ?, ?, ?, ?)"
+            "INSERT INTO \"se_tos_acceptances\" VALUES (?, ?, ?)"
         } else {
-            "INSERT INTO \"se_pastes\" VALEUS ($1, $2, $3, $4, $5, $6, $7)"
+            "INSERT INTO \"se_tos_acceptances\" VALUES ($1, $2, $3)"
         };
 
         let c = &self.base.db.client;
         match sqlquery(query)
-            .bind::<&String>(&paste.id)
-            .bind::<&String>(&paste.url)
-            .bind::<&String>(&paste.password)
-            .bind::<&String>(&paste.content)
-            .bind::<&String>(&paste.date_published.to_string())
-            .bind::<&String>(&paste.date_edited.to_string())
-            .bind::<&String>(match serde_json::to_string(&paste.metadata) {
-                Ok(ref s) => s,
-                Err(_) => return Err(PasteError::ValueError),
-            })
+            .bind::<&String>(&url)
+            .bind::<&String>(&utility::unix_epoch_timestamp().to_string())
+            .bind::<&String>(&utility::hash(ip))
             .execute(c)
             .await
         {
-            Ok(_) => return Ok((props.password, paste)),
-            Err(_) => return Err(PasteError::Other),
+            Ok(_) => return Ok(()),
+            Err(e) => return Err(query_failed(e)),
         };
     }
 
-    /// Delete an existing paste by `url`
+    // settings
+
+    /// Get the value of an instance setting by `key`
     ///
     /// ## Arguments:
-    /// * `url` - the paste to delete
-    /// * `password` - the paste's edit password
-    pub async fn delete_paste_by_url(&self, mut url: String, password: String) -> Result<()> {
-        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
-
-        if url.ends_with("-") {
-            url.pop();
-        }
-
-        // get paste
-        let existing = match self.get_paste_by_url(url.clone()).await {
-            Ok(p) => p,
-            Err(err) => return Err(err),
-        };
-
-        // check password
-        if utility::hash(password) != existing.password {
-            return Err(PasteError::PasswordIncorrect);
+    /// * `key` - the setting
+    pub async fn get_setting(&self, key: String) -> Option<String> {
+        // check in cache
+        if let Some(c) = self.base.cachedb.get(format!("se_setting:{}", key)).await {
+            return Some(c);
         }
 
-        // delete paste view count
-        self.base.cachedb.remove(format!("se_views:{}", url)).await;
-
-        // delete paste
+        // pull from db
         let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
-            "DELETE FROM \"se_pastes\" WHERE \"url\" = ?"
+            "SELECT * FROM \"se_settings\" WHERE \"name\" = ?"
         } else {
-            "DELETE FROM \"se_pastes\" WHERE \"url\" = $1"
+            "SELECT * FROM \"se_settings\" WHERE \"name\" = $1"
         };
 
         let c = &self.base.db.client;
-        match sqlquery(query).bind::<&String>(&url).execute(c).await {
-            Ok(_) => {
-                // remove from cache
-                self.base.cachedb.remove(format!("se_paste:{}", url)).await;
+        let value = match sqlquery(query).bind::<&String>(&key).fetch_one(c).await {
+            Ok(p) => self
+                .base
+                .textify_row(p)
+                .data
+                .get("value")
+                .unwrap()
+                .to_string(),
+            Err(_) => return None,
+        };
 
-                if self.options.view_mode == ViewMode::AuthenticatedOnce {
-                    // delete all view logs
-                    let query: &str =
-                        if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
-                            "DELETE FROM \"se_views\" WHERE \"url\" = ?"
-                        } else {
-                            "DELETE FROM \"se_views\" WHERE \"url\" = $1"
-                        };
+        // store in cache
+        self.base
+            .cachedb
+            .set(format!("se_setting:{}", key), value.clone())
+            .await;
 
-                    if let Err(_) = sqlquery(query).bind::<&String>(&url).execute(c).await {
-                        return Err(PasteError::Other);
-                    };
-                }
+        // return
+        Some(value)
+    }
 
-                // return
-                return Ok(());
+    /// Set (or remove, if `value` is `None`) an instance setting by `key`
+    ///
+    /// ## Arguments:
+    /// * `key` - the setting
+    /// * `value` - the new value of the setting
+    pub async fn set_setting(&self, key: String, value: Option<String>) -> Result<()> {
+        match self
+            .transaction(self.set_setting_statements(key.clone(), value))
+            .await
+        {
+            Ok(_) => {
+                // remove from cache
+                self.base
+                    .cachedb
+                    .remove(format!("se_setting:{}", key))
+                    .await;
+                Ok(())
             }
-            Err(_) => return Err(PasteError::Other),
-        };
+            Err(e) => Err(e),
+        }
     }
 
-    /// Edit an existing paste by `url`
+    /// Get the value of an instance setting by `key`, setting it to `value` first if it isn't set
+    ///
+    /// Unlike [`Database::set_setting`], an existing value is never replaced: if two requests
+    /// (or instances) create the setting at once, the unique index on "se_settings" rejects
+    /// the second insert and both use the value stored first.
     ///
     /// ## Arguments:
-    /// * `url` - the paste to edit
-    /// * `password` - the paste's edit password
-    /// * `new_content` - the new content of the paste
-    /// * `new_url` - the new url of the paste
-    /// * `new_password` - the new password of the paste
-    /// * `editing_as` - the userstate of the user we're editing the paste as
-    pub async fn edit_paste_by_url(
-        &self,
-        mut url: String,
-        password: String,
-        new_content: String,
-        mut new_url: String,
-        mut new_password: String,
-        editing_as: Option<FullUser<UserMetadata>>,
-    ) -> Result<()> {
-        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
-
-        if url.ends_with("-") {
-            url.pop();
+    /// * `key` - the setting
+    /// * `value` - the value to store if the setting isn't set
+    pub async fn get_or_create_setting(&self, key: String, value: String) -> Result<String> {
+        if let Some(existing) = self.get_setting(key.clone()).await {
+            return Ok(existing);
         }
 
-        // get paste
-        let existing = match self.get_paste_by_url(url.clone()).await {
-            Ok(p) => p,
-            Err(err) => return Err(err),
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "INSERT INTO \"se_settings\" VALUES (?, ?)"
+        } else {
+            "INSERT INTO \"se_settings\" VALUES ($1, $2)"
         };
 
-        // check password
-        let mut skip_password_check: bool = false;
-
-        if let Some(ua) = editing_as {
-            // check if we're the paste owner
-            if ua.user.username == existing.metadata.owner {
-                skip_password_check = true;
-            }
-            // check if we have the "ManagePastes" permission
-            else if ua.level.permissions.contains(&"ManagePastes".to_string()) {
-                skip_password_check = true;
-            }
+        match self
+            .transaction(vec![(query, vec![key.clone(), value])])
+            .await
+        {
+            // `AlreadyExists` means it was created first somewhere else
+            Ok(_) | Err(PasteError::AlreadyExists) => (),
+            Err(e) => return Err(e),
         }
 
-        if skip_password_check == false {
-            if utility::hash(password) != existing.password {
-                return Err(PasteError::PasswordIncorrect);
-            }
+        // read back whichever value was stored
+        self.base
+            .cachedb
+            .remove(format!("se_setting:{}", key))
+            .await;
+
+        match self.get_setting(key).await {
+            Some(v) => Ok(v),
+            None => Err(PasteError::Other),
         }
+    }
 
-        // hash new password
-        if !new_password.is_empty() {
-            new_password = utility::hash(new_password);
+    /// Get the statements which set (or remove) an instance setting (see [`Database::set_setting`])
+    ///
+    /// The cached value of the setting must be removed after running them.
+    fn set_setting_statements(
+        &self,
+        key: String,
+        value: Option<String>,
+    ) -> Vec<(&'static str, Vec<String>)> {
+        let (delete, insert) = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql")
+        {
+            (
+                "DELETE FROM \"se_settings\" WHERE \"name\" = ?",
+                "INSERT INTO \"se_settings\" VALUES (?, ?)",
+            )
         } else {
-            new_password = existing.password;
+            (
+                "DELETE FROM \"se_settings\" WHERE \"name\" = $1",
+                "INSERT INTO \"se_settings\" VALUES ($1, $2)",
+            )
+        };
+
+        // remove existing value
+        let mut statements = vec![(delete, vec![key.clone()])];
+
+        // insert new value
+        if let Some(value) = value {
+            statements.push((insert, vec![key, value]));
         }
 
-        // update new_url
-        if new_url.is_empty() {
-            new_url = existing.url;
+        statements
+    }
+
+    /// Get the instance announcement, if it's currently scheduled to be shown
+    pub async fn get_announcement(&self) -> Option<Announcement> {
+        let announcement = match self.get_setting(String::from("announcement")).await {
+            Some(a) => match serde_json::from_str::<Announcement>(&a) {
+                Ok(a) => a,
+                Err(_) => return None,
+            },
+            None => return None,
+        };
+
+        let now = utility::unix_epoch_timestamp();
+
+        if (now < announcement.starts) | ((announcement.ends != 0) && (now >= announcement.ends)) {
+            return None;
         }
 
-        new_url = idna::punycode::encode_str(&new_url).unwrap();
+        Some(announcement)
+    }
 
-        if new_url.ends_with("-") {
-            new_url.pop();
+    /// Replace the instance announcement
+    ///
+    /// Permission checks should be done before calling `set_announcement`.
+    ///
+    /// ## Arguments:
+    /// * `props` - [`AnnouncementCreate`]
+    pub async fn set_announcement(&self, props: AnnouncementCreate) -> Result<Announcement> {
+        // check lengths
+        if (props.content.len() > 5_000) | (props.content.trim().len() < 1) {
+            return Err(PasteError::ValueError);
         }
 
-        // edit paste
-        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
-            "UPDATE \"se_pastes\" SET \"content\" = ?, \"password\" = ?, \"url\" = ?, \"date_edited\" = ? WHERE \"url\" = ?"
-        } else {
-            "UPDATE \"se_pastes\" SET (\"content\" = $1, \"password\" = $2, \"url\" = $3, \"date_edited\" = $4) WHERE \"url\" = $5"
+        if (props.ends != 0) && (props.ends <= props.starts) {
+            return Err(PasteError::ValueError);
+        }
+
+        // ...
+        let announcement = Announcement {
+            id: utility::random_id().chars().take(10).collect(),
+            content: props.content,
+            starts: props.starts,
+            ends: props.ends,
+            timestamp: utility::unix_epoch_timestamp(),
         };
 
-        let c = &self.base.db.client;
-        match sqlquery(query)
-            .bind::<&String>(&new_content)
-            .bind::<&String>(&new_password)
-            .bind::<&String>(&new_url)
-            .bind::<&String>(&utility::unix_epoch_timestamp().to_string())
-            .bind::<&String>(&url)
-            .execute(c)
+        match self
+            .set_setting(
+                String::from("announcement"),
+                Some(serde_json::to_string(&announcement).unwrap()),
+            )
             .await
         {
-            Ok(_) => {
-                // remove from cache
-                self.base.cachedb.remove(format!("se_paste:{}", url)).await;
-
-                // return
-                return Ok(());
-            }
-            Err(_) => return Err(PasteError::Other),
-        };
+            Ok(_) => Ok(announcement),
+            Err(e) => Err(e),
+        }
     }
 
-    /// Edit an existing paste's metadata by `url`
+    /// Remove the instance announcement
     ///
-    /// ## Arguments:
-    /// * `url` - the paste to edit
-    /// * `password` - the paste's edit password
-    /// * `metadata` - the new metadata of the paste
-    /// * `editing_as` - the userstate of the user we're editing the paste as
-    pub async fn edit_paste_metadata_by_url(
-        &self,
-        mut url: String,
-        password: String,
-        metadata: PasteMetadata,
-        editing_as: Option<FullUser<UserMetadata>>,
-    ) -> Result<()> {
-        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+    /// Permission checks should be done before calling `delete_announcement`.
+    pub async fn delete_announcement(&self) -> Result<()> {
+        self.set_setting(String::from("announcement"), None).await
+    }
 
-        if url.ends_with("-") {
-            url.pop();
+    // pinned
+
+    /// Get the urls of all pastes pinned to the front page, in order
+    pub async fn get_pinned_urls(&self) -> Vec<String> {
+        match self.get_setting(String::from("pinned")).await {
+            Some(p) => serde_json::from_str::<Vec<String>>(&p).unwrap_or(Vec::new()),
+            None => Vec::new(),
         }
+    }
 
-        // get paste
-        let existing = match self.get_paste_by_url(url.clone()).await {
-            Ok(p) => p,
-            Err(err) => return Err(err),
-        };
+    /// Get all pastes pinned to the front page, in order
+    ///
+    /// Pinned pastes which no longer exist are skipped.
+    pub async fn get_pinned(&self) -> Vec<PinnedPaste> {
+        let mut out: Vec<PinnedPaste> = Vec::new();
 
-        // check password
-        let mut skip_password_check: bool = false;
+        for url in self.get_pinned_urls().await {
+            let paste = match self.get_paste_by_url(url).await {
+                Ok(p) => p,
+                Err(_) => continue,
+            };
 
-        if let Some(ua) = editing_as {
-            // check if we're the paste owner
-            if ua.user.username == existing.metadata.owner {
-                skip_password_check = true;
-            }
-            // check if we have the "ManagePastes" permission
-            else if ua.level.permissions.contains(&"ManagePastes".to_string()) {
-                skip_password_check = true;
-            }
+            let excerpt = if !paste.metadata.view_password.is_empty() {
+                String::new()
+            } else if !paste.metadata.description.is_empty() {
+                paste.metadata.description.clone()
+            } else if !paste.metadata.excerpt.is_empty() {
+                paste.metadata.excerpt.clone()
+            } else {
+                let content = front_matter::strip(&paste.content).trim();
+                let mut excerpt: String = content.chars().take(200).collect();
+
+                if excerpt.len() < content.len() {
+                    excerpt.push_str("…");
+                }
+
+                excerpt
+            };
+
+            out.push(PinnedPaste {
+                title: if paste.metadata.title.is_empty() {
+                    paste.url.clone()
+                } else {
+                    paste.metadata.title.clone()
+                },
+                url: paste.url,
+                excerpt,
+            });
         }
 
-        if skip_password_check == false {
-            if utility::hash(password) != existing.password {
-                return Err(PasteError::PasswordIncorrect);
-            }
+        out
+    }
+
+    /// Replace the list of pastes pinned to the front page
+    ///
+    /// Permission checks should be done before calling `set_pinned`.
+    ///
+    /// ## Arguments:
+    /// * `urls` - the urls of the pastes to pin, in order
+    pub async fn set_pinned(&self, urls: Vec<String>) -> Result<Vec<String>> {
+        if urls.len() > 25 {
+            return Err(PasteError::ValueError);
         }
 
-        // edit paste
-        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
-            "UPDATE \"se_pastes\" SET \"metadata\" = ? WHERE \"url\" = ?"
-        } else {
-            "UPDATE \"se_pastes\" SET (\"metadata\" = $1) WHERE \"url\" = $2"
-        };
+        let mut pinned: Vec<String> = Vec::new();
 
-        let c = &self.base.db.client;
-        match sqlquery(query)
-            .bind::<&String>(match serde_json::to_string(&metadata) {
-                Ok(ref m) => m,
-                Err(_) => return Err(PasteError::ValueError),
-            })
-            .bind::<&String>(&url)
-            .execute(c)
-            .await
-        {
-            Ok(_) => {
-                // remove from cache
-                self.base.cachedb.remove(format!("se_paste:{}", url)).await;
+        for url in urls {
+            // make sure paste exists
+            let paste = match self.get_paste_by_url(url).await {
+                Ok(p) => p,
+                Err(e) => return Err(e),
+            };
 
-                // return
-                return Ok(());
+            if !pinned.contains(&paste.url) {
+                pinned.push(paste.url);
             }
-            Err(_) => return Err(PasteError::Other),
+        }
+
+        let value = if pinned.is_empty() {
+            None
+        } else {
+            Some(serde_json::to_string(&pinned).unwrap())
         };
+
+        match self.set_setting(String::from("pinned"), value).await {
+            Ok(_) => Ok(pinned),
+            Err(e) => Err(e),
+        }
     }
 
     // views
@@ -523,15 +9664,15 @@ impl Database {
                 if self.options.view_mode == ViewMode::AuthenticatedOnce {
                     let query: &str =
                         if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
-                            "SELECT * FROM \"se_views\" WHERE \"url\" = ?"
+                            "SELECT COUNT(*) FROM \"se_views\" WHERE \"url\" = ?"
                         } else {
-                            "SELECT * FROM \"se_views\" WHERE \"url\" = $1"
+                            "SELECT COUNT(*) FROM \"se_views\" WHERE \"url\" = $1"
                         };
 
                     let c = &self.base.db.client;
-                    match sqlquery(query).bind::<&String>(&url).fetch_all(c).await {
-                        Ok(views) => {
-                            let views = views.len();
+                    match sqlquery(query).bind::<&String>(&url).fetch_one(c).await {
+                        Ok(row) => {
+                            let views = row.try_get::<i64, _>(0).unwrap_or(0);
 
                             // store in cache
                             self.base
@@ -546,8 +9687,35 @@ impl Database {
                     };
                 }
 
-                // return 0 by default
-                0
+                // try to get from "se_pastes"
+                let query: &str =
+                    if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+                        "SELECT \"views\" FROM \"se_pastes\" WHERE \"url\" = ?"
+                    } else {
+                        "SELECT \"views\" FROM \"se_pastes\" WHERE \"url\" = $1"
+                    };
+
+                let c = &self.base.db.client;
+                match sqlquery(query).bind::<&String>(&url).fetch_one(c).await {
+                    Ok(row) => {
+                        let views = row
+                            .try_get::<String, _>(0)
+                            .unwrap_or_default()
+                            .parse::<i32>()
+                            .unwrap_or(0);
+
+                        // store in cache
+                        self.base
+                            .cachedb
+                            .set(format!("se_views:{}", url), views.to_string())
+                            .await;
+
+                        // return
+                        views
+                    }
+                    // return 0 by default
+                    Err(_) => 0,
+                }
             }
         }
     }
@@ -581,35 +9749,290 @@ impl Database {
                         return Ok(());
                     }
 
-                    // create view
-                    let query: &str =
-                        if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
-                            "INSERT INTO \"se_views\" VALUES (?, ?)"
-                        } else {
-                            "INSERT INTO \"se_views\" VALEUS ($1, $2)"
-                        };
+                    // create view
+                    let query: &str =
+                        if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+                            "INSERT INTO \"se_views\" VALUES (?, ?)"
+                        } else {
+                            "INSERT INTO \"se_views\" VALUES ($1, $2)"
+                        };
+
+                    let c = &self.base.db.client;
+                    match sqlquery(query)
+                        .bind::<&String>(&url)
+                        .bind::<&String>(&ua.user.username)
+                        .execute(c)
+                        .await
+                    {
+                        Ok(_) => (), // do nothing so cache is incremented
+                        Err(e) => return Err(query_failed(e)),
+                    };
+                }
+                None => return Ok(()), // not technically an error, just not allowed
+            }
+        }
+
+        // add view
+        // views are written to the database later by flush_views, so the count has to be
+        // loaded into the cache first (or it would restart from 0 after a cache flush)
+        self.get_views_by_url(url.clone()).await;
+
+        match self.base.cachedb.incr(format!("se_views:{}", url)).await {
+            // swapped for some reason??
+            false => (),
+            true => return Err(PasteError::Other),
+        }
+
+        if self.options.view_mode == ViewMode::OpenMultiple {
+            self.pending_views.lock().unwrap().insert(url);
+        }
+
+        Ok(())
+    }
+
+    /// Write the view counts of every url viewed since the last flush from the cache to
+    /// "se_pastes" (see [`ViewMode::OpenMultiple`])
+    ///
+    /// Counts are written as they are in the cache (not added to), so flushing the same
+    /// url twice is harmless. Urls which fail to be written are flushed again next time.
+    ///
+    /// ## Returns:
+    /// * the number of pastes written
+    pub async fn flush_views(&self) -> usize {
+        let urls: Vec<String> = self.pending_views.lock().unwrap().drain().collect();
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "UPDATE \"se_pastes\" SET \"views\" = ? WHERE \"url\" = ?"
+        } else {
+            "UPDATE \"se_pastes\" SET \"views\" = $1 WHERE \"url\" = $2"
+        };
+
+        let c = &self.base.db.client;
+        let mut written: usize = 0;
+
+        for url in urls {
+            let views = match self.base.cachedb.get(format!("se_views:{}", url)).await {
+                Some(v) => v,
+                None => continue,
+            };
+
+            match sqlquery(query)
+                .bind::<&String>(&views)
+                .bind::<&String>(&url)
+                .execute(c)
+                .await
+            {
+                Ok(_) => written += 1,
+                Err(_) => {
+                    self.pending_views.lock().unwrap().insert(url);
+                }
+            }
+        }
+
+        written
+    }
+
+    /// Start a background task which calls [`Database::flush_views`] every minute
+    pub fn spawn_view_flusher(&self) {
+        if self.options.view_mode != ViewMode::OpenMultiple {
+            return;
+        }
+
+        let database = self.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+
+            loop {
+                interval.tick().await;
+                database.flush_views().await;
+            }
+        });
+    }
+
+    /// Count a view of an existing url (and of the viewer's country, if GeoIP is enabled)
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste to count the view for
+    /// * `as_user` - the userstate of the user viewing this (for [`ViewMode::AuthenticatedOnce`])
+    /// * `ip` - the IP address of the viewer (only used to look up the country)
+    pub async fn count_view_by_url(
+        &self,
+        url: String,
+        as_user: Option<FullUser<UserMetadata>>,
+        ip: &str,
+    ) -> Result<()> {
+        if let Err(e) = self.incr_views_by_url(url.clone(), as_user).await {
+            return Err(e);
+        }
+
+        #[cfg(feature = "archive")]
+        self.touch_paste_by_url(&url).await;
+
+        #[cfg(feature = "geoip")]
+        if let Ok(ip) = ip.parse::<std::net::IpAddr>() {
+            return self.incr_country_views_by_url(url, ip).await;
+        }
+
+        #[cfg(not(feature = "geoip"))]
+        let _ = ip;
+
+        Ok(())
+    }
+
+    /// Get the view counts of an existing url by the country they're from
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste url
+    #[cfg(feature = "geoip")]
+    async fn get_country_views_by_url(&self, url: String) -> HashMap<String, i32> {
+        let mut countries: HashMap<String, i32> = HashMap::new();
+
+        if self.options.geoip_database.is_empty() {
+            return countries;
+        }
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT \"country\", \"count\" FROM \"se_view_countries\" WHERE \"url\" = ?"
+        } else {
+            "SELECT \"country\", \"count\" FROM \"se_view_countries\" WHERE \"url\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        let rows = match sqlquery(query).bind::<&String>(&url).fetch_all(c).await {
+            Ok(r) => r,
+            Err(e) => {
+                query_failed(e);
+                return countries;
+            }
+        };
+
+        for row in rows {
+            let res = self.base.textify_row(row).data;
+
+            countries.insert(
+                res.get("country").unwrap().to_string(),
+                res.get("count").unwrap().parse::<i32>().unwrap_or(0),
+            );
+        }
+
+        countries
+    }
+
+    /// Views are never counted by country without the `geoip` feature
+    #[cfg(not(feature = "geoip"))]
+    async fn get_country_views_by_url(&self, _url: String) -> HashMap<String, i32> {
+        HashMap::new()
+    }
+
+    /// Get an existing url's view statistics
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste to get the statistics of
+    pub async fn get_stats_by_url(&self, mut url: String) -> PasteStats {
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
+
+        if url.ends_with("-") {
+            url.pop();
+        }
+
+        let countries = self.get_country_views_by_url(url.clone()).await;
+
+        PasteStats {
+            views: self.get_views_by_url(url).await,
+            countries,
+        }
+    }
+
+    /// Get the ISO country code of `ip` (`ZZ` for unknown)
+    #[cfg(feature = "geoip")]
+    fn lookup_country(reader: &maxminddb::Reader<Vec<u8>>, ip: std::net::IpAddr) -> String {
+        match reader.lookup::<maxminddb::geoip2::Country>(ip) {
+            Ok(c) => match c.country.and_then(|c| c.iso_code) {
+                Some(code) => code.to_string(),
+                None => String::from("ZZ"),
+            },
+            Err(_) => String::from("ZZ"),
+        }
+    }
+
+    /// Get the [`GeoRule`] that applies to `ip`
+    ///
+    /// ## Arguments:
+    /// * `ip` - the IP address of the client
+    #[cfg(feature = "geoip")]
+    pub fn get_geo_rule(&self, ip: &str) -> Option<GeoRule> {
+        if self.options.geo_rules.is_empty() | self.options.geo_allowlist.contains(&ip.to_string())
+        {
+            return None;
+        }
+
+        let reader = match self.geoip {
+            Some(ref r) => r,
+            None => return None,
+        };
+
+        let ip = match ip.parse::<std::net::IpAddr>() {
+            Ok(i) => i,
+            Err(_) => return None,
+        };
+
+        self.options
+            .geo_rules
+            .get(&Self::lookup_country(reader, ip))
+            .cloned()
+    }
+
+    /// Count a view of an existing url from the country of `ip`
+    ///
+    /// The IP address is only used to look up the country, it is never stored.
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste to count the view for
+    /// * `ip` - the IP address of the viewer
+    #[cfg(feature = "geoip")]
+    pub async fn incr_country_views_by_url(
+        &self,
+        mut url: String,
+        ip: std::net::IpAddr,
+    ) -> Result<()> {
+        let reader = match self.geoip {
+            Some(ref r) => r,
+            None => return Ok(()),
+        };
+
+        url = idna::punycode::encode_str(&url).unwrap().to_lowercase();
 
-                    let c = &self.base.db.client;
-                    match sqlquery(query)
-                        .bind::<&String>(&url)
-                        .bind::<&String>(&ua.user.username)
-                        .execute(c)
-                        .await
-                    {
-                        Ok(_) => (), // do nothing so cache is incremented
-                        Err(_) => return Err(PasteError::Other),
-                    };
-                }
-                None => return Ok(()), // not technically an error, just not allowed
-            }
+        if url.ends_with("-") {
+            url.pop();
         }
 
-        // add view
-        // views never reach the database, they're only stored in memory
-        match self.base.cachedb.incr(format!("se_views:{}", url)).await {
-            // swapped for some reason??
-            false => Ok(()),
-            true => Err(PasteError::Other),
+        // resolve country
+        let country = Self::lookup_country(reader, ip);
+
+        // add view (a single upsert, so concurrent views of the same url are all counted)
+        let query: &str = if self.base.db._type == "sqlite" {
+            "INSERT INTO \"se_view_countries\" VALUES (?, ?, '1')
+             ON CONFLICT (\"url\", \"country\")
+             DO UPDATE SET \"count\" = CAST(\"se_view_countries\".\"count\" AS INTEGER) + 1"
+        } else if self.base.db._type == "mysql" {
+            "INSERT INTO \"se_view_countries\" VALUES (?, ?, '1')
+             ON DUPLICATE KEY UPDATE \"count\" = \"count\" + 1"
+        } else {
+            "INSERT INTO \"se_view_countries\" VALUES ($1, $2, '1')
+             ON CONFLICT (\"url\", \"country\")
+             DO UPDATE SET \"count\" = CAST(CAST(\"se_view_countries\".\"count\" AS BIGINT) + 1 AS TEXT)"
+        };
+
+        let c = &self.base.db.client;
+        match sqlquery(query)
+            .bind::<&String>(&url)
+            .bind::<&String>(&country)
+            .execute(c)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(query_failed(e)),
         }
     }
 
@@ -644,6 +10067,108 @@ impl Database {
 
     // documents
 
+    /// Get the [`DocumentLimits`] of a namespace
+    ///
+    /// ## Arguments:
+    /// * `namespace` - the namespace to get the limits of
+    pub fn get_namespace_limits(&self, namespace: &str) -> DocumentLimits {
+        match self.options.namespace_limits.get(namespace) {
+            Some(l) => l.clone(),
+            None => self.options.document_limits.clone(),
+        }
+    }
+
+    /// Validate a document's content against the [`DocumentSchema`] of its namespace
+    ///
+    /// ## Arguments:
+    /// * `namespace` - the namespace the document belongs to
+    /// * `content` - the content of the document
+    pub fn validate_document_content(&self, namespace: &str, content: &str) -> Result<()> {
+        let schema = match self.options.document_schemas.get(namespace) {
+            Some(s) => match s.content {
+                Some(ref c) => c,
+                None => return Ok(()),
+            },
+            None => return Ok(()),
+        };
+
+        let content: Value = match serde_json::from_str(content) {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(PasteError::Validation(vec![FieldError {
+                    field: String::from("content"),
+                    error: e.to_string(),
+                    min: None,
+                    max: None,
+                }]))
+            }
+        };
+
+        validate_schema(schema, &content, "content")
+    }
+
+    /// Validate a document's metadata against the [`DocumentSchema`] of its namespace
+    ///
+    /// ## Arguments:
+    /// * `namespace` - the namespace the document belongs to
+    /// * `metadata` - the metadata of the document
+    pub fn validate_document_metadata<M: Serialize>(
+        &self,
+        namespace: &str,
+        metadata: &M,
+    ) -> Result<()> {
+        let schema = match self.options.document_schemas.get(namespace) {
+            Some(s) => match s.metadata {
+                Some(ref m) => m,
+                None => return Ok(()),
+            },
+            None => return Ok(()),
+        };
+
+        let metadata = match serde_json::to_value(metadata) {
+            Ok(m) => m,
+            Err(_) => return Err(PasteError::ValueError),
+        };
+
+        validate_schema(schema, &metadata, "metadata")
+    }
+
+    /// Get the current usage of a namespace
+    ///
+    /// ## Arguments:
+    /// * `namespace` - the namespace to get the usage of
+    pub async fn get_namespace_usage(&self, namespace: String) -> Result<NamespaceUsage> {
+        if self.options.document_store == false {
+            return Err(PasteError::Other);
+        }
+
+        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
+            "SELECT COUNT(*) FROM \"se_documents\" WHERE \"namespace\" = ?"
+        } else {
+            "SELECT COUNT(*) FROM \"se_documents\" WHERE \"namespace\" = $1"
+        };
+
+        let c = &self.base.db.client;
+        let documents = match sqlquery(query)
+            .bind::<&String>(&namespace)
+            .fetch_one(c)
+            .await
+        {
+            Ok(row) => row.try_get::<i64, _>(0).unwrap_or(0) as usize,
+            Err(e) => return Err(query_failed(e)),
+        };
+
+        // return
+        let limits = self.get_namespace_limits(&namespace);
+
+        Ok(NamespaceUsage {
+            namespace,
+            documents,
+            max_documents: limits.max_documents,
+            max_content_bytes: limits.max_content_bytes,
+        })
+    }
+
     /// Pull an existing document by `id`
     ///
     /// ## Arguments:
@@ -662,7 +10187,12 @@ impl Database {
         }
 
         // check in cache
-        match self.base.cachedb.get(format!("se_document:{}", id)).await {
+        match self
+            .base
+            .cachedb
+            .get(format!("se_document:{}:{}", namespace, id))
+            .await
+        {
             Some(c) => return Ok(serde_json::from_str::<Document<T, M>>(c.as_str()).unwrap()),
             None => (),
         };
@@ -690,7 +10220,7 @@ impl Database {
             id: res.get("id").unwrap().to_string(),
             namespace: res.get("namespace").unwrap().to_string(),
             content: res.get("content").unwrap().to_string().into(),
-            timestamp: res.get("date_published").unwrap().parse::<u128>().unwrap(),
+            timestamp: res.get("timestamp").unwrap().parse::<u128>().unwrap(),
             metadata: match serde_json::from_str(res.get("metadata").unwrap()) {
                 Ok(m) => m,
                 Err(_) => return Err(PasteError::ValueError),
@@ -727,6 +10257,23 @@ impl Database {
             return Err(PasteError::Other);
         }
 
+        // check limits
+        let limits = self.get_namespace_limits(&props.namespace);
+        let content = props.content.to_string();
+
+        if (limits.max_content_bytes != 0) && (content.len() > limits.max_content_bytes) {
+            return Err(PasteError::ValueError);
+        }
+
+        // validate
+        if let Err(e) = self.validate_document_content(&props.namespace, &content) {
+            return Err(e);
+        }
+
+        if let Err(e) = self.validate_document_metadata(&props.namespace, &props.metadata) {
+            return Err(e);
+        }
+
         // ...
         let doc = Document {
             id: utility::random_id(),
@@ -736,28 +10283,104 @@ impl Database {
             metadata: props.metadata,
         };
 
-        // create paste
-        let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
-            "INSERT INTO \"se_documents\" VALUES (?, ?, ?, ?, ?)"
+        // create document
+        //
+        // the insert only inserts a row while the namespace is below its limit, and the
+        // namespace is locked while it's counted so concurrent pushes can't both take its
+        // last free slot (sqlite only runs one write at a time, so it needs no lock)
+        let query: &str = if self.base.db._type == "sqlite" {
+            "INSERT INTO \"se_documents\" SELECT ?, ?, ?, ?, ?
+             WHERE (? = 0) OR ((SELECT COUNT(*) FROM \"se_documents\" WHERE \"namespace\" = ?) < ?)"
+        } else if self.base.db._type == "mysql" {
+            "INSERT INTO \"se_documents\" SELECT ?, ?, ?, ?, ? FROM DUAL
+             WHERE (? = 0) OR ((SELECT COUNT(*) FROM \"se_documents\" WHERE \"namespace\" = ?) < ?)"
         } else {
-            "INSERT INTO \"se_documents\" VALEUS ($1, $2, $3, $4, $5)"
+            "INSERT INTO \"se_documents\" SELECT $1, $2, $3, $4, $5
+             WHERE ($6 = 0) OR ((SELECT COUNT(*) FROM \"se_documents\" WHERE \"namespace\" = $7) < $8)"
         };
 
-        let c = &self.base.db.client;
-        match sqlquery(query)
+        let max_documents = limits.max_documents as i64;
+        let timestamp = doc.timestamp.to_string();
+        let metadata = match serde_json::to_string(&doc.metadata) {
+            Ok(m) => m,
+            Err(_) => return Err(PasteError::ValueError),
+        };
+
+        let insert = sqlquery(query)
             .bind::<&String>(&doc.id)
             .bind::<&String>(&doc.namespace)
-            .bind::<&String>(&doc.content.to_string())
-            .bind::<&String>(&doc.timestamp.to_string())
-            .bind::<&String>(match serde_json::to_string(&doc.metadata) {
-                Ok(ref s) => s,
-                Err(_) => return Err(PasteError::ValueError),
-            })
-            .execute(c)
-            .await
-        {
+            .bind::<&String>(&content)
+            .bind::<&String>(&timestamp)
+            .bind::<&String>(&metadata)
+            .bind::<i64>(max_documents)
+            .bind::<&String>(&doc.namespace)
+            .bind::<i64>(max_documents);
+
+        let c = &self.base.db.client;
+        let res = if self.base.db._type == "postgres" {
+            let mut transaction = match c.begin().await {
+                Ok(t) => t,
+                Err(e) => return Err(query_failed(e)),
+            };
+
+            // released when the transaction ends
+            if let Err(e) = sqlquery("SELECT pg_advisory_xact_lock(hashtext($1))")
+                .bind::<&String>(&format!("se_documents:{}", doc.namespace))
+                .execute(&mut *transaction)
+                .await
+            {
+                let _ = transaction.rollback().await;
+                return Err(query_failed(e));
+            }
+
+            let res = insert.execute(&mut *transaction).await;
+
+            if res.is_ok() {
+                if let Err(e) = transaction.commit().await {
+                    return Err(query_failed(e));
+                }
+            } else {
+                let _ = transaction.rollback().await;
+            }
+
+            res
+        } else if self.base.db._type == "mysql" {
+            // named locks belong to the connection (not a transaction), so they're released by hand
+            let mut conn = match c.acquire().await {
+                Ok(c) => c,
+                Err(e) => return Err(query_failed(e)),
+            };
+
+            // hashed, since lock names can't be longer than 64 characters
+            let lock = utility::hash(format!("se_documents:{}", doc.namespace));
+
+            match sqlquery("SELECT GET_LOCK(?, 10)")
+                .bind::<&String>(&lock)
+                .fetch_one(&mut *conn)
+                .await
+            {
+                Ok(row) if row.try_get::<i64, _>(0).unwrap_or(0) == 1 => (),
+                Ok(_) => return Err(PasteError::Other),
+                Err(e) => return Err(query_failed(e)),
+            };
+
+            let res = insert.execute(&mut *conn).await;
+
+            let _ = sqlquery("SELECT RELEASE_LOCK(?)")
+                .bind::<&String>(&lock)
+                .execute(&mut *conn)
+                .await;
+
+            res
+        } else {
+            insert.execute(c).await
+        };
+
+        match res {
+            // nothing was inserted, so the namespace is full
+            Ok(r) if r.rows_affected() == 0 => return Err(PasteError::QuotaExceeded),
             Ok(_) => return Ok(doc),
-            Err(_) => return Err(PasteError::Other),
+            Err(e) => return Err(query_failed(e)),
         };
     }
 
@@ -809,7 +10432,7 @@ impl Database {
                 // return
                 return Ok(());
             }
-            Err(_) => return Err(PasteError::Other),
+            Err(e) => return Err(query_failed(e)),
         };
     }
 
@@ -834,6 +10457,18 @@ impl Database {
             return Err(PasteError::Other);
         }
 
+        // check limits
+        let limits = self.get_namespace_limits(&namespace);
+
+        if (limits.max_content_bytes != 0) && (new_content.len() > limits.max_content_bytes) {
+            return Err(PasteError::ValueError);
+        }
+
+        // validate
+        if let Err(e) = self.validate_document_content(&namespace, &new_content) {
+            return Err(e);
+        }
+
         // make sure document exists
         if let Err(e) = self.pull::<T, M>(id.clone(), namespace.clone()).await {
             return Err(e);
@@ -841,9 +10476,9 @@ impl Database {
 
         // edit document
         let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
-            "UPDATE \"se_pastes\" SET \"content\" = ? WHERE \"url\" = ? AND \"namespace\" = ?"
+            "UPDATE \"se_documents\" SET \"content\" = ? WHERE \"id\" = ? AND \"namespace\" = ?"
         } else {
-            "UPDATE \"se_pastes\" SET \"content\" = $1 WHERE \"url\" = $2 AND \"namespace\" = $3"
+            "UPDATE \"se_documents\" SET \"content\" = $1 WHERE \"id\" = $2 AND \"namespace\" = $3"
         };
 
         let c = &self.base.db.client;
@@ -864,13 +10499,13 @@ impl Database {
                 // return
                 return Ok(());
             }
-            Err(_) => return Err(PasteError::Other),
+            Err(e) => return Err(query_failed(e)),
         };
     }
 
-    /// Edit an existing paste's metadata by `url`
+    /// Edit an existing document's metadata by `id`
     ///
-    /// Permission checks should be done before calling `update`.
+    /// Permission checks should be done before calling `update_metadata`.
     ///
     /// ## Arguments:
     /// * `id` - the document to edit
@@ -883,12 +10518,17 @@ impl Database {
         &self,
         id: String,
         namespace: String,
-        metadata: PasteMetadata,
+        metadata: M,
     ) -> Result<()> {
         if self.options.document_store == false {
             return Err(PasteError::Other);
         }
 
+        // validate
+        if let Err(e) = self.validate_document_metadata(&namespace, &metadata) {
+            return Err(e);
+        }
+
         // make sure document exists
         if let Err(e) = self.pull::<T, M>(id.clone(), namespace.clone()).await {
             return Err(e);
@@ -896,9 +10536,9 @@ impl Database {
 
         // edit document
         let query: &str = if (self.base.db._type == "sqlite") | (self.base.db._type == "mysql") {
-            "UPDATE \"se_documents\" SET \"metadata\" = ? WHERE \"url\" = ? AND \"namespace\" = ?"
+            "UPDATE \"se_documents\" SET \"metadata\" = ? WHERE \"id\" = ? AND \"namespace\" = ?"
         } else {
-            "UPDATE \"se_documents\" SET \"metadata\" = $1 WHERE \"url\" = $2 AND \"namespace\" = $3"
+            "UPDATE \"se_documents\" SET \"metadata\" = $1 WHERE \"id\" = $2 AND \"namespace\" = $3"
         };
 
         let c = &self.base.db.client;
@@ -922,7 +10562,380 @@ impl Database {
                 // return
                 return Ok(());
             }
-            Err(_) => return Err(PasteError::Other),
+            Err(e) => return Err(query_failed(e)),
+        };
+    }
+}
+
+/// Check a single link, falling back to `GET` for servers which don't allow `HEAD`
+///
+/// Links are requested through [`pinned_client`], so links to hosts which aren't public are
+/// never requested, and redirects are followed by hand so every hop is checked the same way.
+#[cfg(feature = "links")]
+async fn check_link(link: String, timeout: std::time::Duration) -> LinkStatus {
+    const MAX_REDIRECTS: usize = 5;
+
+    let mut target = match reqwest::Url::parse(&link) {
+        Ok(u) => u,
+        Err(_) => return link_error(link, "Link could not be reached."),
+    };
+
+    let mut redirects: usize = 0;
+
+    let res = loop {
+        if (target.scheme() != "http") && (target.scheme() != "https") {
+            return link_error(link, "Link could not be reached.");
+        }
+
+        let http = match pinned_client(&target, timeout).await {
+            Ok(c) => c,
+            Err(_) => return link_error(link, "Link could not be reached."),
+        };
+
+        let mut res = http.head(target.clone()).send().await;
+
+        if let Ok(ref r) = res {
+            if (r.status() == reqwest::StatusCode::METHOD_NOT_ALLOWED)
+                | (r.status() == reqwest::StatusCode::NOT_IMPLEMENTED)
+            {
+                res = http.get(target.clone()).send().await;
+            }
+        }
+
+        let res = match res {
+            Ok(r) => r,
+            Err(e) if e.is_timeout() => return link_error(link, "Link timed out."),
+            Err(_) => return link_error(link, "Link could not be reached."),
+        };
+
+        if !res.status().is_redirection() {
+            break res;
+        }
+
+        redirects += 1;
+
+        if redirects > MAX_REDIRECTS {
+            return link_error(link, "Link redirects too many times.");
+        }
+
+        // redirects without a location are reported as they are
+        target = match res
+            .headers()
+            .get("Location")
+            .and_then(|l| l.to_str().ok())
+            .and_then(|l| target.join(l).ok())
+        {
+            Some(u) => u,
+            None => break res,
+        };
+    };
+
+    LinkStatus {
+        status: res.status().as_u16(),
+        ok: !res.status().is_client_error() && !res.status().is_server_error(),
+        error: String::new(),
+        link,
+    }
+}
+
+/// Get the status of a link which couldn't be checked
+#[cfg(feature = "links")]
+fn link_error(link: String, error: &str) -> LinkStatus {
+    LinkStatus {
+        status: 0,
+        ok: false,
+        error: error.to_string(),
+        link,
+    }
+}
+
+/// Prefix a cache value with its checksum (see [`verify_checksum`])
+fn with_checksum(value: String) -> String {
+    format!("{}:{}", utility::hash(value.clone()), value)
+}
+
+/// Get a cache value stored with [`with_checksum`], if it wasn't changed or cut short
+fn verify_checksum(stored: &str) -> Option<&str> {
+    let (checksum, value) = stored.split_once(':')?;
+
+    if utility::hash(value.to_string()) != checksum {
+        return None;
+    }
+
+    Some(value)
+}
+
+/// Check if `user` has the "ManagePastes" permission
+fn is_manager(user: &Option<FullUser<UserMetadata>>) -> bool {
+    match user {
+        Some(ua) => ua.level.permissions.contains(&"ManagePastes".to_string()),
+        None => false,
+    }
+}
+
+/// Check that a moderation `filter` has at least one filter set (so it can't match every
+/// paste) and compile its content pattern
+fn check_moderation_filter(filter: &ModerationFilter) -> Result<Option<regex::Regex>> {
+    if filter.owner.is_empty()
+        && filter.ip.is_empty()
+        && filter.tag.is_empty()
+        && filter.content.is_empty()
+        && (filter.created_after == 0)
+        && (filter.created_before == 0)
+    {
+        return Err(PasteError::ValueError);
+    }
+
+    if filter.content.is_empty() {
+        return Ok(None);
+    }
+
+    match regex::RegexBuilder::new(&filter.content)
+        .size_limit(1_000_000)
+        .build()
+    {
+        Ok(r) => Ok(Some(r)),
+        Err(_) => Err(PasteError::ValueError),
+    }
+}
+
+/// Record why a query failed (clients only see it as [`PasteError::Other`])
+fn query_failed(error: sqlx::Error) -> PasteError {
+    tracing::error!(error = %error, "query failed");
+    PasteError::Other
+}
+
+/// Build a client which can only connect to the public address `url`'s host resolves to
+///
+/// The host is resolved once here and the client is pinned to that address, so a DNS
+/// answer changing between the check and the request (DNS rebinding) can't send the
+/// request anywhere else. Redirects aren't followed (see [`Database::fetch_text`]).
+///
+/// ## Arguments:
+/// * `url` - the url the client will request
+/// * `timeout` - how long to wait for a response before giving up
+#[cfg(any(feature = "fetch", feature = "links"))]
+async fn pinned_client(
+    url: &reqwest::Url,
+    timeout: std::time::Duration,
+) -> Result<reqwest::Client> {
+    let host = match url.host_str() {
+        Some(h) => h.trim_start_matches('[').trim_end_matches(']'),
+        None => return Err(PasteError::ValueError),
+    };
+
+    let port = url.port_or_known_default().unwrap_or(80);
+
+    let (addrs, domain): (Vec<std::net::SocketAddr>, Option<&str>) =
+        match host.parse::<std::net::IpAddr>() {
+            Ok(ip) => (vec![std::net::SocketAddr::new(ip, port)], None),
+            Err(_) => match tokio::net::lookup_host((host, port)).await {
+                Ok(a) => (a.collect(), Some(host)),
+                Err(_) => return Err(PasteError::ValueError),
+            },
         };
+
+    // every address has to be public, not just the one used
+    if addrs.is_empty() | !addrs.iter().all(|a| is_global(&a.ip())) {
+        return Err(PasteError::NotAllowed);
+    }
+
+    let mut builder = reqwest::Client::builder()
+        .timeout(timeout)
+        .redirect(reqwest::redirect::Policy::none());
+
+    if let Some(domain) = domain {
+        builder = builder.resolve(domain, addrs[0]);
+    }
+
+    match builder.build() {
+        Ok(c) => Ok(c),
+        Err(_) => Err(PasteError::Other),
+    }
+}
+
+/// If `ip` is a public address, so requests to it can't reach the server's own network
+///
+/// Loopback, private (RFC 1918), shared (RFC 6598), link-local (including cloud metadata
+/// endpoints like `169.254.169.254`), unspecified, documentation, multicast and reserved
+/// addresses aren't, and neither are IPv6 unique local and link-local addresses or IPv6
+/// addresses embedding an IPv4 address which isn't.
+#[cfg(any(feature = "fetch", feature = "links"))]
+fn is_global(ip: &std::net::IpAddr) -> bool {
+    match ip {
+        std::net::IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+
+            !(ip.is_unspecified()
+                | ip.is_loopback()
+                | ip.is_private()
+                | ip.is_link_local()
+                | ip.is_broadcast()
+                | ip.is_documentation()
+                | ip.is_multicast()
+                | (a == 0)
+                | ((a == 100) && ((b & 0xc0) == 64))
+                | ((a == 192) && (b == 0) && (c == 0))
+                | ((a == 198) && ((b & 0xfe) == 18))
+                | (a >= 240))
+        }
+        std::net::IpAddr::V6(ip) => {
+            if let Some(v4) = ip.to_ipv4_mapped() {
+                return is_global(&std::net::IpAddr::V4(v4));
+            }
+
+            let segments = ip.segments();
+
+            !(ip.is_unspecified()
+                | ip.is_loopback()
+                | ip.is_multicast()
+                // IPv4-compatible (deprecated, but still routed by some stacks)
+                | segments[..6].iter().all(|s| *s == 0)
+                // unique local (fc00::/7)
+                | ((segments[0] & 0xfe00) == 0xfc00)
+                // link-local (fe80::/10)
+                | ((segments[0] & 0xffc0) == 0xfe80)
+                // documentation (2001:db8::/32)
+                | ((segments[0] == 0x2001) && (segments[1] == 0x0db8))
+                // NAT64 (64:ff9b::/96), which can be translated to any IPv4 address
+                | ((segments[0] == 0x64) && (segments[1] == 0xff9b)))
+        }
+    }
+}
+
+/// Check if the owner of a paste can be changed from `existing` to `new` (see
+/// [`Database::edit_paste_metadata_by_url`])
+///
+/// Only the owner (or a manager) can give a paste away or remove its owner, and pastes
+/// can only be given to the user making the change (other users get pastes through
+/// [`Database::transfer_paste_by_url`]).
+///
+/// ## Arguments:
+/// * `existing` - the paste's current owner (empty if it has none)
+/// * `new` - the paste's new owner (empty to remove it)
+/// * `editing_as` - the username of the user making the change (empty if anonymous)
+/// * `is_manager` - if that user has the "ManagePastes" permission
+/// * `paste_ownership` - [`ServerOptions::paste_ownership`]
+fn check_owner_change(
+    existing: &str,
+    new: &str,
+    editing_as: &str,
+    is_manager: bool,
+    paste_ownership: bool,
+) -> Result<()> {
+    if new == existing {
+        return Ok(());
+    }
+
+    // only the owner (or a manager) can give a paste away
+    let is_owner = !editing_as.is_empty() && (editing_as == existing);
+
+    if !existing.is_empty() && !is_owner && !is_manager {
+        return Err(PasteError::ChangeOwner);
+    }
+
+    // pastes can only be claimed by the user we're editing as
+    if !new.is_empty() && ((paste_ownership == false) | editing_as.is_empty() | (editing_as != new))
+    {
+        return Err(PasteError::ChangeOwner);
+    }
+
+    Ok(())
+}
+
+/// If a query failed because it broke a unique index (e.g. two pastes created with the same url at once)
+fn is_unique_violation(error: &sqlx::Error) -> bool {
+    match error.as_database_error() {
+        Some(e) => e.is_unique_violation(),
+        None => false,
+    }
+}
+
+/// Check if `paste` has expired (pastes under a legal hold never expire)
+fn is_expired(paste: &Paste) -> bool {
+    (paste.metadata.expires_at != 0)
+        && (paste.metadata.legal_hold == false)
+        && (utility::unix_epoch_timestamp() >= paste.metadata.expires_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_owner_change;
+    use crate::model::PasteError;
+
+    #[test]
+    fn owner_unchanged() {
+        assert!(check_owner_change("alice", "alice", "", false, true).is_ok());
+        assert!(check_owner_change("", "", "", false, false).is_ok());
+    }
+
+    #[test]
+    fn owner_set_to_another_user() {
+        // claiming an unowned paste for someone else
+        assert!(matches!(
+            check_owner_change("", "bob", "alice", false, true),
+            Err(PasteError::ChangeOwner)
+        ));
+
+        // giving an owned paste to someone else (that's what transfers are for)
+        assert!(matches!(
+            check_owner_change("alice", "bob", "alice", false, true),
+            Err(PasteError::ChangeOwner)
+        ));
+
+        // taking someone else's paste
+        assert!(matches!(
+            check_owner_change("alice", "bob", "bob", false, true),
+            Err(PasteError::ChangeOwner)
+        ));
+
+        // managers can't give pastes to other users either
+        assert!(matches!(
+            check_owner_change("alice", "bob", "carol", true, true),
+            Err(PasteError::ChangeOwner)
+        ));
+    }
+
+    #[test]
+    fn owner_set_anonymously() {
+        assert!(matches!(
+            check_owner_change("", "alice", "", false, true),
+            Err(PasteError::ChangeOwner)
+        ));
+
+        assert!(matches!(
+            check_owner_change("alice", "bob", "", false, true),
+            Err(PasteError::ChangeOwner)
+        ));
+    }
+
+    #[test]
+    fn owner_cleared() {
+        // by someone who isn't the owner
+        assert!(matches!(
+            check_owner_change("alice", "", "bob", false, true),
+            Err(PasteError::ChangeOwner)
+        ));
+
+        // anonymously (with the paste's password)
+        assert!(matches!(
+            check_owner_change("alice", "", "", false, true),
+            Err(PasteError::ChangeOwner)
+        ));
+
+        // by the owner or a manager
+        assert!(check_owner_change("alice", "", "alice", false, true).is_ok());
+        assert!(check_owner_change("alice", "", "carol", true, true).is_ok());
+    }
+
+    #[test]
+    fn owner_claimed() {
+        assert!(check_owner_change("", "alice", "alice", false, true).is_ok());
+
+        // only with paste ownership enabled
+        assert!(matches!(
+            check_owner_change("", "alice", "alice", false, false),
+            Err(PasteError::ChangeOwner)
+        ));
     }
 }
@@ -0,0 +1,267 @@
+//! Guessing what kind of content a paste is (see `/api/detect`)
+//!
+//! Everything here is a heuristic meant for suggesting metadata while a paste is being
+//! written, so it only looks at the start of long content and prefers being fast over
+//! being exact.
+use crate::model::{Detection, DetectionSuggestions};
+use crate::table;
+use regex::Regex;
+
+/// Bytes of content inspected
+const SCAN: usize = 100_000;
+/// Longest suggested title
+const MAX_TITLE: usize = 80;
+
+/// Patterns of common secrets (private keys, cloud and API tokens)
+const SECRET_PATTERNS: [&str; 7] = [
+    r"-----BEGIN (?:RSA |EC |DSA |OPENSSH |PGP )?PRIVATE KEY( BLOCK)?-----",
+    r"\bAKIA[0-9A-Z]{16}\b",
+    r"\bgh[pousr]_[A-Za-z0-9]{36,}\b",
+    r"\bxox[baprs]-[A-Za-z0-9-]{10,}",
+    r"\b[sr]k_live_[A-Za-z0-9]{16,}",
+    r"\beyJ[A-Za-z0-9_-]{10,}\.eyJ[A-Za-z0-9_-]{10,}\.[A-Za-z0-9_-]+",
+    r#"(?i)\b(?:password|passwd|secret|api[_-]?key|access[_-]?token)\s*[:=]\s*["']?[^\s"']{8,}"#,
+];
+
+/// Lines which start stack traces or are frames of one
+const STACK_TRACE_PATTERNS: [&str; 6] = [
+    r"^Traceback \(most recent call last\):",
+    r"^\s+at [\w$.<>]+\(.*\)$",
+    r"^Exception in thread ",
+    r"panicked at ",
+    r"^goroutine \d+ \[",
+    r"^\s+at .+ in .+:line \d+$",
+];
+
+/// Lines which look like log entries (a timestamp or a level at the start)
+const LOG_PATTERN: &str = r"^(?:\[?\d{4}-\d{2}-\d{2}[ T]\d{2}:\d{2}|\[?(?:TRACE|DEBUG|INFO|WARN|WARNING|ERROR|FATAL)\]?[\s:])";
+
+/// Languages and the patterns scoring content as them (checked in order, first best wins)
+const LANGUAGES: [(&str, &[&str]); 12] = [
+    (
+        "rust",
+        &[
+            r"\bfn \w+\(",
+            r"\blet mut\b",
+            r"\bimpl\b",
+            r"\w::\w",
+            r"\bpub (fn|struct|enum)\b",
+        ],
+    ),
+    (
+        "python",
+        &[
+            r"^\s*def \w+\(.*\):",
+            r"^\s*(from \w+ )?import \w+",
+            r"\bself\.",
+            r"^\s*elif\b",
+        ],
+    ),
+    (
+        "go",
+        &[r"^package \w+", r"\bfunc \w*\(", r":=", r"\bfmt\.\w+\("],
+    ),
+    (
+        "javascript",
+        &[
+            r"\bfunction\b",
+            r"\b(const|let) \w+ =",
+            r"=>",
+            r"\bconsole\.log\(",
+        ],
+    ),
+    (
+        "typescript",
+        &[
+            r"\binterface \w+ \{",
+            r"\w: (string|number|boolean)\b",
+            r"\bexport (type|interface)\b",
+        ],
+    ),
+    (
+        "java",
+        &[
+            r"\bpublic (static )?(class|void)\b",
+            r"\bSystem\.out\.",
+            r"\bprivate \w+ \w+;",
+        ],
+    ),
+    (
+        "c",
+        &[
+            r"^#include\s*<",
+            r"\bint main\(",
+            r"\bprintf\(",
+            r"\bmalloc\(",
+        ],
+    ),
+    (
+        "html",
+        &[
+            r"(?i)<!doctype html",
+            r"(?i)<(html|head|body|div|span)[\s>]",
+            r"</\w+>",
+        ],
+    ),
+    ("css", &[r"^[.#]?[\w-]+\s*\{", r"^\s*[\w-]+:\s*[^;]+;$"]),
+    (
+        "sql",
+        &[
+            r"(?i)\bselect\b.+\bfrom\b",
+            r"(?i)\binsert into\b",
+            r"(?i)\bcreate table\b",
+        ],
+    ),
+    (
+        "shell",
+        &[
+            r"^#!/(usr/)?bin/(env )?(ba|z)?sh",
+            r"^\s*(sudo |apt |echo |export |cd )",
+            r"\$\{?\w+\}?",
+        ],
+    ),
+    ("yaml", &[r"^[\w-]+:(\s|$)", r"^\s+- \w+", r"^---$"]),
+];
+
+/// Guess what kind of content `content` is
+pub fn detect(content: &str) -> Detection {
+    let mut end = content.len().min(SCAN);
+
+    while !content.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    let content = &content[..end];
+    let lines: Vec<&str> = content.lines().filter(|l| !l.trim().is_empty()).collect();
+
+    // secrets, logs and stack traces
+    let secrets = SECRET_PATTERNS
+        .iter()
+        .any(|p| Regex::new(p).unwrap().is_match(content));
+
+    let stack_trace = STACK_TRACE_PATTERNS.iter().any(|p| {
+        let regex = Regex::new(p).unwrap();
+        lines.iter().any(|l| regex.is_match(l))
+    });
+
+    let log_regex = Regex::new(LOG_PATTERN).unwrap();
+    let log_lines = lines.iter().filter(|l| log_regex.is_match(l)).count();
+    let log = (lines.len() >= 2) && (log_lines * 2 >= lines.len());
+
+    // language
+    let language = if is_json(content) {
+        String::from("json")
+    } else if is_markdown(&lines) {
+        String::from("markdown")
+    } else if table::is_table(content, '\t') {
+        String::from("tsv")
+    } else if table::is_table(content, ',') {
+        String::from("csv")
+    } else {
+        guess_language(&lines)
+    };
+
+    let content_type = if stack_trace {
+        "stack_trace"
+    } else if log {
+        "log"
+    } else if (language == "markdown") | (language == "csv") | (language == "tsv") {
+        language.as_str()
+    } else if !language.is_empty() {
+        "code"
+    } else {
+        "text"
+    };
+
+    // suggestions
+    let mut tags: Vec<String> = Vec::new();
+
+    if !language.is_empty() && (language != "markdown") {
+        tags.push(language.clone());
+    }
+
+    if stack_trace {
+        tags.push(String::from("stack-trace"));
+    }
+
+    if log {
+        tags.push(String::from("log"));
+    }
+
+    Detection {
+        content_type: content_type.to_string(),
+        language,
+        secrets,
+        log,
+        stack_trace,
+        suggestions: DetectionSuggestions {
+            // the first line could be the secret
+            title: suggest_title(&lines, secrets),
+            tags,
+        },
+    }
+}
+
+/// Check if `content` is a JSON object or array
+fn is_json(content: &str) -> bool {
+    let trimmed = content.trim();
+
+    (trimmed.starts_with('{') | trimmed.starts_with('['))
+        && serde_json::from_str::<serde_json::Value>(trimmed).is_ok()
+}
+
+/// Check if `lines` use enough Markdown syntax to be Markdown
+fn is_markdown(lines: &[&str]) -> bool {
+    let regex =
+        Regex::new(r"^(#{1,6} \S|[-*+] \S|\d+\. \S|> |```|~~~)|\[[^\]]+\]\([^)]+\)").unwrap();
+    let count = lines.iter().filter(|l| regex.is_match(l)).count();
+
+    (count >= 2) | lines.first().map(|l| l.starts_with("# ")).unwrap_or(false)
+}
+
+/// Guess the programming language of `lines` (empty if nothing scores high enough)
+fn guess_language(lines: &[&str]) -> String {
+    let mut best: (&str, usize) = ("", 0);
+
+    for (language, patterns) in LANGUAGES {
+        let regexes: Vec<Regex> = patterns.iter().map(|p| Regex::new(p).unwrap()).collect();
+        let score = lines
+            .iter()
+            .filter(|l| regexes.iter().any(|r| r.is_match(l)))
+            .count();
+
+        if score > best.1 {
+            best = (language, score);
+        }
+    }
+
+    // a single matching line is too easy to get by accident
+    if (best.1 < 2) && (lines.len() > 1) {
+        return String::new();
+    }
+
+    best.0.to_string()
+}
+
+/// Suggest a title from the first heading, or the first line if there is none (and
+/// `heading_only` isn't set)
+fn suggest_title(lines: &[&str], heading_only: bool) -> String {
+    let heading = lines
+        .iter()
+        .find(|l| l.starts_with("# "))
+        .map(|l| l.trim_start_matches('#').trim());
+
+    let title = match heading {
+        Some(h) => h,
+        None => match lines.first() {
+            Some(l) if heading_only == false => l.trim(),
+            _ => return String::new(),
+        },
+    };
+
+    if title.chars().count() > MAX_TITLE {
+        format!("{}…", title.chars().take(MAX_TITLE - 1).collect::<String>())
+    } else {
+        title.to_string()
+    }
+}
@@ -0,0 +1,81 @@
+//! Line diffs between two versions of paste content
+use crate::model::{DiffLine, DiffOp};
+
+/// Largest number of (old line, new line) pairs compared before giving up on a minimal diff
+const MAX_CELLS: usize = 4_000_000;
+
+/// Get the line diff between `old` and `new`
+///
+/// Lines shared at the start and end are always matched. If the rest is too large to
+/// compare, it's returned as every old line deleted followed by every new line inserted.
+///
+/// ## Arguments:
+/// * `old` - the original content
+/// * `new` - the changed content
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+
+    // trim common prefix and suffix
+    let prefix = a.iter().zip(b.iter()).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+
+    let a_mid = &a[prefix..a.len() - suffix];
+    let b_mid = &b[prefix..b.len() - suffix];
+
+    let mut out: Vec<DiffLine> = a[..prefix].iter().map(|l| line(DiffOp::Equal, l)).collect();
+
+    if (a_mid.len() + 1) * (b_mid.len() + 1) > MAX_CELLS {
+        out.extend(a_mid.iter().map(|l| line(DiffOp::Delete, l)));
+        out.extend(b_mid.iter().map(|l| line(DiffOp::Insert, l)));
+    } else {
+        // longest common subsequence table (from the end)
+        let width = b_mid.len() + 1;
+        let mut table: Vec<u32> = vec![0; (a_mid.len() + 1) * width];
+
+        for i in (0..a_mid.len()).rev() {
+            for j in (0..b_mid.len()).rev() {
+                table[i * width + j] = if a_mid[i] == b_mid[j] {
+                    table[(i + 1) * width + j + 1] + 1
+                } else {
+                    table[(i + 1) * width + j].max(table[i * width + j + 1])
+                };
+            }
+        }
+
+        // walk table
+        let (mut i, mut j) = (0, 0);
+
+        while (i < a_mid.len()) && (j < b_mid.len()) {
+            if a_mid[i] == b_mid[j] {
+                out.push(line(DiffOp::Equal, a_mid[i]));
+                i += 1;
+                j += 1;
+            } else if table[(i + 1) * width + j] >= table[i * width + j + 1] {
+                out.push(line(DiffOp::Delete, a_mid[i]));
+                i += 1;
+            } else {
+                out.push(line(DiffOp::Insert, b_mid[j]));
+                j += 1;
+            }
+        }
+
+        out.extend(a_mid[i..].iter().map(|l| line(DiffOp::Delete, l)));
+        out.extend(b_mid[j..].iter().map(|l| line(DiffOp::Insert, l)));
+    }
+
+    out.extend(a[a.len() - suffix..].iter().map(|l| line(DiffOp::Equal, l)));
+    out
+}
+
+fn line(op: DiffOp, content: &str) -> DiffLine {
+    DiffLine {
+        op,
+        content: content.to_string(),
+    }
+}
@@ -0,0 +1,104 @@
+//! Plaintext excerpts of Markdown paste content (see [`crate::model::PasteMetadata::excerpt`])
+use crate::front_matter;
+
+/// Longest excerpt generated (in characters)
+const MAX_LENGTH: usize = 300;
+
+/// Get the first `sentences` sentences of `content` as plain text
+///
+/// Front matter, headings, code blocks, HTML tags and Markdown formatting are removed.
+///
+/// ## Arguments:
+/// * `content` - Markdown content
+/// * `sentences` - the number of sentences to keep
+pub fn excerpt(content: &str, sentences: usize) -> String {
+    let text = plaintext(front_matter::strip(content));
+
+    // take sentences
+    let mut out = String::new();
+    let mut count: usize = 0;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        out.push(c);
+
+        if ['.', '!', '?'].contains(&c) && chars.peek().map_or(true, |n| n.is_whitespace()) {
+            count += 1;
+
+            if count >= sentences {
+                break;
+            }
+        }
+    }
+
+    // limit length
+    if out.chars().count() > MAX_LENGTH {
+        out = out
+            .chars()
+            .take(MAX_LENGTH - 1)
+            .collect::<String>()
+            .trim_end()
+            .to_string();
+        out.push('…');
+    }
+
+    out
+}
+
+/// Strip Markdown from `content`, joining its text into a single line
+fn plaintext(content: &str) -> String {
+    let image = regex::Regex::new(r"!\[([^\]]*)\]\([^)]*\)").unwrap();
+    let link = regex::Regex::new(r"\[([^\]]*)\]\([^)]*\)").unwrap();
+    let tag = regex::Regex::new(r"<[^>]*>").unwrap();
+    let list = regex::Regex::new(r"^([-*+]|\d+[.)])\s+").unwrap();
+
+    let mut out: Vec<String> = Vec::new();
+    let mut fence: Option<String> = None;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+
+        // code blocks
+        if let Some(ref f) = fence {
+            if trimmed.starts_with(f.as_str()) {
+                fence = None;
+            }
+
+            continue;
+        }
+
+        if trimmed.starts_with("```") | trimmed.starts_with("~~~") {
+            fence = Some(trimmed[..3].to_string());
+            continue;
+        }
+
+        // headings, rules and tables
+        if trimmed.starts_with('#')
+            | trimmed.starts_with('|')
+            | (trimmed.len() >= 3 && trimmed.chars().all(|c| "-*_ ".contains(c)))
+        {
+            continue;
+        }
+
+        // block markers
+        let line = list.replace(trimmed.trim_start_matches(['>', ' ']), "");
+
+        // inline formatting
+        let line = image.replace_all(&line, "$1");
+        let line = link.replace_all(&line, "$1");
+        let line = tag.replace_all(&line, "");
+        let line = line
+            .replace(['`', '*'], "")
+            .replace("~~", "")
+            .replace("__", "");
+
+        if !line.trim().is_empty() {
+            out.push(line.trim().to_string());
+        }
+    }
+
+    out.join(" ")
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
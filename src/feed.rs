@@ -0,0 +1,110 @@
+//! Atom feeds of an owner's public pastes (see `/api/feed/:username.atom`)
+use crate::model::PasteListing;
+
+/// Render an Atom feed of `pastes`
+///
+/// ## Arguments:
+/// * `base` - the origin the feed is served from (e.g. `https://example.com`)
+/// * `username` - the owner of the pastes
+/// * `pastes` - the owner's newest public pastes, newest first
+pub fn render(base: &str, username: &str, pastes: &[PasteListing]) -> String {
+    let feed_url = format!("{}/api/feed/{}.atom", base, username);
+
+    // the feed changed when its newest entry did
+    let updated = pastes
+        .iter()
+        .map(edited)
+        .max()
+        .unwrap_or_else(dorsal::utility::unix_epoch_timestamp);
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    out.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    out.push_str(&format!("  <id>{}</id>\n", escape(&feed_url)));
+    out.push_str(&format!("  <title>{}</title>\n", escape(username)));
+    out.push_str(&format!(
+        "  <link rel=\"self\" href=\"{}\"/>\n",
+        escape(&feed_url)
+    ));
+    out.push_str(&format!("  <updated>{}</updated>\n", timestamp(updated)));
+    out.push_str(&format!(
+        "  <author><name>{}</name></author>\n",
+        escape(username)
+    ));
+
+    for paste in pastes {
+        let url = format!("{}/{}", base, paste.url);
+
+        out.push_str("  <entry>\n");
+        out.push_str(&format!("    <id>{}</id>\n", escape(&url)));
+        out.push_str(&format!(
+            "    <title>{}</title>\n",
+            escape(if paste.title.is_empty() {
+                &paste.url
+            } else {
+                &paste.title
+            })
+        ));
+        out.push_str(&format!("    <link href=\"{}\"/>\n", escape(&url)));
+        out.push_str(&format!(
+            "    <published>{}</published>\n",
+            timestamp(paste.date_published)
+        ));
+        out.push_str(&format!(
+            "    <updated>{}</updated>\n",
+            timestamp(edited(paste))
+        ));
+
+        if !paste.excerpt.is_empty() {
+            out.push_str(&format!(
+                "    <summary>{}</summary>\n",
+                escape(&paste.excerpt)
+            ));
+        }
+
+        out.push_str("  </entry>\n");
+    }
+
+    out.push_str("</feed>\n");
+    out
+}
+
+/// Get the timestamp a paste was last changed at (pastes which were never edited have no edit date)
+fn edited(paste: &PasteListing) -> u128 {
+    paste.date_edited.max(paste.date_published)
+}
+
+/// Format a timestamp in milliseconds as an RFC 3339 date (in UTC)
+fn timestamp(ms: u128) -> String {
+    let seconds = (ms / 1_000) as i64;
+    let (days, time) = (seconds.div_euclid(86_400), seconds.rem_euclid(86_400));
+
+    // days since 1970-01-01 to a civil date (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3_600,
+        (time % 3_600) / 60,
+        time % 60
+    )
+}
+
+/// Escape `text` for XML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
@@ -0,0 +1,102 @@
+//! YAML (`---`) and TOML (`+++`) front matter at the top of paste content
+use crate::model::PasteMetadata;
+use serde_json::Value;
+
+/// Split `content` into its front matter and the rest of the content
+///
+/// ## Returns:
+/// * `Some((front_matter, body))` if the content starts with valid front matter
+pub fn parse(content: &str) -> Option<(Value, &str)> {
+    let first = content.lines().next()?;
+
+    let delimiter = match first.trim_end() {
+        "---" => "---",
+        "+++" => "+++",
+        _ => return None,
+    };
+
+    // find the closing delimiter
+    let start = first.len()
+        + if content[first.len()..].starts_with("\r\n") {
+            2
+        } else {
+            1
+        };
+
+    if start > content.len() {
+        return None;
+    }
+
+    let mut offset = start;
+    let mut end: Option<(usize, usize)> = None;
+
+    for line in content[start..].split_inclusive('\n') {
+        if line.trim_end() == delimiter {
+            end = Some((offset, offset + line.len()));
+            break;
+        }
+
+        offset += line.len();
+    }
+
+    let (end, body_start) = end?;
+    let raw = &content[start..end];
+
+    // parse
+    let value: Value = if delimiter == "---" {
+        serde_yaml::from_str(raw).ok()?
+    } else {
+        let table: toml::Table = toml::from_str(raw).ok()?;
+        serde_json::to_value(table).ok()?
+    };
+
+    if !value.is_object() {
+        return None;
+    }
+
+    Some((value, &content[body_start..]))
+}
+
+/// Get `content` without its front matter (for rendering)
+pub fn strip(content: &str) -> &str {
+    match parse(content) {
+        Some((_, body)) => body,
+        None => content,
+    }
+}
+
+/// Apply the front matter of `content` to `metadata`
+///
+/// The full front matter is stored in `metadata.front_matter`, and the `title`, `description`,
+/// `tags` and `language` fields are copied to their metadata fields when present.
+pub fn apply(content: &str, metadata: &mut PasteMetadata) {
+    let front_matter = match parse(content) {
+        Some((f, _)) => f,
+        None => {
+            metadata.front_matter = Value::Null;
+            return;
+        }
+    };
+
+    if let Some(title) = front_matter.get("title").and_then(|v| v.as_str()) {
+        metadata.title = title.to_string();
+    }
+
+    if let Some(description) = front_matter.get("description").and_then(|v| v.as_str()) {
+        metadata.description = description.to_string();
+    }
+
+    if let Some(language) = front_matter.get("language").and_then(|v| v.as_str()) {
+        metadata.language = language.to_string();
+    }
+
+    if let Some(tags) = front_matter.get("tags").and_then(|v| v.as_array()) {
+        metadata.tags = tags
+            .iter()
+            .filter_map(|t| t.as_str())
+            .map(|t| t.to_string())
+            .collect();
+    }
+
+    metadata.front_matter = front_matter;
+}
@@ -0,0 +1,244 @@
+//! Responds to gRPC requests (requires the `grpc` feature)
+use crate::database::Database;
+use crate::model::{self, PasteCreate, PasteError};
+
+use std::net::SocketAddr;
+use tonic::{Request, Response, Status, Streaming};
+
+pub mod proto {
+    tonic::include_proto!("pastemd");
+}
+
+use proto::pastes_server::{Pastes, PastesServer};
+use proto::{
+    CreatePasteRequest, CreatePasteResponse, DeletePasteRequest, EditPasteRequest, Empty,
+    GetPasteRequest, Paste,
+};
+
+impl From<PasteError> for Status {
+    fn from(e: PasteError) -> Self {
+        use crate::model::PasteError::*;
+        match e {
+            PasswordIncorrect | NotAllowed | GeoBlocked | ChangeOwner | WebAuthnRequired
+            | Banned => Status::permission_denied(e.to_string()),
+            PasswordRequired => Status::unauthenticated(e.to_string()),
+            AlreadyExists | UrlTaken => Status::already_exists(e.to_string()),
+            ValueError | Validation(_) | ContentTooLarge => Status::invalid_argument(e.to_string()),
+            QuotaExceeded | RateLimited => Status::resource_exhausted(e.to_string()),
+            TosNotAccepted => Status::failed_precondition(e.to_string()),
+            Overloaded => Status::unavailable(e.to_string()),
+            NotFound => Status::not_found(e.to_string()),
+            _ => Status::internal(e.to_string()),
+        }
+    }
+}
+
+impl From<model::Paste> for Paste {
+    fn from(p: model::Paste) -> Self {
+        // never send the view password hash
+        let p = model::PublicPaste::from(p);
+
+        Self {
+            id: p.id,
+            url: p.url,
+            content: p.content,
+            date_published: p.date_published as u64,
+            date_edited: p.date_edited as u64,
+            metadata: serde_json::to_string(&p.metadata).unwrap_or_default(),
+        }
+    }
+}
+
+/// gRPC paste service
+pub struct PasteService {
+    pub database: Database,
+}
+
+impl PasteService {
+    /// Respond to a paste being created, recording terms of service acceptance if needed
+    async fn respond(
+        &self,
+        password: String,
+        paste: model::Paste,
+        ip: Option<SocketAddr>,
+    ) -> Result<Response<CreatePasteResponse>, Status> {
+        if !self.database.options.tos_paste.is_empty() {
+            if let Err(e) = self
+                .database
+                .record_tos_acceptance(
+                    paste.url.clone(),
+                    ip.map(|a| a.ip().to_string()).unwrap_or_default(),
+                )
+                .await
+            {
+                return Err(e.into());
+            }
+        }
+
+        Ok(Response::new(CreatePasteResponse {
+            password,
+            paste: Some(paste.into()),
+        }))
+    }
+}
+
+#[tonic::async_trait]
+impl Pastes for PasteService {
+    async fn get_paste(
+        &self,
+        request: Request<GetPasteRequest>,
+    ) -> Result<Response<Paste>, Status> {
+        match self
+            .database
+            .get_paste_by_url(request.into_inner().url)
+            .await
+        {
+            Ok(mut p) => {
+                // protected pastes can only be unlocked through the REST api
+                if !p.metadata.view_password.is_empty() {
+                    return Err(PasteError::PasswordRequired.into());
+                }
+
+                // quarantined pastes are returned without their content
+                if p.metadata.scan_status == "infected" {
+                    p.content = String::new();
+                }
+
+                Ok(Response::new(p.into()))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn create_paste(
+        &self,
+        request: Request<CreatePasteRequest>,
+    ) -> Result<Response<CreatePasteResponse>, Status> {
+        let ip = request.remote_addr();
+        let props = request.into_inner();
+
+        // gRPC clients are anonymous
+        if !self.database.options.tos_paste.is_empty() && (props.accept_tos == false) {
+            return Err(PasteError::TosNotAccepted.into());
+        }
+
+        match self
+            .database
+            .create_paste(PasteCreate {
+                url: props.url,
+                content: props.content,
+                password: props.password,
+                accept_tos: props.accept_tos,
+                author: String::new(),
+                expires_in: String::new(),
+                expires_at: 0,
+                invite: String::new(),
+            })
+            .await
+        {
+            Ok((password, paste)) => self.respond(password, paste, ip).await,
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn upload_paste(
+        &self,
+        request: Request<Streaming<CreatePasteRequest>>,
+    ) -> Result<Response<CreatePasteResponse>, Status> {
+        let ip = request.remote_addr();
+        let mut stream = request.into_inner();
+        let mut props: Option<PasteCreate> = None;
+
+        while let Some(chunk) = stream.message().await? {
+            match props {
+                Some(ref mut p) => {
+                    p.content.push_str(&chunk.content);
+
+                    // same limit as create_paste, checked early so we stop reading
+                    if p.content.len() > 200_000 {
+                        return Err(PasteError::ValueError.into());
+                    }
+                }
+                None => {
+                    props = Some(PasteCreate {
+                        url: chunk.url,
+                        content: chunk.content,
+                        password: chunk.password,
+                        accept_tos: chunk.accept_tos,
+                        author: String::new(),
+                        expires_in: String::new(),
+                        expires_at: 0,
+                        invite: String::new(),
+                    })
+                }
+            }
+        }
+
+        let props = match props {
+            Some(p) => p,
+            None => return Err(PasteError::ValueError.into()),
+        };
+
+        // gRPC clients are anonymous
+        if !self.database.options.tos_paste.is_empty() && (props.accept_tos == false) {
+            return Err(PasteError::TosNotAccepted.into());
+        }
+
+        match self.database.create_paste(props).await {
+            Ok((password, paste)) => self.respond(password, paste, ip).await,
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn edit_paste(
+        &self,
+        request: Request<EditPasteRequest>,
+    ) -> Result<Response<Empty>, Status> {
+        let props = request.into_inner();
+
+        match self
+            .database
+            .edit_paste_by_url(
+                props.url,
+                props.password,
+                String::new(),
+                props.new_content,
+                props.new_url,
+                props.new_password,
+                None,
+                None,
+            )
+            .await
+        {
+            Ok(_) => Ok(Response::new(Empty {})),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete_paste(
+        &self,
+        request: Request<DeletePasteRequest>,
+    ) -> Result<Response<Empty>, Status> {
+        let props = request.into_inner();
+
+        match self
+            .database
+            .delete_paste_by_url(props.url, props.password, None)
+            .await
+        {
+            Ok(_) => Ok(Response::new(Empty {})),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Serve the gRPC paste service on `addr`
+///
+/// This runs separately from the axum routes, so it should be given its own port.
+pub async fn serve(database: Database, addr: SocketAddr) -> Result<(), tonic::transport::Error> {
+    tonic::transport::Server::builder()
+        .layer(tower_http::trace::TraceLayer::new_for_grpc())
+        .add_service(PastesServer::new(PasteService { database }))
+        .serve(addr)
+        .await
+}
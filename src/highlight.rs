@@ -0,0 +1,71 @@
+//! Server-side syntax highlighting of code pastes (requires the `highlight` feature, see [`crate::database::ServerOptions::highlight`])
+//!
+//! Highlighted code is marked up with `hl-` prefixed classes instead of inline styles, so
+//! it can be themed with a stylesheet (see [`stylesheet`]).
+use crate::model::HighlightLanguage;
+use std::sync::OnceLock;
+use syntect::highlighting::ThemeSet;
+use syntect::html::{css_for_theme_with_class_style, ClassStyle, ClassedHTMLGenerator};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+/// Class style of highlighted spans
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+
+/// Get the bundled syntaxes (loaded once)
+fn syntaxes() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+/// Get every language which can be highlighted, sorted by name
+pub fn languages() -> Vec<HighlightLanguage> {
+    let mut out: Vec<HighlightLanguage> = syntaxes()
+        .syntaxes()
+        .iter()
+        .filter(|s| !s.hidden)
+        .map(|s| HighlightLanguage {
+            name: s.name.clone(),
+            extensions: s.file_extensions.clone(),
+        })
+        .collect();
+
+    out.sort_by_key(|l| l.name.to_lowercase());
+    out
+}
+
+/// Check if `language` is rendered as Markdown instead of being highlighted
+pub fn is_markdown(language: &str) -> bool {
+    ["", "markdown", "md"].contains(&language.to_lowercase().as_str())
+}
+
+/// Highlight `content` as `language` (a name or file extension, e.g. `rust` or `rs`)
+///
+/// ## Returns:
+/// * the highlighted HTML, or `None` if the language isn't known
+pub fn highlight(content: &str, language: &str) -> Option<String> {
+    let syntaxes = syntaxes();
+    let syntax = syntaxes.find_syntax_by_token(language)?;
+    let mut generator = ClassedHTMLGenerator::new_with_class_style(syntax, syntaxes, CLASS_STYLE);
+
+    for line in LinesWithEndings::from(content) {
+        if generator
+            .parse_html_for_line_which_includes_newline(line)
+            .is_err()
+        {
+            return None;
+        }
+    }
+
+    Some(format!(
+        "<pre class=\"hl-code\" data-language=\"{}\"><code>{}</code></pre>",
+        syntax.name.replace('"', "&quot;"),
+        generator.finalize()
+    ))
+}
+
+/// Get a stylesheet for highlighted code from one of the bundled themes (e.g. `InspiredGitHub` or `base16-ocean.dark`)
+pub fn stylesheet(theme: &str) -> Option<String> {
+    let themes = ThemeSet::load_defaults();
+    css_for_theme_with_class_style(themes.themes.get(theme)?, CLASS_STYLE).ok()
+}
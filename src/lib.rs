@@ -1,5 +1,63 @@
+pub mod audit;
+pub mod data;
 pub mod database;
+pub mod detect;
+pub mod diff;
+pub mod excerpt;
+pub mod feed;
+pub mod front_matter;
+pub mod links;
+pub mod lint;
+pub mod logs;
+pub mod manager;
 pub mod model;
+pub mod normalize;
+pub mod notebook;
 pub mod routing;
+pub mod search;
+pub mod slug;
+pub mod stack_trace;
+pub mod table;
+pub mod validation;
+pub mod watermark;
+
+#[cfg(feature = "archive")]
+pub mod archive;
+
+#[cfg(feature = "binary")]
+pub mod binary;
+
+#[cfg(feature = "checksums")]
+pub mod checksum;
+
+#[cfg(feature = "grpc")]
+pub mod grpc;
+
+#[cfg(feature = "highlight")]
+pub mod highlight;
+
+#[cfg(feature = "client")]
+pub mod client;
+
+#[cfg(feature = "markdown")]
+pub mod markdown;
+
+#[cfg(feature = "plugins")]
+pub mod plugins;
+
+#[cfg(feature = "scanning")]
+pub mod scanner;
+
+#[cfg(feature = "scripting")]
+pub mod scripting;
+
+#[cfg(feature = "signed_urls")]
+pub mod signing;
+
+#[cfg(feature = "thumbnails")]
+pub mod thumbnail;
+
+#[cfg(feature = "webauthn")]
+pub mod webauthn;
 
 pub use dorsal::DatabaseOpts;
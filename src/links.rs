@@ -0,0 +1,49 @@
+//! Outbound links in paste content (see [`crate::database::ServerOptions::link_check`])
+
+/// Largest number of links taken from a single paste
+const MAX_LINKS: usize = 100;
+
+/// Get all unique `http(s)` links in `content`, in the order they first appear
+///
+/// Links inside fenced code blocks aren't rendered as links, so they're skipped.
+pub fn extract(content: &str) -> Vec<String> {
+    let regex = regex::Regex::new(r#"https?://[^\s<>()\[\]"'`]+"#).unwrap();
+
+    let mut out: Vec<String> = Vec::new();
+    let mut fence: Option<String> = None;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+
+        // code blocks
+        if let Some(ref f) = fence {
+            if trimmed.starts_with(f.as_str()) {
+                fence = None;
+            }
+
+            continue;
+        }
+
+        if trimmed.starts_with("```") | trimmed.starts_with("~~~") {
+            fence = Some(trimmed[..3].to_string());
+            continue;
+        }
+
+        // links
+        for m in regex.find_iter(line) {
+            let link = m
+                .as_str()
+                .trim_end_matches(['.', ',', ';', ':', '!', '?', '*', '_']);
+
+            if !out.iter().any(|l| l == link) {
+                out.push(link.to_string());
+
+                if out.len() >= MAX_LINKS {
+                    return out;
+                }
+            }
+        }
+    }
+
+    out
+}
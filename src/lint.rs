@@ -0,0 +1,151 @@
+//! Markdown lint rules
+use crate::model::LintDiagnostic;
+use std::collections::HashSet;
+
+/// Lint Markdown `content`
+///
+/// ## Rules:
+/// * `heading_jump` - a heading is more than one level deeper than the previous heading
+/// * `broken_reference` - a reference link (`[text][label]` or `[label][]`) has no definition
+/// * `long_line` - a line (outside of code blocks) is longer than `max_line_length` characters
+///
+/// ## Arguments:
+/// * `content` - the Markdown to lint
+/// * `max_line_length` - the longest allowed line (`0` to disable the `long_line` rule)
+pub fn lint(content: &str, max_line_length: usize) -> Vec<LintDiagnostic> {
+    let mut out: Vec<LintDiagnostic> = Vec::new();
+
+    let definition = regex::Regex::new(r"^ {0,3}\[([^\]]+)\]:\s*\S+").unwrap();
+    let reference = regex::Regex::new(r"\[([^\]]*)\]\[([^\]]*)\]").unwrap();
+    let code_span = regex::Regex::new(r"`[^`]*`").unwrap();
+
+    // collect reference definitions
+    let mut definitions: HashSet<String> = HashSet::new();
+    let mut fence: Option<&str> = None;
+
+    for line in content.lines() {
+        if let Some(marker) = code_fence(line) {
+            match fence {
+                Some(f) if marker.starts_with(f) => fence = None,
+                Some(_) => (),
+                None => fence = Some(marker),
+            }
+
+            continue;
+        }
+
+        if fence.is_some() {
+            continue;
+        }
+
+        if let Some(c) = definition.captures(line) {
+            definitions.insert(normalize_label(&c[1]));
+        }
+    }
+
+    // check lines
+    let mut last_heading: usize = 0;
+    fence = None;
+
+    for (i, line) in content.lines().enumerate() {
+        let number = i + 1;
+
+        if let Some(marker) = code_fence(line) {
+            match fence {
+                Some(f) if marker.starts_with(f) => fence = None,
+                Some(_) => (),
+                None => fence = Some(marker),
+            }
+
+            continue;
+        }
+
+        if fence.is_some() {
+            continue;
+        }
+
+        // long_line
+        let length = line.chars().count();
+
+        if (max_line_length != 0) && (length > max_line_length) && !definition.is_match(line) {
+            out.push(LintDiagnostic {
+                line: number,
+                column: max_line_length + 1,
+                rule: String::from("long_line"),
+                message: format!(
+                    "Line is {} characters long (maximum is {}).",
+                    length, max_line_length
+                ),
+            });
+        }
+
+        // heading_jump
+        let trimmed = line.trim_start();
+        let level = trimmed.chars().take_while(|c| *c == '#').count();
+
+        if (level >= 1)
+            && (level <= 6)
+            && (line.len() - trimmed.len() <= 3)
+            && trimmed[level..].chars().next().map_or(true, |c| c == ' ')
+        {
+            if (last_heading != 0) && (level > last_heading + 1) {
+                out.push(LintDiagnostic {
+                    line: number,
+                    column: line.len() - trimmed.len() + 1,
+                    rule: String::from("heading_jump"),
+                    message: format!("Heading level jumps from {} to {}.", last_heading, level),
+                });
+            }
+
+            last_heading = level;
+        }
+
+        // broken_reference
+        let without_code =
+            code_span.replace_all(line, |c: &regex::Captures| " ".repeat(c[0].len()));
+
+        for c in reference.captures_iter(&without_code) {
+            // `[label][]` uses the text as the label
+            let label = if c[2].is_empty() { &c[1] } else { &c[2] };
+
+            if !definitions.contains(&normalize_label(label)) {
+                out.push(LintDiagnostic {
+                    line: number,
+                    column: line[..c.get(0).unwrap().start()].chars().count() + 1,
+                    rule: String::from("broken_reference"),
+                    message: format!("Reference \"{}\" is never defined.", label),
+                });
+            }
+        }
+    }
+
+    out
+}
+
+/// Get the fence marker (` ``` ` or `~~~`) if `line` opens or closes a code block
+fn code_fence(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start();
+
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+
+    for marker in ["```", "~~~"] {
+        if trimmed.starts_with(marker) {
+            let c = marker.chars().next().unwrap();
+            let length = trimmed.chars().take_while(|x| *x == c).count();
+            return Some(&trimmed[..length]);
+        }
+    }
+
+    None
+}
+
+/// Normalize a reference label (labels are case-insensitive and ignore extra whitespace)
+fn normalize_label(label: &str) -> String {
+    label
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase()
+}
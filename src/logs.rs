@@ -0,0 +1,111 @@
+//! Rendering log files (see [`crate::database::ServerOptions::log_files`])
+//!
+//! Every line is marked up with the level it was logged at (lines without a level, like
+//! multi-line messages, keep the level of the line before them), so pages can filter
+//! lines by level with a stylesheet or script using the `data-level` attributes. Repeated
+//! lines (ignoring their timestamps) are collapsed into one with a count. Everything is
+//! marked up with `lg-` prefixed classes so it can be themed with a stylesheet.
+use regex::Regex;
+
+/// Levels lines can be logged at, from least to most severe
+pub const LEVELS: [&str; 5] = ["trace", "debug", "info", "warn", "error"];
+
+/// A level written in a line
+const LEVEL_PATTERN: &str = r"\b(TRACE|DEBUG|INFO|WARN|WARNING|ERROR|ERR|FATAL|CRITICAL|trace|debug|info|warn|warning|error|fatal|critical)\b";
+/// A timestamp at the start of a line
+const TIMESTAMP_PATTERN: &str =
+    r"^\[?\d{4}-\d{2}-\d{2}[ T]\d{2}:\d{2}(:\d{2})?([.,]\d+)?(Z|[+-]\d{2}:?\d{2})?\]?\s*";
+
+/// Render a log file to HTML
+pub fn render(content: &str) -> String {
+    let level_regex = Regex::new(LEVEL_PATTERN).unwrap();
+    let timestamp_regex = Regex::new(TIMESTAMP_PATTERN).unwrap();
+
+    // collapse repeated lines
+    let mut lines: Vec<(&str, usize)> = Vec::new();
+
+    for line in content.lines() {
+        match lines.last_mut() {
+            Some((last, count))
+                if timestamp_regex.replace(last, "") == timestamp_regex.replace(line, "") =>
+            {
+                *count += 1
+            }
+            _ => lines.push((line, 1)),
+        }
+    }
+
+    // render
+    let mut body = String::new();
+    let mut found: Vec<&str> = Vec::new();
+    let mut level = "";
+
+    for (line, count) in lines {
+        let matched = level_regex.find(line);
+
+        if let Some(m) = matched {
+            level = normalize_level(m.as_str());
+
+            if !found.contains(&level) {
+                found.push(level);
+            }
+        }
+
+        if level.is_empty() {
+            body.push_str("<div class=\"lg-line\">");
+        } else {
+            body.push_str(&format!(
+                "<div class=\"lg-line lg-{}\" data-level=\"{}\">",
+                level, level
+            ));
+        }
+
+        match matched {
+            Some(m) => body.push_str(&format!(
+                "{}<span class=\"lg-level\">{}</span>{}",
+                escape(&line[..m.start()]),
+                escape(m.as_str()),
+                escape(&line[m.end()..])
+            )),
+            None => body.push_str(&escape(line)),
+        }
+
+        if count > 1 {
+            body.push_str(&format!(" <span class=\"lg-repeat\">×{}</span>", count));
+        }
+
+        body.push_str("</div>");
+    }
+
+    // levels are listed from least to most severe
+    let levels: Vec<&str> = LEVELS
+        .iter()
+        .filter(|l| found.contains(l))
+        .copied()
+        .collect();
+
+    format!(
+        "<div class=\"lg\" data-levels=\"{}\">{}</div>",
+        levels.join(" "),
+        body
+    )
+}
+
+/// Get which of [`LEVELS`] a level written in a line is
+fn normalize_level(level: &str) -> &'static str {
+    match level.to_lowercase().as_str() {
+        "trace" => "trace",
+        "debug" => "debug",
+        "info" => "info",
+        "warn" | "warning" => "warn",
+        _ => "error",
+    }
+}
+
+/// Escape `text` for HTML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
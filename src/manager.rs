@@ -0,0 +1,153 @@
+//! Storage-independent paste CRUD (see [`PasteManager`])
+//!
+//! [`SqlPasteManager`] implements [`PasteManager`] on top of a [`Database`]. Code which only
+//! needs to create, read, edit and delete pastes can be written against the trait, so it
+//! also works with other backends (e.g. an in-memory store in tests).
+//!
+//! The API router still takes a [`Database`] directly, since most of its routes need more
+//! than paste CRUD.
+use crate::database::{Database, Result};
+use crate::model::{Paste, PasteCreate, PasteMetadata, WebAuthnAssertion};
+use dorsal::db::special::auth_db::{FullUser, UserMetadata};
+use std::future::Future;
+
+/// The SQL implementation of [`PasteManager`]
+#[derive(Clone)]
+pub struct SqlPasteManager {
+    pub database: Database,
+}
+
+impl SqlPasteManager {
+    /// Create a new [`SqlPasteManager`]
+    ///
+    /// ## Arguments:
+    /// * `database` - the [`Database`] pastes are stored in
+    pub fn new(database: Database) -> Self {
+        Self { database }
+    }
+}
+
+impl From<Database> for SqlPasteManager {
+    fn from(database: Database) -> Self {
+        Self::new(database)
+    }
+}
+
+/// Paste storage backend
+///
+/// Implementations should behave like the [`Database`] methods of the same names,
+/// including their errors (e.g. [`crate::model::PasteError::NotFound`] for missing pastes
+/// and [`crate::model::PasteError::PasswordIncorrect`] for wrong passwords).
+pub trait PasteManager: Send + Sync {
+    /// Get an existing paste by `url`
+    fn get_paste_by_url(&self, url: String) -> impl Future<Output = Result<Paste>> + Send;
+
+    /// Create a new paste
+    ///
+    /// ## Returns:
+    /// * Result containing a tuple with the unhashed edit password and the paste
+    fn create_paste(
+        &self,
+        props: PasteCreate,
+    ) -> impl Future<Output = Result<(String, Paste)>> + Send;
+
+    /// Edit an existing paste's content, url or password by `url`
+    ///
+    /// ## Arguments:
+    /// * `url` - the paste to edit
+    /// * `password` - the paste's edit password
+    /// * `edit_link` - an edit link token (used instead of `password`, can be empty)
+    /// * `new_content` - the new content of the paste
+    /// * `new_url` - the new url of the paste (empty to keep it)
+    /// * `new_password` - the new edit password of the paste (empty to keep it)
+    /// * `editing_as` - the userstate of the user we're editing the paste as
+    /// * `assertion` - passkey assertion from the owner (for high security pastes)
+    fn edit_paste_by_url(
+        &self,
+        url: String,
+        password: String,
+        edit_link: String,
+        new_content: String,
+        new_url: String,
+        new_password: String,
+        editing_as: Option<FullUser<UserMetadata>>,
+        assertion: Option<WebAuthnAssertion>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Edit an existing paste's metadata by `url`
+    fn edit_paste_metadata_by_url(
+        &self,
+        url: String,
+        password: String,
+        metadata: PasteMetadata,
+        editing_as: Option<FullUser<UserMetadata>>,
+        assertion: Option<WebAuthnAssertion>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Delete an existing paste by `url`
+    fn delete_paste_by_url(
+        &self,
+        url: String,
+        password: String,
+        assertion: Option<WebAuthnAssertion>,
+    ) -> impl Future<Output = Result<()>> + Send;
+}
+
+impl PasteManager for SqlPasteManager {
+    async fn get_paste_by_url(&self, url: String) -> Result<Paste> {
+        self.database.get_paste_by_url(url).await
+    }
+
+    async fn create_paste(&self, props: PasteCreate) -> Result<(String, Paste)> {
+        self.database.create_paste(props).await
+    }
+
+    async fn edit_paste_by_url(
+        &self,
+        url: String,
+        password: String,
+        edit_link: String,
+        new_content: String,
+        new_url: String,
+        new_password: String,
+        editing_as: Option<FullUser<UserMetadata>>,
+        assertion: Option<WebAuthnAssertion>,
+    ) -> Result<()> {
+        self.database
+            .edit_paste_by_url(
+                url,
+                password,
+                edit_link,
+                new_content,
+                new_url,
+                new_password,
+                editing_as,
+                assertion,
+            )
+            .await
+    }
+
+    async fn edit_paste_metadata_by_url(
+        &self,
+        url: String,
+        password: String,
+        metadata: PasteMetadata,
+        editing_as: Option<FullUser<UserMetadata>>,
+        assertion: Option<WebAuthnAssertion>,
+    ) -> Result<()> {
+        self.database
+            .edit_paste_metadata_by_url(url, password, metadata, editing_as, assertion)
+            .await
+    }
+
+    async fn delete_paste_by_url(
+        &self,
+        url: String,
+        password: String,
+        assertion: Option<WebAuthnAssertion>,
+    ) -> Result<()> {
+        self.database
+            .delete_paste_by_url(url, password, assertion)
+            .await
+    }
+}
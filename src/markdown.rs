@@ -0,0 +1,70 @@
+//! Built-in Markdown renderer (requires the `markdown` feature, see [`crate::database::Renderer::markdown`])
+//!
+//! Raw HTML in content is escaped instead of being passed through, and link and image
+//! destinations are limited to relative urls and `http(s)`/`mailto` urls, so the output is
+//! safe to put on a page without another sanitizer.
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+
+/// Url schemes links and images can use
+const ALLOWED_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+
+/// Render Markdown `content` to sanitized HTML
+pub fn render(content: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+    options.insert(Options::ENABLE_FOOTNOTES);
+
+    let events = Parser::new_ext(content, options).map(|event| match event {
+        // escape raw html
+        Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+        // check urls
+        Event::Start(Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => Event::Start(Tag::Link {
+            link_type,
+            dest_url: sanitize_url(dest_url),
+            title,
+            id,
+        }),
+        Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => Event::Start(Tag::Image {
+            link_type,
+            dest_url: sanitize_url(dest_url),
+            title,
+            id,
+        }),
+        event => event,
+    });
+
+    let mut out = String::new();
+    html::push_html(&mut out, events);
+    out
+}
+
+/// Replace urls with a scheme which isn't allowed (e.g. `javascript:`) with `#`
+fn sanitize_url(url: CowStr) -> CowStr {
+    // browsers ignore whitespace and control characters in schemes
+    let cleaned: String = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect();
+
+    let scheme = match cleaned.find([':', '/', '?', '#']) {
+        Some(i) if cleaned[i..].starts_with(':') => Some(cleaned[..i].to_lowercase()),
+        _ => None,
+    };
+
+    match scheme {
+        Some(s) if !ALLOWED_SCHEMES.contains(&s.as_str()) => CowStr::Borrowed("#"),
+        _ => url,
+    }
+}
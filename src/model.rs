@@ -1,10 +1,12 @@
 use axum::{
-    http::StatusCode,
+    http::{HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
 
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
 use dorsal::DefaultReturn;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -18,6 +20,34 @@ pub struct Paste {
     pub metadata: PasteMetadata,
 }
 
+/// A paste as returned by the API, without its edit password and view password hashes
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PublicPaste {
+    pub id: String,
+    pub url: String,
+    pub content: String,
+    pub date_published: u128,
+    pub date_edited: u128,
+    pub metadata: PasteMetadata,
+}
+
+impl From<Paste> for PublicPaste {
+    fn from(paste: Paste) -> Self {
+        let mut metadata = paste.metadata;
+        metadata.view_password = String::new();
+        metadata.view_password_hashed = false;
+
+        Self {
+            id: paste.id,
+            url: paste.url,
+            content: paste.content,
+            date_published: paste.date_published,
+            date_edited: paste.date_edited,
+            metadata,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 /// All of these fields are
 pub struct PasteMetadata {
@@ -26,91 +56,1282 @@ pub struct PasteMetadata {
     pub title: String,
     /// Paste page description
     #[serde(default)]
-    pub description: String,
-    /// Paste theme color
+    pub description: String,
+    /// Paste theme color
+    #[serde(default)]
+    pub theme_color: String,
+    /// Paste favicon link
+    #[serde(default)]
+    pub favicon: String,
+    /// Hash of the paste view password (can be disabled, left out of [`PublicPaste`])
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub view_password: String,
+    /// If `view_password` is hashed (older versions stored it in plaintext)
+    #[serde(default)]
+    pub view_password_hashed: bool,
+    /// Paste owner username
+    #[serde(default)]
+    pub owner: String,
+    /// If search engines should be asked not to index the paste
+    #[serde(default)]
+    pub noindex: bool,
+    /// IPFS CID of the paste's last pinned snapshot
+    #[serde(default)]
+    pub ipfs_cid: String,
+    /// URL the paste's content was fetched from
+    #[serde(default)]
+    pub source_url: String,
+    /// If the server's masked words should be left visible when rendering the paste
+    #[serde(default)]
+    pub unmasked: bool,
+    /// If the paste contains sensitive content that viewers must agree to see first
+    #[serde(default)]
+    pub sensitive: bool,
+    /// Paste tags
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Paste content language
+    #[serde(default)]
+    pub language: String,
+    /// How the paste's content is rendered: `log`, `stack_trace`, `csv`, `tsv` or `binary`
+    /// (see [`crate::validation::CONTENT_TYPES`]), or empty to detect it
+    #[serde(default)]
+    pub content_type: String,
+    /// MIME type binary pastes are downloaded as (`application/octet-stream` if empty)
+    #[serde(default)]
+    pub mime_type: String,
+    /// Size of a binary paste's decoded content in bytes (`0` for other pastes)
+    #[serde(default)]
+    pub size: usize,
+    /// Malware scan result of a binary paste: `pending`, `clean`, `infected` (quarantined) or
+    /// `failed` (empty if it wasn't scanned)
+    #[serde(default)]
+    pub scan_status: String,
+    /// Front matter parsed from the paste's content (`null` if there is none)
+    #[serde(default)]
+    pub front_matter: Value,
+    /// If the paste can't be deleted (only set by users with the "ManagePastes" permission)
+    #[serde(default)]
+    pub legal_hold: bool,
+    /// Plaintext excerpt of the paste's content (generated whenever its content is written)
+    #[serde(default)]
+    pub excerpt: String,
+    /// Checksums of the paste's raw content (computed whenever its content is written)
+    #[serde(default)]
+    pub checksums: PasteChecksums,
+    /// If deleting the paste or changing its passwords needs a passkey assertion from its owner
+    #[serde(default)]
+    pub high_security: bool,
+    /// Nonce the paste's signed urls are tied to (rotated to revoke them)
+    #[serde(default)]
+    pub signing_nonce: String,
+    /// If the paste can only be changed by users with the "ManagePastes" permission (set through `/api/admin/moderation`)
+    #[serde(default)]
+    pub locked: bool,
+    /// Timestamp the paste expires at (`0` for never)
+    #[serde(default)]
+    pub expires_at: u128,
+}
+
+impl Default for PasteMetadata {
+    fn default() -> Self {
+        Self {
+            title: String::new(),
+            description: String::new(),
+            theme_color: String::new(),
+            favicon: String::new(),
+            view_password: String::new(),
+            view_password_hashed: false,
+            owner: String::new(),
+            noindex: false,
+            ipfs_cid: String::new(),
+            source_url: String::new(),
+            unmasked: false,
+            sensitive: false,
+            tags: Vec::new(),
+            language: String::new(),
+            content_type: String::new(),
+            mime_type: String::new(),
+            size: 0,
+            scan_status: String::new(),
+            front_matter: Value::Null,
+            legal_hold: false,
+            excerpt: String::new(),
+            checksums: PasteChecksums::default(),
+            high_security: false,
+            signing_nonce: String::new(),
+            locked: false,
+            expires_at: 0,
+        }
+    }
+}
+
+/// Checksums of a paste's raw content, as lowercase hex (see `/api/:url/checksums`)
+///
+/// Binary pastes are hashed after they're decoded, so the checksums match downloads.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PasteChecksums {
+    #[serde(default)]
+    pub md5: String,
+    #[serde(default)]
+    pub sha1: String,
+    #[serde(default)]
+    pub sha256: String,
+    #[serde(default)]
+    pub blake3: String,
+}
+
+/// Basic serialized content storage for extra features that don't need their own table
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Document<T, M> {
+    // identifiers
+    pub id: String,
+    pub namespace: String,
+    // document;
+    pub content: T,
+    pub timestamp: u128,
+    pub metadata: M,
+}
+
+// props
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PasteCreate {
+    #[serde(default)]
+    pub url: String,
+    pub content: String,
+    #[serde(default)]
+    pub password: String,
+    /// If the creator accepts the terms of service (see [`crate::database::ServerOptions::tos_paste`])
+    #[serde(default)]
+    pub accept_tos: bool,
+    /// Username of the user creating the paste, set by the server for scripted rules (empty when anonymous)
+    #[serde(skip)]
+    pub author: String,
+    /// When the paste expires: `1h`, `1d`, `1w` or `never` (overrides `expires_at`)
+    #[serde(default)]
+    pub expires_in: String,
+    /// Timestamp the paste expires at (`0` for never)
+    #[serde(default)]
+    pub expires_at: u128,
+    /// Unhashed invite code, see [`Invite`]
+    #[serde(default)]
+    pub invite: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PasteCreateFromUrl {
+    /// The remote URL to fetch the content from
+    pub source: String,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub password: String,
+    #[serde(default)]
+    pub accept_tos: bool,
+    /// Username of the user creating the paste, set by the server for scripted rules (empty when anonymous)
+    #[serde(skip)]
+    pub author: String,
+}
+
+/// Query of `/api/new/binary` (the request body is the paste's content)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PasteCreateBinary {
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub password: String,
+    #[serde(default)]
+    pub accept_tos: bool,
+    /// MIME type of the content, set by the server from the request's `Content-Type`
+    #[serde(skip)]
+    pub mime_type: String,
+    /// Username of the user creating the paste, set by the server for scripted rules (empty when anonymous)
+    #[serde(skip)]
+    pub author: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PasteDelete {
+    pub password: String,
+    /// Passkey assertion from the owner (for high security pastes)
+    #[serde(default)]
+    pub assertion: Option<WebAuthnAssertion>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PasteEdit {
+    /// The paste's edit password (not needed with an edit link)
+    #[serde(default)]
+    pub password: String,
+    pub new_content: String,
+    #[serde(default)]
+    pub new_password: String,
+    #[serde(default)]
+    pub new_url: String,
+    /// Passkey assertion from the owner (for changing the password of high security pastes)
+    #[serde(default)]
+    pub assertion: Option<WebAuthnAssertion>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PasteEditMetadata {
+    pub password: String,
+    pub metadata: PasteMetadata,
+    /// Passkey assertion from the owner (for changing the view password, owner or
+    /// `high_security` of high security pastes)
+    #[serde(default)]
+    pub assertion: Option<WebAuthnAssertion>,
+}
+
+/// Query of `/api/:url/edit`
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct PasteEditQuery {
+    /// Token of an edit link (used instead of the password)
+    #[serde(default)]
+    pub t: String,
+}
+
+/// Body of `/api/:url/edit-link`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EditLinkCreate {
+    #[serde(default)]
+    pub password: String,
+    /// Milliseconds until the link expires (`0` for 7 days, at most 30 days)
+    #[serde(default)]
+    pub expires_in: u128,
+}
+
+/// Signed token allowing a paste's content to be edited without its password
+///
+/// Changing the paste's password revokes every edit link created before.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EditLink {
+    pub url: String,
+    /// Token to send as `t` to `/api/:url/edit`
+    pub token: String,
+    /// Timestamp the link expires at
+    pub expires: u128,
+}
+
+/// Body of `/api/:url/sign`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SignedUrlCreate {
+    #[serde(default)]
+    pub password: String,
+    /// Milliseconds until the url expires (`0` for 1 day, at most 30 days)
+    #[serde(default)]
+    pub expires_in: u128,
+}
+
+/// Time-limited url granting read access to a paste protected by a view password
+///
+/// Revoking the paste's signed urls (`/api/:url/sign/revoke`) invalidates every url
+/// created before.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SignedUrl {
+    pub url: String,
+    /// Signature to send as `sig`
+    pub sig: String,
+    /// Timestamp the url expires at (sent as `exp`)
+    pub exp: u128,
+    /// Query to append to the paste's routes (`?sig=...&exp=...`)
+    pub query: String,
+}
+
+/// Body of `/api/:url/sign/revoke`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SignedUrlRevoke {
+    #[serde(default)]
+    pub password: String,
+}
+
+/// Signature of a signed url (see [`SignedUrl`]), accepted by the routes reading a paste's content
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SignedQuery {
+    #[serde(default)]
+    pub sig: String,
+    /// Timestamp the url expires at
+    #[serde(default)]
+    pub exp: u64,
+}
+
+/// Body of `/api/:url/unlock`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PasteUnlock {
+    /// The paste's view password
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PastePin {
+    #[serde(default)]
+    pub password: String,
+}
+
+/// Token allowing pastes to be created through `/api/ingest/:token` without credentials
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IngestToken {
+    /// Hash of the token
+    pub id: String,
+    /// Prefix given to the url of every paste created with the token
+    pub prefix: String,
+    /// Timestamp the token expires at (`0` for never)
+    pub expires: u128,
+    /// Maximum content length of pastes created with the token (`0` for the default limit)
+    pub max_size: usize,
+    /// Username of the user who created the token
+    pub owner: String,
+    pub timestamp: u128,
+    /// Number of pastes created with the token
+    pub uses: usize,
+    /// Timestamp of the last paste created with the token (`0` for never)
+    pub last_used: u128,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct IngestTokenCreate {
+    #[serde(default)]
+    pub prefix: String,
+    /// Milliseconds until the token expires (`0` for never)
+    #[serde(default)]
+    pub expires_in: u128,
+    #[serde(default)]
+    pub max_size: usize,
+}
+
+/// Invite allowing guests to create a limited number of pastes with boosted limits
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Invite {
+    /// Hash of the invite code
+    pub id: String,
+    /// Username of the user who created the invite
+    pub owner: String,
+    /// Number of pastes which can still be created with the invite
+    pub remaining: usize,
+    /// Maximum content length of pastes created with the invite (`0` for the default limit)
+    pub max_size: usize,
+    /// Timestamp the invite expires at (`0` for never)
+    pub expires: u128,
+    pub timestamp: u128,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InviteCreate {
+    /// Number of pastes which can be created with the invite
+    pub uses: usize,
+    #[serde(default)]
+    pub max_size: usize,
+    /// Milliseconds until the invite expires (`0` for never)
+    #[serde(default)]
+    pub expires_in: u128,
+}
+
+/// Named, ordered list of pastes
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Collection {
+    pub id: String,
+    pub title: String,
+    /// Username of the user who created the collection
+    pub owner: String,
+    pub timestamp: u128,
+    /// Urls of the pastes in the collection, in order
+    pub pastes: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CollectionCreate {
+    pub title: String,
+}
+
+/// Body of `/api/collections/:id/add` and `/api/collections/:id/remove`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CollectionPaste {
+    pub url: String,
+}
+
+/// Position of a paste within a [`Collection`]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CollectionNavigation {
+    pub collection: String,
+    pub title: String,
+    /// Url of the previous paste in the collection
+    pub previous: Option<String>,
+    /// Url of the next paste in the collection
+    pub next: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DocumentCreate<T, M> {
+    pub namespace: String,
+    pub content: T,
+    pub metadata: M,
+}
+
+/// Body of `POST /api/docs/:namespace` (the namespace is taken from the path)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DocumentCreateBody<T, M> {
+    pub content: T,
+    pub metadata: M,
+}
+
+/// Body of `PUT /api/docs/:namespace/:id`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DocumentEdit<T, M> {
+    pub content: T,
+    #[serde(default)]
+    pub metadata: Option<M>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LintRequest {
+    pub content: String,
+}
+
+/// Query of `/api/:url`
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct PasteViewQuery {
+    /// Preferred language of the content (overrides `Accept-Language`)
+    #[serde(default)]
+    pub lang: String,
+    /// `1` to bypass (and refresh) the cached paste (requires the "ManagePastes" permission)
+    #[serde(default)]
+    pub nocache: u8,
+}
+
+/// Query of `/api/:url/html`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RenderQuery {
+    /// Return the paste's content as plain text instead (same as `/api/:url/raw`)
+    #[serde(default)]
+    pub raw: bool,
+    /// Column to sort CSV and TSV pastes by (`col2`, or `-col2` for descending)
+    #[serde(default)]
+    pub sort: String,
+    /// Agree to view sensitive pastes for the rest of the session (same as `/api/consent`)
+    #[serde(default)]
+    pub consent: bool,
+}
+
+/// Body of `/api/admin/cache/purge`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CachePurge {
+    /// Cache keys to remove (a `*` matches any characters, e.g. `se_paste:*`)
+    pub patterns: Vec<String>,
+}
+
+/// Filters of `/api/admin/moderation` (pastes have to match every filter given, and at
+/// least one has to be given)
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ModerationFilter {
+    /// Username of the pastes' owner
+    #[serde(default)]
+    pub owner: String,
+    /// IP address the pastes were created from (compared by its hash, see
+    /// [`crate::database::ServerOptions::moderation`])
+    #[serde(default)]
+    pub ip: String,
+    /// Tag the pastes have
+    #[serde(default)]
+    pub tag: String,
+    /// Regular expression matching the pastes' content
+    #[serde(default)]
+    pub content: String,
+    /// Only match pastes created at or after this timestamp (`0` for any)
+    #[serde(default)]
+    pub created_after: u128,
+    /// Only match pastes created at or before this timestamp (`0` for any)
+    #[serde(default)]
+    pub created_before: u128,
+}
+
+/// What `/api/admin/moderation` does with the pastes it matches
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ModerationAction {
+    /// Delete the pastes (pastes under a legal hold are skipped)
+    Delete,
+    /// Lock the pastes (see [`PasteMetadata::locked`])
+    Lock,
+    /// Unlock the pastes
+    Unlock,
+}
+
+/// Body of `/api/admin/moderation`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ModerationRequest {
+    pub action: ModerationAction,
+    pub filter: ModerationFilter,
+}
+
+/// Pastes a moderation filter matches (see `/api/admin/moderation/preview`)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ModerationPreview {
+    /// Urls of the matched pastes, newest first
+    pub urls: Vec<String>,
+}
+
+/// Background job started by `/api/admin/moderation` (polled through `/api/admin/moderation/:id`)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ModerationJob {
+    pub id: String,
+    pub action: ModerationAction,
+    /// `matching`, `running` or `finished`
+    pub status: String,
+    /// Number of pastes matched by the filter
+    pub matched: usize,
+    /// Number of matched pastes the action was applied to
+    pub processed: usize,
+    /// Number of matched pastes the action failed for (e.g. pastes under a legal hold)
+    pub failed: usize,
+    /// Username of the user who started the job
+    pub started_by: String,
+    /// Timestamp the job was started at
+    pub started: u128,
+    /// Timestamp the job finished at (`0` while it's running)
+    pub finished: u128,
+}
+
+/// Body of `/api/admin/checkpoint`
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct CheckpointRequest {
+    /// `passive` (default), `full`, `restart` or `truncate`
+    #[serde(default)]
+    pub mode: String,
+}
+
+/// Result of a SQLite WAL checkpoint
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Checkpoint {
+    /// If the checkpoint couldn't finish because of other connections
+    pub busy: bool,
+    /// Frames in the write-ahead log (`-1` when not in WAL mode)
+    pub log_frames: i64,
+    /// Frames moved into the database file (`-1` when not in WAL mode)
+    pub checkpointed_frames: i64,
+}
+
+/// A paste being written, sent to the mutation log (see [`crate::audit`])
+///
+/// Passwords are never included, and content only when
+/// [`crate::database::AuditLogOptions::include_content`] is set.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuditRecord {
+    /// `create`, `edit`, `append`, `edit_metadata`, `delete`, `expire` or `audit_dropped`
+    pub action: String,
+    pub url: String,
+    /// New url of a renamed paste (empty if it wasn't renamed)
+    pub new_url: String,
+    /// Username of the user who made the change (empty for anonymous users)
+    pub actor: String,
+    pub timestamp: u128,
+    /// Length of the paste's new content in bytes (`0` if the content didn't change)
+    pub content_length: usize,
+    /// Hash of the paste's new content (empty if the content didn't change)
+    pub content_hash: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    /// Number of records dropped because the log's buffer was full (for `audit_dropped`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dropped: Option<usize>,
+}
+
+/// Limits and features of an instance, so clients can adapt to it (`/api/meta`)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InstanceMeta {
+    /// Version of pastemd the instance is running
+    pub version: String,
+    pub limits: InstanceLimits,
+    /// Names of the enabled [`crate::database::ServerOptions`] (e.g. `search`)
+    pub features: Vec<String>,
+    /// Values accepted for `expires_in` when creating a paste (empty if pastes can't expire)
+    pub expiry_options: Vec<String>,
+    /// Languages pastes can be highlighted as (empty if highlighting is disabled)
+    pub languages: Vec<HighlightLanguage>,
+}
+
+/// Length limits of an instance (see [`crate::validation`])
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InstanceLimits {
+    pub url_min_length: usize,
+    pub url_max_length: usize,
+    /// Regex paste urls must match
+    pub url_pattern: String,
+    /// Longest paste content allowed (invites can raise this)
+    pub content_max_length: usize,
+    pub title_max_length: usize,
+    pub description_max_length: usize,
+    pub max_tags: usize,
+    pub tag_max_length: usize,
+}
+
+/// A language pastes can be highlighted as
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HighlightLanguage {
+    pub name: String,
+    /// File extensions of the language (usable as `metadata.language`, like its name)
+    pub extensions: Vec<String>,
+}
+
+/// Query of `/api/:url/query`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DataQuery {
+    /// A JSON pointer (`/items/0/name`) or JSONPath (`$.items[0].name`)
+    #[serde(default)]
+    pub path: String,
+}
+
+/// Query of `/api/:url/table`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TableQuery {
+    /// Column to sort rows by (`col2`, or `-col2` for descending)
+    #[serde(default)]
+    pub sort: String,
+    /// Format of the table (only `json` is supported)
+    #[serde(default)]
+    pub format: String,
+}
+
+/// A CSV or TSV paste parsed into rows (see [`crate::table`])
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PasteTable {
+    /// Names of the table's columns (`col1`, `col2`, and so on if the table has no header)
+    pub columns: Vec<String>,
+    /// Rows of the table (every row has a field for every column)
+    pub rows: Vec<Vec<String>>,
+}
+
+/// Body of `/api/detect`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DetectRequest {
+    pub content: String,
+}
+
+/// What kind of content a paste seems to be (see [`crate::detect`])
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Detection {
+    /// `markdown`, `csv`, `tsv`, `code`, `log`, `stack_trace` or `text`
+    pub content_type: String,
+    /// Guessed language, usable as `metadata.language` (empty if unknown)
+    pub language: String,
+    /// If the content seems to contain secrets (private keys, tokens or passwords)
+    pub secrets: bool,
+    /// If most lines look like log entries
+    pub log: bool,
+    /// If the content contains a stack trace
+    pub stack_trace: bool,
+    pub suggestions: DetectionSuggestions,
+}
+
+/// Metadata suggested for content by [`crate::detect::detect`]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DetectionSuggestions {
+    /// The first heading, or the first line (empty if the content seems to contain secrets)
+    pub title: String,
+    pub tags: Vec<String>,
+}
+
+/// One check made by [`crate::database::Database::doctor`]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DoctorCheck {
+    /// What was checked (e.g. `table se_pastes`)
+    pub name: String,
+    pub ok: bool,
+    /// How to fix the problem (empty if the check passed)
+    pub remediation: String,
+}
+
+/// Result of [`crate::database::Database::doctor`]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DoctorReport {
+    /// If every check passed
+    pub ok: bool,
+    pub checks: Vec<DoctorCheck>,
+}
+
+/// Portable copy of every table of an instance (see [`crate::database::Database::dump`])
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Dump {
+    /// Always `pastemd-v1`
+    pub format: String,
+    /// Database type the dump was made from
+    pub source: String,
+    pub timestamp: u128,
+    pub tables: Vec<DumpTable>,
+}
+
+/// Every row of a table in a [`Dump`]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DumpTable {
+    pub name: String,
+    /// Column names, sorted
+    pub columns: Vec<String>,
+    /// Values of every row in the order of `columns`, sorted
+    pub rows: Vec<Vec<String>>,
+    /// Hash of `rows`, checked when the dump is loaded
+    pub checksum: String,
+}
+
+/// Translated variant of a paste's content
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PasteTranslation {
+    pub url: String,
+    /// Language tag, e.g. `fr` or `pt-br`
+    pub lang: String,
+    pub content: String,
+    pub timestamp: u128,
+}
+
+/// Body of `/api/:url/translations/:lang`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TranslationEdit {
+    /// The paste's edit password (can be empty if the request is made as the paste's owner)
+    #[serde(default)]
+    pub password: String,
+    pub content: String,
+}
+
+/// Query of `/api/suggest-url`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UrlSuggestionQuery {
+    pub title: String,
+}
+
+/// Query of `/api/search`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SearchQuery {
+    pub q: String,
+    /// Maximum number of results (up to `50`, `0` for `10`)
+    #[serde(default)]
+    pub limit: usize,
+    /// Number of results to skip
+    #[serde(default)]
+    pub offset: usize,
+}
+
+/// Options of [`crate::database::Database::search_pastes`]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SearchOptions {
+    /// Maximum number of results (up to `50`, `0` for `10`)
+    #[serde(default)]
+    pub limit: usize,
+    /// Number of results to skip
+    #[serde(default)]
+    pub offset: usize,
+}
+
+/// A paste found by [`crate::database::Database::search_pastes`]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SearchResult {
+    pub url: String,
+    pub title: String,
+    pub excerpt: String,
+    /// HTML-escaped content around the first match, with matched words wrapped in `<mark>` tags
+    pub snippet: String,
+    pub date_published: u128,
+    pub date_edited: u128,
+}
+
+/// Query of `/api/pastes` and `/api/owner/:username/pastes`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PasteListQuery {
+    /// Page number (starting at `0`)
+    #[serde(default)]
+    pub page: usize,
+}
+
+/// A paste listed by [`crate::database::Database::get_pastes_by_owner`] or
+/// [`crate::database::Database::get_recent_public_pastes`]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PasteListing {
+    pub url: String,
+    pub title: String,
+    /// Paste excerpt (empty for pastes with a view password)
+    pub excerpt: String,
+    pub owner: String,
+    pub date_published: u128,
+    pub date_edited: u128,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PreviewRequest {
+    pub content: String,
+}
+
+/// A single problem found by [`crate::lint::lint`]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LintDiagnostic {
+    /// Line number (starting at 1)
+    pub line: usize,
+    /// Column number (starting at 1)
+    pub column: usize,
+    /// Name of the rule that found the problem
+    pub rule: String,
+    pub message: String,
+}
+
+/// Kind of change made to a line in a [`DiffLine`]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+/// A single line of a diff made by [`crate::diff::diff_lines`]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DiffLine {
+    pub op: DiffOp,
+    pub content: String,
+}
+
+/// Proposed edit to a paste, waiting to be accepted or rejected by its owner
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Suggestion {
+    pub id: String,
+    /// Url of the paste the suggestion is for
+    pub url: String,
+    /// Suggested content of the paste
+    pub content: String,
+    /// Username of the user who made the suggestion (empty if they weren't logged in)
+    pub author: String,
+    pub timestamp: u128,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SuggestionCreate {
+    pub content: String,
+}
+
+/// Body of `/api/:url/suggestions/:id/accept` and `/api/:url/suggestions/:id/reject` (and
+/// query of `/api/:url/suggestions` and `/api/:url/suggestions/:id`)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SuggestionReview {
+    /// The paste's edit password (can be empty if the request is made as the paste's owner)
+    #[serde(default)]
+    pub password: String,
+}
+
+/// A [`Suggestion`] and its diff against the current content of the paste
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SuggestionDiff {
+    pub suggestion: Suggestion,
+    pub diff: Vec<DiffLine>,
+}
+
+/// Comment attached to a range of lines in a paste
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Annotation {
+    pub id: String,
+    /// Url of the paste the annotation is on
+    pub url: String,
+    /// First line of the range (starting at 1)
+    pub line_start: usize,
+    /// Last line of the range (inclusive)
+    pub line_end: usize,
+    pub content: String,
+    /// Username of the user who made the annotation (empty if they weren't logged in)
+    pub author: String,
+    pub timestamp: u128,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AnnotationCreate {
+    pub line_start: usize,
+    /// Defaults to `line_start`
     #[serde(default)]
-    pub theme_color: String,
-    /// Paste favicon link
+    pub line_end: usize,
+    pub content: String,
+}
+
+/// Body of `/api/:url/annotations/:id/delete`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AnnotationDelete {
+    /// The paste's edit password (can be empty if the request is made as the annotation's author or the paste's owner)
     #[serde(default)]
-    pub favicon: String,
-    /// Paste view password (can be disabled)
+    pub password: String,
+}
+
+/// Output of a runnable code block (see [`crate::notebook`])
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BlockOutput {
+    /// Position of the block among the runnable blocks of the paste
+    pub index: usize,
+    pub language: String,
+    /// Hash of the language and code the output was made from
+    pub hash: String,
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_code: i32,
+    /// Why the block couldn't be run (empty if it was)
+    pub error: String,
+}
+
+/// Last known status of a link in a paste (see [`crate::links`])
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LinkStatus {
+    pub link: String,
+    /// HTTP status code returned by the link (`0` if it couldn't be reached)
+    pub status: u16,
+    /// If the link responded with a non-error status
+    pub ok: bool,
+    /// Why the link couldn't be reached (empty if it was)
+    pub error: String,
+}
+
+/// Result of checking every outbound link in a paste
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LinkReport {
+    pub url: String,
+    pub links: Vec<LinkStatus>,
+    /// Timestamp the links were last checked at
+    pub checked: u128,
+}
+
+/// Result of scanning a binary paste for malware
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScanReport {
+    pub url: String,
+    /// `clean`, `infected` or `failed` (see [`PasteMetadata::scan_status`])
+    pub status: String,
+    /// Name of the malware found (empty if none was)
+    pub signature: String,
+    /// Timestamp the paste was scanned at
+    pub scanned: u128,
+}
+
+/// Body of `/api/:url/rotation`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RotationCreate {
     #[serde(default)]
-    pub view_password: String,
-    /// Paste owner username
+    pub password: String,
+    /// Where the paste's content is replaced from: an `http(s)` url (requires the `fetch`
+    /// feature) or `docs:<namespace>` for the newest document in a namespace
+    pub source: String,
+    /// Milliseconds between rotations (at least a minute, at most 30 days)
+    pub interval: u128,
+}
+
+/// Body of `/api/:url/rotation/delete`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RotationDelete {
     #[serde(default)]
-    pub owner: String,
+    pub password: String,
 }
 
-impl Default for PasteMetadata {
-    fn default() -> Self {
-        Self {
-            title: String::new(),
-            description: String::new(),
-            theme_color: String::new(),
-            favicon: String::new(),
-            view_password: String::new(),
-            owner: String::new(),
-        }
-    }
+/// Schedule replacing a paste's content from a source (see [`crate::database::ServerOptions::rotations`])
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Rotation {
+    pub url: String,
+    /// See [`RotationCreate::source`]
+    pub source: String,
+    /// Milliseconds between rotations
+    pub interval: u128,
+    /// Timestamp the paste is rotated next at
+    pub next_run: u128,
 }
 
-/// Basic serialized content storage for extra features that don't need their own table
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct Document<T, M> {
-    // identifiers
-    pub id: String,
-    pub namespace: String,
-    // document;
-    pub content: T,
+/// Content a rotated paste had before it was replaced
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Revision {
+    pub content: String,
+    /// Timestamp the content was replaced at
     pub timestamp: u128,
-    pub metadata: M,
 }
 
-// props
+/// Body of `/api/:url/claim`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PasteClaim {
+    /// The paste's edit password
+    pub password: String,
+}
 
+/// Body of `/api/:url/transfer`
 #[derive(Serialize, Deserialize, Debug)]
-pub struct PasteCreate {
+pub struct PasteTransfer {
+    /// Username of the new owner
+    pub owner: String,
+    /// Passkey assertion from the owner (for high security pastes)
+    #[serde(default)]
+    pub assertion: Option<WebAuthnAssertion>,
+}
+
+/// What `/api/import` does with pastes whose url is already taken
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportConflict {
+    /// Leave the existing paste alone and don't import the paste
+    Skip,
+    /// Import the paste at a new url
+    Rename,
+    /// Replace the existing paste's content and metadata (only if it's owned by the importing user)
+    Overwrite,
+}
+
+impl Default for ImportConflict {
+    fn default() -> Self {
+        Self::Skip
+    }
+}
+
+/// Query of `/api/import`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ImportQuery {
     #[serde(default)]
+    pub on_conflict: ImportConflict,
+}
+
+/// A paste in the body of `/api/import`
+///
+/// Pastes from `/api/owner/:username/export` are accepted as they are, as are Hastebin-style
+/// (`key` and `data`) and Pastebin-style (`paste_key`, `paste_title` and `paste_content`) dumps.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ImportedPaste {
+    /// Url of the paste (a random url is used if it's empty)
+    #[serde(default, alias = "key", alias = "paste_key")]
     pub url: String,
+    #[serde(alias = "data", alias = "paste_content")]
     pub content: String,
+    /// Title of the paste (used if `metadata` has none)
+    #[serde(default, alias = "paste_title")]
+    pub title: String,
+    #[serde(default)]
+    pub date_published: u128,
     #[serde(default)]
+    pub metadata: PasteMetadata,
+}
+
+/// What happened to a paste given to `/api/import`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImportResult {
+    /// Url of the paste in the import
+    pub url: String,
+    /// Url the paste was imported at (empty if it was skipped)
+    pub new_url: String,
+    /// `created`, `renamed`, `overwritten` or `skipped`
+    pub status: String,
+    /// Unhashed edit password of created pastes (overwritten pastes keep theirs)
     pub password: String,
 }
 
+/// Body of `/api/:url/hold`
 #[derive(Serialize, Deserialize, Debug)]
-pub struct PasteDelete {
+pub struct PasteHold {
+    pub legal_hold: bool,
+}
+
+/// Body of `/api/:url/run`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PasteRun {
+    /// The paste's edit password (can be empty if the request is made as the paste's owner)
+    #[serde(default)]
     pub password: String,
 }
 
+/// Record of a watermarked copy of a paste (see [`crate::watermark`])
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Download {
+    /// Id embedded into the copy
+    pub id: String,
+    pub url: String,
+    /// Username of the user who fetched the copy (empty if they weren't logged in)
+    pub username: String,
+    pub timestamp: u128,
+}
+
+/// Body of `/api/:url/trace`
 #[derive(Serialize, Deserialize, Debug)]
-pub struct PasteEdit {
+pub struct WatermarkTrace {
+    /// The leaked content
+    pub content: String,
+    /// The paste's edit password (can be empty if the request is made as the paste's owner)
+    #[serde(default)]
     pub password: String,
-    pub new_content: String,
+}
+
+/// Instance-wide announcement shown on every page
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Announcement {
+    pub id: String,
+    pub content: String,
+    /// Timestamp the announcement is shown from (`0` for immediately)
+    pub starts: u128,
+    /// Timestamp the announcement stops being shown at (`0` for never)
+    pub ends: u128,
+    pub timestamp: u128,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AnnouncementCreate {
+    pub content: String,
     #[serde(default)]
-    pub new_password: String,
+    pub starts: u128,
     #[serde(default)]
-    pub new_url: String,
+    pub ends: u128,
 }
 
+/// A paste pinned to the instance front page
 #[derive(Serialize, Deserialize, Debug)]
-pub struct PasteEditMetadata {
-    pub password: String,
-    pub metadata: PasteMetadata,
+pub struct PinnedPaste {
+    pub url: String,
+    /// Paste title (the paste's url if it doesn't have one)
+    pub title: String,
+    /// Paste description, or its excerpt (empty for pastes with a view password)
+    pub excerpt: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-pub struct DocumentCreate<T, M> {
+pub struct PinnedEdit {
+    /// Urls of the pastes to pin, in order (replaces the current list)
+    pub urls: Vec<String>,
+}
+
+/// View statistics of a paste
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PasteStats {
+    /// Total views
+    pub views: i32,
+    /// Views by ISO country code (`ZZ` for unknown), only counted when GeoIP is enabled
+    pub countries: HashMap<String, i32>,
+}
+
+/// Current usage of a [`Document`] namespace
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NamespaceUsage {
     pub namespace: String,
-    pub content: T,
-    pub metadata: M,
+    /// Number of documents in the namespace
+    pub documents: usize,
+    /// Maximum number of documents in the namespace (`0` for unlimited)
+    pub max_documents: usize,
+    /// Maximum length of a single document's content in bytes (`0` for unlimited)
+    pub max_content_bytes: usize,
+}
+
+/// Signed response of `navigator.credentials.get` (every field is base64url)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WebAuthnAssertion {
+    pub credential_id: String,
+    pub client_data_json: String,
+    pub authenticator_data: String,
+    pub signature: String,
+}
+
+/// A passkey registered by a user (see [`PasteMetadata::high_security`])
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WebAuthnCredential {
+    /// Credential id (base64url)
+    pub id: String,
+    /// Username of the user who registered the credential
+    pub username: String,
+    /// DER-encoded P-256 public key (base64url)
+    pub public_key: String,
+    /// Last signature counter sent by the authenticator
+    pub sign_count: u32,
+    pub timestamp: u128,
+}
+
+/// Body of `/api/auth/webauthn/credentials`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WebAuthnCredentialCreate {
+    /// Credential id (base64url)
+    pub id: String,
+    /// Result of `getPublicKey()` (base64url)
+    pub public_key: String,
+    /// Result of `getPublicKeyAlgorithm()` (only `-7` is supported)
+    pub algorithm: i64,
+}
+
+/// Body of `/api/auth/webauthn/credentials/:id/delete`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WebAuthnCredentialDelete {
+    /// Assertion from any of the user's credentials
+    pub assertion: WebAuthnAssertion,
+}
+
+/// A single invalid field, returned as part of [`PasteError::Validation`]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FieldError {
+    /// Path to the invalid field (e.g. `content/items/0`)
+    pub field: String,
+    /// What's wrong with the field
+    pub error: String,
+    /// Smallest allowed length (for `too_short` errors)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<usize>,
+    /// Largest allowed length or count (for `too_long` and `too_many` errors)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<usize>,
+}
+
+/// Response of API version 2 (requested with `Accept-Version: 2`)
+///
+/// Version 1 responses are [`DefaultReturn`]s.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ApiResponse<T> {
+    /// Response payload (`null` for errors)
+    pub data: Option<T>,
+    /// What went wrong (`null` for successful responses)
+    pub error: Option<ApiError>,
+    pub meta: ApiMeta,
+}
+
+/// Error of an [`ApiResponse`]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ApiError {
+    /// Machine-readable name of the error (see [`PasteError::code`])
+    pub code: String,
+    pub message: String,
+    /// Invalid fields (for `validation` errors)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldError>,
+}
+
+/// Metadata of an [`ApiResponse`]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ApiMeta {
+    pub version: u8,
+    pub message: String,
+    /// Position in the list (only for paginated routes)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pagination: Option<Pagination>,
+}
+
+/// Pagination metadata of an [`ApiResponse`]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Pagination {
+    /// Page number (starting at `0`)
+    pub page: usize,
+    pub page_size: usize,
+    /// If there may be another page after this one
+    pub has_more: bool,
 }
 
 /// General API errors
 pub enum PasteError {
     PasswordIncorrect,
+    /// A view password is needed but none was given
+    PasswordRequired,
     AlreadyExists,
+    /// A paste with the url already exists
+    UrlTaken,
     ValueError,
+    /// Content is longer than allowed
+    ContentTooLarge,
     NotFound,
+    NotAllowed,
+    QuotaExceeded,
+    Validation(Vec<FieldError>),
+    RateLimited,
+    Overloaded,
+    TosNotAccepted,
+    GeoBlocked,
+    ChangeOwner,
+    WebAuthnRequired,
+    /// The user is banned (their level has the "Banned" permission)
+    Banned,
+    /// Rejected by a plugin, with its reason
+    Rejected(String),
     Other,
 }
 
@@ -119,18 +1340,100 @@ impl PasteError {
         use crate::model::PasteError::*;
         match self {
             PasswordIncorrect => String::from("The given password is invalid."),
-            AlreadyExists => String::from("A paste with this URL already exists."),
+            PasswordRequired => String::from("A password is required to view this paste."),
+            AlreadyExists => String::from("This already exists."),
+            UrlTaken => String::from("A paste with this URL already exists."),
             ValueError => String::from("One of the field values given is invalid."),
+            ContentTooLarge => String::from("The given content is too long."),
             NotFound => String::from("No paste with this URL has been found."),
+            NotAllowed => String::from("You are not allowed to do this."),
+            QuotaExceeded => String::from("This namespace has reached its document limit."),
+            Validation(_) => String::from("One or more fields failed validation."),
+            RateLimited => String::from("Too many requests, please try again later."),
+            Overloaded => String::from("The server is too busy, please try again later."),
+            TosNotAccepted => String::from("You must accept the terms of service to do this."),
+            GeoBlocked => String::from("This is not available in your region."),
+            ChangeOwner => String::from("You are not allowed to change the owner of this paste."),
+            WebAuthnRequired => String::from("A valid passkey assertion is required to do this."),
+            Banned => String::from("You are banned from doing this."),
+            Rejected(reason) => {
+                if reason.is_empty() {
+                    String::from("This was rejected by the server.")
+                } else {
+                    reason.to_string()
+                }
+            }
             _ => String::from("An unspecified error has occured"),
         }
     }
+
+    /// Machine-readable name of the error (e.g. `not_found`)
+    pub fn code(&self) -> &'static str {
+        use crate::model::PasteError::*;
+        match self {
+            PasswordIncorrect => "password_incorrect",
+            PasswordRequired => "password_required",
+            AlreadyExists => "already_exists",
+            UrlTaken => "url_taken",
+            ValueError => "value_error",
+            ContentTooLarge => "content_too_large",
+            NotFound => "not_found",
+            NotAllowed => "not_allowed",
+            QuotaExceeded => "quota_exceeded",
+            Validation(_) => "validation",
+            RateLimited => "rate_limited",
+            Overloaded => "overloaded",
+            TosNotAccepted => "tos_not_accepted",
+            GeoBlocked => "geo_blocked",
+            ChangeOwner => "change_owner",
+            WebAuthnRequired => "webauthn_required",
+            Banned => "banned",
+            Rejected(_) => "rejected",
+            Other => "other",
+        }
+    }
+
+    /// Get the error with the given [`PasteError::code`]
+    ///
+    /// Errors carrying data ([`PasteError::Validation`] and [`PasteError::Rejected`]) are
+    /// returned empty.
+    pub fn from_code(code: &str) -> Option<Self> {
+        use crate::model::PasteError::*;
+        Some(match code {
+            "password_incorrect" => PasswordIncorrect,
+            "password_required" => PasswordRequired,
+            "already_exists" => AlreadyExists,
+            "url_taken" => UrlTaken,
+            "value_error" => ValueError,
+            "content_too_large" => ContentTooLarge,
+            "not_found" => NotFound,
+            "not_allowed" => NotAllowed,
+            "quota_exceeded" => QuotaExceeded,
+            "validation" => Validation(Vec::new()),
+            "rate_limited" => RateLimited,
+            "overloaded" => Overloaded,
+            "tos_not_accepted" => TosNotAccepted,
+            "geo_blocked" => GeoBlocked,
+            "change_owner" => ChangeOwner,
+            "webauthn_required" => WebAuthnRequired,
+            "banned" => Banned,
+            "rejected" => Rejected(String::new()),
+            "other" => Other,
+            _ => return None,
+        })
+    }
 }
 
+/// [`PasteError::code`] of an error response, kept in the response's extensions
+#[derive(Clone, Copy, Debug)]
+pub struct ErrorCode(pub &'static str);
+
 impl IntoResponse for PasteError {
     fn into_response(self) -> Response {
         use crate::model::PasteError::*;
-        match self {
+        let code = ErrorCode(self.code());
+
+        let mut res = match self {
             PasswordIncorrect => (
                 StatusCode::UNAUTHORIZED,
                 Json(DefaultReturn::<u16> {
@@ -140,7 +1443,7 @@ impl IntoResponse for PasteError {
                 }),
             )
                 .into_response(),
-            AlreadyExists => (
+            AlreadyExists | QuotaExceeded | TosNotAccepted | Rejected(_) => (
                 StatusCode::BAD_REQUEST,
                 Json(DefaultReturn::<u16> {
                     success: false,
@@ -149,6 +1452,79 @@ impl IntoResponse for PasteError {
                 }),
             )
                 .into_response(),
+            NotAllowed | WebAuthnRequired | PasswordRequired => (
+                StatusCode::UNAUTHORIZED,
+                Json(DefaultReturn::<u16> {
+                    success: false,
+                    message: self.to_string(),
+                    payload: 401,
+                }),
+            )
+                .into_response(),
+            Validation(ref errors) => (
+                StatusCode::BAD_REQUEST,
+                Json(DefaultReturn::<Vec<FieldError>> {
+                    success: false,
+                    message: self.to_string(),
+                    payload: errors.clone(),
+                }),
+            )
+                .into_response(),
+            RateLimited => (
+                StatusCode::TOO_MANY_REQUESTS,
+                Json(DefaultReturn::<u16> {
+                    success: false,
+                    message: self.to_string(),
+                    payload: 429,
+                }),
+            )
+                .into_response(),
+            Overloaded => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                [("Retry-After", "1")],
+                Json(DefaultReturn::<u16> {
+                    success: false,
+                    message: self.to_string(),
+                    payload: 503,
+                }),
+            )
+                .into_response(),
+            GeoBlocked => (
+                StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS,
+                Json(DefaultReturn::<u16> {
+                    success: false,
+                    message: self.to_string(),
+                    payload: 451,
+                }),
+            )
+                .into_response(),
+            ChangeOwner | Banned => (
+                StatusCode::FORBIDDEN,
+                Json(DefaultReturn::<u16> {
+                    success: false,
+                    message: self.to_string(),
+                    payload: 403,
+                }),
+            )
+                .into_response(),
+            UrlTaken => (
+                StatusCode::CONFLICT,
+                Json(DefaultReturn::<u16> {
+                    success: false,
+                    message: self.to_string(),
+                    payload: 409,
+                }),
+            )
+                .into_response(),
+            ContentTooLarge => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                Json(DefaultReturn::<u16> {
+                    success: false,
+                    message: self.to_string(),
+                    payload: 413,
+                }),
+            )
+                .into_response(),
             NotFound => (
                 StatusCode::NOT_FOUND,
                 Json(DefaultReturn::<u16> {
@@ -167,6 +1543,14 @@ impl IntoResponse for PasteError {
                 }),
             )
                 .into_response(),
+        };
+
+        // used for the error object of versioned responses
+        if let Ok(value) = HeaderValue::from_str(code.0) {
+            res.headers_mut().insert("X-Error-Code", value);
         }
+
+        res.extensions_mut().insert(code);
+        res
     }
 }
@@ -0,0 +1,61 @@
+//! Write-time content normalization (see [`crate::database::ServerOptions::normalization`])
+use crate::database::Normalization;
+
+/// Normalize `content`
+///
+/// ## Returns:
+/// * the normalized content and the names of the steps which changed it
+///   (`line_endings`, `trailing_whitespace`, `blank_lines`)
+pub fn normalize(content: &str, options: &Normalization) -> (String, Vec<String>) {
+    let mut out = content.to_string();
+    let mut changed: Vec<String> = Vec::new();
+
+    // line endings
+    if (options.line_endings == true) && out.contains('\r') {
+        out = out.replace("\r\n", "\n").replace('\r', "\n");
+        changed.push(String::from("line_endings"));
+    }
+
+    // trailing whitespace
+    if options.trim_trailing_whitespace == true {
+        let trimmed = out
+            .split('\n')
+            .map(|l| l.trim_end_matches([' ', '\t']))
+            .collect::<Vec<&str>>()
+            .join("\n");
+
+        if trimmed != out {
+            out = trimmed;
+            changed.push(String::from("trailing_whitespace"));
+        }
+    }
+
+    // blank lines
+    if options.max_blank_lines != 0 {
+        let mut collapsed: Vec<&str> = Vec::new();
+        let mut blank: usize = 0;
+
+        for line in out.split('\n') {
+            if line.trim().is_empty() {
+                blank += 1;
+
+                if blank > options.max_blank_lines {
+                    continue;
+                }
+            } else {
+                blank = 0;
+            }
+
+            collapsed.push(line);
+        }
+
+        let collapsed = collapsed.join("\n");
+
+        if collapsed != out {
+            out = collapsed;
+            changed.push(String::from("blank_lines"));
+        }
+    }
+
+    (out, changed)
+}
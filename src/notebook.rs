@@ -0,0 +1,69 @@
+//! Runnable code blocks (fenced code blocks with a `run` attribute, e.g. ` ```python run `)
+
+/// A runnable code block found in paste content
+#[derive(Clone, Debug, PartialEq)]
+pub struct CodeBlock {
+    /// Position of the block among the runnable blocks of the paste (starting at 0)
+    pub index: usize,
+    pub language: String,
+    pub code: String,
+}
+
+/// Get all runnable code blocks in `content`
+///
+/// Blocks without a language, or without `run` in their info string, are skipped.
+pub fn runnable_blocks(content: &str) -> Vec<CodeBlock> {
+    let mut out: Vec<CodeBlock> = Vec::new();
+    let mut current: Option<(String, String, bool)> = None; // (fence, language, runnable)
+    let mut code: Vec<&str> = Vec::new();
+
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+
+        match current {
+            Some((ref fence, ref language, runnable)) => {
+                if trimmed.starts_with(fence.as_str())
+                    && trimmed
+                        .trim_end()
+                        .chars()
+                        .all(|c| c == fence.chars().next().unwrap())
+                {
+                    if runnable {
+                        out.push(CodeBlock {
+                            index: out.len(),
+                            language: language.clone(),
+                            code: code.join("\n"),
+                        });
+                    }
+
+                    current = None;
+                    code.clear();
+                } else {
+                    code.push(line);
+                }
+            }
+            None => {
+                if line.len() - trimmed.len() > 3 {
+                    continue;
+                }
+
+                for marker in ['`', '~'] {
+                    let length = trimmed.chars().take_while(|c| *c == marker).count();
+
+                    if length < 3 {
+                        continue;
+                    }
+
+                    let mut info = trimmed[length..].split_whitespace();
+                    let language = info.next().unwrap_or("").to_string();
+                    let runnable = !language.is_empty() && info.any(|a| a == "run");
+
+                    current = Some((marker.to_string().repeat(length), language, runnable));
+                    break;
+                }
+            }
+        }
+    }
+
+    out
+}
@@ -0,0 +1,260 @@
+//! Experimental WASM plugins (requires the `plugins` feature, see [`crate::database::ServerOptions::plugins`])
+//!
+//! Plugins are run in a sandbox with limited fuel and memory, and can't reach anything but
+//! the host API below. Every hook call gets a fresh instance of the plugin, so plugins can't
+//! keep state between calls. A plugin which fails to load, traps or runs out of fuel is skipped.
+//!
+//! Plugins must export their `memory` and `alloc(len: i32) -> i32`, and can export any of
+//! these hooks, which are called with the pointer and length of a JSON [`HookInput`]:
+//!
+//! * `on_create(ptr: i32, len: i32)` - before a paste is created
+//! * `on_render(ptr: i32, len: i32)` - after a paste is rendered to HTML (`html` is set)
+//! * `on_view(ptr: i32, len: i32)` - before a paste is returned by `/api/:url`
+//!
+//! The host API is imported from the `pastemd` module:
+//!
+//! * `set_tags(ptr: i32, len: i32)` - replace the paste's tags with a JSON array of strings (`on_create` only)
+//! * `set_html(ptr: i32, len: i32)` - replace the rendered HTML (`on_render` only)
+//! * `reject(ptr: i32, len: i32)` - stop the action with a UTF-8 reason (`on_create` and `on_view` only)
+use crate::model::PasteMetadata;
+use serde::Serialize;
+use std::sync::Arc;
+use wasmi::{Caller, Config, Engine, Extern, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+/// Fuel given to every hook call (roughly the number of instructions it can run)
+const FUEL: u64 = 10_000_000;
+/// Largest memory a plugin can grow to in bytes
+const MAX_MEMORY: usize = 16 * 1024 * 1024;
+
+/// The hooks a plugin can implement
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Hook {
+    Create,
+    Render,
+    View,
+}
+
+impl Hook {
+    /// Name of the function plugins export for this hook
+    fn export(&self) -> &'static str {
+        match self {
+            Hook::Create => "on_create",
+            Hook::Render => "on_render",
+            Hook::View => "on_view",
+        }
+    }
+}
+
+/// What hooks are given (and what they change)
+#[derive(Serialize, Clone, Debug)]
+pub struct HookInput {
+    pub url: String,
+    pub content: String,
+    pub metadata: PasteMetadata,
+    /// Rendered HTML (`on_render` only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub html: Option<String>,
+}
+
+/// Store data of a single hook call
+struct State {
+    hook: Hook,
+    limits: StoreLimits,
+    tags: Option<Vec<String>>,
+    html: Option<String>,
+    rejected: Option<String>,
+}
+
+/// Loaded plugins
+#[derive(Clone)]
+pub struct Plugins {
+    engine: Engine,
+    linker: Linker<State>,
+    modules: Arc<Vec<Module>>,
+}
+
+impl std::fmt::Debug for Plugins {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Plugins")
+            .field("modules", &self.modules.len())
+            .finish()
+    }
+}
+
+impl Plugins {
+    /// Load the WASM modules at `paths` (modules which can't be read or compiled are skipped)
+    pub fn load(paths: &[String]) -> Self {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+
+        let engine = Engine::new(&config);
+        let mut modules: Vec<Module> = Vec::new();
+
+        for path in paths {
+            let wasm = match std::fs::read(path) {
+                Ok(w) => w,
+                Err(_) => continue,
+            };
+
+            if let Ok(module) = Module::new(&engine, &wasm) {
+                modules.push(module);
+            }
+        }
+
+        Self {
+            linker: host_api(&engine),
+            engine,
+            modules: Arc::new(modules),
+        }
+    }
+
+    /// If no plugins are loaded
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+
+    /// Run `hook` in every plugin, in the order they were given
+    ///
+    /// Every plugin sees the changes made by the plugins before it.
+    ///
+    /// ## Returns:
+    /// * the changed input, or the reason given by the first plugin which rejected it
+    pub fn run(&self, hook: Hook, mut input: HookInput) -> Result<HookInput, String> {
+        for module in self.modules.iter() {
+            let state = match self.call(module, hook, &input) {
+                Some(s) => s,
+                None => continue,
+            };
+
+            if let Some(reason) = state.rejected {
+                return Err(reason);
+            }
+
+            if let Some(tags) = state.tags {
+                input.metadata.tags = tags;
+            }
+
+            if let Some(html) = state.html {
+                input.html = Some(html);
+            }
+        }
+
+        Ok(input)
+    }
+
+    /// Call `hook` in a new instance of `module`
+    ///
+    /// Returns `None` if the plugin doesn't implement the hook or fails.
+    fn call(&self, module: &Module, hook: Hook, input: &HookInput) -> Option<State> {
+        let mut store = Store::new(
+            &self.engine,
+            State {
+                hook,
+                limits: StoreLimitsBuilder::new()
+                    .memory_size(MAX_MEMORY)
+                    .instances(1)
+                    .build(),
+                tags: None,
+                html: None,
+                rejected: None,
+            },
+        );
+
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(FUEL).ok()?;
+
+        let instance = self
+            .linker
+            .instantiate(&mut store, module)
+            .ok()?
+            .start(&mut store)
+            .ok()?;
+
+        let hook_fn = instance
+            .get_typed_func::<(i32, i32), ()>(&store, hook.export())
+            .ok()?;
+        let alloc = instance.get_typed_func::<i32, i32>(&store, "alloc").ok()?;
+        let memory = instance.get_memory(&store, "memory")?;
+
+        // write input
+        let bytes = serde_json::to_vec(input).ok()?;
+        let len = i32::try_from(bytes.len()).ok()?;
+        let ptr = alloc.call(&mut store, len).ok()?;
+        memory.write(&mut store, ptr as usize, &bytes).ok()?;
+
+        // call hook
+        hook_fn.call(&mut store, (ptr, len)).ok()?;
+        Some(store.into_data())
+    }
+}
+
+/// Read `len` bytes at `ptr` from the calling plugin's memory
+fn read(caller: &Caller<'_, State>, ptr: i32, len: i32) -> Option<Vec<u8>> {
+    let memory = caller.get_export("memory").and_then(Extern::into_memory)?;
+    let mut buffer = vec![0; usize::try_from(len).ok()?];
+
+    match memory.read(caller, usize::try_from(ptr).ok()?, &mut buffer) {
+        Ok(_) => Some(buffer),
+        Err(_) => None,
+    }
+}
+
+/// Define the host API (see the module docs)
+fn host_api(engine: &Engine) -> Linker<State> {
+    let mut linker: Linker<State> = Linker::new(engine);
+
+    linker
+        .func_wrap(
+            "pastemd",
+            "set_tags",
+            |mut caller: Caller<'_, State>, ptr: i32, len: i32| {
+                if caller.data().hook != Hook::Create {
+                    return;
+                }
+
+                if let Some(bytes) = read(&caller, ptr, len) {
+                    if let Ok(tags) = serde_json::from_slice::<Vec<String>>(&bytes) {
+                        caller.data_mut().tags = Some(tags);
+                    }
+                }
+            },
+        )
+        .unwrap();
+
+    linker
+        .func_wrap(
+            "pastemd",
+            "set_html",
+            |mut caller: Caller<'_, State>, ptr: i32, len: i32| {
+                if caller.data().hook != Hook::Render {
+                    return;
+                }
+
+                if let Some(bytes) = read(&caller, ptr, len) {
+                    caller.data_mut().html = Some(String::from_utf8_lossy(&bytes).to_string());
+                }
+            },
+        )
+        .unwrap();
+
+    linker
+        .func_wrap(
+            "pastemd",
+            "reject",
+            |mut caller: Caller<'_, State>, ptr: i32, len: i32| {
+                if caller.data().hook == Hook::Render {
+                    return;
+                }
+
+                let reason = match read(&caller, ptr, len) {
+                    Some(bytes) => String::from_utf8_lossy(&bytes).to_string(),
+                    None => String::new(),
+                };
+
+                caller.data_mut().rejected = Some(reason);
+            },
+        )
+        .unwrap();
+
+    linker
+}
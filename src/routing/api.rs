@@ -1,8 +1,69 @@
 //! Responds to API requests
 use crate::model::{PasteCreate, PasteDelete, PasteEdit, PasteError, PasteEditMetadata, Paste};
-use crate::database::Database;
+use crate::model::PublicPaste;
+#[cfg(feature = "ipfs")]
+use crate::model::PastePin;
+use crate::model::{PasteClaim, PasteTransfer};
+use crate::model::{ImportQuery, ImportResult, ImportedPaste};
+use crate::model::{Revision, Rotation, RotationCreate, RotationDelete};
+use crate::model::{ModerationFilter, ModerationJob, ModerationPreview, ModerationRequest};
+use crate::model::{Document, DocumentCreate, DocumentCreateBody, DocumentEdit, NamespaceUsage};
+use crate::model::{IngestToken, IngestTokenCreate, Invite, InviteCreate, PasteStats};
+use crate::model::{LintDiagnostic, LintRequest, PreviewRequest, UrlSuggestionQuery};
+use crate::model::{DetectRequest, Detection, InstanceMeta, PasteListing, PasteListQuery};
+use crate::model::{DataQuery, PasteTable, RenderQuery, TableQuery};
+use crate::model::{SearchOptions, SearchQuery, SearchResult};
+use crate::model::{Collection, CollectionCreate, CollectionNavigation, CollectionPaste};
+use crate::model::{Suggestion, SuggestionCreate, SuggestionDiff, SuggestionReview};
+use crate::model::{Annotation, AnnotationCreate, AnnotationDelete, BlockOutput};
+use crate::model::{Download, WatermarkTrace};
+#[cfg(feature = "links")]
+use crate::model::LinkReport;
+use crate::model::{Announcement, AnnouncementCreate, PinnedEdit, PinnedPaste};
+use crate::model::{
+    CachePurge, Checkpoint, CheckpointRequest, DoctorReport, EditLink, EditLinkCreate,
+    PasteEditQuery, PasteUnlock,
+};
+use crate::model::{PasteHold, PasteTranslation, PasteViewQuery, SignedQuery, TranslationEdit};
+#[cfg(feature = "signed_urls")]
+use crate::model::{SignedUrl, SignedUrlCreate, SignedUrlRevoke};
+use crate::model::{ApiError, ApiMeta, ApiResponse, ErrorCode, FieldError, Pagination};
+#[cfg(feature = "runners")]
+use crate::model::PasteRun;
+#[cfg(feature = "webauthn")]
+use crate::model::{WebAuthnCredential, WebAuthnCredentialCreate, WebAuthnCredentialDelete};
+#[cfg(feature = "fetch")]
+use crate::model::PasteCreateFromUrl;
+#[cfg(feature = "binary")]
+use crate::model::PasteCreateBinary;
+#[cfg(feature = "scanning")]
+use crate::model::ScanReport;
+#[cfg(feature = "checksums")]
+use crate::model::PasteChecksums;
+use crate::database::{Database, ViewMode, PAGE_SIZE};
 use dorsal::DefaultReturn;
+use dorsal::db::special::auth_db::{FullUser, UserMetadata};
+use serde_json::Value;
 
+use axum::body::Body;
+use axum::error_handling::HandleErrorLayer;
+use axum::extract::{ConnectInfo, MatchedPath, NestedPath, Request};
+use axum::http::header::{CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE};
+use axum::middleware::{self, Next};
+use axum::response::Response;
+use axum::BoxError;
+use tower::ServiceBuilder;
+use tower_http::classify::ServerErrorsFailureClass;
+use tower_http::trace::{DefaultOnResponse, TraceLayer};
+use tower_http::LatencyUnit;
+use tracing::{Level, Span};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures_util::{Stream, StreamExt};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::time::Duration;
 use axum::response::IntoResponse;
 use axum::{
     extract::{Path, State, Query},
@@ -11,176 +72,3785 @@ use axum::{
 };
 use axum_extra::extract::cookie::CookieJar;
 
-pub fn routes(database: Database) -> Router {
-    Router::new()
-        .route("/new", post(create_paste))
-        // pastes
-        .route("/:url", get(get_paste_by_url))
-        .route("/:url/delete", post(delete_paste_by_url))
-        .route("/:url/edit", post(edit_paste_by_url))
-        .route("/:url/metadata", post(edit_paste_metadata_by_url))
-        // auth
-        .route("/auth/callback", get(callback_request))
-        .route("/auth/logout", get(logout_request))
-        // ...
-        .with_state(database)
+pub fn routes(database: Database) -> Router {
+    let mut router = Router::new();
+
+    if database.options.document_store == true {
+        router = router
+            // documents
+            .route(
+                "/docs/:namespace",
+                get(get_namespace_usage).post(create_document),
+            )
+            .route(
+                "/docs/:namespace/:id",
+                get(get_document).put(edit_document).delete(delete_document),
+            );
+    }
+
+    if database.options.lint == true {
+        router = router.route("/lint", post(lint_markdown));
+    }
+
+    if database.options.renderer.is_some() {
+        router = router
+            .route("/preview", post(preview_markdown))
+            .route("/:url/html", get(get_rendered_paste_by_url));
+    }
+
+    if database.options.search == true {
+        router = router.route("/search", get(search_pastes));
+    }
+
+    if database.options.tables == true {
+        router = router.route("/:url/table", get(get_paste_table_by_url));
+    }
+
+    if database.options.structured_data == true {
+        router = router.route("/:url/query", get(query_paste_by_url));
+    }
+
+    if database.options.ingest == true {
+        router = router
+            // ingest
+            .route("/ingest/new", post(create_ingest_token))
+            .route(
+                "/ingest/:token",
+                post(ingest_paste).put(stream_ingest_paste),
+            )
+            .route("/ingest/:id/delete", post(delete_ingest_token));
+    }
+
+    if database.options.invites == true {
+        router = router
+            // invites
+            .route("/invites/new", post(create_invite))
+            .route("/invites/:code", get(get_invite))
+            .route("/invites/:id/delete", post(delete_invite));
+    }
+
+    if database.options.collections == true {
+        router = router
+            // collections
+            .route("/collections/new", post(create_collection))
+            .route("/collections/:id", get(get_collection))
+            .route("/collections/:id/add", post(add_paste_to_collection))
+            .route(
+                "/collections/:id/remove",
+                post(remove_paste_from_collection),
+            )
+            .route("/collections/:id/delete", post(delete_collection))
+            .route("/:url/collections", get(get_paste_collections_by_url));
+    }
+
+    if database.options.suggestions == true {
+        router = router
+            // suggestions
+            .route(
+                "/:url/suggestions",
+                get(get_suggestions_by_url).post(create_suggestion),
+            )
+            .route("/:url/suggestions/:id", get(get_suggestion_diff))
+            .route("/:url/suggestions/:id/accept", post(accept_suggestion))
+            .route("/:url/suggestions/:id/reject", post(reject_suggestion));
+    }
+
+    if database.options.view_password == true {
+        router = router.route("/:url/unlock", post(unlock_paste_by_url));
+
+        #[cfg(feature = "signed_urls")]
+        {
+            router = router
+                // signed urls
+                .route("/:url/sign", post(create_signed_url))
+                .route("/:url/sign/revoke", post(revoke_signed_urls_by_url));
+        }
+    }
+
+    if database.options.edit_links == true {
+        router = router.route("/:url/edit-link", post(create_edit_link));
+    }
+
+    if database.options.paste_ownership == true {
+        router = router
+            // ownership
+            .route("/:url/claim", post(claim_paste_by_url))
+            .route("/:url/transfer", post(transfer_paste_by_url));
+    }
+
+    if database.options.translations == true {
+        router = router
+            // translations
+            .route("/:url/translations", get(get_translations_by_url))
+            .route("/:url/translations/:lang", post(edit_translation_by_url));
+    }
+
+    if database.options.annotations == true {
+        router = router
+            // annotations
+            .route(
+                "/:url/annotations",
+                get(get_annotations_by_url).post(create_annotation),
+            )
+            .route("/:url/annotations/:id/delete", post(delete_annotation));
+    }
+
+    #[cfg(feature = "fetch")]
+    if database.options.from_url == true {
+        router = router.route("/new/from-url", post(create_paste_from_url));
+    }
+
+    if database.options.imports == true {
+        router = router.route("/import", post(import_pastes));
+    }
+
+    if database.options.moderation == true {
+        router = router
+            // moderation
+            .route("/admin/moderation", post(start_moderation_job))
+            .route("/admin/moderation/preview", post(preview_moderation))
+            .route("/admin/moderation/:id", get(get_moderation_job));
+    }
+
+    if database.options.rotations == true {
+        router = router
+            // rotations
+            .route("/:url/rotation", post(set_rotation_by_url))
+            .route("/:url/rotation/delete", post(delete_rotation_by_url))
+            .route("/:url/revisions", get(get_revisions_by_url));
+    }
+
+    #[cfg(feature = "binary")]
+    if database.options.binary_pastes == true {
+        router = router
+            // binary pastes
+            .route("/new/binary", post(create_binary_paste))
+            .route("/:url/binary", get(get_binary_paste_by_url));
+    }
+
+    #[cfg(feature = "checksums")]
+    {
+        router = router.route("/:url/checksums", get(get_paste_checksums_by_url));
+    }
+
+    #[cfg(feature = "scanning")]
+    if database.options.scanner.is_some() {
+        router = router
+            // malware scanning
+            .route("/:url/scan", post(scan_paste_by_url))
+            .route("/admin/quarantine", get(get_quarantined_pastes));
+    }
+
+    #[cfg(feature = "runners")]
+    if !database.options.runners.is_empty() {
+        router = router.route("/:url/run", post(run_paste_by_url));
+    }
+
+    #[cfg(feature = "ipfs")]
+    if database.options.ipfs.is_some() {
+        router = router.route("/:url/pin", post(pin_paste_by_url));
+    }
+
+    #[cfg(feature = "thumbnails")]
+    if database.options.thumbnails == true {
+        router = router.route("/:url/thumb.png", get(get_paste_thumbnail_by_url));
+    }
+
+    #[cfg(feature = "links")]
+    if database.options.link_check.is_some() {
+        router = router.route("/:url/links", get(get_link_report_by_url));
+    }
+
+    #[cfg(feature = "webauthn")]
+    if database.options.webauthn.is_some() {
+        router = router
+            // passkeys
+            .route("/auth/webauthn/challenge", post(create_webauthn_challenge))
+            .route(
+                "/auth/webauthn/credentials",
+                get(get_credentials).post(create_credential),
+            )
+            .route(
+                "/auth/webauthn/credentials/:id/delete",
+                post(delete_credential),
+            );
+    }
+
+    router = router
+        .route("/new", post(create_paste))
+        .route("/suggest-url", get(suggest_urls))
+        .route("/detect", post(detect_content))
+        // pastes
+        .route("/:url", get(get_paste_by_url))
+        .route("/:url/raw", get(get_raw_paste_by_url))
+        .route("/:url/delete", post(delete_paste_by_url))
+        .route("/:url/edit", post(edit_paste_by_url))
+        .route("/:url/metadata", post(edit_paste_metadata_by_url))
+        .route("/:url/tail", get(tail_paste_by_url))
+        .route("/:url/views", get(get_paste_views_by_url))
+        .route("/:url/stats", get(get_paste_stats_by_url))
+        .route("/:url/outputs", get(get_paste_outputs_by_url))
+        .route("/:url/trace", post(trace_paste_by_url))
+        .route("/:url/hold", post(set_legal_hold_by_url))
+        // auth
+        .route("/auth/callback", get(callback_request))
+        .route("/auth/logout", get(logout_request))
+        // sensitive content
+        .route("/consent", post(consent_request))
+        // announcement
+        .route(
+            "/announcement",
+            get(get_announcement).post(set_announcement),
+        )
+        .route("/announcement/delete", post(delete_announcement))
+        .route("/announcement/dismiss", post(dismiss_announcement))
+        // pinned
+        .route("/pinned", get(get_pinned).post(set_pinned))
+        // meta
+        .route("/meta", get(get_instance_meta))
+        // listing
+        .route("/pastes", get(get_recent_public_pastes))
+        .route("/owner/:username/pastes", get(get_pastes_by_owner))
+        .route("/owner/:username/export", get(export_pastes_by_owner))
+        .route("/feed/:feed", get(get_owner_feed))
+        // admin
+        .route("/admin/cache/purge", post(purge_cache))
+        .route("/admin/checkpoint", post(checkpoint))
+        .route("/admin/doctor", get(doctor))
+        // ...
+        .fallback(not_found);
+
+    #[cfg(feature = "geoip")]
+    if !database.options.geo_rules.is_empty() {
+        router = router.route_layer(middleware::from_fn_with_state(database.clone(), geoblock));
+    }
+
+    if (database.options.create_rate_limit != 0) | (database.options.edit_rate_limit != 0) {
+        router = router.route_layer(middleware::from_fn_with_state(database.clone(), rate_limit));
+    }
+
+    if !database.options.cache_control.is_empty() {
+        router = router.route_layer(middleware::from_fn_with_state(
+            database.clone(),
+            cache_control,
+        ));
+    }
+
+    // applied to every response (including the fallback)
+    router = router.layer(middleware::from_fn(versioned));
+
+    if database.options.concurrency_limit != 0 {
+        // applied to every route separately
+        router = router.layer(
+            ServiceBuilder::new()
+                .layer(HandleErrorLayer::new(handle_overload))
+                .load_shed()
+                .concurrency_limit(database.options.concurrency_limit),
+        );
+    }
+
+    // outermost, so every request is traced (and given an id) before anything else runs
+    router = router
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(request_span)
+                .on_response(
+                    DefaultOnResponse::new()
+                        .level(Level::INFO)
+                        .latency_unit(LatencyUnit::Millis),
+                )
+                .on_failure(
+                    |error: ServerErrorsFailureClass, latency: Duration, _: &Span| {
+                        tracing::error!(%error, latency_ms = latency.as_millis(), "request failed");
+                    },
+                ),
+        )
+        .layer(middleware::from_fn(request_id));
+
+    router.with_state(database)
+}
+
+/// Header holding the id of a request
+const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// Give every request an id (keeping the one sent by the client or a proxy, if any) and
+/// return it in the `X-Request-Id` header of the response
+async fn request_id(mut req: Request, next: Next) -> Response {
+    let id = match req.headers().get(REQUEST_ID_HEADER) {
+        Some(v) if !v.is_empty() && (v.len() <= 64) => v.clone(),
+        _ => match HeaderValue::from_str(&dorsal::utility::random_id()) {
+            Ok(v) => v,
+            Err(_) => return next.run(req).await,
+        },
+    };
+
+    req.headers_mut().insert(REQUEST_ID_HEADER, id.clone());
+
+    let mut res = next.run(req).await;
+    res.headers_mut().insert(REQUEST_ID_HEADER, id);
+    res
+}
+
+/// Create the span a request is traced in (see [`request_id`])
+fn request_span(req: &Request) -> Span {
+    let id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+
+    tracing::info_span!(
+        "request",
+        method = %req.method(),
+        path = %req.uri().path(),
+        request_id = %id,
+    )
+}
+
+/// Apply [`crate::database::ServerOptions::geo_rules`] to paste creation and viewing routes
+#[cfg(feature = "geoip")]
+async fn geoblock(
+    State(database): State<Database>,
+    addr: Option<ConnectInfo<SocketAddr>>,
+    matched: Option<MatchedPath>,
+    nested: Option<NestedPath>,
+    req: Request,
+    next: Next,
+) -> Response {
+    use crate::database::GeoRule;
+
+    let rule = match database.get_geo_rule(&get_client_ip(&database, req.headers(), addr)) {
+        Some(r) => r,
+        None => return next.run(req).await,
+    };
+
+    // get route as it was given to the router (without where it's nested)
+    let route = match matched {
+        Some(ref m) => match nested {
+            Some(ref n) => m.as_str().strip_prefix(n.as_str()).unwrap_or(m.as_str()),
+            None => m.as_str(),
+        },
+        None => return next.run(req).await,
+    };
+
+    let creating = [
+        "/new",
+        "/new/from-url",
+        "/new/binary",
+        "/import",
+        "/ingest/:token",
+    ]
+    .contains(&route);
+    let viewing = (req.method() == axum::http::Method::GET) && route.starts_with("/:url");
+
+    let blocked = match rule {
+        GeoRule::BlockCreate => creating,
+        GeoRule::BlockView => viewing,
+        GeoRule::BlockAll => creating | viewing,
+    };
+
+    if blocked {
+        return PasteError::GeoBlocked.into_response();
+    }
+
+    next.run(req).await
+}
+
+/// Apply [`crate::database::ServerOptions::create_rate_limit`] and
+/// [`crate::database::ServerOptions::edit_rate_limit`] to paste creation and editing routes
+async fn rate_limit(
+    State(database): State<Database>,
+    addr: Option<ConnectInfo<SocketAddr>>,
+    matched: Option<MatchedPath>,
+    nested: Option<NestedPath>,
+    req: Request,
+    next: Next,
+) -> Response {
+    // get route as it was given to the router (without where it's nested)
+    let route = match matched {
+        Some(ref m) => match nested {
+            Some(ref n) => m.as_str().strip_prefix(n.as_str()).unwrap_or(m.as_str()),
+            None => m.as_str(),
+        },
+        None => return next.run(req).await,
+    };
+
+    let (key, limit) = if ["/new", "/new/from-url", "/new/binary", "/import"].contains(&route) {
+        ("se_create_rate", database.options.create_rate_limit)
+    } else if ["/:url/edit", "/:url/metadata", "/:url/translations/:lang"].contains(&route)
+        && (req.method() == axum::http::Method::POST)
+    {
+        ("se_edit_rate", database.options.edit_rate_limit)
+    } else {
+        return next.run(req).await;
+    };
+
+    let ip = get_client_ip(&database, req.headers(), addr);
+
+    if let Err(e) = database
+        .check_rate_limit(format!("{}:{}", key, dorsal::utility::hash(ip)), limit)
+        .await
+    {
+        return e.into_response();
+    }
+
+    next.run(req).await
+}
+
+/// Add the `Cache-Control` policy configured for the matched route to successful responses
+async fn cache_control(
+    State(database): State<Database>,
+    matched: Option<MatchedPath>,
+    nested: Option<NestedPath>,
+    req: Request,
+    next: Next,
+) -> Response {
+    // get route as it was given to the router (without where it's nested)
+    let policy = match matched {
+        Some(ref m) => {
+            let route = match nested {
+                Some(ref n) => m.as_str().strip_prefix(n.as_str()).unwrap_or(m.as_str()),
+                None => m.as_str(),
+            };
+
+            database.options.cache_control.get(route).cloned()
+        }
+        None => None,
+    };
+
+    let mut res = next.run(req).await;
+
+    if let Some(policy) = policy {
+        if res.status().is_success() && !res.headers().contains_key(CACHE_CONTROL) {
+            if let Ok(value) = HeaderValue::from_str(&policy) {
+                res.headers_mut().insert(CACHE_CONTROL, value);
+            }
+        }
+    }
+
+    res
+}
+
+/// Newest API version (see [`versioned`])
+pub const API_VERSION: u8 = 2;
+
+/// Fields holding timestamps, always returned as numbers (milliseconds) in version 2
+const TIMESTAMP_FIELDS: [&str; 8] = [
+    "timestamp",
+    "date_published",
+    "date_edited",
+    "expires",
+    "expires_at",
+    "checked",
+    "last_used",
+    "last_viewed",
+];
+
+/// Rewrite JSON responses into [`ApiResponse`]s for clients asking for version 2 with the
+/// `Accept-Version` header
+///
+/// Responses stay [`DefaultReturn`]s (version 1) when the header is missing or `1`.
+async fn versioned(req: Request, next: Next) -> Response {
+    let version: u8 = match req.headers().get("Accept-Version") {
+        Some(v) => match v.to_str().unwrap_or("").trim().parse() {
+            Ok(v) => v,
+            Err(_) => return PasteError::ValueError.into_response(),
+        },
+        None => 1,
+    };
+
+    if (version < 1) | (version > API_VERSION) {
+        return PasteError::ValueError.into_response();
+    }
+
+    if version == 1 {
+        return next.run(req).await;
+    }
+
+    // paginated routes take a page number
+    let page: Option<usize> = match Query::<HashMap<String, String>>::try_from_uri(req.uri()) {
+        Ok(q) => q.get("page").and_then(|p| p.parse().ok()),
+        Err(_) => None,
+    };
+
+    let res = next.run(req).await;
+
+    let json = match res.headers().get(CONTENT_TYPE) {
+        Some(v) => v.as_bytes().starts_with(b"application/json"),
+        None => false,
+    };
+
+    if json == false {
+        return res;
+    }
+
+    // read response
+    let (mut parts, body) = res.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(b) => b,
+        Err(_) => return PasteError::Other.into_response(),
+    };
+
+    let mut v1: Value = match serde_json::from_slice(&bytes) {
+        Ok(v) => v,
+        Err(_) => return Response::from_parts(parts, Body::from(bytes)),
+    };
+
+    let (success, message, mut payload) = match v1.as_object_mut() {
+        Some(o) => match (
+            o.remove("success"),
+            o.remove("message"),
+            o.remove("payload"),
+        ) {
+            (Some(Value::Bool(s)), Some(Value::String(m)), Some(p)) => (s, m, p),
+            _ => return Response::from_parts(parts, Body::from(bytes)),
+        },
+        None => return Response::from_parts(parts, Body::from(bytes)),
+    };
+
+    // build response
+    let res: ApiResponse<Value> = if success | parts.status.is_success() {
+        normalize_timestamps(&mut payload);
+
+        ApiResponse {
+            meta: ApiMeta {
+                version,
+                message,
+                pagination: match (page, payload.as_array()) {
+                    (Some(page), Some(items)) => Some(Pagination {
+                        page,
+                        page_size: PAGE_SIZE,
+                        has_more: items.len() >= PAGE_SIZE,
+                    }),
+                    _ => None,
+                },
+            },
+            data: Some(payload),
+            error: None,
+        }
+    } else {
+        ApiResponse {
+            data: None,
+            error: Some(ApiError {
+                code: match parts.extensions.get::<ErrorCode>() {
+                    Some(c) => c.0.to_string(),
+                    None => match parts.status {
+                        StatusCode::NOT_FOUND => String::from("not_found"),
+                        _ => String::from("other"),
+                    },
+                },
+                message: message.clone(),
+                fields: serde_json::from_value::<Vec<FieldError>>(payload).unwrap_or_default(),
+            }),
+            meta: ApiMeta {
+                version,
+                message,
+                pagination: None,
+            },
+        }
+    };
+
+    parts.headers.remove(CONTENT_LENGTH);
+    parts
+        .headers
+        .insert("API-Version", HeaderValue::from(version as u16));
+
+    Response::from_parts(parts, Body::from(serde_json::to_vec(&res).unwrap()))
+}
+
+/// Turn every timestamp stored as text in `value` into a number
+fn normalize_timestamps(value: &mut Value) {
+    match value {
+        Value::Object(o) => {
+            for (key, v) in o.iter_mut() {
+                if let Value::String(s) = v {
+                    if TIMESTAMP_FIELDS.contains(&key.as_str()) {
+                        if let Ok(n) = s.parse::<u64>() {
+                            *v = Value::from(n);
+                        }
+                    }
+                } else {
+                    normalize_timestamps(v);
+                }
+            }
+        }
+        Value::Array(a) => {
+            for v in a.iter_mut() {
+                normalize_timestamps(v);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Turn errors from the concurrency limit layers into responses
+async fn handle_overload(err: BoxError) -> PasteError {
+    if err.is::<tower::load_shed::error::Overloaded>() {
+        PasteError::Overloaded
+    } else {
+        PasteError::Other
+    }
+}
+
+/// Get the user a request is being made as from their `__Secure-Token` cookie
+///
+/// Returns `None` when there is no cookie or guppy is disabled.
+async fn get_editing_as(
+    jar: &CookieJar,
+    database: &Database,
+) -> Result<Option<FullUser<UserMetadata>>, PasteError> {
+    if let Some(cookie) = jar.get("__Secure-Token") {
+        let value = cookie.value_trimmed();
+
+        if database.options.guppy == true {
+            match database.auth.get_user_by_unhashed(value.to_string()).await {
+                Ok(ua) => {
+                    if ua.level.permissions.contains(&"Banned".to_string()) {
+                        return Err(PasteError::Banned);
+                    }
+
+                    Ok(Option::Some(ua))
+                }
+                Err(_) => Err(PasteError::Other),
+            }
+        } else {
+            Ok(Option::None)
+        }
+    } else {
+        Ok(Option::None)
+    }
+}
+
+/// Get the IP address a request was made from
+///
+/// Uses [`crate::database::ServerOptions::real_ip_header`] if it's set and present.
+pub fn get_client_ip(
+    database: &Database,
+    headers: &HeaderMap,
+    addr: Option<ConnectInfo<SocketAddr>>,
+) -> String {
+    if !database.options.real_ip_header.is_empty() {
+        if let Some(value) = headers
+            .get(database.options.real_ip_header.as_str())
+            .and_then(|v| v.to_str().ok())
+        {
+            // the first address is the client, the rest are proxies
+            return value.split(",").next().unwrap_or("").trim().to_string();
+        }
+    }
+
+    match addr {
+        Some(ConnectInfo(a)) => a.ip().to_string(),
+        None => String::new(),
+    }
+}
+
+/// Make sure anonymous users have accepted the terms of service (if the server has any)
+///
+/// ## Returns:
+/// * Result containing if the acceptance should be recorded
+async fn check_tos(
+    jar: &CookieJar,
+    database: &Database,
+    accept_tos: bool,
+) -> Result<bool, PasteError> {
+    if database.options.tos_paste.is_empty() {
+        return Ok(false);
+    }
+
+    match get_editing_as(jar, database).await {
+        Ok(Some(_)) => Ok(false),
+        Ok(None) => {
+            if accept_tos == false {
+                return Err(PasteError::TosNotAccepted);
+            }
+
+            Ok(true)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Create a new paste (`/api/new`)
+///
+/// Normalization steps which changed the content are listed in the `X-Normalized` header.
+async fn create_paste(
+    jar: CookieJar,
+    headers: HeaderMap,
+    addr: Option<ConnectInfo<SocketAddr>>,
+    State(database): State<Database>,
+    Json(mut paste_to_create): Json<PasteCreate>,
+) -> Result<(HeaderMap, Json<DefaultReturn<(String, Paste)>>), PasteError> {
+    let record_tos = match check_tos(&jar, &database, paste_to_create.accept_tos).await {
+        Ok(r) => r,
+        Err(e) => return Err(e),
+    };
+
+    if let Ok(Some(ua)) = get_editing_as(&jar, &database).await {
+        paste_to_create.author = ua.user.username;
+    }
+
+    // report normalization
+    let mut res_headers = HeaderMap::new();
+    let normalized =
+        crate::normalize::normalize(&paste_to_create.content, &database.options.normalization).1;
+
+    if !normalized.is_empty() {
+        if let Ok(value) = HeaderValue::from_str(&normalized.join(", ")) {
+            res_headers.insert("X-Normalized", value);
+        }
+    }
+
+    let res = database.create_paste(paste_to_create).await;
+
+    match res {
+        Ok(paste) => {
+            if let Err(e) = database
+                .record_creator_ip(
+                    paste.1.url.clone(),
+                    get_client_ip(&database, &headers, addr),
+                )
+                .await
+            {
+                return Err(e);
+            }
+
+            if record_tos == true {
+                if let Err(e) = database
+                    .record_tos_acceptance(
+                        paste.1.url.clone(),
+                        get_client_ip(&database, &headers, addr),
+                    )
+                    .await
+                {
+                    return Err(e);
+                }
+            }
+
+            Ok((
+                res_headers,
+                Json(DefaultReturn {
+                    success: true,
+                    message: String::from("Paste created"),
+                    payload: paste,
+                }),
+            ))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Create a new paste from a remote URL (`/api/new/from-url`)
+#[cfg(feature = "fetch")]
+async fn create_paste_from_url(
+    jar: CookieJar,
+    headers: HeaderMap,
+    addr: Option<ConnectInfo<SocketAddr>>,
+    State(database): State<Database>,
+    Json(mut paste_to_create): Json<PasteCreateFromUrl>,
+) -> Result<Json<DefaultReturn<(String, Paste)>>, PasteError> {
+    let record_tos = match check_tos(&jar, &database, paste_to_create.accept_tos).await {
+        Ok(r) => r,
+        Err(e) => return Err(e),
+    };
+
+    if let Ok(Some(ua)) = get_editing_as(&jar, &database).await {
+        paste_to_create.author = ua.user.username;
+    }
+
+    match database.create_paste_from_url(paste_to_create).await {
+        Ok(paste) => {
+            if let Err(e) = database
+                .record_creator_ip(
+                    paste.1.url.clone(),
+                    get_client_ip(&database, &headers, addr),
+                )
+                .await
+            {
+                return Err(e);
+            }
+
+            if record_tos == true {
+                if let Err(e) = database
+                    .record_tos_acceptance(
+                        paste.1.url.clone(),
+                        get_client_ip(&database, &headers, addr),
+                    )
+                    .await
+                {
+                    return Err(e);
+                }
+            }
+
+            Ok(Json(DefaultReturn {
+                success: true,
+                message: String::from("Paste created"),
+                payload: paste,
+            }))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Create a new binary paste from the request body (`/api/new/binary?url=...&password=...`)
+///
+/// The body is the paste's raw content and its `Content-Type` header is the paste's MIME
+/// type (e.g. `curl --data-binary @image.png -H "Content-Type: image/png" ...`).
+#[cfg(feature = "binary")]
+async fn create_binary_paste(
+    jar: CookieJar,
+    headers: HeaderMap,
+    addr: Option<ConnectInfo<SocketAddr>>,
+    State(database): State<Database>,
+    Query(mut paste_to_create): Query<PasteCreateBinary>,
+    body: axum::body::Bytes,
+) -> Result<Json<DefaultReturn<(String, Paste)>>, PasteError> {
+    let record_tos = match check_tos(&jar, &database, paste_to_create.accept_tos).await {
+        Ok(r) => r,
+        Err(e) => return Err(e),
+    };
+
+    if let Ok(Some(ua)) = get_editing_as(&jar, &database).await {
+        paste_to_create.author = ua.user.username;
+    }
+
+    // mime type (without parameters like the charset)
+    paste_to_create.mime_type = match headers.get(CONTENT_TYPE) {
+        Some(value) => value
+            .to_str()
+            .unwrap_or("")
+            .split(';')
+            .next()
+            .unwrap_or("")
+            .trim()
+            .to_lowercase(),
+        None => String::new(),
+    };
+
+    match database.create_binary_paste(paste_to_create, &body).await {
+        Ok(paste) => {
+            if let Err(e) = database
+                .record_creator_ip(
+                    paste.1.url.clone(),
+                    get_client_ip(&database, &headers, addr),
+                )
+                .await
+            {
+                return Err(e);
+            }
+
+            if record_tos == true {
+                if let Err(e) = database
+                    .record_tos_acceptance(
+                        paste.1.url.clone(),
+                        get_client_ip(&database, &headers, addr),
+                    )
+                    .await
+                {
+                    return Err(e);
+                }
+            }
+
+            Ok(Json(DefaultReturn {
+                success: true,
+                message: String::from("Paste created"),
+                payload: paste,
+            }))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Download the decoded content of a binary paste (`/api/:url/binary`)
+///
+/// Content is always sent as an attachment with its MIME type, so browsers don't run it on
+/// this origin. Pastes protected by a view password can only be downloaded with a signed url.
+/// Interrupted downloads can be resumed with a `Range` header (see [`ranged`]).
+#[cfg(feature = "binary")]
+async fn get_binary_paste_by_url(
+    jar: CookieJar,
+    req_headers: HeaderMap,
+    addr: Option<ConnectInfo<SocketAddr>>,
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Query(signed): Query<SignedQuery>,
+) -> Result<Response, PasteError> {
+    let paste = match database.get_paste_by_url(url).await {
+        Ok(p) => p,
+        Err(e) => return Err(e),
+    };
+
+    if let Err(e) = database.run_view_hook(&paste) {
+        return Err(e);
+    }
+
+    if !is_readable(&database, &paste, &signed).await {
+        return Err(PasteError::NotAllowed);
+    }
+
+    if paste.metadata.content_type != "binary" {
+        return Err(PasteError::ValueError);
+    }
+
+    if !crate::binary::is_downloadable(&paste.metadata.scan_status) {
+        return Err(PasteError::NotAllowed);
+    }
+
+    let bytes = match crate::binary::decode(&paste.content) {
+        Some(b) => b,
+        None => return Err(PasteError::Other),
+    };
+
+    // count view (failing to count shouldn't stop the paste from being downloaded)
+    let viewer = if database.options.view_mode == ViewMode::AuthenticatedOnce {
+        get_editing_as(&jar, &database).await.unwrap_or(None)
+    } else {
+        None
+    };
+
+    let _ = database
+        .count_view_by_url(
+            paste.url.clone(),
+            viewer,
+            &get_client_ip(&database, &req_headers, addr),
+        )
+        .await;
+
+    let mime_type = if paste.metadata.mime_type.is_empty() {
+        crate::binary::DEFAULT_MIME_TYPE
+    } else {
+        paste.metadata.mime_type.as_str()
+    };
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "Content-Type",
+        HeaderValue::from_str(mime_type)
+            .unwrap_or(HeaderValue::from_static(crate::binary::DEFAULT_MIME_TYPE)),
+    );
+    headers.insert(
+        "Content-Disposition",
+        HeaderValue::from_str(&format!("attachment; filename=\"{}\"", paste.url))
+            .unwrap_or(HeaderValue::from_static("attachment")),
+    );
+    headers.insert(
+        "X-Content-Type-Options",
+        HeaderValue::from_static("nosniff"),
+    );
+    headers.insert(
+        "Content-Security-Policy",
+        HeaderValue::from_static("sandbox"),
+    );
+
+    if paste.metadata.noindex == true {
+        headers.insert("X-Robots-Tag", HeaderValue::from_static("noindex"));
+    }
+
+    let etag = format!("\"{}\"", dorsal::utility::hash(paste.content));
+    Ok(ranged(&req_headers, headers, etag, bytes))
+}
+
+/// Get the checksums of an existing paste's raw content (`/api/:url/checksums`)
+///
+/// Checksums match `/api/:url/raw` (or `/api/:url/binary` for binary pastes) on servers
+/// which don't watermark pastes. Pastes protected by a view password need a signed url, but
+/// quarantined pastes still have checksums (they help identify what was quarantined).
+#[cfg(feature = "checksums")]
+async fn get_paste_checksums_by_url(
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Query(signed): Query<SignedQuery>,
+) -> Result<Json<DefaultReturn<PasteChecksums>>, PasteError> {
+    let paste = match database.get_paste_by_url(url).await {
+        Ok(p) => p,
+        Err(e) => return Err(e),
+    };
+
+    if let Err(e) = database.run_view_hook(&paste) {
+        return Err(e);
+    }
+
+    if !is_unlocked(&database, &paste, &signed).await {
+        return Err(PasteError::NotAllowed);
+    }
+
+    Ok(Json(DefaultReturn {
+        success: true,
+        message: String::from("Paste checksums"),
+        payload: database.get_checksums(&paste),
+    }))
+}
+
+/// Scan a binary paste for malware again (`/api/:url/scan`)
+///
+/// Only users with the "ManagePastes" permission can request scans.
+#[cfg(feature = "scanning")]
+async fn scan_paste_by_url(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path(url): Path<String>,
+) -> Result<Json<DefaultReturn<ScanReport>>, PasteError> {
+    if let Err(e) = get_paste_manager(&jar, &database).await {
+        return Err(e);
+    }
+
+    match database.scan_paste_by_url(url).await {
+        Ok(report) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Paste scanned"),
+            payload: report,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Get every quarantined paste (`/api/admin/quarantine`)
+#[cfg(feature = "scanning")]
+async fn get_quarantined_pastes(
+    jar: CookieJar,
+    State(database): State<Database>,
+) -> Result<Json<DefaultReturn<Vec<ScanReport>>>, PasteError> {
+    if let Err(e) = get_paste_manager(&jar, &database).await {
+        return Err(e);
+    }
+
+    match database.get_quarantined_pastes().await {
+        Ok(reports) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Quarantined pastes"),
+            payload: reports,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Lint Markdown before publishing it (`/api/lint`)
+async fn lint_markdown(
+    State(database): State<Database>,
+    Json(props): Json<LintRequest>,
+) -> Result<Json<DefaultReturn<Vec<LintDiagnostic>>>, PasteError> {
+    if props.content.len() > 200_000 {
+        return Err(PasteError::ValueError);
+    }
+
+    Ok(Json(DefaultReturn {
+        success: true,
+        message: String::from("Content linted"),
+        payload: crate::lint::lint(&props.content, database.options.lint_max_line_length),
+    }))
+}
+
+/// Get available paste urls generated from a title (`/api/suggest-url?title=...`)
+async fn suggest_urls(
+    State(database): State<Database>,
+    Query(props): Query<UrlSuggestionQuery>,
+) -> Result<Json<DefaultReturn<Vec<String>>>, PasteError> {
+    match database.suggest_urls(props.title).await {
+        Ok(urls) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Urls generated"),
+            payload: urls,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Search public pastes (`/api/search?q=...`)
+async fn search_pastes(
+    State(database): State<Database>,
+    Query(props): Query<SearchQuery>,
+) -> Result<Json<DefaultReturn<Vec<SearchResult>>>, PasteError> {
+    match database
+        .search_pastes(
+            props.q,
+            SearchOptions {
+                limit: props.limit,
+                offset: props.offset,
+            },
+        )
+        .await
+    {
+        Ok(results) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Pastes found"),
+            payload: results,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Guess what kind of content is being written (`/api/detect`)
+async fn detect_content(
+    Json(props): Json<DetectRequest>,
+) -> Result<Json<DefaultReturn<Detection>>, PasteError> {
+    if props.content.len() > crate::validation::MAX_CONTENT_LENGTH {
+        return Err(PasteError::ContentTooLarge);
+    }
+
+    Ok(Json(DefaultReturn {
+        success: true,
+        message: String::from("Content detected"),
+        payload: crate::detect::detect(&props.content),
+    }))
+}
+
+/// Render content without creating a paste (`/api/preview`)
+async fn preview_markdown(
+    headers: HeaderMap,
+    addr: Option<ConnectInfo<SocketAddr>>,
+    State(database): State<Database>,
+    Json(props): Json<PreviewRequest>,
+) -> Result<Json<DefaultReturn<String>>, PasteError> {
+    // check rate limit
+    let ip = get_client_ip(&database, &headers, addr);
+
+    if let Err(e) = database
+        .check_rate_limit(
+            format!("se_preview_rate:{}", dorsal::utility::hash(ip)),
+            database.options.preview_rate_limit,
+        )
+        .await
+    {
+        return Err(e);
+    }
+
+    // render
+    match database.preview(props.content).await {
+        Ok(html) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Content rendered"),
+            payload: html,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Delete an existing paste (`/api/:url/delete`)
+async fn delete_paste_by_url(
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Json(paste_to_delete): Json<PasteDelete>,
+) -> Result<Json<DefaultReturn<()>>, PasteError> {
+    match database
+        .delete_paste_by_url(url, paste_to_delete.password, paste_to_delete.assertion)
+        .await
+    {
+        Ok(_) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Paste deleted"),
+            payload: (),
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Edit an existing paste (`/api/:url/edit`)
+async fn edit_paste_by_url(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Query(query): Query<PasteEditQuery>,
+    Json(paste_to_edit): Json<PasteEdit>,
+) -> Result<Json<DefaultReturn<()>>, PasteError> {
+    match database
+        .edit_paste_by_url(
+            url,
+            paste_to_edit.password,
+            query.t,
+            paste_to_edit.new_content,
+            paste_to_edit.new_url,
+            paste_to_edit.new_password,
+            match get_editing_as(&jar, &database).await {
+                Ok(ua) => ua,
+                Err(e) => return Err(e),
+            },
+            paste_to_edit.assertion,
+        )
+        .await
+    {
+        Ok(_) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Paste updated"),
+            payload: (),
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Create an edit link for an existing paste (`/api/:url/edit-link`)
+async fn create_edit_link(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Json(props): Json<EditLinkCreate>,
+) -> Result<Json<DefaultReturn<EditLink>>, PasteError> {
+    match database
+        .create_edit_link(
+            url,
+            props.password,
+            props.expires_in,
+            match get_editing_as(&jar, &database).await {
+                Ok(ua) => ua,
+                Err(e) => return Err(e),
+            },
+        )
+        .await
+    {
+        Ok(link) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Edit link created"),
+            payload: link,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Create a signed url granting read access to an existing paste (`/api/:url/sign`)
+#[cfg(feature = "signed_urls")]
+async fn create_signed_url(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Json(props): Json<SignedUrlCreate>,
+) -> Result<Json<DefaultReturn<SignedUrl>>, PasteError> {
+    match database
+        .create_signed_url(
+            url,
+            props.password,
+            props.expires_in,
+            match get_editing_as(&jar, &database).await {
+                Ok(ua) => ua,
+                Err(e) => return Err(e),
+            },
+        )
+        .await
+    {
+        Ok(signed) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Signed url created"),
+            payload: signed,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Revoke every signed url of an existing paste (`/api/:url/sign/revoke`)
+#[cfg(feature = "signed_urls")]
+async fn revoke_signed_urls_by_url(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Json(props): Json<SignedUrlRevoke>,
+) -> Result<Json<DefaultReturn<()>>, PasteError> {
+    match database
+        .revoke_signed_urls_by_url(
+            url,
+            props.password,
+            match get_editing_as(&jar, &database).await {
+                Ok(ua) => ua,
+                Err(e) => return Err(e),
+            },
+        )
+        .await
+    {
+        Ok(_) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Signed urls revoked"),
+            payload: (),
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Schedule replacing an existing paste's content from a source (`/api/:url/rotation`)
+async fn set_rotation_by_url(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Json(props): Json<RotationCreate>,
+) -> Result<Json<DefaultReturn<Rotation>>, PasteError> {
+    match database
+        .set_rotation_by_url(
+            url,
+            props,
+            match get_editing_as(&jar, &database).await {
+                Ok(ua) => ua,
+                Err(e) => return Err(e),
+            },
+        )
+        .await
+    {
+        Ok(rotation) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Rotation scheduled"),
+            payload: rotation,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Stop rotating an existing paste (`/api/:url/rotation/delete`)
+async fn delete_rotation_by_url(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Json(props): Json<RotationDelete>,
+) -> Result<Json<DefaultReturn<()>>, PasteError> {
+    match database
+        .delete_rotation_by_url(
+            url,
+            props.password,
+            match get_editing_as(&jar, &database).await {
+                Ok(ua) => ua,
+                Err(e) => return Err(e),
+            },
+        )
+        .await
+    {
+        Ok(_) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Rotation deleted"),
+            payload: (),
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Get the content an existing paste had before its rotations (`/api/:url/revisions`)
+///
+/// Pastes protected by a view password need a signed url.
+async fn get_revisions_by_url(
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Query(signed): Query<SignedQuery>,
+) -> Result<Json<DefaultReturn<Vec<Revision>>>, PasteError> {
+    let paste = match database.get_paste_by_url(url).await {
+        Ok(p) => p,
+        Err(e) => return Err(e),
+    };
+
+    if let Err(e) = database.run_view_hook(&paste) {
+        return Err(e);
+    }
+
+    if !is_readable(&database, &paste, &signed).await {
+        return Err(PasteError::NotAllowed);
+    }
+
+    match database.get_revisions_by_url(paste.url).await {
+        Ok(revisions) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Paste revisions"),
+            payload: revisions,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Edit an existing paste's metadata (`/api/:url/metadata`)
+async fn edit_paste_metadata_by_url(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Json(mut paste_to_edit): Json<PasteEditMetadata>,
+) -> Result<Json<DefaultReturn<()>>, PasteError> {
+    // if we've been given an authentication cookie (and it's allowed),
+    // we'll check the user and then set metadata.owner
+    if let Some(cookie) = jar.get("__Secure-Token") {
+        let value = cookie.value_trimmed();
+
+        if (database.options.guppy == true) && (database.options.paste_ownership == true) {
+            match database.auth.get_user_by_unhashed(value.to_string()).await {
+                Ok(ua) => paste_to_edit.metadata.owner = ua.user.username,
+                Err(_) => paste_to_edit.metadata.owner = "".to_string(),
+            }
+        }
+    } else {
+        // clear owner field if paste is edited by an anonymous user
+        paste_to_edit.metadata.owner = "".to_string();
+    }
+
+    // ...
+    match database
+        .edit_paste_metadata_by_url(
+            url,
+            paste_to_edit.password,
+            paste_to_edit.metadata,
+            match get_editing_as(&jar, &database).await {
+                Ok(ua) => ua,
+                Err(e) => return Err(e),
+            },
+            paste_to_edit.assertion,
+        )
+        .await
+    {
+        Ok(_) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Paste updated"),
+            payload: (),
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Pin a snapshot of an existing paste to IPFS (`/api/:url/pin`)
+#[cfg(feature = "ipfs")]
+async fn pin_paste_by_url(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Json(paste_to_pin): Json<PastePin>,
+) -> Result<Json<DefaultReturn<String>>, PasteError> {
+    match database
+        .pin_paste_by_url(
+            url,
+            paste_to_pin.password,
+            match get_editing_as(&jar, &database).await {
+                Ok(ua) => ua,
+                Err(e) => return Err(e),
+            },
+        )
+        .await
+    {
+        Ok(cid) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Paste pinned"),
+            payload: cid,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Follow an existing paste's content as it grows (`/api/:url/tail`)
+///
+/// Server-sent events: `content` with the current content first, then `append` with each
+/// block added to the end of the paste. If the paste is changed in any other way, `reset`
+/// is sent with the full new content. The stream ends when the paste is deleted.
+async fn tail_paste_by_url(
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Query(signed): Query<SignedQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, PasteError> {
+    let paste = match database.get_paste_by_url(url).await {
+        Ok(p) => p,
+        Err(e) => return Err(e),
+    };
+
+    if !is_readable(&database, &paste, &signed).await {
+        return Err(PasteError::NotAllowed);
+    }
+
+    let stream = futures_util::stream::unfold(
+        (database, paste.url, signed, None::<String>),
+        |(database, url, signed, sent)| async move {
+            let sent = match sent {
+                Some(s) => s,
+                None => {
+                    // backfill
+                    return match database.get_paste_by_url(url.clone()).await {
+                        Ok(p) => Some((
+                            Ok(Event::default().event("content").data(&p.content)),
+                            (database, url, signed, Some(p.content)),
+                        )),
+                        Err(_) => None,
+                    };
+                }
+            };
+
+            loop {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+
+                let paste = match database.get_paste_by_url(url.clone()).await {
+                    Ok(p) => p,
+                    Err(_) => return None, // paste is gone
+                };
+
+                // the paste may have been protected (or its signed urls revoked) since
+                if !is_readable(&database, &paste, &signed).await {
+                    return None;
+                }
+
+                let content = paste.content;
+
+                if content == sent {
+                    continue;
+                }
+
+                let event = match content.strip_prefix(&sent) {
+                    Some(appended) => Event::default().event("append").data(appended),
+                    None => Event::default().event("reset").data(&content),
+                };
+
+                return Some((Ok(event), (database, url, signed, Some(content))));
+            }
+        },
+    );
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Get an existing paste's view count (`/api/:url/views`)
+async fn get_paste_views_by_url(
+    State(database): State<Database>,
+    Path(url): Path<String>,
+) -> Result<Json<DefaultReturn<i32>>, PasteError> {
+    // make sure paste exists
+    if let Err(e) = database.get_paste_by_url(url.clone()).await {
+        return Err(e);
+    }
+
+    Ok(Json(DefaultReturn {
+        success: true,
+        message: String::from("Paste views"),
+        payload: database.get_views_by_url(url).await,
+    }))
+}
+
+/// Get an existing paste's view statistics (`/api/:url/stats`)
+async fn get_paste_stats_by_url(
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Query(signed): Query<SignedQuery>,
+) -> Result<Json<DefaultReturn<PasteStats>>, PasteError> {
+    let paste = match database.get_paste_by_url(url.clone()).await {
+        Ok(p) => p,
+        Err(e) => return Err(e),
+    };
+
+    if !is_unlocked(&database, &paste, &signed).await {
+        return Err(PasteError::NotAllowed);
+    }
+
+    Ok(Json(DefaultReturn {
+        success: true,
+        message: String::from("Paste stats"),
+        payload: database.get_stats_by_url(url).await,
+    }))
+}
+
+/// Claim a paste without an owner as the current user (`/api/:url/claim`)
+async fn claim_paste_by_url(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Json(props): Json<PasteClaim>,
+) -> Result<Json<DefaultReturn<()>>, PasteError> {
+    let ua = match get_editing_as(&jar, &database).await {
+        Ok(Some(ua)) => ua,
+        Ok(None) => return Err(PasteError::NotAllowed),
+        Err(e) => return Err(e),
+    };
+
+    match database.claim_paste_by_url(url, props.password, ua).await {
+        Ok(_) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Paste claimed"),
+            payload: (),
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Give a paste to another user (`/api/:url/transfer`)
+async fn transfer_paste_by_url(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Json(props): Json<PasteTransfer>,
+) -> Result<Json<DefaultReturn<()>>, PasteError> {
+    let ua = match get_editing_as(&jar, &database).await {
+        Ok(Some(ua)) => ua,
+        Ok(None) => return Err(PasteError::NotAllowed),
+        Err(e) => return Err(e),
+    };
+
+    match database
+        .transfer_paste_by_url(url, props.owner, ua, props.assertion)
+        .await
+    {
+        Ok(_) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Paste transferred"),
+            payload: (),
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Place or clear a legal hold on a paste (`/api/:url/hold`)
+async fn set_legal_hold_by_url(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Json(props): Json<PasteHold>,
+) -> Result<Json<DefaultReturn<()>>, PasteError> {
+    let ua = match get_paste_manager(&jar, &database).await {
+        Ok(ua) => ua,
+        Err(e) => return Err(e),
+    };
+
+    match database
+        .set_legal_hold_by_url(url, props.legal_hold, ua)
+        .await
+    {
+        Ok(_) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Paste updated"),
+            payload: (),
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Get an existing paste by url (`/api/:url`)
+pub async fn get_paste_by_url(
+    jar: CookieJar,
+    req_headers: HeaderMap,
+    addr: Option<ConnectInfo<SocketAddr>>,
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Query(props): Query<PasteViewQuery>,
+    Query(signed): Query<SignedQuery>,
+) -> Result<(HeaderMap, Json<DefaultReturn<PublicPaste>>), PasteError> {
+    let paste = if props.nocache != 0 {
+        // bypass cache
+        if let Err(e) = get_paste_manager(&jar, &database).await {
+            return Err(e);
+        }
+
+        database.refresh_paste_by_url(url).await
+    } else {
+        database.get_paste_by_url(url).await
+    };
+
+    match paste {
+        Ok(mut p) => {
+            if let Err(e) = database.run_view_hook(&p) {
+                return Err(e);
+            }
+
+            // protected pastes are only returned with a signed url (or through `/api/:url/unlock`)
+            if !is_unlocked(&database, &p, &signed).await {
+                return Err(PasteError::PasswordRequired);
+            }
+
+            // quarantined pastes are returned without their content
+            if is_quarantined(&p) {
+                p.content = String::new();
+            }
+
+            let mut headers = HeaderMap::new();
+
+            // count view (failing to count shouldn't stop the paste from being viewed)
+            if props.nocache == 0 {
+                let viewer = if database.options.view_mode == ViewMode::AuthenticatedOnce {
+                    get_editing_as(&jar, &database).await.unwrap_or(None)
+                } else {
+                    None
+                };
+
+                let _ = database
+                    .count_view_by_url(
+                        p.url.clone(),
+                        viewer,
+                        &get_client_ip(&database, &req_headers, addr),
+                    )
+                    .await;
+            }
+
+            headers.insert(
+                "X-Views",
+                HeaderValue::from(database.get_views_by_url(p.url.clone()).await),
+            );
+
+            // use translation
+            if database.options.translations == true {
+                let langs = if !props.lang.is_empty() {
+                    vec![props.lang]
+                } else {
+                    get_accepted_languages(&req_headers)
+                };
+
+                if let Some(t) = database.get_translation(p.url.clone(), langs).await {
+                    if let Ok(value) = HeaderValue::from_str(&t.lang) {
+                        headers.insert("Content-Language", value);
+                    }
+
+                    p.content = t.content;
+                }
+            }
+
+            // watermark protected pastes
+            let p = if database.options.watermark == true {
+                let username = match get_editing_as(&jar, &database).await {
+                    Ok(Some(ua)) => ua.user.username,
+                    Ok(None) => String::new(),
+                    Err(e) => return Err(e),
+                };
+
+                match database.watermark_paste(p, username).await {
+                    Ok(p) => p,
+                    Err(e) => return Err(e),
+                }
+            } else {
+                p
+            };
+
+            if p.metadata.noindex == true {
+                headers.insert("X-Robots-Tag", HeaderValue::from_static("noindex"));
+            }
+
+            Ok((
+                headers,
+                Json(DefaultReturn {
+                    success: true,
+                    message: String::from("Paste exists"),
+                    payload: p.into(),
+                }),
+            ))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Get the content of an existing paste as plain text (`/api/:url/raw`)
+///
+/// Meant for scripts (e.g. `curl https://example.com/api/:url/raw`), so the content is
+/// returned without a JSON envelope. Pastes protected by a view password can only be
+/// fetched with a signed url, and quarantined pastes can't be fetched this way at all.
+/// Interrupted downloads can be resumed with a `Range` header (see [`ranged`]).
+async fn get_raw_paste_by_url(
+    jar: CookieJar,
+    req_headers: HeaderMap,
+    addr: Option<ConnectInfo<SocketAddr>>,
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Query(signed): Query<SignedQuery>,
+) -> Result<Response, PasteError> {
+    let paste = match database.get_paste_by_url(url).await {
+        Ok(p) => p,
+        Err(e) => return Err(e),
+    };
+
+    if let Err(e) = database.run_view_hook(&paste) {
+        return Err(e);
+    }
+
+    if !is_readable(&database, &paste, &signed).await {
+        return Err(PasteError::NotAllowed);
+    }
+
+    // count view (failing to count shouldn't stop the paste from being viewed)
+    let viewer = if database.options.view_mode == ViewMode::AuthenticatedOnce {
+        get_editing_as(&jar, &database).await.unwrap_or(None)
+    } else {
+        None
+    };
+
+    let _ = database
+        .count_view_by_url(
+            paste.url.clone(),
+            viewer,
+            &get_client_ip(&database, &req_headers, addr),
+        )
+        .await;
+
+    // watermark protected pastes
+    let paste = if database.options.watermark == true {
+        let username = match get_editing_as(&jar, &database).await {
+            Ok(Some(ua)) => ua.user.username,
+            Ok(None) => String::new(),
+            Err(e) => return Err(e),
+        };
+
+        match database.watermark_paste(paste, username).await {
+            Ok(p) => p,
+            Err(e) => return Err(e),
+        }
+    } else {
+        paste
+    };
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "Content-Type",
+        HeaderValue::from_static("text/plain; charset=utf-8"),
+    );
+    headers.insert(
+        "X-Content-Type-Options",
+        HeaderValue::from_static("nosniff"),
+    );
+
+    if paste.metadata.noindex == true {
+        headers.insert("X-Robots-Tag", HeaderValue::from_static("noindex"));
+    }
+
+    let etag = format!("\"{}\"", dorsal::utility::hash(paste.content.clone()));
+    Ok(ranged(
+        &req_headers,
+        headers,
+        etag,
+        paste.content.into_bytes(),
+    ))
+}
+
+/// Respond with `body`, or the part of it asked for by the request's `Range` header
+///
+/// Only single byte ranges are supported (`bytes=0-99`, `bytes=100-` or `bytes=-100`), other
+/// ranges are ignored and get the whole body. A range is also ignored if the request's
+/// `If-Range` doesn't match `etag`, so resumed downloads never mix two versions of a paste.
+///
+/// ## Arguments:
+/// * `req_headers` - the request's headers
+/// * `headers` - the response's headers
+/// * `etag` - the `ETag` of `body` (quoted)
+/// * `body` - the full response body
+fn ranged(
+    req_headers: &HeaderMap,
+    mut headers: HeaderMap,
+    etag: String,
+    body: Vec<u8>,
+) -> Response {
+    headers.insert("Accept-Ranges", HeaderValue::from_static("bytes"));
+
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+        headers.insert("ETag", value);
+    }
+
+    let range = match req_headers.get("Range").and_then(|r| r.to_str().ok()) {
+        Some(r) => r,
+        None => return (headers, body).into_response(),
+    };
+
+    if let Some(if_range) = req_headers.get("If-Range") {
+        if if_range.to_str().unwrap_or("") != etag {
+            return (headers, body).into_response();
+        }
+    }
+
+    match parse_range(range, body.len()) {
+        Some(Some((start, end))) => {
+            if let Ok(value) =
+                HeaderValue::from_str(&format!("bytes {}-{}/{}", start, end, body.len()))
+            {
+                headers.insert("Content-Range", value);
+            }
+
+            (
+                StatusCode::PARTIAL_CONTENT,
+                headers,
+                body[start..=end].to_vec(),
+            )
+                .into_response()
+        }
+        Some(None) => {
+            if let Ok(value) = HeaderValue::from_str(&format!("bytes */{}", body.len())) {
+                headers.insert("Content-Range", value);
+            }
+
+            (StatusCode::RANGE_NOT_SATISFIABLE, headers).into_response()
+        }
+        None => (headers, body).into_response(),
+    }
+}
+
+/// Parse a `Range` header for a body `len` bytes long
+///
+/// ## Returns:
+/// * `None` if the header isn't a single, valid byte range (so it should be ignored)
+/// * `Some(None)` if the range starts past the end of the body
+/// * `Some(Some((start, end)))` with the first and last byte of the range otherwise
+fn parse_range(range: &str, len: usize) -> Option<Option<(usize, usize)>> {
+    let spec = range.trim().strip_prefix("bytes=")?.trim();
+
+    if spec.contains(",") {
+        return None;
+    }
+
+    let (start, end) = spec.split_once("-")?;
+    let (start, end) = (start.trim(), end.trim());
+
+    if start.is_empty() {
+        // the last `end` bytes
+        let suffix = end.parse::<usize>().ok()?;
+
+        if (suffix == 0) | (len == 0) {
+            return Some(None);
+        }
+
+        return Some(Some((len.saturating_sub(suffix), len - 1)));
+    }
+
+    let start = start.parse::<usize>().ok()?;
+    let end = if end.is_empty() {
+        usize::MAX
+    } else {
+        end.parse::<usize>().ok()?
+    };
+
+    if end < start {
+        return None;
+    }
+
+    if start >= len {
+        return Some(None);
+    }
+
+    Some(Some((start, end.min(len - 1))))
+}
+
+/// Get an existing paste rendered to HTML (`/api/:url/html`)
+///
+/// Pastes are rendered with [`crate::database::ServerOptions::renderer`] (e.g.
+/// [`crate::database::Renderer::markdown`]) through [`Database::get_rendered`], so code
+/// pastes are highlighted when that's enabled. `?raw=true` returns the plain text content.
+/// Sensitive pastes show an interstitial instead until the viewer agrees to see them.
+async fn get_rendered_paste_by_url(
+    jar: CookieJar,
+    req_headers: HeaderMap,
+    addr: Option<ConnectInfo<SocketAddr>>,
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Query(props): Query<RenderQuery>,
+    Query(signed): Query<SignedQuery>,
+) -> Result<Response, PasteError> {
+    if props.raw == true {
+        return get_raw_paste_by_url(
+            jar,
+            req_headers,
+            addr,
+            State(database),
+            Path(url),
+            Query(signed),
+        )
+        .await;
+    }
+
+    let renderer = match database.options.renderer {
+        Some(ref r) => r.clone(),
+        None => return Err(PasteError::NotAllowed),
+    };
+
+    let mut paste = match database.get_paste_by_url(url).await {
+        Ok(p) => p,
+        Err(e) => return Err(e),
+    };
+
+    if let Err(e) = database.run_view_hook(&paste) {
+        return Err(e);
+    }
+
+    if !is_readable(&database, &paste, &signed).await {
+        return Err(PasteError::NotAllowed);
+    }
+
+    // sensitive pastes are only rendered once the viewer has agreed to see them
+    if (paste.metadata.sensitive == true) && (props.consent == false) && !has_consented(&jar) {
+        return Ok(sensitive_interstitial(&props, &signed));
+    }
+
+    // count view (failing to count shouldn't stop the paste from being viewed)
+    let viewer = if database.options.view_mode == ViewMode::AuthenticatedOnce {
+        get_editing_as(&jar, &database).await.unwrap_or(None)
+    } else {
+        None
+    };
+
+    let _ = database
+        .count_view_by_url(
+            paste.url.clone(),
+            viewer,
+            &get_client_ip(&database, &req_headers, addr),
+        )
+        .await;
+
+    // render
+    paste.content = database.mask_content(&paste);
+    let table = if props.sort.is_empty() {
+        None
+    } else {
+        database.get_rendered_table(&paste, &props.sort).await
+    };
+
+    let html = match table {
+        Some(html) => html,
+        None => {
+            database
+                .get_rendered(&paste, "html", |content| (renderer.0)(content))
+                .await
+        }
+    };
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "Content-Type",
+        HeaderValue::from_static("text/html; charset=utf-8"),
+    );
+    headers.insert(
+        "X-Content-Type-Options",
+        HeaderValue::from_static("nosniff"),
+    );
+    headers.insert(
+        "Content-Security-Policy",
+        HeaderValue::from_static(
+            "default-src 'none'; img-src http: https:; style-src 'unsafe-inline'",
+        ),
+    );
+
+    if paste.metadata.noindex == true {
+        headers.insert("X-Robots-Tag", HeaderValue::from_static("noindex"));
+    }
+
+    let html = if paste.metadata.sensitive == true {
+        // responses depend on the viewer's consent, so they can't be shared
+        headers.insert(CACHE_CONTROL, HeaderValue::from_static("private"));
+
+        if props.consent == true {
+            headers.insert("Set-Cookie", HeaderValue::from_static(CONSENT_COOKIE));
+        }
+
+        format!("{}\n{}", RATING_META, html)
+    } else {
+        html
+    };
+
+    Ok((headers, html).into_response())
+}
+
+/// Get the rows of an existing CSV or TSV paste (`/api/:url/table`)
+async fn get_paste_table_by_url(
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Query(props): Query<TableQuery>,
+    Query(signed): Query<SignedQuery>,
+) -> Result<Json<DefaultReturn<PasteTable>>, PasteError> {
+    if !props.format.is_empty() && (props.format != "json") {
+        return Err(PasteError::ValueError);
+    }
+
+    let mut paste = match database.get_paste_by_url(url).await {
+        Ok(p) => p,
+        Err(e) => return Err(e),
+    };
+
+    if let Err(e) = database.run_view_hook(&paste) {
+        return Err(e);
+    }
+
+    if !is_readable(&database, &paste, &signed).await {
+        return Err(PasteError::NotAllowed);
+    }
+
+    paste.content = database.mask_content(&paste);
+
+    match database.get_table(&paste, &props.sort) {
+        Ok(table) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Paste table"),
+            payload: table,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Query the data of an existing JSON, YAML or TOML paste (`/api/:url/query?path=$.items[0]`)
+async fn query_paste_by_url(
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Query(props): Query<DataQuery>,
+    Query(signed): Query<SignedQuery>,
+) -> Result<Json<DefaultReturn<Vec<Value>>>, PasteError> {
+    let mut paste = match database.get_paste_by_url(url).await {
+        Ok(p) => p,
+        Err(e) => return Err(e),
+    };
+
+    if let Err(e) = database.run_view_hook(&paste) {
+        return Err(e);
+    }
+
+    if !is_readable(&database, &paste, &signed).await {
+        return Err(PasteError::NotAllowed);
+    }
+
+    paste.content = database.mask_content(&paste);
+
+    match database.query_paste(&paste, &props.path) {
+        Ok(values) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Query results"),
+            payload: values,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Check if the content of an existing paste can be read, given the signed url it was requested with
+///
+/// Quarantined pastes can never be read (see [`is_quarantined`]), and pastes protected by a
+/// view password can only be read with a signed url (see [`is_unlocked`]).
+async fn is_readable(database: &Database, paste: &Paste, signed: &SignedQuery) -> bool {
+    !is_quarantined(paste) && is_unlocked(database, paste, signed).await
+}
+
+/// Check if an existing paste isn't protected by a view password, or was requested with a
+/// valid signed url for it (see [`Database::check_signed_url`])
+async fn is_unlocked(database: &Database, paste: &Paste, signed: &SignedQuery) -> bool {
+    paste.metadata.view_password.is_empty()
+        || database
+            .check_signed_url(paste, &signed.sig, signed.exp)
+            .await
+}
+
+/// Check if an existing paste was quarantined by a malware scan (see [`Database::scan_paste_by_url`])
+fn is_quarantined(paste: &Paste) -> bool {
+    paste.metadata.scan_status == "infected"
+}
+
+/// Get an existing paste protected by a view password (`/api/:url/unlock`)
+async fn unlock_paste_by_url(
+    jar: CookieJar,
+    headers: HeaderMap,
+    addr: Option<ConnectInfo<SocketAddr>>,
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Json(props): Json<PasteUnlock>,
+) -> Result<Json<DefaultReturn<PublicPaste>>, PasteError> {
+    // check rate limit (slows down guessing)
+    let ip = get_client_ip(&database, &headers, addr);
+
+    if let Err(e) = database
+        .check_rate_limit(format!("se_unlock_rate:{}", dorsal::utility::hash(ip)), 10)
+        .await
+    {
+        return Err(e);
+    }
+
+    let mut paste = match database.unlock_paste_by_url(url, props.password).await {
+        Ok(p) => p,
+        Err(e) => return Err(e),
+    };
+
+    if let Err(e) = database.run_view_hook(&paste) {
+        return Err(e);
+    }
+
+    // quarantined pastes are returned without their content
+    if is_quarantined(&paste) {
+        paste.content = String::new();
+    }
+
+    // watermark (every unlocked paste is protected)
+    let paste = if database.options.watermark == true {
+        let username = match get_editing_as(&jar, &database).await {
+            Ok(Some(ua)) => ua.user.username,
+            Ok(None) => String::new(),
+            Err(e) => return Err(e),
+        };
+
+        match database.watermark_paste(paste, username).await {
+            Ok(p) => p,
+            Err(e) => return Err(e),
+        }
+    } else {
+        paste
+    };
+
+    Ok(Json(DefaultReturn {
+        success: true,
+        message: String::from("Paste unlocked"),
+        payload: paste.into(),
+    }))
+}
+
+/// Get the languages listed in a request's `Accept-Language` header, most preferred first
+fn get_accepted_languages(headers: &HeaderMap) -> Vec<String> {
+    let header = match headers.get("Accept-Language").and_then(|v| v.to_str().ok()) {
+        Some(h) => h,
+        None => return Vec::new(),
+    };
+
+    let mut langs: Vec<(String, f32)> = header
+        .split(",")
+        .filter_map(|l| {
+            let mut parts = l.trim().split(";");
+            let tag = parts.next()?.trim().to_string();
+
+            let quality = parts
+                .find_map(|p| p.trim().strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            if tag.is_empty() | (tag == "*") | (quality <= 0.0) {
+                return None;
+            }
+
+            Some((tag, quality))
+        })
+        .collect();
+
+    // stable, so equal qualities keep their order
+    langs.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    langs.into_iter().map(|l| l.0).collect()
+}
+
+// documents
+
+/// Get the user a document request is being made as, making sure they're allowed to manage documents
+///
+/// Documents are only accessible to users with the "ManageDocuments" permission.
+async fn get_document_manager(
+    jar: &CookieJar,
+    database: &Database,
+) -> Result<FullUser<UserMetadata>, PasteError> {
+    if database.options.guppy == false {
+        return Err(PasteError::NotAllowed);
+    }
+
+    let cookie = match jar.get("__Secure-Token") {
+        Some(c) => c,
+        None => return Err(PasteError::NotAllowed),
+    };
+
+    match database
+        .auth
+        .get_user_by_unhashed(cookie.value_trimmed().to_string())
+        .await
+    {
+        Ok(ua) => {
+            if !ua
+                .level
+                .permissions
+                .contains(&"ManageDocuments".to_string())
+            {
+                return Err(PasteError::NotAllowed);
+            }
+
+            Ok(ua)
+        }
+        Err(_) => Err(PasteError::NotAllowed),
+    }
+}
+
+/// Create a new document (`/api/docs/:namespace`)
+async fn create_document(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path(namespace): Path<String>,
+    Json(document_to_create): Json<DocumentCreateBody<String, Value>>,
+) -> Result<Json<DefaultReturn<Document<String, Value>>>, PasteError> {
+    if let Err(e) = get_document_manager(&jar, &database).await {
+        return Err(e);
+    }
+
+    match database
+        .push(DocumentCreate {
+            namespace,
+            content: document_to_create.content,
+            metadata: document_to_create.metadata,
+        })
+        .await
+    {
+        Ok(doc) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Document created"),
+            payload: doc,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Get the usage of a document namespace (`/api/docs/:namespace`)
+async fn get_namespace_usage(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path(namespace): Path<String>,
+) -> Result<Json<DefaultReturn<NamespaceUsage>>, PasteError> {
+    if let Err(e) = get_document_manager(&jar, &database).await {
+        return Err(e);
+    }
+
+    match database.get_namespace_usage(namespace).await {
+        Ok(usage) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Namespace usage"),
+            payload: usage,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Get an existing document (`/api/docs/:namespace/:id`)
+async fn get_document(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path((namespace, id)): Path<(String, String)>,
+) -> Result<Json<DefaultReturn<Document<String, Value>>>, PasteError> {
+    if let Err(e) = get_document_manager(&jar, &database).await {
+        return Err(e);
+    }
+
+    match database.pull::<String, Value>(id, namespace).await {
+        Ok(doc) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Document exists"),
+            payload: doc,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Edit an existing document (`/api/docs/:namespace/:id`)
+async fn edit_document(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path((namespace, id)): Path<(String, String)>,
+    Json(document_to_edit): Json<DocumentEdit<String, Value>>,
+) -> Result<Json<DefaultReturn<()>>, PasteError> {
+    if let Err(e) = get_document_manager(&jar, &database).await {
+        return Err(e);
+    }
+
+    if let Err(e) = database
+        .update::<String, Value>(id.clone(), namespace.clone(), document_to_edit.content)
+        .await
+    {
+        return Err(e);
+    }
+
+    if let Some(metadata) = document_to_edit.metadata {
+        if let Err(e) = database
+            .update_metadata::<String, Value>(id, namespace, metadata)
+            .await
+        {
+            return Err(e);
+        }
+    }
+
+    Ok(Json(DefaultReturn {
+        success: true,
+        message: String::from("Document updated"),
+        payload: (),
+    }))
+}
+
+/// Delete an existing document (`/api/docs/:namespace/:id`)
+async fn delete_document(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path((namespace, id)): Path<(String, String)>,
+) -> Result<Json<DefaultReturn<()>>, PasteError> {
+    if let Err(e) = get_document_manager(&jar, &database).await {
+        return Err(e);
+    }
+
+    match database.drop::<String, Value>(id, namespace).await {
+        Ok(_) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Document deleted"),
+            payload: (),
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+// ingest
+
+/// Get the user a request is being made as, making sure they're allowed to manage pastes
+async fn get_paste_manager(
+    jar: &CookieJar,
+    database: &Database,
+) -> Result<FullUser<UserMetadata>, PasteError> {
+    match get_editing_as(jar, database).await {
+        Ok(Some(ua)) => {
+            if !ua.level.permissions.contains(&"ManagePastes".to_string()) {
+                return Err(PasteError::NotAllowed);
+            }
+
+            Ok(ua)
+        }
+        Ok(None) => Err(PasteError::NotAllowed),
+        Err(e) => Err(e),
+    }
+}
+
+/// Create a new ingest token (`/api/ingest/new`)
+async fn create_ingest_token(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Json(token_to_create): Json<IngestTokenCreate>,
+) -> Result<Json<DefaultReturn<(String, IngestToken)>>, PasteError> {
+    let ua = match get_paste_manager(&jar, &database).await {
+        Ok(ua) => ua,
+        Err(e) => return Err(e),
+    };
+
+    match database
+        .create_ingest_token(token_to_create, ua.user.username)
+        .await
+    {
+        Ok(token) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Token created"),
+            payload: token,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Delete an existing ingest token by its id (`/api/ingest/:id/delete`)
+async fn delete_ingest_token(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path(id): Path<String>,
+) -> Result<Json<DefaultReturn<()>>, PasteError> {
+    if let Err(e) = get_paste_manager(&jar, &database).await {
+        return Err(e);
+    }
+
+    match database.delete_ingest_token(id).await {
+        Ok(_) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Token deleted"),
+            payload: (),
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Create a new invite (`/api/invites/new`)
+async fn create_invite(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Json(invite_to_create): Json<InviteCreate>,
+) -> Result<Json<DefaultReturn<(String, Invite)>>, PasteError> {
+    let ua = match get_paste_manager(&jar, &database).await {
+        Ok(ua) => ua,
+        Err(e) => return Err(e),
+    };
+
+    match database
+        .create_invite(invite_to_create, ua.user.username)
+        .await
+    {
+        Ok(invite) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Invite created"),
+            payload: invite,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Get an invite by its code, to see how many uses it has left (`/api/invites/:code`)
+async fn get_invite(
+    State(database): State<Database>,
+    Path(code): Path<String>,
+) -> Result<Json<DefaultReturn<Invite>>, PasteError> {
+    match database.get_invite(code).await {
+        Ok(invite) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Invite exists"),
+            payload: invite,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Delete an existing invite by its id (`/api/invites/:id/delete`)
+async fn delete_invite(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path(id): Path<String>,
+) -> Result<Json<DefaultReturn<()>>, PasteError> {
+    if let Err(e) = get_paste_manager(&jar, &database).await {
+        return Err(e);
+    }
+
+    match database.delete_invite(id).await {
+        Ok(_) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Invite deleted"),
+            payload: (),
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Create a new paste from the request body using an ingest token (`/api/ingest/:token`)
+async fn ingest_paste(
+    State(database): State<Database>,
+    Path(token): Path<String>,
+    content: String,
+) -> Result<Json<DefaultReturn<(String, Paste)>>, PasteError> {
+    match database.ingest_paste(token, content).await {
+        Ok(paste) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Paste created"),
+            payload: paste,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Create a new paste from a streamed request body using an ingest token (`/api/ingest/:token`)
+///
+/// The paste is created from the first chunk, every following chunk is appended to it
+/// as it arrives (e.g. `my_build | curl -T - https://example.com/api/ingest/:token`).
+async fn stream_ingest_paste(
+    State(database): State<Database>,
+    Path(token): Path<String>,
+    body: Body,
+) -> Result<Json<DefaultReturn<(String, Paste)>>, PasteError> {
+    let max_size = match database.get_ingest_token(token.clone()).await {
+        Ok(t) => t.max_size,
+        Err(PasteError::NotFound) => return Err(PasteError::NotAllowed),
+        Err(e) => return Err(e),
+    };
+
+    let mut stream = body.into_data_stream();
+    let mut pending: Vec<u8> = Vec::new();
+    let mut size: usize = 0;
+    let mut created: Option<(String, Paste)> = None;
+
+    while let Some(chunk) = stream.next().await {
+        let chunk = match chunk {
+            Ok(c) => c,
+            Err(_) => return Err(PasteError::Other),
+        };
+
+        size += chunk.len();
+
+        if (max_size != 0) && (size > max_size) {
+            return Err(PasteError::ValueError);
+        }
+
+        // only take complete characters, a chunk can end in the middle of one
+        pending.extend_from_slice(&chunk);
+
+        let valid = match std::str::from_utf8(&pending) {
+            Ok(s) => s.len(),
+            Err(e) => match e.error_len() {
+                Some(_) => pending.len(), // actually invalid, replace it below
+                None => e.valid_up_to(),
+            },
+        };
+
+        let content = String::from_utf8_lossy(&pending[..valid]).to_string();
+        pending.drain(..valid);
+
+        if content.is_empty() {
+            continue;
+        }
+
+        // create or append
+        match created {
+            Some((ref password, ref paste)) => {
+                if let Err(e) = database
+                    .append_paste_by_url(paste.url.clone(), password.clone(), content, None)
+                    .await
+                {
+                    return Err(e);
+                }
+            }
+            None => match database.ingest_paste(token.clone(), content).await {
+                Ok(p) => created = Some(p),
+                Err(e) => return Err(e),
+            },
+        }
+    }
+
+    // an incomplete character left at the end of the body is replaced
+    if let Some((ref password, ref paste)) = created {
+        if !pending.is_empty() {
+            if let Err(e) = database
+                .append_paste_by_url(
+                    paste.url.clone(),
+                    password.clone(),
+                    String::from_utf8_lossy(&pending).to_string(),
+                    None,
+                )
+                .await
+            {
+                return Err(e);
+            }
+        }
+    }
+
+    match created {
+        Some((password, paste)) => match database.get_paste_by_url(paste.url).await {
+            Ok(paste) => Ok(Json(DefaultReturn {
+                success: true,
+                message: String::from("Paste created"),
+                payload: (password, paste),
+            })),
+            Err(e) => Err(e),
+        },
+        None => Err(PasteError::ValueError),
+    }
+}
+
+// collections
+
+/// Get the user a request is being made as, making sure they're logged in
+async fn get_logged_in(
+    jar: &CookieJar,
+    database: &Database,
+) -> Result<FullUser<UserMetadata>, PasteError> {
+    match get_editing_as(jar, database).await {
+        Ok(Some(ua)) => Ok(ua),
+        Ok(None) => Err(PasteError::NotAllowed),
+        Err(e) => Err(e),
+    }
+}
+
+/// Create a new collection (`/api/collections/new`)
+async fn create_collection(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Json(collection_to_create): Json<CollectionCreate>,
+) -> Result<Json<DefaultReturn<Collection>>, PasteError> {
+    let ua = match get_logged_in(&jar, &database).await {
+        Ok(ua) => ua,
+        Err(e) => return Err(e),
+    };
+
+    match database
+        .create_collection(collection_to_create, ua.user.username)
+        .await
+    {
+        Ok(collection) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Collection created"),
+            payload: collection,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Get an existing collection by its id (`/api/collections/:id`)
+async fn get_collection(
+    State(database): State<Database>,
+    Path(id): Path<String>,
+) -> Result<Json<DefaultReturn<Collection>>, PasteError> {
+    match database.get_collection(id).await {
+        Ok(collection) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Collection exists"),
+            payload: collection,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Add a paste to the end of an existing collection (`/api/collections/:id/add`)
+async fn add_paste_to_collection(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path(id): Path<String>,
+    Json(props): Json<CollectionPaste>,
+) -> Result<Json<DefaultReturn<Collection>>, PasteError> {
+    let ua = match get_logged_in(&jar, &database).await {
+        Ok(ua) => ua,
+        Err(e) => return Err(e),
+    };
+
+    match database.add_paste_to_collection(id, props.url, ua).await {
+        Ok(collection) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Paste added"),
+            payload: collection,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Remove a paste from an existing collection (`/api/collections/:id/remove`)
+async fn remove_paste_from_collection(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path(id): Path<String>,
+    Json(props): Json<CollectionPaste>,
+) -> Result<Json<DefaultReturn<Collection>>, PasteError> {
+    let ua = match get_logged_in(&jar, &database).await {
+        Ok(ua) => ua,
+        Err(e) => return Err(e),
+    };
+
+    match database
+        .remove_paste_from_collection(id, props.url, ua)
+        .await
+    {
+        Ok(collection) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Paste removed"),
+            payload: collection,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Delete an existing collection by its id (`/api/collections/:id/delete`)
+async fn delete_collection(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path(id): Path<String>,
+) -> Result<Json<DefaultReturn<()>>, PasteError> {
+    let ua = match get_logged_in(&jar, &database).await {
+        Ok(ua) => ua,
+        Err(e) => return Err(e),
+    };
+
+    match database.delete_collection(id, ua).await {
+        Ok(_) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Collection deleted"),
+            payload: (),
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Get the previous and next pastes of every collection a paste is in (`/api/:url/collections`)
+async fn get_paste_collections_by_url(
+    State(database): State<Database>,
+    Path(url): Path<String>,
+) -> Result<Json<DefaultReturn<Vec<CollectionNavigation>>>, PasteError> {
+    match database.get_collection_navigation_by_url(url).await {
+        Ok(navigation) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Paste exists"),
+            payload: navigation,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+// suggestions
+
+/// Get all pending suggestions for a paste (`/api/:url/suggestions`)
+///
+/// Only the paste's reviewers can list suggestions (see [`Database::check_suggestion_reviewer`]).
+async fn get_suggestions_by_url(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Query(review): Query<SuggestionReview>,
+    Query(signed): Query<SignedQuery>,
+) -> Result<Json<DefaultReturn<Vec<Suggestion>>>, PasteError> {
+    // suggestions are edited copies of the content
+    let paste = match database.get_paste_by_url(url).await {
+        Ok(p) => p,
+        Err(e) => return Err(e),
+    };
+
+    if !is_readable(&database, &paste, &signed).await {
+        return Err(PasteError::NotAllowed);
+    }
+
+    if let Err(e) = database.check_suggestion_reviewer(
+        &paste,
+        review.password,
+        match get_editing_as(&jar, &database).await {
+            Ok(ua) => ua,
+            Err(e) => return Err(e),
+        },
+    ) {
+        return Err(e);
+    }
+
+    match database.get_suggestions_by_url(paste.url).await {
+        Ok(suggestions) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Paste exists"),
+            payload: suggestions,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Suggest an edit to a paste (`/api/:url/suggestions`)
+async fn create_suggestion(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Query(signed): Query<SignedQuery>,
+    Json(suggestion_to_create): Json<SuggestionCreate>,
+) -> Result<Json<DefaultReturn<Suggestion>>, PasteError> {
+    // the suggestion's diff includes the content, so only readers can suggest edits
+    let paste = match database.get_paste_by_url(url).await {
+        Ok(p) => p,
+        Err(e) => return Err(e),
+    };
+
+    if !is_readable(&database, &paste, &signed).await {
+        return Err(PasteError::NotAllowed);
+    }
+
+    let author = match get_editing_as(&jar, &database).await {
+        Ok(Some(ua)) => ua.user.username,
+        Ok(None) => String::new(),
+        Err(e) => return Err(e),
+    };
+
+    match database
+        .create_suggestion(paste.url, suggestion_to_create, author)
+        .await
+    {
+        Ok(suggestion) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Suggestion created"),
+            payload: suggestion,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Get a suggestion and its diff against the paste (`/api/:url/suggestions/:id`)
+///
+/// Only the paste's reviewers can see suggestions (see [`Database::check_suggestion_reviewer`]).
+async fn get_suggestion_diff(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path((url, id)): Path<(String, String)>,
+    Query(review): Query<SuggestionReview>,
+    Query(signed): Query<SignedQuery>,
+) -> Result<Json<DefaultReturn<SuggestionDiff>>, PasteError> {
+    // the diff includes the paste's current content
+    let paste = match database.get_paste_by_url(url).await {
+        Ok(p) => p,
+        Err(e) => return Err(e),
+    };
+
+    if !is_readable(&database, &paste, &signed).await {
+        return Err(PasteError::NotAllowed);
+    }
+
+    if let Err(e) = database.check_suggestion_reviewer(
+        &paste,
+        review.password,
+        match get_editing_as(&jar, &database).await {
+            Ok(ua) => ua,
+            Err(e) => return Err(e),
+        },
+    ) {
+        return Err(e);
+    }
+
+    match database.get_suggestion_diff(paste.url, id).await {
+        Ok(diff) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Suggestion exists"),
+            payload: diff,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Accept a suggestion, replacing the paste's content with it (`/api/:url/suggestions/:id/accept`)
+async fn accept_suggestion(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path((url, id)): Path<(String, String)>,
+    Json(props): Json<SuggestionReview>,
+) -> Result<Json<DefaultReturn<()>>, PasteError> {
+    match database
+        .accept_suggestion(
+            url,
+            id,
+            props.password,
+            match get_editing_as(&jar, &database).await {
+                Ok(ua) => ua,
+                Err(e) => return Err(e),
+            },
+        )
+        .await
+    {
+        Ok(_) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Suggestion accepted"),
+            payload: (),
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Reject a suggestion (`/api/:url/suggestions/:id/reject`)
+async fn reject_suggestion(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path((url, id)): Path<(String, String)>,
+    Json(props): Json<SuggestionReview>,
+) -> Result<Json<DefaultReturn<()>>, PasteError> {
+    match database
+        .reject_suggestion(
+            url,
+            id,
+            props.password,
+            match get_editing_as(&jar, &database).await {
+                Ok(ua) => ua,
+                Err(e) => return Err(e),
+            },
+        )
+        .await
+    {
+        Ok(_) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Suggestion rejected"),
+            payload: (),
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+// translations
+
+/// Get all translations of a paste (`/api/:url/translations`)
+async fn get_translations_by_url(
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Query(signed): Query<SignedQuery>,
+) -> Result<Json<DefaultReturn<Vec<PasteTranslation>>>, PasteError> {
+    let paste = match database.get_paste_by_url(url).await {
+        Ok(p) => p,
+        Err(e) => return Err(e),
+    };
+
+    if !is_readable(&database, &paste, &signed).await {
+        return Err(PasteError::NotAllowed);
+    }
+
+    match database.get_translations_by_url(paste.url).await {
+        Ok(translations) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Paste exists"),
+            payload: translations,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Create, replace or delete (empty content) a translation of a paste (`/api/:url/translations/:lang`)
+async fn edit_translation_by_url(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path((url, lang)): Path<(String, String)>,
+    Json(props): Json<TranslationEdit>,
+) -> Result<Json<DefaultReturn<()>>, PasteError> {
+    match database
+        .edit_translation_by_url(
+            url,
+            lang,
+            props.content,
+            props.password,
+            match get_editing_as(&jar, &database).await {
+                Ok(ua) => ua,
+                Err(e) => return Err(e),
+            },
+        )
+        .await
+    {
+        Ok(_) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Translation updated"),
+            payload: (),
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+// annotations
+
+/// Get all annotations on a paste (`/api/:url/annotations`)
+async fn get_annotations_by_url(
+    State(database): State<Database>,
+    Path(url): Path<String>,
+) -> Result<Json<DefaultReturn<Vec<Annotation>>>, PasteError> {
+    match database.get_annotations_by_url(url).await {
+        Ok(annotations) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Paste exists"),
+            payload: annotations,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Annotate a range of lines in a paste (`/api/:url/annotations`)
+async fn create_annotation(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Json(annotation_to_create): Json<AnnotationCreate>,
+) -> Result<Json<DefaultReturn<Annotation>>, PasteError> {
+    let author = match get_editing_as(&jar, &database).await {
+        Ok(Some(ua)) => ua.user.username,
+        Ok(None) => String::new(),
+        Err(e) => return Err(e),
+    };
+
+    match database
+        .create_annotation(url, annotation_to_create, author)
+        .await
+    {
+        Ok(annotation) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Annotation created"),
+            payload: annotation,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Delete an annotation (`/api/:url/annotations/:id/delete`)
+async fn delete_annotation(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path((url, id)): Path<(String, String)>,
+    Json(props): Json<AnnotationDelete>,
+) -> Result<Json<DefaultReturn<()>>, PasteError> {
+    match database
+        .delete_annotation(
+            url,
+            id,
+            props.password,
+            match get_editing_as(&jar, &database).await {
+                Ok(ua) => ua,
+                Err(e) => return Err(e),
+            },
+        )
+        .await
+    {
+        Ok(_) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Annotation deleted"),
+            payload: (),
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+// runners
+
+/// Run a paste's runnable code blocks (`/api/:url/run`)
+#[cfg(feature = "runners")]
+async fn run_paste_by_url(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Json(props): Json<PasteRun>,
+) -> Result<Json<DefaultReturn<Vec<BlockOutput>>>, PasteError> {
+    match database
+        .run_paste_by_url(
+            url,
+            props.password,
+            match get_editing_as(&jar, &database).await {
+                Ok(ua) => ua,
+                Err(e) => return Err(e),
+            },
+        )
+        .await
+    {
+        Ok(outputs) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Paste run"),
+            payload: outputs,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Get the stored outputs of a paste's runnable code blocks (`/api/:url/outputs`)
+async fn get_paste_outputs_by_url(
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Query(signed): Query<SignedQuery>,
+) -> Result<Json<DefaultReturn<Vec<BlockOutput>>>, PasteError> {
+    let paste = match database.get_paste_by_url(url).await {
+        Ok(p) => p,
+        Err(e) => return Err(e),
+    };
+
+    if !is_readable(&database, &paste, &signed).await {
+        return Err(PasteError::NotAllowed);
+    }
+
+    Ok(Json(DefaultReturn {
+        success: true,
+        message: String::from("Paste outputs"),
+        payload: database.get_outputs_by_url(paste.url).await,
+    }))
+}
+
+// thumbnails
+
+/// Get a minimap thumbnail of a paste (`/api/:url/thumb.png`)
+///
+/// Thumbnails are cheap to draw, so they're rendered on request and cached by clients
+/// through an `ETag` of the paste's content.
+#[cfg(feature = "thumbnails")]
+async fn get_paste_thumbnail_by_url(
+    req_headers: HeaderMap,
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Query(signed): Query<SignedQuery>,
+) -> Result<Response, PasteError> {
+    let paste = match database.get_paste_by_url(url).await {
+        Ok(p) => p,
+        Err(e) => return Err(e),
+    };
+
+    // protected pastes only have a thumbnail through a signed url
+    if !is_readable(&database, &paste, &signed).await {
+        return Err(PasteError::NotAllowed);
+    }
+
+    let etag = format!("\"{}\"", dorsal::utility::hash(paste.content.clone()));
+
+    if let Some(value) = req_headers.get("If-None-Match") {
+        if value.to_str().unwrap_or("") == etag {
+            return Ok(StatusCode::NOT_MODIFIED.into_response());
+        }
+    }
+
+    Ok((
+        [
+            ("Content-Type".to_string(), "image/png".to_string()),
+            ("ETag".to_string(), etag),
+        ],
+        crate::thumbnail::render(&paste.content),
+    )
+        .into_response())
+}
+
+// links
+
+/// Get the dead-link report of a paste (`/api/:url/links`)
+///
+/// Links are checked right away the first time a paste's report is requested, but only if
+/// the request is made by the paste's owner (or a user with the "ManagePastes" permission),
+/// since checking makes the server request every link.
+#[cfg(feature = "links")]
+async fn get_link_report_by_url(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Query(signed): Query<SignedQuery>,
+) -> Result<Json<DefaultReturn<LinkReport>>, PasteError> {
+    // reports list the paste's links
+    let paste = match database.get_paste_by_url(url).await {
+        Ok(p) => p,
+        Err(e) => return Err(e),
+    };
+
+    if !is_readable(&database, &paste, &signed).await {
+        return Err(PasteError::NotAllowed);
+    }
+
+    let report = match database.get_link_report_by_url(paste.url.clone()).await {
+        Some(r) => r,
+        None => {
+            match get_editing_as(&jar, &database).await {
+                Ok(Some(ua)) => {
+                    if (ua.user.username != paste.metadata.owner)
+                        && !ua.level.permissions.contains(&"ManagePastes".to_string())
+                    {
+                        return Err(PasteError::NotFound);
+                    }
+                }
+                Ok(None) => return Err(PasteError::NotFound),
+                Err(e) => return Err(e),
+            };
+
+            match database.check_links_by_url(paste.url).await {
+                Ok(r) => r,
+                Err(e) => return Err(e),
+            }
+        }
+    };
+
+    Ok(Json(DefaultReturn {
+        success: true,
+        message: String::from("Paste links"),
+        payload: report,
+    }))
+}
+
+// watermarks
+
+/// Find which download of a paste leaked some content (`/api/:url/trace`)
+async fn trace_paste_by_url(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path(url): Path<String>,
+    Json(props): Json<WatermarkTrace>,
+) -> Result<Json<DefaultReturn<Download>>, PasteError> {
+    match database
+        .trace_paste_by_url(
+            url,
+            props.content,
+            props.password,
+            match get_editing_as(&jar, &database).await {
+                Ok(ua) => ua,
+                Err(e) => return Err(e),
+            },
+        )
+        .await
+    {
+        Ok(download) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Download found"),
+            payload: download,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+// general
+
+/// Fallback for unknown API paths
+///
+/// Only applies to paths under wherever [`routes`] is nested, so the embedding app
+/// can use its own (HTML) fallback for everything else.
+pub async fn not_found() -> impl IntoResponse {
+    (
+        StatusCode::NOT_FOUND,
+        Json(DefaultReturn::<u16> {
+            success: false,
+            message: String::from("Path does not exist"),
+            payload: 404,
+        }),
+    )
+}
+
+// sensitive content
+
+/// Cookie remembering that a session agreed to view sensitive pastes
+const CONSENT_COOKIE: &str =
+    "__Secure-Consent=1; SameSite=Lax; Secure; Path=/; HostOnly=true; HttpOnly=true";
+
+/// Tag marking rendered sensitive pastes (and their interstitial) as adult content
+const RATING_META: &str = "<meta name=\"rating\" content=\"adult\">";
+
+/// If the request has agreed to view sensitive pastes this session (through `/api/consent`)
+pub fn has_consented(jar: &CookieJar) -> bool {
+    jar.get("__Secure-Consent").is_some()
+}
+
+/// Get the page shown instead of a sensitive paste until the viewer agrees to see it
+///
+/// Continuing reloads the page with `?consent=true` (keeping its sort and signed url), which
+/// records consent like `/api/consent` does.
+fn sensitive_interstitial(props: &RenderQuery, signed: &SignedQuery) -> Response {
+    let mut query = String::from("?consent=true");
+
+    if !props.sort.is_empty()
+        && props
+            .sort
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() | (c == '-'))
+    {
+        query.push_str(&format!("&amp;sort={}", props.sort));
+    }
+
+    if !signed.sig.is_empty() && signed.sig.chars().all(|c| c.is_ascii_hexdigit()) {
+        query.push_str(&format!("&amp;sig={}&amp;exp={}", signed.sig, signed.exp));
+    }
+
+    let html = format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+{}
+<meta name=\"robots\" content=\"noindex\">
+<title>Sensitive content</title>
+</head>
+<body>
+<p>This paste has been marked as sensitive. Do you want to view it?</p>
+<p><a href=\"{}\">View paste</a></p>
+</body>
+</html>
+",
+        RATING_META, query
+    );
+
+    (
+        [
+            ("Content-Type", "text/html; charset=utf-8"),
+            ("X-Content-Type-Options", "nosniff"),
+            ("X-Robots-Tag", "noindex"),
+            ("Content-Security-Policy", "default-src 'none'"),
+            ("Cache-Control", "private, no-store"),
+        ],
+        html,
+    )
+        .into_response()
+}
+
+/// Agree to view sensitive pastes for the rest of the session (`/api/consent`)
+pub async fn consent_request() -> impl IntoResponse {
+    (
+        [("Set-Cookie".to_string(), CONSENT_COOKIE.to_string())],
+        Json(DefaultReturn {
+            success: true,
+            message: String::from("Consent recorded"),
+            payload: (),
+        }),
+    )
+}
+
+// announcement
+
+/// Get the instance announcement (`/api/announcement`)
+///
+/// Returns no announcement if it isn't scheduled to be shown or has been dismissed.
+pub async fn get_announcement(
+    jar: CookieJar,
+    State(database): State<Database>,
+) -> Json<DefaultReturn<Option<Announcement>>> {
+    let announcement = match database.get_announcement().await {
+        Some(a) => match jar.get("__Secure-Dismissed") {
+            Some(c) if c.value_trimmed() == a.id => None,
+            _ => Some(a),
+        },
+        None => None,
+    };
+
+    Json(DefaultReturn {
+        success: true,
+        message: String::from("Announcement"),
+        payload: announcement,
+    })
 }
 
-/// Create a new paste (`/api/new`)
-async fn create_paste(
+/// Replace the instance announcement (`/api/announcement`)
+async fn set_announcement(
+    jar: CookieJar,
     State(database): State<Database>,
-    Json(paste_to_create): Json<PasteCreate>,
-) -> Result<Json<DefaultReturn<(String, Paste)>>, PasteError> {
-    let res = database.create_paste(paste_to_create).await;
+    Json(props): Json<AnnouncementCreate>,
+) -> Result<Json<DefaultReturn<Announcement>>, PasteError> {
+    if let Err(e) = get_paste_manager(&jar, &database).await {
+        return Err(e);
+    }
 
-    match res {
-        Ok(paste) => Ok(Json(DefaultReturn {
+    match database.set_announcement(props).await {
+        Ok(announcement) => Ok(Json(DefaultReturn {
             success: true,
-            message: String::from("Paste created"),
-            payload: paste,
+            message: String::from("Announcement updated"),
+            payload: announcement,
         })),
         Err(e) => Err(e),
     }
 }
 
-/// Delete an existing paste (`/api/:url/delete`)
-async fn delete_paste_by_url(
+/// Remove the instance announcement (`/api/announcement/delete`)
+async fn delete_announcement(
+    jar: CookieJar,
     State(database): State<Database>,
-    Path(url): Path<String>,
-    Json(paste_to_delete): Json<PasteDelete>,
 ) -> Result<Json<DefaultReturn<()>>, PasteError> {
-    match database
-        .delete_paste_by_url(url, paste_to_delete.password)
-        .await
-    {
+    if let Err(e) = get_paste_manager(&jar, &database).await {
+        return Err(e);
+    }
+
+    match database.delete_announcement().await {
         Ok(_) => Ok(Json(DefaultReturn {
             success: true,
-            message: String::from("Paste deleted"),
+            message: String::from("Announcement deleted"),
             payload: (),
         })),
         Err(e) => Err(e),
     }
 }
 
-/// Edit an existing paste (`/api/:url/edit`)
-async fn edit_paste_by_url(
-    jar: CookieJar,
+/// Hide the current instance announcement for the rest of the session (`/api/announcement/dismiss`)
+async fn dismiss_announcement(State(database): State<Database>) -> impl IntoResponse {
+    let id = match database.get_announcement().await {
+        Some(a) => a.id,
+        None => String::new(),
+    };
+
+    (
+        [(
+            "Set-Cookie".to_string(),
+            format!(
+                "__Secure-Dismissed={}; SameSite=Lax; Secure; Path=/; HostOnly=true; HttpOnly=true",
+                id
+            ),
+        )],
+        Json(DefaultReturn {
+            success: true,
+            message: String::from("Announcement dismissed"),
+            payload: (),
+        }),
+    )
+}
+
+// meta
+
+/// Get the limits and features of the instance (`/api/meta`)
+async fn get_instance_meta(State(database): State<Database>) -> Json<DefaultReturn<InstanceMeta>> {
+    Json(DefaultReturn {
+        success: true,
+        message: String::from("Instance meta"),
+        payload: database.get_instance_meta(),
+    })
+}
+
+// listing
+
+/// Get a page of the newest public pastes (`/api/pastes?page=N`)
+async fn get_recent_public_pastes(
     State(database): State<Database>,
-    Path(url): Path<String>,
-    Json(paste_to_edit): Json<PasteEdit>,
-) -> Result<Json<DefaultReturn<()>>, PasteError> {
+    Query(props): Query<PasteListQuery>,
+) -> Result<Json<DefaultReturn<Vec<PasteListing>>>, PasteError> {
     match database
-        .edit_paste_by_url(
-            url,
-            paste_to_edit.password,
-            paste_to_edit.new_content,
-            paste_to_edit.new_url,
-            paste_to_edit.new_password,
-            // get editing_as
-            if let Some(cookie) = jar.get("__Secure-Token") {
-                let value = cookie.value_trimmed();
-
-                if database.options.guppy == true {
-                    match database.auth.get_user_by_unhashed(value.to_string()).await {
-                        Ok(ua) => Option::Some(ua),
-                        Err(_) => return Err(PasteError::Other),
-                    }
-                } else {
-                    Option::None
-                }
-            } else {
-                Option::None
-            },
-        )
+        .get_recent_public_pastes(props.page.saturating_mul(PAGE_SIZE), PAGE_SIZE)
         .await
     {
-        Ok(_) => Ok(Json(DefaultReturn {
+        Ok(pastes) => Ok(Json(DefaultReturn {
             success: true,
-            message: String::from("Paste updated"),
-            payload: (),
+            message: String::from("Pastes exist"),
+            payload: pastes,
         })),
         Err(e) => Err(e),
     }
 }
 
-/// Edit an existing paste's metadata (`/api/:url/metadata`)
-async fn edit_paste_metadata_by_url(
+/// Download every paste owned by a user as a JSON array (`/api/owner/:username/export`)
+///
+/// Only the user themselves (or a user with the "ManagePastes" permission) can export
+/// their pastes. The array is streamed as pastes are read.
+async fn export_pastes_by_owner(
     jar: CookieJar,
+    Path(username): Path<String>,
     State(database): State<Database>,
-    Path(url): Path<String>,
-    Json(mut paste_to_edit): Json<PasteEditMetadata>,
-) -> Result<Json<DefaultReturn<()>>, PasteError> {
-    // if we've been given an authentication cookie (and it's allowed),
-    // we'll check the user and then set metadata.owner
-    if let Some(cookie) = jar.get("__Secure-Token") {
-        let value = cookie.value_trimmed();
+) -> Result<Response, PasteError> {
+    match get_editing_as(&jar, &database).await {
+        Ok(Some(ua)) => {
+            if (ua.user.username != username)
+                && !ua.level.permissions.contains(&"ManagePastes".to_string())
+            {
+                return Err(PasteError::NotAllowed);
+            }
+        }
+        Ok(None) => return Err(PasteError::NotAllowed),
+        Err(e) => return Err(e),
+    };
 
-        if (database.options.guppy == true) && (database.options.paste_ownership == true) {
-            match database.auth.get_user_by_unhashed(value.to_string()).await {
-                Ok(ua) => paste_to_edit.metadata.owner = ua.user.username,
-                Err(_) => paste_to_edit.metadata.owner = "".to_string(),
+    let pastes = database
+        .export_pastes_by_owner(username.clone())
+        .enumerate()
+        .map(|(i, paste)| {
+            let json = serde_json::to_string(&paste).unwrap();
+
+            if i == 0 {
+                json
+            } else {
+                format!(",{}", json)
             }
+        });
+
+    let body = futures_util::stream::once(async { String::from("[") })
+        .chain(pastes)
+        .chain(futures_util::stream::once(async { String::from("]") }))
+        .map(Ok::<String, Infallible>);
+
+    Ok((
+        [
+            ("Content-Type".to_string(), "application/json".to_string()),
+            (
+                "Content-Disposition".to_string(),
+                format!(
+                    "attachment; filename=\"{}-pastes.json\"",
+                    username.replace(['"', '\\'], "")
+                ),
+            ),
+        ],
+        Body::from_stream(body),
+    )
+        .into_response())
+}
+
+/// Import pastes as the logged in user (`/api/import?on_conflict=skip|rename|overwrite`)
+///
+/// The body is a JSON array of pastes, like the one downloaded from
+/// `/api/owner/:username/export`.
+async fn import_pastes(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Query(props): Query<ImportQuery>,
+    Json(pastes): Json<Vec<ImportedPaste>>,
+) -> Result<Json<DefaultReturn<Vec<ImportResult>>>, PasteError> {
+    let user = match get_editing_as(&jar, &database).await {
+        Ok(Some(ua)) => ua,
+        Ok(None) => return Err(PasteError::NotAllowed),
+        Err(e) => return Err(e),
+    };
+
+    match database
+        .import_pastes(pastes, props.on_conflict, user)
+        .await
+    {
+        Ok(results) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Pastes imported"),
+            payload: results,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Get a page of a user's pastes (`/api/owner/:username/pastes?page=N`)
+///
+/// Only public pastes are listed unless the user is the owner or has the "ManagePastes"
+/// permission.
+async fn get_pastes_by_owner(
+    jar: CookieJar,
+    Path(username): Path<String>,
+    State(database): State<Database>,
+    Query(props): Query<PasteListQuery>,
+) -> Result<Json<DefaultReturn<Vec<PasteListing>>>, PasteError> {
+    let everything = match get_editing_as(&jar, &database).await {
+        Ok(Some(ua)) => {
+            (ua.user.username == username)
+                | ua.level.permissions.contains(&"ManagePastes".to_string())
         }
+        Ok(None) => false,
+        Err(e) => return Err(e),
+    };
+
+    let offset = props.page.saturating_mul(PAGE_SIZE);
+    let pastes = if everything == true {
+        database
+            .get_pastes_by_owner(username, offset, PAGE_SIZE)
+            .await
     } else {
-        // clear owner field if paste is edited by an anonymous user
-        paste_to_edit.metadata.owner = "".to_string();
+        database
+            .get_public_pastes_by_owner(username, offset, PAGE_SIZE)
+            .await
+    };
+
+    match pastes {
+        Ok(pastes) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Pastes exist"),
+            payload: pastes,
+        })),
+        Err(e) => Err(e),
     }
+}
 
-    // ...
-    match database
-        .edit_paste_metadata_by_url(
-            url,
-            paste_to_edit.password,
-            paste_to_edit.metadata,
-            // get editing_as
-            if let Some(cookie) = jar.get("__Secure-Token") {
-                let value = cookie.value_trimmed();
-
-                if database.options.guppy == true {
-                    match database.auth.get_user_by_unhashed(value.to_string()).await {
-                        Ok(ua) => Option::Some(ua),
-                        Err(_) => return Err(PasteError::Other),
-                    }
-                } else {
-                    Option::None
-                }
-            } else {
-                Option::None
-            },
-        )
+/// Get an Atom feed of the newest public pastes owned by a user (`/api/feed/:username.atom`)
+///
+/// Links in the feed use the origin the feed was requested from (the `Host` header, and
+/// `X-Forwarded-Proto` when it's behind a proxy).
+async fn get_owner_feed(
+    headers: HeaderMap,
+    Path(feed): Path<String>,
+    State(database): State<Database>,
+) -> Result<Response, PasteError> {
+    let username = match feed.strip_suffix(".atom") {
+        Some(u) => u.to_string(),
+        None => return Err(PasteError::NotFound),
+    };
+
+    let pastes = match database
+        .get_public_pastes_by_owner(username.clone(), 0, PAGE_SIZE)
         .await
     {
+        Ok(p) => p,
+        Err(e) => return Err(e),
+    };
+
+    // origin
+    let proto = headers
+        .get("X-Forwarded-Proto")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(',').next())
+        .map(|v| v.trim())
+        .filter(|v| (*v == "http") | (*v == "https"))
+        .unwrap_or("https");
+
+    let host = headers
+        .get("Host")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("localhost");
+
+    let mut res_headers = HeaderMap::new();
+    res_headers.insert(
+        "Content-Type",
+        HeaderValue::from_static("application/atom+xml; charset=utf-8"),
+    );
+
+    Ok((
+        res_headers,
+        crate::feed::render(&format!("{}://{}", proto, host), &username, &pastes),
+    )
+        .into_response())
+}
+
+// pinned
+
+/// Get all pastes pinned to the front page (`/api/pinned`)
+pub async fn get_pinned(State(database): State<Database>) -> Json<DefaultReturn<Vec<PinnedPaste>>> {
+    Json(DefaultReturn {
+        success: true,
+        message: String::from("Pinned pastes"),
+        payload: database.get_pinned().await,
+    })
+}
+
+/// Replace the list of pastes pinned to the front page (`/api/pinned`)
+async fn set_pinned(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Json(props): Json<PinnedEdit>,
+) -> Result<Json<DefaultReturn<Vec<String>>>, PasteError> {
+    if let Err(e) = get_paste_manager(&jar, &database).await {
+        return Err(e);
+    }
+
+    match database.set_pinned(props.urls).await {
+        Ok(pinned) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Pinned pastes updated"),
+            payload: pinned,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+// admin
+
+/// Remove cache entries by key pattern (`/api/admin/cache/purge`)
+async fn purge_cache(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Json(props): Json<CachePurge>,
+) -> Result<Json<DefaultReturn<()>>, PasteError> {
+    if let Err(e) = get_paste_manager(&jar, &database).await {
+        return Err(e);
+    }
+
+    match database.purge_cache(props.patterns).await {
         Ok(_) => Ok(Json(DefaultReturn {
             success: true,
-            message: String::from("Paste updated"),
+            message: String::from("Cache purged"),
             payload: (),
         })),
         Err(e) => Err(e),
     }
 }
 
-/// Get an existing paste by url (`/api/:url`)
-pub async fn get_paste_by_url(
+/// Checkpoint the SQLite write-ahead log (`/api/admin/checkpoint`)
+async fn checkpoint(
+    jar: CookieJar,
     State(database): State<Database>,
-    Path(url): Path<String>,
-) -> Result<Json<DefaultReturn<Paste>>, PasteError> {
-    match database.get_paste_by_url(url).await {
-        Ok(p) => Ok(Json(DefaultReturn {
+    Json(props): Json<CheckpointRequest>,
+) -> Result<Json<DefaultReturn<Checkpoint>>, PasteError> {
+    if let Err(e) = get_paste_manager(&jar, &database).await {
+        return Err(e);
+    }
+
+    match database.checkpoint(props.mode).await {
+        Ok(checkpoint) => Ok(Json(DefaultReturn {
             success: true,
-            message: String::from("Paste exists"),
-            payload: p,
+            message: String::from("Checkpoint finished"),
+            payload: checkpoint,
         })),
         Err(e) => Err(e),
     }
 }
 
-// general
-pub async fn not_found() -> impl IntoResponse {
-    Json(DefaultReturn::<u16> {
-        success: false,
-        message: String::from("Path does not exist"),
-        payload: 404,
-    })
+/// List the pastes a moderation filter matches without changing them (`/api/admin/moderation/preview`)
+async fn preview_moderation(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Json(filter): Json<ModerationFilter>,
+) -> Result<Json<DefaultReturn<ModerationPreview>>, PasteError> {
+    if let Err(e) = get_paste_manager(&jar, &database).await {
+        return Err(e);
+    }
+
+    match database.get_moderated_pastes(&filter).await {
+        Ok(urls) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Pastes matched"),
+            payload: ModerationPreview { urls },
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Delete, lock or unlock every paste matching a filter in the background (`/api/admin/moderation`)
+async fn start_moderation_job(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Json(props): Json<ModerationRequest>,
+) -> Result<Json<DefaultReturn<ModerationJob>>, PasteError> {
+    let user = match get_paste_manager(&jar, &database).await {
+        Ok(ua) => ua,
+        Err(e) => return Err(e),
+    };
+
+    match database
+        .start_moderation_job(props.action, props.filter, user)
+        .await
+    {
+        Ok(job) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Moderation job started"),
+            payload: job,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Get the progress of a moderation job (`/api/admin/moderation/:id`)
+async fn get_moderation_job(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path(id): Path<String>,
+) -> Result<Json<DefaultReturn<ModerationJob>>, PasteError> {
+    if let Err(e) = get_paste_manager(&jar, &database).await {
+        return Err(e);
+    }
+
+    match database.get_moderation_job(id).await {
+        Ok(job) => Ok(Json(DefaultReturn {
+            success: true,
+            message: if job.finished == 0 {
+                String::from("Moderation job running")
+            } else {
+                String::from("Moderation job finished")
+            },
+            payload: job,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Check the database, cache and schema (`/api/admin/doctor`)
+async fn doctor(
+    jar: CookieJar,
+    State(database): State<Database>,
+) -> Result<Json<DefaultReturn<DoctorReport>>, PasteError> {
+    if let Err(e) = get_paste_manager(&jar, &database).await {
+        return Err(e);
+    }
+
+    let report = database.doctor().await;
+
+    Ok(Json(DefaultReturn {
+        success: report.ok,
+        message: if report.ok {
+            String::from("No problems found")
+        } else {
+            String::from("Problems found")
+        },
+        payload: report,
+    }))
 }
 
 // auth
@@ -256,3 +3926,89 @@ pub async fn logout_request(State(database): State<Database>, jar: CookieJar) ->
         "Failed to sign out of account.",
     )
 }
+
+// passkeys
+
+/// Create a challenge for a passkey assertion (`/api/auth/webauthn/challenge`)
+#[cfg(feature = "webauthn")]
+async fn create_webauthn_challenge(
+    State(database): State<Database>,
+) -> Result<Json<DefaultReturn<String>>, PasteError> {
+    match database.create_webauthn_challenge().await {
+        Ok(challenge) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Challenge created"),
+            payload: challenge,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Get the passkeys of the current user (`/api/auth/webauthn/credentials`)
+#[cfg(feature = "webauthn")]
+async fn get_credentials(
+    jar: CookieJar,
+    State(database): State<Database>,
+) -> Result<Json<DefaultReturn<Vec<WebAuthnCredential>>>, PasteError> {
+    let ua = match get_logged_in(&jar, &database).await {
+        Ok(ua) => ua,
+        Err(e) => return Err(e),
+    };
+
+    Ok(Json(DefaultReturn {
+        success: true,
+        message: String::from("Credentials exist"),
+        payload: database.get_credentials(ua.user.username).await,
+    }))
+}
+
+/// Register a passkey for the current user (`/api/auth/webauthn/credentials`)
+#[cfg(feature = "webauthn")]
+async fn create_credential(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Json(credential_to_create): Json<WebAuthnCredentialCreate>,
+) -> Result<Json<DefaultReturn<WebAuthnCredential>>, PasteError> {
+    let ua = match get_logged_in(&jar, &database).await {
+        Ok(ua) => ua,
+        Err(e) => return Err(e),
+    };
+
+    match database
+        .create_credential(credential_to_create, ua.user.username)
+        .await
+    {
+        Ok(credential) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Credential created"),
+            payload: credential,
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Delete one of the current user's passkeys (`/api/auth/webauthn/credentials/:id/delete`)
+#[cfg(feature = "webauthn")]
+async fn delete_credential(
+    jar: CookieJar,
+    State(database): State<Database>,
+    Path(id): Path<String>,
+    Json(credential_to_delete): Json<WebAuthnCredentialDelete>,
+) -> Result<Json<DefaultReturn<()>>, PasteError> {
+    let ua = match get_logged_in(&jar, &database).await {
+        Ok(ua) => ua,
+        Err(e) => return Err(e),
+    };
+
+    match database
+        .delete_credential(id, ua.user.username, credential_to_delete.assertion)
+        .await
+    {
+        Ok(_) => Ok(Json(DefaultReturn {
+            success: true,
+            message: String::from("Credential deleted"),
+            payload: (),
+        })),
+        Err(e) => Err(e),
+    }
+}
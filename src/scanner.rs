@@ -0,0 +1,114 @@
+//! Malware scanning of binary pastes (requires the `scanning` feature, see [`crate::database::ServerOptions::scanner`])
+//!
+//! Content is streamed to clamd over TCP (`tcp://host:3310`, with the `INSTREAM` command)
+//! or posted to an HTTP scanner (`http(s)://...`), which has to respond with JSON like
+//! `{"infected": true, "signature": "Eicar-Test-Signature"}`.
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Bytes sent to clamd in each chunk of a stream
+const CHUNK_SIZE: usize = 8_192;
+
+/// Result of a scan
+pub enum Verdict {
+    Clean,
+    /// Malware was found (with the name of its signature)
+    Infected(String),
+}
+
+/// Response of an HTTP scanner
+#[derive(Deserialize)]
+struct HttpVerdict {
+    infected: bool,
+    #[serde(default)]
+    signature: String,
+}
+
+/// Scan `bytes` with the scanner at `url`
+///
+/// ## Arguments:
+/// * `url` - `tcp://host:port` for clamd, or the url of an HTTP scanner
+/// * `timeout` - milliseconds to wait for the scanner before giving up
+/// * `bytes` - the content to scan
+///
+/// ## Returns:
+/// * `None` if the scanner couldn't be reached or its response wasn't understood
+pub async fn scan(url: &str, timeout: u64, bytes: &[u8]) -> Option<Verdict> {
+    match url.strip_prefix("tcp://") {
+        Some(address) => {
+            tokio::time::timeout(Duration::from_millis(timeout), scan_clamd(address, bytes))
+                .await
+                .ok()?
+        }
+        None => scan_http(url, timeout, bytes).await,
+    }
+}
+
+/// Stream `bytes` to clamd at `address`
+async fn scan_clamd(address: &str, bytes: &[u8]) -> Option<Verdict> {
+    let mut stream = TcpStream::connect(address).await.ok()?;
+    stream.write_all(b"zINSTREAM\0").await.ok()?;
+
+    for chunk in bytes.chunks(CHUNK_SIZE) {
+        stream
+            .write_all(&(chunk.len() as u32).to_be_bytes())
+            .await
+            .ok()?;
+        stream.write_all(chunk).await.ok()?;
+    }
+
+    // a zero-length chunk ends the stream
+    stream.write_all(&[0, 0, 0, 0]).await.ok()?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await.ok()?;
+
+    parse_clamd(&String::from_utf8_lossy(&response))
+}
+
+/// Parse a clamd response (`stream: OK` or `stream: <signature> FOUND`)
+fn parse_clamd(response: &str) -> Option<Verdict> {
+    let result = response
+        .trim_end_matches('\0')
+        .trim()
+        .strip_prefix("stream:")?
+        .trim();
+
+    if result == "OK" {
+        return Some(Verdict::Clean);
+    }
+
+    result
+        .strip_suffix(" FOUND")
+        .map(|signature| Verdict::Infected(signature.to_string()))
+}
+
+/// Post `bytes` to the HTTP scanner at `url`
+async fn scan_http(url: &str, timeout: u64, bytes: &[u8]) -> Option<Verdict> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_millis(timeout))
+        .build()
+        .ok()?;
+
+    let res = client
+        .post(url)
+        .header("Content-Type", "application/octet-stream")
+        .body(bytes.to_vec())
+        .send()
+        .await
+        .ok()?;
+
+    if !res.status().is_success() {
+        return None;
+    }
+
+    let verdict = res.json::<HttpVerdict>().await.ok()?;
+
+    if verdict.infected == true {
+        Some(Verdict::Infected(verdict.signature))
+    } else {
+        Some(Verdict::Clean)
+    }
+}
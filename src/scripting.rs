@@ -0,0 +1,155 @@
+//! Operator rules written in Rhai (requires the `scripting` feature, see [`crate::database::ServerOptions::scripts_dir`])
+//!
+//! Every `.rhai` file in the scripts directory is run, in file name order, when a paste is
+//! created or edited. Files are reloaded when they change, so rules can be edited without
+//! restarting the server. Scripts are given these variables:
+//!
+//! * `action` - `"create"` or `"edit"`
+//! * `url` - the paste's url
+//! * `content` - the paste's new content
+//! * `author` - username of the user making the change (empty when anonymous)
+//! * `anonymous` - if `author` is empty
+//!
+//! A script rejects the change by returning a string (the reason) or by throwing. Scripts
+//! which fail to compile, error or go over their limits are skipped.
+//!
+//! ```rhai
+//! if anonymous && content.contains("casino") {
+//!     return "Anonymous pastes can't mention casinos.";
+//! }
+//! ```
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{Dynamic, Engine, EvalAltResult, Scope, AST};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+/// Largest number of operations a single script can run
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// What a change is checked with
+pub struct RuleInput<'a> {
+    /// `"create"` or `"edit"`
+    pub action: &'a str,
+    pub url: &'a str,
+    pub content: &'a str,
+    /// Username of the user making the change (empty when anonymous)
+    pub author: &'a str,
+}
+
+/// Compiled scripts by path, with the modification time they were compiled at
+type ScriptCache = HashMap<PathBuf, (SystemTime, Option<AST>)>;
+
+/// Rules loaded from a scripts directory
+#[derive(Clone)]
+pub struct Scripts {
+    dir: PathBuf,
+    engine: Arc<Engine>,
+    cache: Arc<RwLock<ScriptCache>>,
+}
+
+impl std::fmt::Debug for Scripts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Scripts").field("dir", &self.dir).finish()
+    }
+}
+
+impl Scripts {
+    /// Create a sandboxed engine for the scripts in `dir`
+    pub fn new(dir: &str) -> Self {
+        let mut engine = Engine::new();
+
+        // no files, no output, limited resources
+        engine.set_module_resolver(DummyModuleResolver::new());
+        engine.disable_symbol("eval");
+        engine.on_print(|_| {});
+        engine.on_debug(|_, _, _| {});
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.set_max_call_levels(32);
+        engine.set_max_expr_depths(64, 32);
+        engine.set_max_string_size(1_000_000);
+        engine.set_max_array_size(10_000);
+        engine.set_max_map_size(10_000);
+
+        Self {
+            dir: PathBuf::from(dir),
+            engine: Arc::new(engine),
+            cache: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Get the compiled scripts in the directory, recompiling the ones which changed
+    fn load(&self) -> Vec<AST> {
+        let mut paths: Vec<(PathBuf, SystemTime)> = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.extension().map_or(false, |e| e == "rhai"))
+                .filter_map(|p| match std::fs::metadata(&p).and_then(|m| m.modified()) {
+                    Ok(modified) => Some((p, modified)),
+                    Err(_) => None,
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+
+        paths.sort();
+
+        // compile changed scripts
+        let mut cache = match self.cache.write() {
+            Ok(c) => c,
+            Err(e) => e.into_inner(),
+        };
+
+        cache.retain(|path, _| paths.iter().any(|(p, _)| p == path));
+
+        for (path, modified) in &paths {
+            if cache.get(path).map_or(false, |(m, _)| m == modified) {
+                continue;
+            }
+
+            let ast = match std::fs::read_to_string(path) {
+                Ok(script) => self.engine.compile(script).ok(),
+                Err(_) => None,
+            };
+
+            cache.insert(path.clone(), (*modified, ast));
+        }
+
+        paths
+            .iter()
+            .filter_map(|(p, _)| cache.get(p).and_then(|(_, ast)| ast.clone()))
+            .collect()
+    }
+
+    /// Run every script on `input`
+    ///
+    /// ## Returns:
+    /// * the reason given by the first script which rejected the change
+    pub fn check(&self, input: &RuleInput) -> Result<(), String> {
+        for ast in self.load() {
+            let mut scope = Scope::new();
+            scope.push_constant("action", input.action.to_string());
+            scope.push_constant("url", input.url.to_string());
+            scope.push_constant("content", input.content.to_string());
+            scope.push_constant("author", input.author.to_string());
+            scope.push_constant("anonymous", input.author.is_empty());
+
+            match self.engine.eval_ast_with_scope::<Dynamic>(&mut scope, &ast) {
+                Ok(value) => {
+                    if let Ok(reason) = value.into_string() {
+                        return Err(reason);
+                    }
+                }
+                Err(e) => {
+                    if let EvalAltResult::ErrorRuntime(value, _) = *e {
+                        return Err(value.to_string());
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
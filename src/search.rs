@@ -0,0 +1,139 @@
+//! Full-text search over paste content (see [`crate::database::ServerOptions::search`])
+//!
+//! Pastes are kept in an index table (`se_search`) which is updated whenever a paste is
+//! written. The index uses whatever the database backend provides:
+//!
+//! * sqlite - an FTS5 virtual table, ranked with `bm25`
+//! * postgres - a generated `tsvector` column with a GIN index, ranked with `ts_rank`
+//! * mysql - a `FULLTEXT` index, ranked by natural language relevance
+use crate::model::Paste;
+
+/// Longest search query accepted
+pub const MAX_QUERY_LENGTH: usize = 250;
+
+/// Characters of context kept on each side of the first match in a snippet
+const SNIPPET_CONTEXT: usize = 80;
+/// Bytes of content scanned for matches when making a snippet
+const SNIPPET_SCAN: usize = 100_000;
+
+/// Check if a paste can be returned in search results
+///
+/// Pastes with a view password, pastes which ask not to be indexed, sensitive pastes and
+/// binary pastes are never indexed.
+pub fn is_searchable(paste: &Paste) -> bool {
+    paste.metadata.view_password.is_empty()
+        && (paste.metadata.noindex == false)
+        && (paste.metadata.sensitive == false)
+        && (paste.metadata.content_type != "binary")
+}
+
+/// Get a short snippet of `content` around the first word of `query` found in it
+///
+/// The snippet is HTML-escaped and every matched word in it is wrapped in `<mark>` tags.
+/// Only the start of long content is scanned, so the snippet is the start of the content
+/// if no match is found there.
+///
+/// ## Arguments:
+/// * `content` - the paste's content
+/// * `query` - the search query
+pub fn snippet(content: &str, query: &str) -> String {
+    let mut end = content.len().min(SNIPPET_SCAN);
+
+    while !content.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    let text: Vec<char> = content[..end]
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .chars()
+        .collect();
+
+    // lowercase char by char so positions in both match
+    let lower: Vec<char> = text.iter().map(|c| lowercase(*c)).collect();
+    let words: Vec<Vec<char>> = query
+        .split_whitespace()
+        .map(|w| w.chars().map(lowercase).collect())
+        .collect();
+
+    // find matches
+    let mut matches: Vec<(usize, usize)> = Vec::new();
+    let mut i: usize = 0;
+
+    while i < lower.len() {
+        match words.iter().find(|w| lower[i..].starts_with(w)) {
+            Some(w) => {
+                matches.push((i, i + w.len()));
+                i += w.len();
+            }
+            None => i += 1,
+        }
+    }
+
+    // cut window
+    let (start, end) = match matches.first() {
+        Some((s, e)) => (
+            s.saturating_sub(SNIPPET_CONTEXT),
+            (e + SNIPPET_CONTEXT).min(text.len()),
+        ),
+        None => (0, (SNIPPET_CONTEXT * 2).min(text.len())),
+    };
+
+    matches.retain(|(s, _)| (*s >= start) && (*s < end));
+
+    let mut out = String::new();
+
+    if start > 0 {
+        out.push('…');
+    }
+
+    for (i, c) in text.iter().enumerate().take(end).skip(start) {
+        if matches.iter().any(|(s, _)| *s == i) {
+            out.push_str("<mark>");
+        }
+
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(*c),
+        }
+
+        if matches
+            .iter()
+            .any(|(_, e)| (*e == i + 1) | ((*e > end) && (i + 1 == end)))
+        {
+            out.push_str("</mark>");
+        }
+    }
+
+    if end < text.len() {
+        out.push('…');
+    }
+
+    out
+}
+
+/// Lowercase a single character (characters which lowercase to more than one are kept)
+fn lowercase(c: char) -> char {
+    let mut lower = c.to_lowercase();
+
+    match (lower.next(), lower.next()) {
+        (Some(l), None) => l,
+        _ => c,
+    }
+}
+
+/// Turn a user's query into an FTS5 query matching every word in it
+///
+/// Every word is quoted, so FTS5 operators and syntax in the query are matched literally
+/// instead of failing the query.
+pub fn fts5_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
@@ -0,0 +1,69 @@
+//! Signed urls for protected pastes (requires the `signed_urls` feature, see `/api/:url/sign`)
+//!
+//! A signed url (e.g. `/api/:url/raw?sig=...&exp=...`) grants read access to a paste
+//! protected by a view password until it expires, without sharing the password. Signatures
+//! are HMAC-SHA256 over the paste's url, the expiry and the paste's signing nonce, so
+//! rotating the nonce (`/api/:url/sign/revoke`) revokes every url signed before.
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Sign `url`
+///
+/// ## Arguments:
+/// * `secret` - the server's signing secret
+/// * `url` - the paste url
+/// * `nonce` - the paste's signing nonce
+/// * `expires` - timestamp the signature expires at
+///
+/// ## Returns:
+/// * the signature, as lowercase hex
+pub fn sign(secret: &str, url: &str, nonce: &str, expires: u128) -> String {
+    hex(&mac(secret, url, nonce, expires).finalize().into_bytes())
+}
+
+/// Check if `signature` is the signature of `url` (compared in constant time)
+///
+/// ## Arguments:
+/// * `secret` - the server's signing secret
+/// * `url` - the paste url
+/// * `nonce` - the paste's signing nonce
+/// * `expires` - timestamp the signature expires at
+/// * `signature` - the signature to check, as hex
+pub fn verify(secret: &str, url: &str, nonce: &str, expires: u128, signature: &str) -> bool {
+    match unhex(signature) {
+        Some(bytes) => mac(secret, url, nonce, expires)
+            .verify_slice(&bytes)
+            .is_ok(),
+        None => false,
+    }
+}
+
+/// Start the HMAC of a signed url (urls can't contain line breaks, so the fields can't run into each other)
+fn mac(secret: &str, url: &str, nonce: &str, expires: u128) -> HmacSha256 {
+    // HMAC takes keys of any length
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(format!("{}\n{}\n{}", url, expires, nonce).as_bytes());
+    mac
+}
+
+/// Format `bytes` as lowercase hex
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Parse hex `text`
+///
+/// ## Returns:
+/// * `None` if the text isn't valid hex
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if (text.len() % 2) != 0 {
+        return None;
+    }
+
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
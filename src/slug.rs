@@ -0,0 +1,77 @@
+//! Readable paste url candidates from titles
+
+/// Words dropped from slugs unless nothing else is left
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "for", "from", "in", "is", "it", "of", "on",
+    "or", "the", "to", "with",
+];
+
+/// Longest slug generated (in characters)
+const MAX_LENGTH: usize = 60;
+
+/// Turn `title` into an ASCII slug (e.g. `"Crème Brûlée for Beginners"` becomes `creme-brulee-beginners`)
+///
+/// ## Arguments:
+/// * `title` - the title
+/// * `keep_stop_words` - if words like "the" and "of" should be kept
+pub fn slugify(title: &str, keep_stop_words: bool) -> String {
+    let ascii = deunicode::deunicode(title).to_lowercase();
+
+    let words: Vec<&str> = ascii
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    let kept: Vec<&str> = words
+        .iter()
+        .filter(|w| keep_stop_words | !STOP_WORDS.contains(w))
+        .cloned()
+        .collect();
+
+    let words = if kept.is_empty() { words } else { kept };
+
+    // join words without going over the length limit
+    let mut out = String::new();
+
+    for word in words {
+        if out.len() + word.len() + 1 > MAX_LENGTH {
+            if out.is_empty() {
+                out.push_str(&word[..MAX_LENGTH]);
+            }
+
+            break;
+        }
+
+        if !out.is_empty() {
+            out.push('-');
+        }
+
+        out.push_str(word);
+    }
+
+    out
+}
+
+/// Get url candidates for `title`, most readable first
+///
+/// Candidates aren't checked against existing pastes.
+pub fn candidates(title: &str) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+
+    for slug in [slugify(title, false), slugify(title, true)] {
+        if !slug.is_empty() && !out.contains(&slug) {
+            out.push(slug);
+        }
+    }
+
+    let base = match out.first() {
+        Some(b) => b.clone(),
+        None => return out,
+    };
+
+    for i in 2..=5 {
+        out.push(format!("{}-{}", base, i));
+    }
+
+    out
+}
@@ -0,0 +1,243 @@
+//! Rendering stack traces (see [`crate::database::ServerOptions::stack_traces`])
+//!
+//! Rust panics, Python tracebacks and Java exceptions are recognized. Exception lines are
+//! highlighted, long runs of frames are collapsed into a `<details>` element, and frames in
+//! known crates link to their documentation. Everything is marked up with `st-` prefixed
+//! classes so it can be themed with a stylesheet (which should keep whitespace, since
+//! every line is its own element).
+use regex::Regex;
+
+/// Languages pastes can set to always be rendered as a stack trace
+pub const LANGUAGES: [&str; 3] = ["stacktrace", "backtrace", "traceback"];
+
+/// Frames shown before the rest of a run of frames is collapsed
+const FRAMES_SHOWN: usize = 3;
+/// Crates documented on `doc.rust-lang.org` instead of `docs.rs`
+const STD_CRATES: [&str; 4] = ["std", "core", "alloc", "proc_macro"];
+
+/// Lines which start a stack trace, or describe the error it's for
+const EXCEPTION_PATTERNS: [&str; 6] = [
+    // rust
+    r"^thread '.*' panicked at ",
+    // python
+    r"^Traceback \(most recent call last\):",
+    r"^[A-Za-z_][\w.]*(Error|Exception|Warning|Exit|Interrupt)(: .*)?$",
+    // java
+    r#"^Exception in thread ".*" "#,
+    r"^Caused by: ",
+    r"^[a-z][\w$]*(\.[\w$]+)+(Exception|Error)(: .*)?$",
+];
+
+/// Lines which start a frame of a stack trace
+const FRAME_PATTERNS: [&str; 5] = [
+    // rust
+    r"^\s+\d+: \S",
+    // python
+    r#"^\s+File ".*", line \d+"#,
+    // java
+    r"^\s+at [\w$.<>/]+\(.*\)$",
+    r"^\s+\.\.\. \d+ (more|common frames omitted)$",
+    // java (suppressed)
+    r"^\s+Suppressed: ",
+];
+
+/// Locations under rust frames
+const RUST_LOCATION_PATTERN: &str = r"^\s+at \S+:\d+(:\d+)?$";
+
+/// What a line in a stack trace is
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Exception,
+    Frame,
+    /// A line belonging to the frame before it (e.g. its location or source)
+    FrameDetail,
+    Other,
+}
+
+/// Check if `content` starts with a stack trace
+pub fn is_stack_trace(content: &str) -> bool {
+    let first = match content.lines().find(|l| !l.trim().is_empty()) {
+        Some(l) => l,
+        None => return false,
+    };
+
+    EXCEPTION_PATTERNS
+        .iter()
+        .any(|p| Regex::new(p).unwrap().is_match(first))
+}
+
+/// Render a stack trace to HTML
+///
+/// ## Returns:
+/// * the rendered HTML, or `None` if `content` has no recognized frames or exceptions
+pub fn render(content: &str) -> Option<String> {
+    let exceptions: Vec<Regex> = EXCEPTION_PATTERNS
+        .iter()
+        .map(|p| Regex::new(p).unwrap())
+        .collect();
+    let frames: Vec<Regex> = FRAME_PATTERNS
+        .iter()
+        .map(|p| Regex::new(p).unwrap())
+        .collect();
+
+    let rust_location = Regex::new(RUST_LOCATION_PATTERN).unwrap();
+
+    let mut lines: Vec<(Kind, &str)> = Vec::new();
+    let mut after_panic = false;
+
+    for line in content.lines() {
+        let in_frame = match lines.last() {
+            Some((k, _)) => (*k == Kind::Frame) | (*k == Kind::FrameDetail),
+            None => false,
+        };
+
+        let kind = if exceptions.iter().any(|r| r.is_match(line)) | after_panic {
+            Kind::Exception
+        } else if in_frame && rust_location.is_match(line) {
+            Kind::FrameDetail
+        } else if frames.iter().any(|r| r.is_match(line)) {
+            Kind::Frame
+        } else if in_frame && line.starts_with("    ") {
+            // source lines under python frames
+            Kind::FrameDetail
+        } else {
+            Kind::Other
+        };
+
+        // rust puts the panic message on the line after the location
+        after_panic = line.starts_with("thread '") && line.ends_with(':');
+        lines.push((kind, line));
+    }
+
+    if !lines.iter().any(|l| l.0 != Kind::Other) {
+        return None;
+    }
+
+    // group frames into runs
+    let mut out = String::from("<div class=\"st\">");
+    let mut run: Vec<Vec<&str>> = Vec::new();
+
+    for (kind, line) in lines {
+        match kind {
+            Kind::Frame => {
+                run.push(vec![line]);
+                continue;
+            }
+            Kind::FrameDetail => {
+                if let Some(frame) = run.last_mut() {
+                    frame.push(line);
+                }
+
+                continue;
+            }
+            _ => (),
+        }
+
+        push_frames(&mut out, &run);
+        run.clear();
+
+        if kind == Kind::Exception {
+            out.push_str(&format!(
+                "<div class=\"st-exception\">{}</div>",
+                escape(line)
+            ));
+        } else {
+            out.push_str(&format!("<div class=\"st-line\">{}</div>", escape(line)));
+        }
+    }
+
+    push_frames(&mut out, &run);
+    out.push_str("</div>");
+    Some(out)
+}
+
+/// Push a run of frames to `out`, collapsing every frame after the first few
+fn push_frames(out: &mut String, run: &[Vec<&str>]) {
+    if run.is_empty() {
+        return;
+    }
+
+    out.push_str("<div class=\"st-frames\">");
+
+    for lines in run.iter().take(FRAMES_SHOWN) {
+        push_frame(out, lines);
+    }
+
+    if run.len() > FRAMES_SHOWN {
+        let hidden = run.len() - FRAMES_SHOWN;
+
+        out.push_str(&format!(
+            "<details class=\"st-collapsed\"><summary>{} more frame{}</summary>",
+            hidden,
+            if hidden == 1 { "" } else { "s" }
+        ));
+
+        for lines in run.iter().skip(FRAMES_SHOWN) {
+            push_frame(out, lines);
+        }
+
+        out.push_str("</details>");
+    }
+
+    out.push_str("</div>");
+}
+
+/// Push a single frame to `out`
+fn push_frame(out: &mut String, lines: &[&str]) {
+    out.push_str("<div class=\"st-frame\">");
+
+    for line in lines {
+        out.push_str(&frame_line(line));
+    }
+
+    out.push_str("</div>");
+}
+
+/// Render a line of a frame, linking it to its crate's documentation if it's in a known crate
+fn frame_line(line: &str) -> String {
+    match crate_link(line) {
+        Some((name, href)) => format!(
+            "<div class=\"st-line\">{} <a class=\"st-link\" href=\"{}\" rel=\"nofollow noopener\">{}</a></div>",
+            escape(line),
+            escape(&href),
+            escape(&name)
+        ),
+        None => format!("<div class=\"st-line\">{}</div>", escape(line)),
+    }
+}
+
+/// Get the name and documentation url of the crate a rust frame is in
+///
+/// Frames in crates downloaded from a registry link to `docs.rs` (with the downloaded
+/// version), and frames in the standard library link to `doc.rust-lang.org`.
+fn crate_link(line: &str) -> Option<(String, String)> {
+    let registry =
+        Regex::new(r"/registry/src/[^/]+/([A-Za-z0-9_-]+)-(\d+\.\d+\.\d+[\w.+-]*)/").unwrap();
+
+    if let Some(captures) = registry.captures(line) {
+        let name = captures[1].to_string();
+        let version = captures[2].to_string();
+        return Some((
+            format!("{} {}", name, version),
+            format!("https://docs.rs/{}/{}", name, version),
+        ));
+    }
+
+    let symbol = Regex::new(r"^\s+\d+: <?([a-z_][a-z0-9_]*)::").unwrap();
+
+    match symbol.captures(line) {
+        Some(captures) if STD_CRATES.contains(&&captures[1]) => Some((
+            captures[1].to_string(),
+            format!("https://doc.rust-lang.org/{}/", &captures[1]),
+        )),
+        _ => None,
+    }
+}
+
+/// Escape `text` for HTML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,196 @@
+//! CSV and TSV tables (see [`crate::database::ServerOptions::tables`])
+//!
+//! Tables are parsed following RFC 4180 (quoted fields can contain delimiters, quotes
+//! written as `""` and line breaks). Tables are rendered with sort links in their headers
+//! (`?sort=col2`, or `?sort=-col2` for descending), marked up with `tb-` prefixed classes
+//! so they can be themed with a stylesheet.
+use crate::model::PasteTable;
+use std::cmp::Ordering;
+
+/// Parse `content` into a table of fields separated by `delimiter`
+///
+/// The first row is used as the table's columns, unless it contains a number (then it's
+/// most likely data, and columns are named `col1`, `col2`, and so on).
+pub fn parse(content: &str, delimiter: char) -> PasteTable {
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    let mut row: Vec<String> = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if quoted {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    field.push('"');
+                    chars.next();
+                } else {
+                    quoted = false;
+                }
+            } else {
+                field.push(c);
+            }
+
+            continue;
+        }
+
+        match c {
+            '"' if field.is_empty() => quoted = true,
+            '\r' => (),
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c if c == delimiter => row.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+
+    if !field.is_empty() | !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+
+    // remove blank lines
+    rows.retain(|r| !((r.len() == 1) && r[0].is_empty()));
+
+    // columns
+    let width = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+    let has_header = match rows.first() {
+        Some(r) => !r.iter().any(|f| f.trim().parse::<f64>().is_ok()),
+        None => false,
+    };
+
+    let columns: Vec<String> = if has_header {
+        let mut columns = rows.remove(0);
+        columns.resize(width, String::new());
+        columns
+    } else {
+        (1..=width).map(|i| format!("col{}", i)).collect()
+    };
+
+    for row in rows.iter_mut() {
+        row.resize(width, String::new());
+    }
+
+    PasteTable { columns, rows }
+}
+
+/// Check if `content` looks like a table of fields separated by `delimiter`
+///
+/// Every row in the first lines of the content (at least three) has to have the same
+/// (more than one) number of fields.
+pub fn is_table(content: &str, delimiter: char) -> bool {
+    let start: String = content.lines().take(50).collect::<Vec<&str>>().join("\n");
+    let mut widths = start
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| parse(l, delimiter).columns.len());
+
+    let first = match widths.next() {
+        Some(w) => w,
+        None => return false,
+    };
+
+    let mut count: usize = 1;
+
+    for width in widths {
+        if width != first {
+            return false;
+        }
+
+        count += 1;
+    }
+
+    (first > 1) && (count > 2)
+}
+
+/// Parse a sort parameter (`col2`, or `-col2` for descending) into a column index
+///
+/// ## Returns:
+/// * the column index and if the sort is descending, or `None` if `sort` isn't valid
+pub fn parse_sort(sort: &str, width: usize) -> Option<(usize, bool)> {
+    let (column, descending) = match sort.strip_prefix('-') {
+        Some(s) => (s, true),
+        None => (sort, false),
+    };
+
+    let index = column.strip_prefix("col")?.parse::<usize>().ok()?;
+
+    if (index == 0) | (index > width) {
+        return None;
+    }
+
+    Some((index - 1, descending))
+}
+
+/// Sort the rows of `table` by `sort` (see [`parse_sort`])
+///
+/// Numbers are compared as numbers, everything else as text.
+pub fn sort(table: &mut PasteTable, sort: &str) {
+    let (index, descending) = match parse_sort(sort, table.columns.len()) {
+        Some(s) => s,
+        None => return,
+    };
+
+    table.rows.sort_by(|a, b| {
+        let ordering = match (
+            a[index].trim().parse::<f64>(),
+            b[index].trim().parse::<f64>(),
+        ) {
+            (Ok(x), Ok(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+            _ => a[index].to_lowercase().cmp(&b[index].to_lowercase()),
+        };
+
+        if descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+}
+
+/// Render `table` to HTML (it should already be sorted by `sort`)
+pub fn render(table: &PasteTable, sort: &str) -> String {
+    let sorted = parse_sort(sort, table.columns.len());
+    let mut out = String::from("<table class=\"tb\"><thead><tr>");
+
+    for (i, column) in table.columns.iter().enumerate() {
+        // link to the opposite order of the current sort
+        let (aria, href) = match sorted {
+            Some((index, false)) if index == i => ("ascending", format!("-col{}", i + 1)),
+            Some((index, true)) if index == i => ("descending", format!("col{}", i + 1)),
+            _ => ("none", format!("col{}", i + 1)),
+        };
+
+        out.push_str(&format!(
+            "<th aria-sort=\"{}\"><a class=\"tb-sort\" href=\"?sort={}\">{}</a></th>",
+            aria,
+            href,
+            escape(column)
+        ));
+    }
+
+    out.push_str("</tr></thead><tbody>");
+
+    for row in &table.rows {
+        out.push_str("<tr>");
+
+        for field in row {
+            out.push_str(&format!("<td>{}</td>", escape(field)));
+        }
+
+        out.push_str("</tr>");
+    }
+
+    out.push_str("</tbody></table>");
+    out
+}
+
+/// Escape `text` for HTML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,85 @@
+//! Small "minimap" thumbnails of paste content (requires the `thumbnails` feature)
+//!
+//! Every character of the first screenful of content is drawn as a small block, so the
+//! thumbnail shows the shape of the paste (headings, paragraphs, code blocks) without
+//! needing fonts.
+use crate::front_matter;
+
+/// Width of the thumbnail in pixels
+pub const WIDTH: usize = 240;
+/// Height of the thumbnail in pixels
+pub const HEIGHT: usize = 160;
+
+/// Padding around the content in pixels
+const PADDING: usize = 8;
+/// Width of a single character in pixels
+const CHAR_WIDTH: usize = 2;
+/// Height of a single line in pixels (including the gap below it)
+const LINE_HEIGHT: usize = 4;
+
+const BACKGROUND: u8 = 255;
+const CODE_BACKGROUND: u8 = 235;
+const TEXT: u8 = 150;
+const HEADING: u8 = 40;
+
+/// Render a PNG thumbnail of `content`
+pub fn render(content: &str) -> Vec<u8> {
+    let mut pixels: Vec<u8> = vec![BACKGROUND; WIDTH * HEIGHT];
+
+    let columns = (WIDTH - PADDING * 2) / CHAR_WIDTH;
+    let rows = (HEIGHT - PADDING * 2) / LINE_HEIGHT;
+    let mut in_code = false;
+
+    for (row, line) in front_matter::strip(content).lines().take(rows).enumerate() {
+        let trimmed = line.trim_start();
+        let fence = trimmed.starts_with("```") | trimmed.starts_with("~~~");
+        let top = PADDING + row * LINE_HEIGHT;
+
+        if fence {
+            in_code = !in_code;
+        }
+
+        // code block background
+        if in_code | fence {
+            for y in top..top + LINE_HEIGHT {
+                for x in PADDING / 2..WIDTH - PADDING / 2 {
+                    pixels[y * WIDTH + x] = CODE_BACKGROUND;
+                }
+            }
+        }
+
+        let shade = if !in_code && trimmed.starts_with('#') {
+            HEADING
+        } else {
+            TEXT
+        };
+
+        // characters
+        for (column, c) in line.chars().take(columns).enumerate() {
+            if c.is_whitespace() {
+                continue;
+            }
+
+            let left = PADDING + column * CHAR_WIDTH;
+
+            for y in top..top + LINE_HEIGHT - 1 {
+                for x in left..left + CHAR_WIDTH {
+                    pixels[y * WIDTH + x] = shade;
+                }
+            }
+        }
+    }
+
+    // encode
+    let mut out: Vec<u8> = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, WIDTH as u32, HEIGHT as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    // writing to a Vec can't fail
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(&pixels).unwrap();
+    writer.finish().unwrap();
+
+    out
+}
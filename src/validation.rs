@@ -0,0 +1,160 @@
+//! Per-field validation of request bodies (see [`crate::model::PasteError::Validation`])
+use crate::model::{FieldError, PasteError, PasteMetadata};
+
+/// Collects [`FieldError`]s for every invalid field instead of stopping at the first
+#[derive(Default)]
+pub struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an error for `field`
+    pub fn error(&mut self, field: &str, error: &str, min: Option<usize>, max: Option<usize>) {
+        self.errors.push(FieldError {
+            field: field.to_string(),
+            error: error.to_string(),
+            min,
+            max,
+        });
+    }
+
+    /// Check that `value` is between `min` and `max` bytes long
+    pub fn length(&mut self, field: &str, value: &str, min: usize, max: usize) {
+        if value.len() < min {
+            self.error(field, "too_short", Some(min), None);
+        } else if value.len() > max {
+            self.error(field, "too_long", None, Some(max));
+        }
+    }
+
+    /// Check that `value` matches `regex`
+    pub fn pattern(&mut self, field: &str, value: &str, regex: &regex::Regex) {
+        if !regex.is_match(value) {
+            self.error(field, "invalid_characters", None, None);
+        }
+    }
+
+    /// Check that `value` is one of `values`
+    pub fn one_of(&mut self, field: &str, value: &str, values: &[&str]) {
+        if !values.contains(&value) {
+            self.error(field, "invalid_value", None, None);
+        }
+    }
+
+    /// Check that a list has at most `max` items
+    pub fn count(&mut self, field: &str, count: usize, max: usize) {
+        if count > max {
+            self.error(field, "too_many", None, Some(max));
+        }
+    }
+
+    /// Get the collected errors as a [`PasteError::Validation`], if there are any
+    pub fn finish(self) -> Result<(), PasteError> {
+        if self.errors.is_empty() {
+            return Ok(());
+        }
+
+        Err(PasteError::Validation(self.errors))
+    }
+}
+
+/// Pattern of characters allowed in paste urls
+pub const URL_PATTERN: &str = "^[\\w\\_\\-\\.\\!\\p{Extended_Pictographic}]+$";
+
+/// Characters allowed in paste urls
+pub fn url_regex() -> regex::Regex {
+    regex::Regex::new(URL_PATTERN).unwrap()
+}
+
+/// Shortest paste url allowed
+pub const MIN_URL_LENGTH: usize = 3;
+/// Longest paste url allowed
+pub const MAX_URL_LENGTH: usize = 250;
+/// Default maximum content length of a paste
+pub const MAX_CONTENT_LENGTH: usize = 200_000;
+/// Longest paste title allowed
+pub const MAX_TITLE_LENGTH: usize = 250;
+/// Longest paste description allowed
+pub const MAX_DESCRIPTION_LENGTH: usize = 1_000;
+/// Largest number of tags on a paste
+pub const MAX_TAGS: usize = 25;
+/// Longest tag allowed
+pub const MAX_TAG_LENGTH: usize = 50;
+/// Values of [`PasteMetadata::content_type`] (the same as [`crate::model::Detection::content_type`])
+pub const CONTENT_TYPES: [&str; 9] = [
+    "",
+    "markdown",
+    "csv",
+    "tsv",
+    "code",
+    "log",
+    "stack_trace",
+    "text",
+    "binary",
+];
+/// Largest number of pastes imported at once through `/api/import`
+pub const MAX_IMPORTED_PASTES: usize = 500;
+/// Longest MIME type allowed
+pub const MAX_MIME_TYPE_LENGTH: usize = 100;
+/// Pattern of MIME types (without parameters)
+pub const MIME_TYPE_PATTERN: &str = r"^[\w.+-]+/[\w.+-]+$";
+/// Shortest time between rotations of a paste, in milliseconds (rotations are checked every minute)
+pub const MIN_ROTATION_INTERVAL: u128 = 60 * 1000;
+/// Longest time between rotations of a paste, in milliseconds
+pub const MAX_ROTATION_INTERVAL: u128 = 30 * 24 * 60 * 60 * 1000;
+
+/// Check a paste's url and content (up to `max_content` bytes)
+pub fn paste(
+    v: &mut Validator,
+    url_field: &str,
+    url: &str,
+    content_field: &str,
+    content: &str,
+    max_content: usize,
+) {
+    v.length(url_field, url, MIN_URL_LENGTH, MAX_URL_LENGTH);
+    v.pattern(url_field, url, &url_regex());
+    v.length(content_field, content, 1, max_content);
+}
+
+/// Check user-editable paste metadata
+pub fn metadata(v: &mut Validator, metadata: &PasteMetadata) {
+    v.length("metadata/title", &metadata.title, 0, MAX_TITLE_LENGTH);
+    v.length(
+        "metadata/description",
+        &metadata.description,
+        0,
+        MAX_DESCRIPTION_LENGTH,
+    );
+    v.length("metadata/theme_color", &metadata.theme_color, 0, 32);
+    v.length("metadata/favicon", &metadata.favicon, 0, 2_000);
+    v.length("metadata/language", &metadata.language, 0, 32);
+    v.one_of(
+        "metadata/content_type",
+        &metadata.content_type,
+        &CONTENT_TYPES,
+    );
+
+    if !metadata.mime_type.is_empty() {
+        v.length(
+            "metadata/mime_type",
+            &metadata.mime_type,
+            0,
+            MAX_MIME_TYPE_LENGTH,
+        );
+        v.pattern(
+            "metadata/mime_type",
+            &metadata.mime_type,
+            &regex::Regex::new(MIME_TYPE_PATTERN).unwrap(),
+        );
+    }
+    v.count("metadata/tags", metadata.tags.len(), MAX_TAGS);
+
+    for (i, tag) in metadata.tags.iter().enumerate() {
+        v.length(&format!("metadata/tags/{}", i), tag, 1, MAX_TAG_LENGTH);
+    }
+}
@@ -0,0 +1,56 @@
+//! Invisible watermarks made of zero-width characters
+//!
+//! A watermark is an ASCII id with each bit written as a zero-width space (`0`) or a
+//! zero-width non-joiner (`1`), wrapped in word joiners.
+
+const MARK: char = '\u{2060}';
+const ZERO: char = '\u{200B}';
+const ONE: char = '\u{200C}';
+
+/// Embed `id` into `content` (at the end of its first line)
+///
+/// ## Arguments:
+/// * `content` - the content to watermark
+/// * `id` - an ASCII id
+pub fn embed(content: &str, id: &str) -> String {
+    let mut mark = String::from(MARK);
+
+    for byte in id.bytes() {
+        for i in (0..8).rev() {
+            mark.push(if (byte >> i) & 1 == 1 { ONE } else { ZERO });
+        }
+    }
+
+    mark.push(MARK);
+
+    match content.find('\n') {
+        Some(i) => format!("{}{}{}", &content[..i], mark, &content[i..]),
+        None => format!("{}{}", content, mark),
+    }
+}
+
+/// Find the id embedded into `content` by [`embed`]
+pub fn extract(content: &str) -> Option<String> {
+    let start = content.find(MARK)? + MARK.len_utf8();
+    let end = start + content[start..].find(MARK)?;
+
+    let bits: Vec<u8> = content[start..end]
+        .chars()
+        .filter_map(|c| match c {
+            ZERO => Some(0),
+            ONE => Some(1),
+            _ => None,
+        })
+        .collect();
+
+    if bits.is_empty() || (bits.len() % 8 != 0) {
+        return None;
+    }
+
+    let bytes: Vec<u8> = bits
+        .chunks(8)
+        .map(|c| c.iter().fold(0, |acc, b| (acc << 1) | b))
+        .collect();
+
+    String::from_utf8(bytes).ok()
+}
@@ -0,0 +1,120 @@
+//! Passkey (WebAuthn) assertions for high security pastes (requires the `webauthn` feature)
+//!
+//! Only ES256 (ECDSA P-256 with SHA-256) credentials are supported. Public keys are stored
+//! as DER-encoded SubjectPublicKeyInfo, which is what browsers return from
+//! `AuthenticatorAttestationResponse.getPublicKey()`.
+use crate::model::WebAuthnAssertion;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use p256::ecdsa::signature::Verifier;
+use p256::ecdsa::{Signature, VerifyingKey};
+use p256::pkcs8::DecodePublicKey;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// COSE identifier of the only supported algorithm (ES256)
+pub const ES256: i64 = -7;
+
+/// Milliseconds a challenge can be used for
+pub const CHALLENGE_TTL: u128 = 300_000;
+
+/// Authenticator data flag set when the user was present
+const USER_PRESENT: u8 = 0x01;
+
+/// The fields we check in `clientDataJSON`
+#[derive(Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    kind: String,
+    challenge: String,
+    origin: String,
+}
+
+/// Create a new random challenge (base64url)
+pub fn challenge() -> String {
+    URL_SAFE_NO_PAD.encode(dorsal::utility::random_id())
+}
+
+/// Decode a base64url value (padding is allowed)
+pub fn decode(value: &str) -> Option<Vec<u8>> {
+    URL_SAFE_NO_PAD.decode(value.trim_end_matches('=')).ok()
+}
+
+/// Check that `public_key` is a DER-encoded P-256 public key
+pub fn is_public_key(public_key: &[u8]) -> bool {
+    VerifyingKey::from_public_key_der(public_key).is_ok()
+}
+
+/// Get the challenge `assertion` claims to be signed for (not verified)
+pub fn get_challenge(assertion: &WebAuthnAssertion) -> Option<String> {
+    let client_data_json = decode(&assertion.client_data_json)?;
+
+    match serde_json::from_slice::<ClientData>(&client_data_json) {
+        Ok(c) => Some(c.challenge),
+        Err(_) => None,
+    }
+}
+
+/// Verify a signed assertion
+///
+/// ## Arguments:
+/// * `assertion` - the assertion
+/// * `public_key` - the credential's DER-encoded public key
+/// * `challenge` - the challenge the assertion must be signed for
+/// * `rp_id` - the relying party id (the site's domain)
+/// * `origin` - the origin the assertion must be made from
+///
+/// ## Returns:
+/// * the authenticator's signature counter, or `None` if the assertion is invalid
+pub fn verify(
+    assertion: &WebAuthnAssertion,
+    public_key: &[u8],
+    challenge: &str,
+    rp_id: &str,
+    origin: &str,
+) -> Option<u32> {
+    // client data
+    let client_data_json = decode(&assertion.client_data_json)?;
+    let client_data: ClientData = match serde_json::from_slice(&client_data_json) {
+        Ok(c) => c,
+        Err(_) => return None,
+    };
+
+    if (client_data.kind != "webauthn.get")
+        | (client_data.challenge != challenge)
+        | (client_data.origin != origin)
+    {
+        return None;
+    }
+
+    // authenticator data (rp id hash, flags, then a big-endian signature counter)
+    let authenticator_data = decode(&assertion.authenticator_data)?;
+
+    if authenticator_data.len() < 37 {
+        return None;
+    }
+
+    if (authenticator_data[..32] != Sha256::digest(rp_id.as_bytes())[..])
+        | (authenticator_data[32] & USER_PRESENT == 0)
+    {
+        return None;
+    }
+
+    // signature (over the authenticator data and the client data hash)
+    let key = VerifyingKey::from_public_key_der(public_key).ok()?;
+    let signature = Signature::from_der(&decode(&assertion.signature)?).ok()?;
+
+    let mut signed = authenticator_data.clone();
+    signed.extend_from_slice(&Sha256::digest(&client_data_json));
+
+    if key.verify(&signed, &signature).is_err() {
+        return None;
+    }
+
+    Some(u32::from_be_bytes([
+        authenticator_data[33],
+        authenticator_data[34],
+        authenticator_data[35],
+        authenticator_data[36],
+    ]))
+}